@@ -0,0 +1,316 @@
+use anchor_lang::prelude::*;
+
+#[event]
+pub struct SplitStakeEvent {
+    pub farm_state: Pubkey,
+    pub owner: Pubkey,
+    pub source_user_state: Pubkey,
+    pub new_user_state: Pubkey,
+    pub stake_shares_scaled: u128,
+    pub lock_end_ts: u64,
+}
+
+#[event]
+pub struct StakeEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub active_stake_scaled: u128,
+    pub ts: u64,
+}
+
+#[event]
+pub struct UnstakeEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub stake_shares_scaled: u128,
+    pub active_stake_scaled: u128,
+    pub amount_unlocked: u64,
+    pub amount_locked: u64,
+    pub amount_penalty: u64,
+    pub ts: u64,
+}
+
+#[event]
+pub struct HarvestEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub reward_index: u64,
+    pub reward_mint: Pubkey,
+    pub reward_user: u64,
+    pub reward_treasury: u64,
+    pub reward_penalty: u64,
+    pub reward_split: u64,
+    pub ts: u64,
+}
+
+/// Emitted by `harvest_to_owner` next to the `HarvestEvent`
+#[event]
+pub struct HarvestToOwnerEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub reward_index: u64,
+    /// Signer and fee payer of the harvest
+    pub harvester: Pubkey,
+    /// `harvester` is not `owner`
+    pub is_third_party: bool,
+}
+
+#[event]
+pub struct FarmUpgradeSnapshotEvent {
+    pub farm_state: Pubkey,
+    pub marker: u64,
+    pub slot: u64,
+    pub accumulators_hash: [u8; 32],
+    pub pause_until_slot: u64,
+}
+
+#[event]
+pub struct DivertedRewardsSweptEvent {
+    pub farm_state: Pubkey,
+    pub reward_index: u64,
+    pub reward_mint: Pubkey,
+    pub amount: u64,
+}
+
+#[event]
+pub struct PositionRenouncedEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub allow_harvest: bool,
+}
+
+/// The harvest fee override of the farm no longer fits the global bounds, the
+/// harvest used `treasury_fee_bps` instead
+#[event]
+pub struct HarvestFeeOverrideClampedEvent {
+    pub farm_state: Pubkey,
+    pub harvest_fee_bps_override: u64,
+    pub treasury_fee_bps: u64,
+}
+
+#[event]
+pub struct UpdateFarmConfigEvent {
+    pub farm_state: Pubkey,
+    pub mode: u16,
+    pub data: Vec<u8>,
+}
+
+#[event]
+pub struct UpdateGlobalConfigEvent {
+    pub global_config: Pubkey,
+    pub mode: u8,
+    pub value: [u8; 32],
+}
+
+#[event]
+pub struct CancelPendingFarmAdminEvent {
+    pub farm_state: Pubkey,
+    pub authority: Pubkey,
+    pub cancelled_pending_farm_admin: Pubkey,
+}
+
+#[event]
+pub struct UpdateDisabledInstructionsEvent {
+    pub global_config: Pubkey,
+    pub authority: Pubkey,
+    pub previous_disabled_instructions: u128,
+    pub disabled_instructions: u128,
+}
+
+#[event]
+pub struct UpdateFarmPauseFlagsEvent {
+    pub farm_state: Pubkey,
+    pub authority: Pubkey,
+    pub previous_pause_flags: u8,
+    pub pause_flags: u8,
+}
+
+#[event]
+pub struct TreasuryWithdrawalQueuedEvent {
+    pub global_config: Pubkey,
+    pub request: Pubkey,
+    pub request_id: u64,
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+    pub executable_ts: u64,
+}
+
+#[event]
+pub struct TreasuryWithdrawalExecutedEvent {
+    pub global_config: Pubkey,
+    pub request: Pubkey,
+    pub request_id: u64,
+    pub amount: u64,
+}
+
+#[event]
+pub struct TreasuryWithdrawalCancelledEvent {
+    pub global_config: Pubkey,
+    pub request: Pubkey,
+    pub request_id: u64,
+}
+
+/// Emitted on funding and curve changes of a reward
+#[event]
+pub struct RewardDepletionProjectionEvent {
+    pub farm_state: Pubkey,
+    pub reward_index: u64,
+    pub reward_mint: Pubkey,
+    pub rewards_available: u64,
+    /// In the farm `time_unit`, see `RewardInfo::projected_depletion_ts`
+    pub projected_depletion_ts: u64,
+}
+
+/// Emitted by `add_rewards`, the coverage fields are `None` when the curve never stops
+/// issuing or the oracle price is unavailable, see `reward_funding_coverage`
+#[event]
+pub struct RewardFundingCoverageEvent {
+    pub farm_state: Pubkey,
+    pub reward_index: u64,
+    pub reward_mint: Pubkey,
+    pub rewards_available: u64,
+    pub rewards_vault_amount: u64,
+    pub scheduled_remaining: Option<u128>,
+    pub coverage_bps: Option<u64>,
+    /// Below `GlobalConfig::min_reward_funding_coverage_bps`
+    pub below_threshold: bool,
+}
+
+/// Emitted for every non-zero reward credited to a user on refresh, with the
+/// `settlement-events` feature. Logged with `emit!` rather than through the event
+/// authority since settlements happen inside `farm_operations`, without a `ctx`.
+#[event]
+pub struct RewardSettlementEvent {
+    pub farm_state: Pubkey,
+    pub owner: Pubkey,
+    pub user_id: u64,
+    pub reward_index: u8,
+    pub amount: u64,
+    /// `RewardInfo::reward_per_share_scaled` the amount was settled against
+    pub reward_per_share_scaled: u128,
+}
+
+#[event]
+pub struct CreditUsersEvent {
+    pub farm_state: Pubkey,
+    pub reward_index: u64,
+    pub batch_nonce: u64,
+    pub num_users: u64,
+    pub total_amount: u64,
+}
+
+#[event]
+pub struct RaffleCommittedEvent {
+    pub farm_state: Pubkey,
+    pub raffle_state: Pubkey,
+    pub raffle_id: u64,
+    pub reward_index: u64,
+    pub prize_amount: u64,
+    pub slot_target: u64,
+}
+
+/// Emitted once per raffle, when it reaches `RaffleStatus::Settled` or `Cancelled`
+#[event]
+pub struct RaffleSettledEvent {
+    pub farm_state: Pubkey,
+    pub raffle_state: Pubkey,
+    pub raffle_id: u64,
+    pub reward_index: u64,
+    pub prize_amount: u64,
+    /// `RaffleStatus`
+    pub status: u8,
+    /// User state credited with the prize, `Pubkey::default()` if none
+    pub winner: Pubkey,
+}
+
+#[event]
+pub struct RewardShortfallEvent {
+    pub farm_state: Pubkey,
+    pub reward_index: u64,
+    pub reward_mint: Pubkey,
+    pub rewards_vault_amount: u64,
+    pub rewards_owed: u64,
+    pub deficit: u64,
+}
+
+#[event]
+pub struct DelegatedStakeChangedEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub delegate_authority: Pubkey,
+    pub previous_stake: u64,
+    pub new_stake: u64,
+    /// Active stake attributed to `delegate_authority` after the change
+    pub authority_stake: u64,
+}
+
+#[event]
+pub struct WithdrawalQueuedEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    pub request_ts: u64,
+    /// Withdrawals ahead in the queue
+    pub queue_position: u64,
+}
+
+#[event]
+pub struct QueuedWithdrawalPaidEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+    /// Left in the queue for the next windows, 0 once paid out in full
+    pub amount_remaining: u64,
+}
+
+#[event]
+pub struct QueuedWithdrawalCancelledEvent {
+    pub farm_state: Pubkey,
+    pub user_state: Pubkey,
+    pub owner: Pubkey,
+    pub amount: u64,
+}
+
+/// Same as `emit_cpi!`, but without relying on a `ctx` binding so it can be
+/// called through [`crate::emit_event`].
+#[cfg(feature = "event-cpi")]
+pub fn emit_cpi_event<E: anchor_lang::Event>(
+    event_authority: &AccountInfo,
+    event_authority_bump: u8,
+    event: &E,
+) -> Result<()> {
+    use anchor_lang::solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke_signed,
+    };
+
+    let ix_data: Vec<u8> = anchor_lang::event::EVENT_IX_TAG_LE
+        .iter()
+        .copied()
+        .chain(event.data())
+        .collect();
+    let ix = Instruction::new_with_bytes(
+        crate::ID,
+        &ix_data,
+        vec![AccountMeta::new_readonly(event_authority.key(), true)],
+    );
+
+    invoke_signed(
+        &ix,
+        &[event_authority.clone()],
+        &[&[
+            anchor_lang::event::EVENT_AUTHORITY_SEED,
+            &[event_authority_bump],
+        ]],
+    )
+    .map_err(Into::into)
+}
@@ -1,18 +1,26 @@
 use crate::state::{
-    LockingMode, RewardPerTimeUnitPoint, RewardScheduleCurve, RewardType, TimeUnit,
+    FarmConfigAuthorityGroup, FarmConfigValue, FarmHistory, FarmHistorySnapshot, LockTopUpPolicy,
+    LockingMode, ProgramInstruction, RaffleStatus, RewardConfigValue, RewardScheduleCurve,
+    RewardType, TimeUnit,
 };
 use crate::types::{
-    AddRewardEffects, HarvestEffects, StakeEffects, WithdrawEffects, WithdrawRewardEffects,
+    AddRewardEffects, FarmHistoryEntry, FarmHistoryPage, FarmStatus, FarmTvl, HarvestEffects,
+    HarvestEligibility, RewardFundingCoverage, RewardShortfall, RewardStatus, StakeEffects,
+    TvlPrice, UnstakeEffects, WithdrawEffects, WithdrawRewardEffects, FARM_HISTORY_VERSION,
+    FARM_STATUS_VERSION, FARM_TVL_VERSION, HARVEST_ELIGIBILITY_VERSION,
+};
+use crate::utils::consts::{
+    BPS_DIV_FACTOR, FARM_HISTORY_CAPACITY, MAX_FARM_HISTORY_PAGE_SIZE, MAX_UPGRADE_PAUSE_SLOTS,
 };
-use crate::utils::consts::BPS_DIV_FACTOR;
 use crate::utils::math::{ten_pow, u64_mul_div};
-use crate::xmsg;
 use crate::{
     dbg_msg, stake_operations as stake_ops, utils::consts::MAX_REWARDS_TOKENS, FarmConfigOption,
-    FarmError, FarmState, GlobalConfig, GlobalConfigOption, RewardInfo, UserState,
+    FarmError, FarmState, GlobalConfig, GlobalConfigOption, RaffleState, ReferrerState, RewardInfo,
+    TreasuryWithdrawalRequest, UserPage, UserState,
 };
+use crate::{vmsg, xmsg};
 use anchor_lang::prelude::*;
-use borsh::BorshDeserialize;
+use anchor_lang::solana_program::hash::hashv;
 use decimal_wad::decimal::Decimal;
 use num_enum::TryFromPrimitive;
 use std::cmp;
@@ -29,7 +37,7 @@ pub fn update_global_config(
         GlobalConfigOption::SetPendingGlobalAdmin => {
             let value: [u8; 32] = value[0..32].try_into().unwrap();
             let pubkey = Pubkey::new_from_array(value);
-            xmsg!(
+            vmsg!(
                 "Changing global_config admin {} -> {:?}",
                 global_config.global_admin,
                 pubkey
@@ -42,14 +50,484 @@ pub fn update_global_config(
                 xmsg!("ERROR: treasury_fee_bps must be <= 10000");
                 return Err(FarmError::InvalidConfigValue.into());
             }
-            xmsg!(
+            vmsg!(
                 "Changing global_config treasury_fee_bps {} -> {:?}",
                 global_config.treasury_fee_bps,
                 value
             );
             global_config.treasury_fee_bps = value;
         }
+        GlobalConfigOption::SetMaxFarms => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            vmsg!(
+                "Changing global_config max_farms {} -> {:?}",
+                global_config.max_farms,
+                value
+            );
+            global_config.max_farms = value;
+        }
+        GlobalConfigOption::SetEmergencyAuthority => {
+            let value: [u8; 32] = value[0..32].try_into().unwrap();
+            let pubkey = Pubkey::new_from_array(value);
+            vmsg!(
+                "Changing global_config emergency_authority {} -> {:?}",
+                global_config.emergency_authority,
+                pubkey
+            );
+            global_config.emergency_authority = pubkey;
+        }
+        GlobalConfigOption::AddAllowlistedMint => {
+            let value: [u8; 32] = value[0..32].try_into().unwrap();
+            let mint = Pubkey::new_from_array(value);
+            require_keys_neq!(mint, Pubkey::default(), FarmError::InvalidConfigValue);
+            if global_config.allowlisted_mints.contains(&mint) {
+                xmsg!("Mint {} is already allowlisted", mint);
+                return err!(FarmError::InvalidConfigValue);
+            }
+            let slot = global_config
+                .allowlisted_mints
+                .iter_mut()
+                .find(|m| **m == Pubkey::default())
+                .ok_or(FarmError::AllowlistedMintsFull)?;
+            vmsg!("Adding {} to global_config allowlisted_mints", mint);
+            *slot = mint;
+        }
+        GlobalConfigOption::RemoveAllowlistedMint => {
+            let value: [u8; 32] = value[0..32].try_into().unwrap();
+            let mint = Pubkey::new_from_array(value);
+            require_keys_neq!(mint, Pubkey::default(), FarmError::InvalidConfigValue);
+            let slot = global_config
+                .allowlisted_mints
+                .iter_mut()
+                .find(|m| **m == mint)
+                .ok_or(FarmError::InvalidConfigValue)?;
+            vmsg!("Removing {} from global_config allowlisted_mints", mint);
+            *slot = Pubkey::default();
+        }
+        GlobalConfigOption::AddAllowlistedTransferHookProgram => {
+            let value: [u8; 32] = value[0..32].try_into().unwrap();
+            let program = Pubkey::new_from_array(value);
+            require_keys_neq!(program, Pubkey::default(), FarmError::InvalidConfigValue);
+            if global_config
+                .allowlisted_transfer_hook_programs
+                .contains(&program)
+            {
+                xmsg!("Transfer hook program {} is already allowlisted", program);
+                return err!(FarmError::InvalidConfigValue);
+            }
+            let slot = global_config
+                .allowlisted_transfer_hook_programs
+                .iter_mut()
+                .find(|p| **p == Pubkey::default())
+                .ok_or(FarmError::AllowlistedTransferHookProgramsFull)?;
+            vmsg!(
+                "Adding {} to global_config allowlisted_transfer_hook_programs",
+                program
+            );
+            *slot = program;
+        }
+        GlobalConfigOption::RemoveAllowlistedTransferHookProgram => {
+            let value: [u8; 32] = value[0..32].try_into().unwrap();
+            let program = Pubkey::new_from_array(value);
+            require_keys_neq!(program, Pubkey::default(), FarmError::InvalidConfigValue);
+            let slot = global_config
+                .allowlisted_transfer_hook_programs
+                .iter_mut()
+                .find(|p| **p == program)
+                .ok_or(FarmError::InvalidConfigValue)?;
+            vmsg!(
+                "Removing {} from global_config allowlisted_transfer_hook_programs",
+                program
+            );
+            *slot = Pubkey::default();
+        }
+        GlobalConfigOption::SetTreasuryWithdrawalDelay => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            vmsg!(
+                "Changing global_config treasury_withdrawal_delay_s {} -> {:?}",
+                global_config.treasury_withdrawal_delay_s,
+                value
+            );
+            global_config.treasury_withdrawal_delay_s = value;
+        }
+        GlobalConfigOption::SetDefaultDepositWarmupPeriod => {
+            let value = u32::from_le_bytes(value[..4].try_into().unwrap());
+            vmsg!(
+                "Changing global_config default_deposit_warmup_period {} -> {:?}",
+                global_config.default_deposit_warmup_period,
+                value
+            );
+            global_config.default_deposit_warmup_period = value;
+        }
+        GlobalConfigOption::SetDefaultWithdrawalCooldownPeriod => {
+            let value = u32::from_le_bytes(value[..4].try_into().unwrap());
+            vmsg!(
+                "Changing global_config default_withdrawal_cooldown_period {} -> {:?}",
+                global_config.default_withdrawal_cooldown_period,
+                value
+            );
+            global_config.default_withdrawal_cooldown_period = value;
+        }
+        GlobalConfigOption::SetDefaultDepositCapAmount => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            vmsg!(
+                "Changing global_config default_deposit_cap_amount {} -> {:?}",
+                global_config.default_deposit_cap_amount,
+                value
+            );
+            global_config.default_deposit_cap_amount = value;
+        }
+        GlobalConfigOption::SetMinHarvestFeeBps => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            if value > 10_000 {
+                xmsg!("ERROR: min_harvest_fee_bps must be <= 10000");
+                return Err(FarmError::InvalidConfigValue.into());
+            }
+            if global_config.max_harvest_fee_bps != 0 && value > global_config.max_harvest_fee_bps {
+                xmsg!(
+                    "ERROR: min_harvest_fee_bps must be <= max_harvest_fee_bps {}",
+                    global_config.max_harvest_fee_bps
+                );
+                return Err(FarmError::InvalidConfigValue.into());
+            }
+            vmsg!(
+                "Changing global_config min_harvest_fee_bps {} -> {:?}",
+                global_config.min_harvest_fee_bps,
+                value
+            );
+            global_config.min_harvest_fee_bps = value;
+        }
+        GlobalConfigOption::SetMinRewardFundingCoverageBps => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            if value > 10_000 {
+                xmsg!("ERROR: min_reward_funding_coverage_bps must be <= 10000");
+                return Err(FarmError::InvalidConfigValue.into());
+            }
+            vmsg!(
+                "Changing global_config min_reward_funding_coverage_bps {} -> {:?}",
+                global_config.min_reward_funding_coverage_bps,
+                value
+            );
+            global_config.min_reward_funding_coverage_bps = value;
+        }
+        GlobalConfigOption::SetReferralFeeBps => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            if value > 10_000 {
+                xmsg!("ERROR: referral_fee_bps must be <= 10000");
+                return Err(FarmError::InvalidConfigValue.into());
+            }
+            vmsg!(
+                "Changing global_config referral_fee_bps {} -> {:?}",
+                global_config.referral_fee_bps,
+                value
+            );
+            global_config.referral_fee_bps = value;
+        }
+        GlobalConfigOption::SetMaxHarvestFeeBps => {
+            let value = u64::from_le_bytes(value[..8].try_into().unwrap());
+            if value > 10_000 {
+                xmsg!("ERROR: max_harvest_fee_bps must be <= 10000");
+                return Err(FarmError::InvalidConfigValue.into());
+            }
+            if value != 0 && value < global_config.min_harvest_fee_bps {
+                xmsg!(
+                    "ERROR: max_harvest_fee_bps must be >= min_harvest_fee_bps {}",
+                    global_config.min_harvest_fee_bps
+                );
+                return Err(FarmError::InvalidConfigValue.into());
+            }
+            vmsg!(
+                "Changing global_config max_harvest_fee_bps {} -> {:?}",
+                global_config.max_harvest_fee_bps,
+                value
+            );
+            global_config.max_harvest_fee_bps = value;
+        }
+    }
+    Ok(())
+}
+
+pub fn apply_global_farm_defaults(global_config: &GlobalConfig, farm_state: &mut FarmState) {
+    farm_state.deposit_warmup_period = global_config.default_deposit_warmup_period;
+    farm_state.withdrawal_cooldown_period = global_config.default_withdrawal_cooldown_period;
+    farm_state.deposit_cap_amount = global_config.default_deposit_cap_amount;
+}
+
+pub fn copy_farm_config(template: &FarmState, farm_state: &mut FarmState) {
+    farm_state.time_unit = template.time_unit;
+
+    farm_state.deposit_warmup_period = template.deposit_warmup_period;
+    farm_state.withdrawal_cooldown_period = template.withdrawal_cooldown_period;
+
+    farm_state.locking_mode = template.locking_mode;
+    farm_state.locking_start_timestamp = template.locking_start_timestamp;
+    farm_state.locking_duration = template.locking_duration;
+    farm_state.locking_early_withdrawal_penalty_bps = template.locking_early_withdrawal_penalty_bps;
+    farm_state.lock_top_up_policy = template.lock_top_up_policy;
+
+    farm_state.deposit_cap_amount = template.deposit_cap_amount;
+
+    farm_state.scope_prices = template.scope_prices;
+    farm_state.scope_oracle_price_id = template.scope_oracle_price_id;
+    farm_state.scope_oracle_max_age = template.scope_oracle_max_age;
+
+    farm_state.hook_program = template.hook_program;
+    farm_state.hook_config = template.hook_config;
+
+    farm_state.user_pages_enabled = template.user_pages_enabled;
+    farm_state.virtual_stake_offset = template.virtual_stake_offset;
+    farm_state.virtual_amount_offset = template.virtual_amount_offset;
+    farm_state.new_users_cutoff_ts = template.new_users_cutoff_ts;
+    farm_state.min_active_user_stake = template.min_active_user_stake;
+    farm_state.require_refresh_in_slot = template.require_refresh_in_slot;
+    farm_state.history_snapshot_interval = template.history_snapshot_interval;
+    farm_state.zero_stake_emissions_to_treasury = template.zero_stake_emissions_to_treasury;
+    farm_state.pause_flags = template.pause_flags;
+}
+
+/// Early withdrawal penalty of the positions opened under the current penalty terms
+fn early_withdrawal_penalty_bps(farm_state: &FarmState) -> u64 {
+    if farm_state.get_locking_mode() == LockingMode::None {
+        0
+    } else {
+        farm_state.locking_early_withdrawal_penalty_bps
+    }
+}
+
+/// Starts new penalty terms when the early withdrawal penalty went up from
+/// `prev_penalty_bps`, the positions opened before keep paying at most the lowest
+/// penalty they were under. Decreases apply to every position right away.
+fn update_penalty_terms(farm_state: &mut FarmState, prev_penalty_bps: u64) {
+    if early_withdrawal_penalty_bps(farm_state) <= prev_penalty_bps {
+        return;
+    }
+
+    farm_state.grandfathered_penalty_bps = if farm_state.penalty_terms_version == 0 {
+        prev_penalty_bps
+    } else {
+        cmp::min(farm_state.grandfathered_penalty_bps, prev_penalty_bps)
+    };
+    farm_state.penalty_terms_version += 1;
+
+    vmsg!(
+        "farm_operations::update_penalty_terms penalty_terms_version={} grandfathered_penalty_bps={}",
+        farm_state.penalty_terms_version,
+        farm_state.grandfathered_penalty_bps
+    );
+}
+
+pub fn check_farm_config_authority(
+    farm_state: &FarmState,
+    mode: FarmConfigOption,
+    signer: &Pubkey,
+) -> Result<()> {
+    if *signer == farm_state.farm_admin {
+        return Ok(());
+    }
+
+    let Some(group) = mode.authority_group() else {
+        xmsg!("update_farm_config mode {:?} requires the farm admin", mode);
+        return err!(FarmError::FarmAdminAuthorityRequired);
+    };
+
+    let authority = farm_state.config_authorities[group as usize];
+    if authority == Pubkey::default() || authority != *signer {
+        xmsg!(
+            "update_farm_config mode {:?} requires the farm admin or the {:?} authority {}",
+            mode,
+            group,
+            authority
+        );
+        return match group {
+            FarmConfigAuthorityGroup::Emissions => err!(FarmError::EmissionsAuthorityRequired),
+            FarmConfigAuthorityGroup::Risk => err!(FarmError::RiskAuthorityRequired),
+            FarmConfigAuthorityGroup::Metadata => err!(FarmError::MetadataAuthorityRequired),
+        };
+    }
+
+    Ok(())
+}
+
+pub fn update_farm_pause_flags(
+    global_config: &GlobalConfig,
+    farm_state: &mut FarmState,
+    authority: &Pubkey,
+    pause_flags: u8,
+) -> Result<()> {
+    if *authority == global_config.global_admin {
+        vmsg!(
+            "farm_operations::update_farm_pause_flags global admin {} -> {}",
+            farm_state.pause_flags,
+            pause_flags
+        );
+    } else if global_config.emergency_authority != Pubkey::default()
+        && *authority == global_config.emergency_authority
+    {
+        if pause_flags & farm_state.pause_flags != farm_state.pause_flags {
+            xmsg!(
+                "Emergency authority can only add pause flags, current={} requested={}",
+                farm_state.pause_flags,
+                pause_flags
+            );
+            return err!(FarmError::EmergencyAuthorityCannotUnpause);
+        }
+        vmsg!(
+            "farm_operations::update_farm_pause_flags emergency authority {} -> {}",
+            farm_state.pause_flags,
+            pause_flags
+        );
+    } else {
+        return err!(FarmError::InvalidAdminAuthority);
+    }
+
+    farm_state.pause_flags = pause_flags;
+
+    Ok(())
+}
+
+/// `authority` is either the global admin, who can set any instructions, or the
+/// emergency authority, who can only disable instructions other than the exits.
+pub fn update_disabled_instructions(
+    global_config: &mut GlobalConfig,
+    authority: &Pubkey,
+    disabled_instructions: u128,
+) -> Result<()> {
+    for bit in 0..u128::BITS {
+        if disabled_instructions & (1 << bit) == 0 {
+            continue;
+        }
+        let instruction = u8::try_from(bit)
+            .ok()
+            .and_then(|bit| ProgramInstruction::try_from_primitive(bit).ok());
+        match instruction {
+            Some(instruction) if instruction.can_be_disabled() => {}
+            _ => {
+                xmsg!("Instruction bit {} cannot be disabled", bit);
+                return err!(FarmError::InstructionCannotBeDisabled);
+            }
+        }
+    }
+
+    let previous = global_config.disabled_instructions;
+    if *authority == global_config.global_admin {
+        vmsg!(
+            "farm_operations::update_disabled_instructions global admin {:#x} -> {:#x}",
+            previous,
+            disabled_instructions
+        );
+    } else if global_config.emergency_authority != Pubkey::default()
+        && *authority == global_config.emergency_authority
+    {
+        if disabled_instructions & previous != previous {
+            xmsg!(
+                "Emergency authority can only disable instructions, current={:#x} requested={:#x}",
+                previous,
+                disabled_instructions
+            );
+            return err!(FarmError::EmergencyAuthorityCannotUnpause);
+        }
+        let added = disabled_instructions & !previous;
+        for bit in 0..u128::BITS {
+            if added & (1 << bit) == 0 {
+                continue;
+            }
+            let instruction = ProgramInstruction::try_from_primitive(bit as u8).unwrap();
+            if instruction.is_exit() {
+                xmsg!("Only the global admin can disable {:?}", instruction);
+                return err!(FarmError::GlobalAdminRequiredForInstruction);
+            }
+        }
+        vmsg!(
+            "farm_operations::update_disabled_instructions emergency authority {:#x} -> {:#x}",
+            previous,
+            disabled_instructions
+        );
+    } else {
+        return err!(FarmError::InvalidAdminAuthority);
+    }
+
+    global_config.disabled_instructions = disabled_instructions;
+
+    Ok(())
+}
+
+pub fn queue_treasury_withdrawal(
+    global_config: &mut GlobalConfig,
+    request: &mut TreasuryWithdrawalRequest,
+    global_config_key: &Pubkey,
+    mint: &Pubkey,
+    destination: &Pubkey,
+    amount: u64,
+    unix_ts: u64,
+) -> Result<()> {
+    require!(amount > 0, FarmError::NothingToWithdraw);
+
+    request.global_config = *global_config_key;
+    request.request_id = global_config.num_treasury_withdrawal_requests;
+    request.mint = *mint;
+    request.destination = *destination;
+    request.amount = amount;
+    request.executable_ts = unix_ts
+        .checked_add(global_config.treasury_withdrawal_delay_s)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    global_config.num_treasury_withdrawal_requests = global_config
+        .num_treasury_withdrawal_requests
+        .checked_add(1)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    vmsg!(
+        "farm_operations::queue_treasury_withdrawal request_id={} amount={} executable_ts={}",
+        request.request_id,
+        amount,
+        request.executable_ts
+    );
+
+    Ok(())
+}
+
+/// A freeze authority can freeze the farm vault, SPL Token and Token-2022 mints alike.
+/// The Token-2022 extensions letting a third party close the mint or move the vault
+/// balances are checked by `token_operations::check_mint_extensions`.
+pub fn check_farm_token_mint(
+    global_config: &GlobalConfig,
+    mint: &Pubkey,
+    freeze_authority: Option<Pubkey>,
+) -> Result<()> {
+    if let Some(freeze_authority) = freeze_authority {
+        if !global_config.allowlisted_mints.contains(mint) {
+            xmsg!(
+                "Farm token mint {} has freeze authority {} and is not allowlisted",
+                mint,
+                freeze_authority
+            );
+            return err!(FarmError::FarmTokenMintHasFreezeAuthority);
+        }
+        vmsg!(
+            "Farm token mint {} has freeze authority {}, allowlisted",
+            mint,
+            freeze_authority
+        );
+    }
+
+    Ok(())
+}
+
+pub fn register_new_farm(global_config: &mut GlobalConfig) -> Result<()> {
+    if global_config.max_farms > 0 && global_config.num_farms >= global_config.max_farms {
+        xmsg!(
+            "Cannot create farm, num_farms={} max_farms={}",
+            global_config.num_farms,
+            global_config.max_farms
+        );
+        return err!(FarmError::MaxFarmsReached);
     }
+
+    global_config.num_farms = global_config
+        .num_farms
+        .checked_add(1)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
     Ok(())
 }
 
@@ -58,11 +536,16 @@ pub fn initialize_reward(
     reward_vault: Pubkey,
     mint: Pubkey,
     mint_decimals: u8,
+    token_program: Pubkey,
     ts: u64,
 ) -> Result<()> {
     if farm_state.num_reward_tokens == MAX_REWARDS_TOKENS as u64 {
         return Err(FarmError::MaxRewardNumberReached.into());
     }
+    require!(
+        farm_state.can_initialize_reward_mint(&mint),
+        FarmError::RewardAlreadyInitialized
+    );
 
     let reward_info = &mut farm_state.reward_infos[farm_state.num_reward_tokens as usize];
 
@@ -71,6 +554,7 @@ pub fn initialize_reward(
 
     reward_info.token.mint = mint;
     reward_info.token.decimals = mint_decimals as u64;
+    reward_info.token.token_program = token_program;
 
     reward_info.last_issuance_ts = ts;
 
@@ -82,27 +566,51 @@ pub fn initialize_reward(
     Ok(())
 }
 
+/// `rewards_vault_amount` is the balance of the rewards vault before `amount` is added.
 pub fn add_reward(
     farm_state: &mut FarmState,
     scope_price: Option<DatedPrice>,
     mint: Pubkey,
     reward_index: usize,
     amount: u64,
+    rewards_vault_amount: u64,
     ts: u64,
 ) -> Result<AddRewardEffects> {
-    xmsg!("farm_operations::add_reward amount={}", amount);
+    vmsg!("farm_operations::add_reward amount={}", amount);
     refresh_global_rewards(farm_state, scope_price, ts)?;
 
     let reward = &mut farm_state.reward_infos[reward_index];
     require!(reward.token.mint == mint, FarmError::RewardDoesNotExist);
 
+    let mut amount_available = amount;
+    if reward.shortfall != 0 {
+        let deficit = reward_shortfall(reward, rewards_vault_amount)?.deficit;
+        let covered = cmp::min(deficit, amount);
+        amount_available -= covered;
+        if covered == deficit {
+            reward.shortfall = 0;
+        }
+        vmsg!(
+            "farm_operations::add_reward shortfall deficit={} covered={} cleared={}",
+            deficit,
+            covered,
+            reward.shortfall == 0
+        );
+    }
+
     reward.rewards_available = reward
         .rewards_available
-        .checked_add(amount)
+        .checked_add(amount_available)
         .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
 
+    let projected_depletion_ts =
+        refresh_projected_depletion_ts(farm_state, reward_index, scope_price, ts)?;
+    let funding_coverage = reward_funding_coverage(farm_state, reward_index, scope_price, ts)?;
+
     Ok(AddRewardEffects {
         reward_amount: amount,
+        projected_depletion_ts,
+        funding_coverage,
     })
 }
 
@@ -114,7 +622,7 @@ pub fn withdraw_reward(
     amount: u64,
     ts: u64,
 ) -> Result<WithdrawRewardEffects> {
-    xmsg!("farm_operations::withdraw_reward amount={}", amount);
+    vmsg!("farm_operations::withdraw_reward amount={}", amount);
     require!(amount > 0, FarmError::RewardDoesNotExist);
     refresh_global_rewards(farm_state, scope_price, ts)?;
 
@@ -133,29 +641,32 @@ pub fn withdraw_reward(
 
     reward.rewards_available -= max_withdrawable;
 
+    let projected_depletion_ts =
+        refresh_projected_depletion_ts(farm_state, reward_index, scope_price, ts)?;
+
     Ok(WithdrawRewardEffects {
         reward_amount: max_withdrawable,
+        projected_depletion_ts,
     })
 }
 
+/// Returns the index of the reward whose configuration changed, if any.
+///
+/// `global_admin_signed` unlocks the changes that could hurt users with rewards already
+/// accrued, such as delaying `claims_open_ts`.
 pub fn update_farm_config(
     farm_state: &mut FarmState,
     scope_price: Option<DatedPrice>,
-    mode: FarmConfigOption,
-    data: &[u8],
-) -> Result<()> {
-    xmsg!(
-        "farm_operations::update_farm_config mode={:?} with data of len {}",
-        mode,
-        data.len()
-    );
-    match mode {
-        FarmConfigOption::UpdateRewardRps
-        | FarmConfigOption::UpdateRewardMinClaimDuration
-        | FarmConfigOption::RewardType
-        | FarmConfigOption::RpsDecimals
-        | FarmConfigOption::UpdateRewardScheduleCurvePoints => {
-            let reward_index: u64 = BorshDeserialize::try_from_slice(&data[..8])?;
+    value: FarmConfigValue,
+    global_config: &GlobalConfig,
+    global_admin_signed: bool,
+) -> Result<Option<usize>> {
+    vmsg!("farm_operations::update_farm_config value={:?}", value);
+    match value {
+        FarmConfigValue::Reward {
+            reward_index,
+            value,
+        } => {
             require!(
                 reward_index < farm_state.num_reward_tokens,
                 FarmError::RewardIndexOutOfRange
@@ -172,164 +683,532 @@ pub fn update_farm_config(
             let reward_info = &mut farm_state.reward_infos[reward_index as usize];
 
             require!(reward_info.is_initialised(), FarmError::NoRewardInList);
-            xmsg!("Updating reward index={}", reward_index);
+            vmsg!("Updating reward index={}", reward_index);
             update_reward_config(
                 reward_info,
-                mode,
-                &data[8..],
+                value,
+                TimeUnit::now_from_clock(time_unit, &Clock::get()?),
+                global_admin_signed,
+            )?;
+
+            refresh_projected_depletion_ts(
+                farm_state,
+                reward_index as usize,
+                scope_price,
                 TimeUnit::now_from_clock(time_unit, &Clock::get()?),
             )?;
+
+            return Ok(Some(reward_index as usize));
         }
-        FarmConfigOption::WithdrawAuthority => {
-            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config withdraw_authority={pubkey}",);
-            xmsg!("prev value {:?}", farm_state.withdraw_authority);
+        FarmConfigValue::WithdrawAuthority(pubkey) => {
+            vmsg!("farm_operations::update_farm_config withdraw_authority={pubkey}",);
+            vmsg!("prev value {:?}", farm_state.withdraw_authority);
             farm_state.withdraw_authority = pubkey;
         }
-        FarmConfigOption::DepositWarmupPeriod => {
+        FarmConfigValue::DepositWarmupPeriod(value) => {
             if farm_state.is_delegated() {
                 xmsg!("farm_operations::update_farm_config ERROR: delegated farm cannot change deposit_warmup_period");
                 return err!(FarmError::FarmDelegated);
             }
-            let value: u32 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config deposit_warmup_period={value}",);
-            xmsg!("prev value {:?}", farm_state.deposit_warmup_period);
+            vmsg!("farm_operations::update_farm_config deposit_warmup_period={value}",);
+            vmsg!("prev value {:?}", farm_state.deposit_warmup_period);
             farm_state.deposit_warmup_period = value;
         }
-        FarmConfigOption::WithdrawCooldownPeriod => {
+        FarmConfigValue::WithdrawCooldownPeriod(value) => {
             if farm_state.is_delegated() {
                 xmsg!("farm_operations::update_farm_config ERROR: delegated farm cannot change withdrawal_cooldown_period");
                 return err!(FarmError::FarmDelegated);
             }
-            let value: u32 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config withdrawal_cooldown_period={value}",);
-            xmsg!("prev value {:?}", farm_state.withdrawal_cooldown_period);
+            vmsg!("farm_operations::update_farm_config withdrawal_cooldown_period={value}",);
+            vmsg!("prev value {:?}", farm_state.withdrawal_cooldown_period);
             farm_state.withdrawal_cooldown_period = value;
         }
-        FarmConfigOption::LockingMode => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config locking_mode={value}",);
-            xmsg!("prev value {:?}", farm_state.locking_mode);
+        FarmConfigValue::LockingMode(value) => {
+            vmsg!("farm_operations::update_farm_config locking_mode={value}",);
+            vmsg!("prev value {:?}", farm_state.locking_mode);
+            LockingMode::try_from_primitive(value)
+                .map_err(|_| error!(FarmError::InvalidConfigValue))?;
+            let prev_penalty_bps = early_withdrawal_penalty_bps(farm_state);
             farm_state.locking_mode = value;
-            LockingMode::try_from_primitive(value).unwrap();
+            update_penalty_terms(farm_state, prev_penalty_bps);
+        }
+        FarmConfigValue::UpdateLockTopUpPolicy(value) => {
+            LockTopUpPolicy::try_from_primitive(value)
+                .map_err(|_| error!(FarmError::InvalidConfigValue))?;
+            vmsg!("farm_operations::update_farm_config lock_top_up_policy={value}",);
+            vmsg!("prev value {:?}", farm_state.lock_top_up_policy);
+            farm_state.lock_top_up_policy = value;
         }
-        FarmConfigOption::LockingStartTimestamp => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config locking_start_timestamp={value}",);
-            xmsg!("prev value {:?}", farm_state.locking_start_timestamp);
+        FarmConfigValue::UpdateHarvestFeeBpsOverride(value) => {
+            if let Some(bps) = value {
+                if global_config.max_harvest_fee_bps == 0
+                    || bps < global_config.min_harvest_fee_bps
+                    || bps > global_config.max_harvest_fee_bps
+                {
+                    xmsg!(
+                        "Harvest fee override {} outside of [{}, {}]",
+                        bps,
+                        global_config.min_harvest_fee_bps,
+                        global_config.max_harvest_fee_bps
+                    );
+                    return err!(FarmError::InvalidConfigValue);
+                }
+            }
+            vmsg!("farm_operations::update_farm_config harvest_fee_bps_override={value:?}",);
+            vmsg!(
+                "prev value {} {}",
+                farm_state.harvest_fee_override_enabled,
+                farm_state.harvest_fee_bps_override
+            );
+            farm_state.harvest_fee_override_enabled = value.is_some() as u64;
+            farm_state.harvest_fee_bps_override = value.unwrap_or(0);
+        }
+        FarmConfigValue::LockingStartTimestamp(value) => {
+            vmsg!("farm_operations::update_farm_config locking_start_timestamp={value}",);
+            vmsg!("prev value {:?}", farm_state.locking_start_timestamp);
             farm_state.locking_start_timestamp = value;
         }
-        FarmConfigOption::LockingEarlyWithdrawalPenaltyBps => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
+        FarmConfigValue::LockingEarlyWithdrawalPenaltyBps(value) => {
             require_gte!(10000, value, FarmError::InvalidConfigValue);
-            xmsg!(
+            vmsg!(
                 "farm_operations::update_farm_config locking_early_withdrawal_penalty_bps={value}",
             );
-            xmsg!(
+            vmsg!(
                 "prev value {:?}",
                 farm_state.locking_early_withdrawal_penalty_bps
             );
+            let prev_penalty_bps = early_withdrawal_penalty_bps(farm_state);
             farm_state.locking_early_withdrawal_penalty_bps = value;
+            update_penalty_terms(farm_state, prev_penalty_bps);
         }
-        FarmConfigOption::LockingDuration => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config locking_duration={value}",);
-            xmsg!("prev value {:?}", farm_state.locking_duration);
+        FarmConfigValue::LockingDuration(value) => {
+            vmsg!("farm_operations::update_farm_config locking_duration={value}",);
+            vmsg!("prev value {:?}", farm_state.locking_duration);
             farm_state.locking_duration = value;
         }
-        FarmConfigOption::DepositCapAmount => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config deposit_cap_amount={value}",);
-            xmsg!("prev value {:?}", farm_state.deposit_cap_amount);
+        FarmConfigValue::DepositCapAmount(value) => {
+            vmsg!("farm_operations::update_farm_config deposit_cap_amount={value}",);
+            vmsg!("prev value {:?}", farm_state.deposit_cap_amount);
             farm_state.deposit_cap_amount = value;
         }
-        FarmConfigOption::SlashedAmountSpillAddress => {
-            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config slashed_amount_spill_address={pubkey}",);
-            xmsg!("prev value {:?}", farm_state.slashed_amount_spill_address);
+        FarmConfigValue::SlashedAmountSpillAddress(pubkey) => {
+            vmsg!("farm_operations::update_farm_config slashed_amount_spill_address={pubkey}",);
+            vmsg!("prev value {:?}", farm_state.slashed_amount_spill_address);
             farm_state.slashed_amount_spill_address = pubkey;
         }
-        FarmConfigOption::ScopePricesAccount => {
-            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config scope_prices_account={pubkey}",);
-            xmsg!("prev value {:?}", farm_state.scope_prices);
+        FarmConfigValue::ScopePricesAccount(pubkey) => {
+            vmsg!("farm_operations::update_farm_config scope_prices_account={pubkey}",);
+            vmsg!("prev value {:?}", farm_state.scope_prices);
             farm_state.scope_prices = pubkey;
         }
-        FarmConfigOption::ScopeOraclePriceId => {
-            let value: u16 = BorshDeserialize::try_from_slice(&data[..2])?;
-            xmsg!("farm_operations::update_farm_config scope_oracle_price_id={value}",);
-            xmsg!("prev value {:?}", farm_state.scope_oracle_price_id);
+        FarmConfigValue::ScopeOraclePriceId(value) => {
+            vmsg!("farm_operations::update_farm_config scope_oracle_price_id={value}",);
+            vmsg!("prev value {:?}", farm_state.scope_oracle_price_id);
             farm_state.scope_oracle_price_id = value.into();
         }
-        FarmConfigOption::ScopeOracleMaxAge => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config scope_oracle_max_age={value}",);
-            xmsg!("prev value {:?}", farm_state.scope_oracle_max_age);
+        FarmConfigValue::ScopeOracleMaxAge(value) => {
+            vmsg!("farm_operations::update_farm_config scope_oracle_max_age={value}",);
+            vmsg!("prev value {:?}", farm_state.scope_oracle_max_age);
             farm_state.scope_oracle_max_age = value;
         }
-        FarmConfigOption::UpdatePendingFarmAdmin => {
-            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config farm_admin={pubkey}",);
-            xmsg!("prev value {:?}", farm_state.pending_farm_admin);
+        FarmConfigValue::UpdatePendingFarmAdmin(pubkey) => {
+            vmsg!("farm_operations::update_farm_config farm_admin={pubkey}",);
+            vmsg!("prev value {:?}", farm_state.pending_farm_admin);
             farm_state.pending_farm_admin = pubkey;
         }
-        FarmConfigOption::UpdateStrategyId => {
-            let pubkey: Pubkey = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config strategy_id={pubkey}",);
-            xmsg!("prev value {:?}", farm_state.strategy_id);
+        FarmConfigValue::UpdateStrategyId(pubkey) => {
+            vmsg!("farm_operations::update_farm_config strategy_id={pubkey}",);
+            vmsg!("prev value {:?}", farm_state.strategy_id);
             farm_state.strategy_id = pubkey;
         }
+        FarmConfigValue::UpdateHookProgram(pubkey) => {
+            vmsg!("farm_operations::update_farm_config hook_program={pubkey}",);
+            vmsg!("prev value {:?}", farm_state.hook_program);
+            farm_state.hook_program = pubkey;
+        }
+        FarmConfigValue::UpdateHookConfig(pubkey) => {
+            vmsg!("farm_operations::update_farm_config hook_config={pubkey}",);
+            vmsg!("prev value {:?}", farm_state.hook_config);
+            farm_state.hook_config = pubkey;
+        }
+        FarmConfigValue::UpdateVirtualOffsets {
+            virtual_stake_offset,
+            virtual_amount_offset,
+        } => {
+            require!(
+                !farm_state.is_delegated()
+                    || (virtual_stake_offset == 0 && virtual_amount_offset == 0),
+                FarmError::FarmDelegated
+            );
+            require!(
+                (virtual_stake_offset == 0) == (virtual_amount_offset == 0),
+                FarmError::InvalidConfigValue
+            );
+            if farm_state.total_active_stake_scaled != 0
+                || farm_state.total_pending_stake_scaled != 0
+                || farm_state.total_staked_amount != 0
+            {
+                xmsg!("Virtual offsets can only be changed while the farm has no stake");
+                return err!(FarmError::InvalidConfigValue);
+            }
+            vmsg!(
+                "farm_operations::update_farm_config virtual_stake_offset={virtual_stake_offset} virtual_amount_offset={virtual_amount_offset}",
+            );
+            vmsg!(
+                "prev value {:?} {:?}",
+                farm_state.virtual_stake_offset,
+                farm_state.virtual_amount_offset
+            );
+            farm_state.virtual_stake_offset = virtual_stake_offset;
+            farm_state.virtual_amount_offset = virtual_amount_offset;
+        }
+        FarmConfigValue::UpdateAdminWithdrawalLimit {
+            max_per_window,
+            window_s,
+        } => {
+            require!(
+                (max_per_window == 0) == (window_s == 0),
+                FarmError::InvalidConfigValue
+            );
+            // Loosening an existing limit would let a compromised admin key drain the vault
+            // in two steps, past that point only global admin co-signed withdrawals bypass it
+            if farm_state.max_admin_withdrawal_per_window > 0
+                && (max_per_window == 0
+                    || max_per_window > farm_state.max_admin_withdrawal_per_window
+                    || window_s < farm_state.admin_withdrawal_window_s)
+            {
+                xmsg!("Admin withdrawal limit can only be tightened once set");
+                return err!(FarmError::InvalidConfigValue);
+            }
+            vmsg!(
+                "farm_operations::update_farm_config max_admin_withdrawal_per_window={max_per_window} admin_withdrawal_window_s={window_s}",
+            );
+            vmsg!(
+                "prev value {:?} {:?}",
+                farm_state.max_admin_withdrawal_per_window,
+                farm_state.admin_withdrawal_window_s
+            );
+            farm_state.max_admin_withdrawal_per_window = max_per_window;
+            farm_state.admin_withdrawal_window_s = window_s;
+        }
+        FarmConfigValue::UpdateUserWithdrawalLimit {
+            max_per_window,
+            window_s,
+        } => {
+            require!(
+                (max_per_window == 0) == (window_s == 0),
+                FarmError::InvalidConfigValue
+            );
+            vmsg!(
+                "farm_operations::update_farm_config max_user_withdrawal_per_window={max_per_window} user_withdrawal_window_s={window_s}",
+            );
+            vmsg!(
+                "prev value {:?} {:?}",
+                farm_state.max_user_withdrawal_per_window,
+                farm_state.user_withdrawal_window_s
+            );
+            farm_state.max_user_withdrawal_per_window = max_per_window;
+            farm_state.user_withdrawal_window_s = window_s;
+        }
+        FarmConfigValue::AddDelegateAuthority(authority) => {
+            require!(farm_state.is_delegated(), FarmError::FarmNotDelegated);
+            require_keys_neq!(authority, Pubkey::default(), FarmError::InvalidConfigValue);
+            if farm_state.delegate_authority_index(&authority).is_some() {
+                xmsg!("Delegate authority {} is already allowed", authority);
+                return err!(FarmError::InvalidConfigValue);
+            }
+            let i = farm_state
+                .additional_delegate_authorities
+                .iter()
+                .position(|a| *a == Pubkey::default())
+                .ok_or(FarmError::DelegateAuthoritiesFull)?;
+            vmsg!("farm_operations::update_farm_config add delegate authority {authority}",);
+            farm_state.additional_delegate_authorities[i] = authority;
+            farm_state.additional_delegate_authorities_stake[i] = 0;
+        }
+        FarmConfigValue::RemoveDelegateAuthority(authority) => {
+            require_keys_neq!(authority, Pubkey::default(), FarmError::InvalidConfigValue);
+            let i = farm_state
+                .additional_delegate_authorities
+                .iter()
+                .position(|a| *a == authority)
+                .ok_or(FarmError::InvalidConfigValue)?;
+            let attributed_stake = farm_state.additional_delegate_authorities_stake[i];
+            if attributed_stake > 0 {
+                xmsg!(
+                    "Delegate authority {} still has {} stake attributed",
+                    authority,
+                    attributed_stake
+                );
+                return err!(FarmError::DelegateAuthorityHasAttributedStake);
+            }
+            vmsg!("farm_operations::update_farm_config remove delegate authority {authority}",);
+            farm_state.additional_delegate_authorities[i] = Pubkey::default();
+        }
+        FarmConfigValue::UpdateAllowDuplicateRewardMints(value) => {
+            require_gte!(1, value, FarmError::InvalidConfigValue);
+            vmsg!("farm_operations::update_farm_config allow_duplicate_reward_mints={value}",);
+            vmsg!("prev value {:?}", farm_state.allow_duplicate_reward_mints);
+            farm_state.allow_duplicate_reward_mints = value;
+        }
+        FarmConfigValue::UpdatePrimaryFarm {
+            primary_farm,
+            max_staleness,
+        } => {
+            require!(farm_state.is_delegated(), FarmError::FarmNotDelegated);
+            if farm_state.num_users > 0 {
+                xmsg!("Primary farm can only be set before the first user is created");
+                return err!(FarmError::InvalidConfigValue);
+            }
+            vmsg!(
+                "farm_operations::update_farm_config primary_farm={} mirror_max_staleness={}",
+                primary_farm,
+                max_staleness
+            );
+            vmsg!(
+                "prev value {:?} {:?}",
+                farm_state.primary_farm,
+                farm_state.mirror_max_staleness
+            );
+            farm_state.primary_farm = primary_farm;
+            farm_state.mirror_max_staleness = max_staleness;
+        }
+        FarmConfigValue::UpdateConfigAuthority { group, authority } => {
+            vmsg!(
+                "farm_operations::update_farm_config config_authority group={:?} authority={}",
+                group,
+                authority
+            );
+            vmsg!(
+                "prev value {:?}",
+                farm_state.config_authorities[group as usize]
+            );
+            farm_state.config_authorities[group as usize] = authority;
+        }
+        FarmConfigValue::UpdateNewUsersCutoffTs(value) => {
+            vmsg!("farm_operations::update_farm_config new_users_cutoff_ts={value}",);
+            vmsg!("prev value {:?}", farm_state.new_users_cutoff_ts);
+            farm_state.new_users_cutoff_ts = value;
+        }
+        FarmConfigValue::UpdateRequireRefreshInSlot(value) => {
+            require_gte!(1, value, FarmError::InvalidConfigValue);
+            vmsg!("farm_operations::update_farm_config require_refresh_in_slot={value}",);
+            vmsg!("prev value {:?}", farm_state.require_refresh_in_slot);
+            farm_state.require_refresh_in_slot = value;
+        }
+        FarmConfigValue::UpdateHistorySnapshotInterval(value) => {
+            vmsg!("farm_operations::update_farm_config history_snapshot_interval={value}",);
+            vmsg!("prev value {:?}", farm_state.history_snapshot_interval);
+            farm_state.history_snapshot_interval = value;
+        }
+        FarmConfigValue::UpdateZeroStakeEmissionsToTreasury(value) => {
+            require_gte!(1, value, FarmError::InvalidConfigValue);
+            vmsg!("farm_operations::update_farm_config zero_stake_emissions_to_treasury={value}",);
+            vmsg!(
+                "prev value {:?}",
+                farm_state.zero_stake_emissions_to_treasury
+            );
+            farm_state.zero_stake_emissions_to_treasury = value;
+        }
+        FarmConfigValue::UpdateMinActiveUserStake(value) => {
+            vmsg!("farm_operations::update_farm_config min_active_user_stake={value}",);
+            vmsg!("prev value {:?}", farm_state.min_active_user_stake);
+            farm_state.min_active_user_stake = value;
+        }
+        FarmConfigValue::UpdateUserPagesEnabled(value) => {
+            require_gte!(1, value, FarmError::InvalidConfigValue);
+            // Pages are indexed by user_id, enabling them late would leave the
+            // pages of the existing users empty
+            if value == 1 && farm_state.user_pages_enabled == 0 && farm_state.num_users > 0 {
+                xmsg!("User pages can only be enabled before the first user is created");
+                return err!(FarmError::InvalidConfigValue);
+            }
+            vmsg!("farm_operations::update_farm_config user_pages_enabled={value}",);
+            vmsg!("prev value {:?}", farm_state.user_pages_enabled);
+            farm_state.user_pages_enabled = value;
+        }
     };
-    Ok(())
+    Ok(None)
 }
 
 pub(crate) fn update_reward_config(
     reward_info: &mut RewardInfo,
-    mode: FarmConfigOption,
-    data: &[u8],
+    value: RewardConfigValue,
     ts: u64,
+    global_admin_signed: bool,
 ) -> Result<()> {
-    match mode {
-        FarmConfigOption::UpdateRewardRps => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config reward_rps={value} last_issuance_ts={ts}",);
-            xmsg!("prev value {:?}", reward_info.reward_schedule_curve);
+    match value {
+        RewardConfigValue::UpdateRewardClaimsOpenTs(value) => {
+            if value > reward_info.claims_open_ts && !global_admin_signed {
+                xmsg!(
+                    "Delaying claims_open_ts {} -> {} requires the global admin",
+                    reward_info.claims_open_ts,
+                    value
+                );
+                return err!(FarmError::InvalidAdminAuthority);
+            }
+            vmsg!("farm_operations::update_farm_config claims_open_ts={value}",);
+            vmsg!("prev value {}", reward_info.claims_open_ts);
+            reward_info.claims_open_ts = value;
+        }
+        RewardConfigValue::UpdateRewardSplit { destination, bps } => {
+            require_gte!(
+                BPS_DIV_FACTOR,
+                u64::from(bps),
+                FarmError::InvalidConfigValue
+            );
+            vmsg!("farm_operations::update_farm_config split_destination={destination} split_bps={bps}",);
+            vmsg!(
+                "prev value {} {}",
+                reward_info.split_destination,
+                reward_info.split_bps
+            );
+            reward_info.split_destination = destination;
+            reward_info.split_bps = bps;
+        }
+        RewardConfigValue::UpdateRewardRps(value) => {
+            vmsg!("farm_operations::update_farm_config reward_rps={value} last_issuance_ts={ts}",);
+            vmsg!("prev value {:?}", reward_info.reward_schedule_curve);
             reward_info.reward_schedule_curve.set_constant(value);
             reward_info.last_issuance_ts = ts;
         }
-        FarmConfigOption::UpdateRewardMinClaimDuration => {
-            let value: u64 = BorshDeserialize::try_from_slice(data)?;
-            xmsg!("farm_operations::update_farm_config reward_min_claim_duration={value}",);
-            xmsg!("prev value {}", reward_info.min_claim_duration_seconds);
+        RewardConfigValue::UpdateRewardEarlyClaimPenaltyBps(value) => {
+            require_gte!(BPS_DIV_FACTOR, value, FarmError::InvalidConfigValue);
+            vmsg!("farm_operations::update_farm_config early_claim_penalty_bps={value}",);
+            vmsg!("prev value {}", reward_info.early_claim_penalty_bps);
+            reward_info.early_claim_penalty_bps = value;
+        }
+        RewardConfigValue::UpdateRewardPenaltyDecayWindow(value) => {
+            vmsg!("farm_operations::update_farm_config penalty_decay_window_s={value}",);
+            vmsg!("prev value {}", reward_info.penalty_decay_window_s);
+            reward_info.penalty_decay_window_s = value;
+        }
+        RewardConfigValue::UpdateRewardMinClaimDuration(value) => {
+            vmsg!("farm_operations::update_farm_config reward_min_claim_duration={value}",);
+            vmsg!("prev value {}", reward_info.min_claim_duration_seconds);
             reward_info.min_claim_duration_seconds = value
         }
-        FarmConfigOption::RewardType => {
-            let value: u8 = BorshDeserialize::try_from_slice(&data[..1])?;
-            xmsg!(
-                "farm_operations::update_farm_config reward_type={value} type={:?}",
-                RewardType::try_from_primitive(value).unwrap()
-            );
-            xmsg!("prev value {:?}", reward_info.reward_type);
+        RewardConfigValue::RewardType(value) => {
+            let reward_type = RewardType::try_from_primitive(value)
+                .map_err(|_| error!(FarmError::InvalidConfigValue))?;
+            // reward_per_share is per active user for ConstantPerUser, the user tallies
+            // only carry over while it is still zero
+            let switches_per_user = (reward_type == RewardType::ConstantPerUser)
+                != (reward_info.reward_type() == RewardType::ConstantPerUser);
+            if switches_per_user && reward_info.reward_per_share_scaled != 0 {
+                xmsg!("ConstantPerUser can only be set or unset before the reward issued anything");
+                return err!(FarmError::InvalidConfigValue);
+            }
+            vmsg!("farm_operations::update_farm_config reward_type={value} type={reward_type:?}",);
+            vmsg!("prev value {:?}", reward_info.reward_type);
             reward_info.reward_type = value;
+            reward_info.last_issuance_ts = ts;
         }
-        FarmConfigOption::RpsDecimals => {
-            let value: u8 = BorshDeserialize::try_from_slice(&data[..1])?;
-            xmsg!("farm_operations::update_farm_config rps_decimals={value}",);
-            xmsg!("prev value {}", reward_info.rewards_per_second_decimals);
+        RewardConfigValue::RpsDecimals(value) => {
+            // Used as an exponent of ten in a u64
+            require_gte!(19, value, FarmError::InvalidConfigValue);
+            vmsg!("farm_operations::update_farm_config rps_decimals={value}",);
+            vmsg!("prev value {}", reward_info.rewards_per_second_decimals);
             reward_info.rewards_per_second_decimals = value;
+            reward_info.last_issuance_ts = ts;
         }
-        FarmConfigOption::UpdateRewardScheduleCurvePoints => {
-            let points: Vec<RewardPerTimeUnitPoint> = BorshDeserialize::try_from_slice(data)?;
-
-            xmsg!("Updating reward schedule curve with points={:?}", points);
-            xmsg!("Prev value {:?}", reward_info.reward_schedule_curve.points);
-            reward_info.reward_schedule_curve = RewardScheduleCurve::from_points(&points).unwrap();
+        RewardConfigValue::UpdateRewardScheduleCurvePoints(points) => {
+            vmsg!("Updating reward schedule curve with points={:?}", points);
+            vmsg!("Prev value {:?}", reward_info.reward_schedule_curve.points);
+            reward_info.reward_schedule_curve = RewardScheduleCurve::from_points(&points)?;
+            reward_info.last_issuance_ts = ts;
         }
-        _ => unimplemented!(),
     }
     Ok(())
 }
 
+pub fn add_user_to_page(
+    farm_state: &FarmState,
+    user_page: &mut UserPage,
+    farm_state_key: &Pubkey,
+    user_state_key: &Pubkey,
+    user_id: u64,
+) -> Result<()> {
+    let (page_index, slot) = UserPage::location(user_id);
+    require_keys_eq!(
+        user_page.farm_state,
+        *farm_state_key,
+        FarmError::InvalidUserPage
+    );
+    require_eq!(user_page.page_index, page_index, FarmError::InvalidUserPage);
+    require!(
+        farm_state.user_pages_enabled == 1,
+        FarmError::InvalidUserPage
+    );
+
+    vmsg!(
+        "farm_operations::add_user_to_page user_id={} page_index={} slot={}",
+        user_id,
+        page_index,
+        slot
+    );
+
+    user_page.user_states[slot] = *user_state_key;
+    user_page.num_users = user_page
+        .num_users
+        .checked_add(1)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    Ok(())
+}
+
+/// Tombstones the slot of a closed user, its `user_id` is never given again.
+pub fn remove_user_from_page(
+    user_page: &mut UserPage,
+    farm_state_key: &Pubkey,
+    user_state_key: &Pubkey,
+    user_id: u64,
+) -> Result<()> {
+    let (page_index, slot) = UserPage::location(user_id);
+    require_keys_eq!(
+        user_page.farm_state,
+        *farm_state_key,
+        FarmError::InvalidUserPage
+    );
+    require_eq!(user_page.page_index, page_index, FarmError::InvalidUserPage);
+    require_keys_eq!(
+        user_page.user_states[slot],
+        *user_state_key,
+        FarmError::InvalidUserPage
+    );
+
+    vmsg!(
+        "farm_operations::remove_user_from_page user_id={} page_index={} slot={}",
+        user_id,
+        page_index,
+        slot
+    );
+
+    user_page.user_states[slot] = Pubkey::default();
+    user_page.num_users = user_page
+        .num_users
+        .checked_sub(1)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    Ok(())
+}
+
+/// A user can only be closed once nothing is staked, pending or left to claim.
+pub fn check_user_closable(user_state: &UserState) -> Result<()> {
+    require!(!user_state.is_renounced(), FarmError::PositionRenounced);
+    require!(
+        user_state.active_stake_scaled == 0
+            && user_state.pending_deposit_stake_scaled == 0
+            && user_state.pending_withdrawal_unstake_scaled == 0
+            && user_state
+                .rewards_issued_unclaimed
+                .iter()
+                .all(|&amount| amount == 0),
+        FarmError::UserStateNotEmpty
+    );
+    Ok(())
+}
+
 pub fn initialize_user(
     farm_state: &mut FarmState,
     user_state: &mut UserState,
@@ -337,6 +1216,15 @@ pub fn initialize_user(
     farm_state_key: &Pubkey,
     ts: u64,
 ) -> Result<()> {
+    if farm_state.new_users_cutoff_ts != 0 && ts >= farm_state.new_users_cutoff_ts {
+        xmsg!(
+            "New users not accepted since {}, ts={}",
+            farm_state.new_users_cutoff_ts,
+            ts
+        );
+        return err!(FarmError::NewUsersCutoffReached);
+    }
+
     user_state.owner = *owner_key;
     user_state.farm_state = *farm_state_key;
 
@@ -373,24 +1261,125 @@ pub fn initialize_reward_ts_if_needed(farm_state: &mut FarmState, current_ts: u6
     }
 }
 
+/// Fails when the farm requires `refresh_farm` in the same slot as its stake changes
+/// and it hasn't run in `slot`.
+pub fn check_refreshed_in_slot(farm_state: &FarmState, slot: u64) -> Result<()> {
+    if farm_state.require_refresh_in_slot != 0 && farm_state.last_refresh_slot != slot {
+        xmsg!(
+            "Farm last refreshed at slot {}, current slot {}: prepend refresh_farm",
+            farm_state.last_refresh_slot,
+            slot
+        );
+        return err!(FarmError::FarmNotRefreshedInSlot);
+    }
+    Ok(())
+}
+
+/// Fails during the stake and unstake pause set by `snapshot_farm_for_upgrade`.
+pub fn check_not_upgrade_paused(farm_state: &FarmState, slot: u64) -> Result<()> {
+    if farm_state.is_upgrade_paused(slot) {
+        xmsg!(
+            "Farm paused for a program upgrade until slot {}, current slot {}",
+            farm_state.upgrade_pause_until_slot,
+            slot
+        );
+        return err!(FarmError::UpgradePaused);
+    }
+    Ok(())
+}
+
+/// Settles the accumulators like `refresh_farm` and records them with `marker`, the
+/// program version the farm is prepared for. Stake and unstake are then paused for
+/// `pause_slots`, 0 lifts a pause in progress.
+pub fn snapshot_farm_for_upgrade(
+    farm_state: &mut FarmState,
+    scope_price: Option<DatedPrice>,
+    marker: u64,
+    pause_slots: u64,
+    slot: u64,
+    ts: u64,
+) -> Result<()> {
+    if marker == 0 || marker < farm_state.upgrade_snapshot_marker {
+        xmsg!(
+            "Upgrade snapshot marker {} invalid, current marker {}",
+            marker,
+            farm_state.upgrade_snapshot_marker
+        );
+        return err!(FarmError::InvalidUpgradeSnapshotMarker);
+    }
+    require_gte!(
+        MAX_UPGRADE_PAUSE_SLOTS,
+        pause_slots,
+        FarmError::UpgradePauseTooLong
+    );
+
+    refresh_global_rewards(farm_state, scope_price, ts)?;
+    farm_state.last_refresh_slot = slot;
+
+    farm_state.upgrade_snapshot_marker = marker;
+    farm_state.upgrade_snapshot_slot = slot;
+    farm_state.upgrade_snapshot_accumulators_hash = farm_state.accumulators_hash();
+    farm_state.upgrade_pause_until_slot = slot + pause_slots;
+
+    vmsg!(
+        "farm_operations::snapshot_farm_for_upgrade marker={} slot={} pause_until_slot={}",
+        marker,
+        slot,
+        farm_state.upgrade_pause_until_slot
+    );
+
+    Ok(())
+}
+
+/// `amount` reduced to the remaining deposit capacity with `allow_partial`, which
+/// must fit it otherwise.
+pub fn stake_amount_within_capacity(
+    farm_state: &FarmState,
+    scope_price: Option<DatedPrice>,
+    amount: u64,
+    allow_partial: bool,
+    ts: u64,
+) -> Result<u64> {
+    if allow_partial {
+        let remaining = farm_state.remaining_deposit_capacity(scope_price, ts)?;
+        let amount = remaining.map_or(amount, |remaining| cmp::min(amount, remaining));
+        require!(amount != 0, FarmError::StakeZero);
+        Ok(amount)
+    } else {
+        if !farm_state.can_accept_deposit(amount, scope_price, ts)? {
+            return Err(FarmError::DepositCapReached.into());
+        }
+        Ok(amount)
+    }
+}
+
 pub fn stake(
     farm_state: &mut FarmState,
     user_state: &mut UserState,
     scope_price: Option<DatedPrice>,
     amount: u64,
+    allow_partial: bool,
     current_ts: u64,
 ) -> Result<StakeEffects> {
-    xmsg!("farm_operations::stake amount={}", amount);
+    vmsg!(
+        "farm_operations::stake amount={} allow_partial={}",
+        amount,
+        allow_partial
+    );
     refresh_global_rewards(farm_state, scope_price, current_ts)?;
     user_refresh_all_rewards(farm_state, user_state)?;
     user_refresh_stake(farm_state, user_state, current_ts)?;
 
-    if !farm_state.can_accept_deposit(amount, scope_price, current_ts)? {
-        return Err(FarmError::DepositCapReached.into());
+    let amount =
+        stake_amount_within_capacity(farm_state, scope_price, amount, allow_partial, current_ts)?;
+
+    let position_amount = user_position_amount(farm_state, user_state);
+    if position_amount == 0 {
+        user_state.penalty_terms_version = farm_state.penalty_terms_version;
     }
 
     if user_state.pending_deposit_stake_scaled != 0 {
-        xmsg!(
+        vmsg!(
             "farm_operations::stake BEFORE: pending_user_stake_scaled={}, pending_user_stake_ts={},\
              pending stake will be extended",
             user_state.pending_deposit_stake_scaled,
@@ -398,38 +1387,55 @@ pub fn stake(
         );
     }
 
-    if farm_state.deposit_warmup_period > 0 {
+    let stake_gained = if farm_state.deposit_warmup_period > 0 {
         user_state.pending_deposit_stake_ts = current_ts
             .checked_add(farm_state.deposit_warmup_period.into())
             .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
         let stake_gained = stake_ops::add_pending_deposit_stake(user_state, farm_state, amount)?;
-        xmsg!(
+        vmsg!(
             "farm_operations::stake AFTER: pending_user_stake_ts={},\
              pending_stake_gained={}",
             user_state.pending_deposit_stake_ts,
             stake_gained
         );
+        stake_gained
     } else {
         let stake_gained = stake_ops::add_active_stake(user_state, farm_state, amount)?;
-        xmsg!(
+        vmsg!(
             "farm_operations::stake AFTER: active_user_stake_scaled={}, active_stake_gained={}",
             user_state.active_stake_scaled,
             stake_gained
         );
         update_user_rewards_tally_on_stake_increase(farm_state, user_state, stake_gained)?;
+        stake_gained
     };
 
-    user_state.last_stake_ts = current_ts;
+    user_state.set_last_stake_ts(
+        farm_state.get_locking_mode(),
+        topped_up_lock_start(
+            farm_state.get_lock_top_up_policy(),
+            user_state.last_stake_ts,
+            position_amount,
+            amount,
+            current_ts,
+        ),
+    );
+
+    refresh_active_user(farm_state, user_state)?;
 
     Ok(StakeEffects {
         amount_to_stake: amount,
+        stake_gained_scaled: stake_gained.to_scaled_val().unwrap(),
     })
 }
 
+/// `authority_index` is the `FarmState::delegate_authority_index` of the authority
+/// setting the stake, the whole stake of the user is attributed to it.
 pub fn set_stake(
     farm_state: &mut FarmState,
     user_state: &mut UserState,
     new_stake: u64,
+    authority_index: u8,
     ts: u64,
 ) -> Result<()> {
     assert_eq!(
@@ -446,8 +1452,16 @@ pub fn set_stake(
         .try_into()
         .expect("Delegated farm: active stake don't fit on u64");
 
+    attribute_delegated_stake(
+        farm_state,
+        user_state,
+        current_stake_amount,
+        new_stake,
+        authority_index,
+    )?;
+
     if current_stake_amount == new_stake {
-        xmsg!("farm_operations::set_stake nothing to do");
+        vmsg!("farm_operations::set_stake nothing to do");
         return Ok(());
     }
 
@@ -465,7 +1479,7 @@ pub fn set_stake(
         } else {
             let diff = new_stake - current_stake_amount;
             initialize_reward_ts_if_needed(farm_state, ts);
-            user_state.last_stake_ts = ts;
+            user_state.set_last_stake_ts(farm_state.get_locking_mode(), ts);
 
             if !farm_state.can_accept_deposit(diff, None, ts)? {
                 return Err(FarmError::DepositCapReached.into());
@@ -479,14 +1493,170 @@ pub fn set_stake(
     op_u64(&mut farm_state.total_staked_amount, diff);
 
     op_u128(&mut user_state.active_stake_scaled, diff_u128);
+    user_state.raffles_at_last_stake_change = farm_state.num_raffles;
 
     for i in 0..farm_state.num_reward_tokens as usize {
         let reward_tally = &mut user_state.rewards_tally_scaled[i];
         let reward_info = &farm_state.reward_infos[i];
+        if reward_info.reward_type() == RewardType::ConstantPerUser {
+            continue;
+        }
 
         *reward_tally = reward_info.reward_per_share_scaled * u128::from(new_stake);
     }
 
+    refresh_active_user(farm_state, user_state)?;
+
+    Ok(())
+}
+
+/// Moves the `current_stake` of `user_state` out of the total of the authority it was
+/// attributed to, and `new_stake` into the total of `authority_index`. The stake of
+/// `delegate_authority`, index 0, is not tracked but derived from the others.
+fn attribute_delegated_stake(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    current_stake: u64,
+    new_stake: u64,
+    authority_index: u8,
+) -> Result<()> {
+    if let Some(i) = (user_state.delegate_authority_index as usize).checked_sub(1) {
+        let total = &mut farm_state.additional_delegate_authorities_stake[i];
+        *total = total
+            .checked_sub(current_stake)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    }
+    if let Some(i) = (authority_index as usize).checked_sub(1) {
+        let total = &mut farm_state.additional_delegate_authorities_stake[i];
+        *total = total
+            .checked_add(new_stake)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    }
+    user_state.delegate_authority_index = authority_index;
+    Ok(())
+}
+
+/// Active and pending deposit stake of `user_state`, in tokens of `farm_state`.
+fn user_position_amount(farm_state: &FarmState, user_state: &UserState) -> u64 {
+    let pending_amount = stake_ops::convert_stake_to_amount(
+        user_state.get_pending_deposit_stake_decimal(),
+        farm_state.get_total_pending_stake_decimal(),
+        farm_state.total_pending_amount,
+    );
+    user_active_amount(farm_state, user_state).saturating_add(pending_amount)
+}
+
+/// `last_stake_ts` after staking `amount` into a position of `position_amount` whose
+/// lock started at `last_stake_ts`, see `LockTopUpPolicy`.
+pub fn topped_up_lock_start(
+    policy: LockTopUpPolicy,
+    last_stake_ts: u64,
+    position_amount: u64,
+    amount: u64,
+    ts: u64,
+) -> u64 {
+    if position_amount == 0 {
+        return ts;
+    }
+
+    match policy {
+        LockTopUpPolicy::ResetAll => ts,
+        LockTopUpPolicy::KeepOriginal => last_stake_ts,
+        LockTopUpPolicy::WeightedAverage => {
+            let weighted_ts = (u128::from(last_stake_ts) * u128::from(position_amount)
+                + u128::from(ts) * u128::from(amount))
+                / (u128::from(position_amount) + u128::from(amount));
+            weighted_ts as u64
+        }
+    }
+}
+
+/// Active stake of `user_state` in tokens of `farm_state`, rounded down.
+pub fn user_active_amount(farm_state: &FarmState, user_state: &UserState) -> u64 {
+    if farm_state.is_delegated() {
+        return user_state
+            .active_stake_scaled
+            .try_into()
+            .expect("Delegated farm: active stake don't fit on u64");
+    }
+
+    active_stake_to_amount(farm_state, user_state.get_active_stake_decimal())
+}
+
+/// Tokens of `farm_state` worth `stake` active shares, rounded down.
+fn active_stake_to_amount(farm_state: &FarmState, stake: Decimal) -> u64 {
+    stake_ops::convert_stake_to_amount(
+        stake,
+        farm_state.get_total_active_stake_decimal()
+            + Decimal::from(farm_state.virtual_stake_offset),
+        farm_state.total_staked_amount + farm_state.virtual_amount_offset,
+    )
+}
+
+pub fn sync_mirrored_stake(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    primary_farm_state: &FarmState,
+    primary_user_state: &UserState,
+    ts: u64,
+) -> Result<u64> {
+    require!(farm_state.is_mirror(), FarmError::FarmNotMirror);
+
+    let mirrored_amount = user_active_amount(primary_farm_state, primary_user_state);
+
+    vmsg!(
+        "farm_operations::sync_mirrored_stake owner={} mirrored_amount={}",
+        user_state.owner,
+        mirrored_amount
+    );
+
+    set_stake(farm_state, user_state, mirrored_amount, 0, ts)?;
+    user_state.last_mirror_sync_ts = ts;
+
+    Ok(mirrored_amount)
+}
+
+/// Checks made by `harvest` before any change, so that callers can skip a harvest that
+/// would fail.
+pub fn check_harvest_allowed(
+    farm_state: &FarmState,
+    user_state: &UserState,
+    reward_index: usize,
+    ts: u64,
+) -> Result<()> {
+    require!(
+        !user_state.is_renounced() || user_state.renounced_harvest_allowed != 0,
+        FarmError::PositionRenounced
+    );
+    let reward_info = &farm_state.reward_infos[reward_index];
+    let eligibility_ts = harvest_eligibility_ts(reward_info, user_state, reward_index);
+    if ts < eligibility_ts {
+        xmsg!(
+            "Reward {} can be harvested from {} (claims_open_ts={} last_claim_ts={} min_claim_duration={}), ts={}",
+            reward_index,
+            eligibility_ts,
+            reward_info.claims_open_ts,
+            user_state.last_claim_ts[reward_index],
+            reward_info.min_claim_duration_seconds,
+            ts
+        );
+        return if ts < reward_info.claims_open_ts {
+            err!(FarmError::RewardClaimsNotOpenYet)
+        } else {
+            err!(FarmError::MinClaimDurationNotReached)
+        };
+    }
+    if farm_state.is_mirror()
+        && ts.saturating_sub(user_state.last_mirror_sync_ts) > farm_state.mirror_max_staleness
+    {
+        xmsg!(
+            "Mirrored stake last synced at {}, max staleness {} ts={}",
+            user_state.last_mirror_sync_ts,
+            farm_state.mirror_max_staleness,
+            ts
+        );
+        return err!(FarmError::MirroredStakeStale);
+    }
     Ok(())
 }
 
@@ -496,51 +1666,183 @@ pub fn harvest(
     global_config: &GlobalConfig,
     scope_price: Option<DatedPrice>,
     reward_index: usize,
+    rewards_vault_amount: u64,
     ts: u64,
 ) -> Result<HarvestEffects> {
-    xmsg!("farm_operations::harvest reward_index={}", reward_index);
+    vmsg!("farm_operations::harvest reward_index={}", reward_index);
+    check_harvest_allowed(farm_state, user_state, reward_index, ts)?;
     refresh_global_rewards(farm_state, scope_price, ts)?;
     user_refresh_reward(farm_state, user_state, reward_index)?;
 
-    let reward = user_state.rewards_issued_unclaimed[reward_index];
-    require!(
-        ts.checked_sub(user_state.last_claim_ts[reward_index])
-            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?
-            >= farm_state.reward_infos[reward_index].min_claim_duration_seconds,
-        FarmError::MinClaimDurationNotReached
-    );
+    let reward_info = &farm_state.reward_infos[reward_index];
+    let reward_owed = user_state.rewards_issued_unclaimed[reward_index];
+    // With a shortfall every harvest gets the same share of what is owed, the rest stays
+    // owed to the user, so the coverage ratio is unchanged for the later harvests
+    let reward = if reward_info.shortfall != 0 && reward_owed > 0 {
+        let RewardShortfall {
+            rewards_vault_amount,
+            rewards_owed,
+            ..
+        } = reward_shortfall(reward_info, rewards_vault_amount)?;
+        let reward = u64_mul_div(
+            reward_owed,
+            cmp::min(rewards_vault_amount, rewards_owed),
+            rewards_owed,
+        );
+        vmsg!(
+            "farm_operations::harvest shortfall pro-rata {}/{} vault={} owed={}",
+            reward,
+            reward_owed,
+            rewards_vault_amount,
+            rewards_owed
+        );
+        reward
+    } else {
+        reward_owed
+    };
+    let (treasury_fee_bps, fee_override_clamped) = harvest_fee_bps(farm_state, global_config);
     if reward == 0 {
         return Ok(HarvestEffects {
             reward_treasury: 0,
             reward_user: 0,
+            reward_penalty: 0,
+            reward_split: 0,
+            treasury_fee_bps,
+            fee_override_clamped,
         });
     }
 
+    let reward_penalty = early_claim_penalty(
+        &farm_state.reward_infos[reward_index],
+        reward,
+        ts.saturating_sub(user_state.reward_accrual_start_ts[reward_index].into()),
+    );
+    if reward_penalty > 0 {
+        let reward_info = &mut farm_state.reward_infos[reward_index];
+        vmsg!(
+            "farm_operations::harvest early claim penalty {} of {}",
+            reward_penalty,
+            reward
+        );
+        reward_info.rewards_available = reward_info
+            .rewards_available
+            .checked_add(reward_penalty)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+        reward_info.rewards_issued_cumulative = reward_info
+            .rewards_issued_cumulative
+            .checked_sub(reward_penalty)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    }
+
     farm_state.reward_infos[reward_index].rewards_issued_unclaimed = farm_state.reward_infos
         [reward_index]
         .rewards_issued_unclaimed
         .checked_sub(reward)
         .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
-    user_state.rewards_issued_unclaimed[reward_index] = 0;
+    user_state.rewards_issued_unclaimed[reward_index] = reward_owed - reward;
     user_state.last_claim_ts[reward_index] = ts;
 
-    let reward_treasury = u64_mul_div(reward, global_config.treasury_fee_bps, BPS_DIV_FACTOR);
+    let reward = reward - reward_penalty;
+    let reward_treasury = u64_mul_div(reward, treasury_fee_bps, BPS_DIV_FACTOR);
     let reward_user = reward
         .checked_sub(reward_treasury)
         .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
 
+    let reward_info = &farm_state.reward_infos[reward_index];
+    let reward_split = if reward_info.has_split() {
+        u64_mul_div(reward_user, reward_info.split_bps.into(), BPS_DIV_FACTOR)
+    } else {
+        0
+    };
+    let reward_user = reward_user - reward_split;
+
     Ok(HarvestEffects {
         reward_user,
         reward_treasury,
+        reward_penalty,
+        reward_split,
+        treasury_fee_bps,
+        fee_override_clamped,
     })
 }
 
+/// Harvest fee of `farm_state` and whether its override had to be clamped.
+///
+/// The override is checked against the global bounds when set, but these can change
+/// afterwards: it is then clamped to the current bounds, or ignored if overrides
+/// were disabled globally, rather than failing the harvests.
+pub fn harvest_fee_bps(farm_state: &FarmState, global_config: &GlobalConfig) -> (u64, bool) {
+    if farm_state.harvest_fee_override_enabled == 0 {
+        return (global_config.treasury_fee_bps, false);
+    }
+    let bps = farm_state.harvest_fee_bps_override;
+    if global_config.max_harvest_fee_bps == 0 {
+        return (global_config.treasury_fee_bps, true);
+    }
+    let clamped = bps.clamp(
+        global_config.min_harvest_fee_bps,
+        global_config.max_harvest_fee_bps,
+    );
+    (clamped, clamped != bps)
+}
+
+/// Earliest time (in `time_unit`) at which `user_state` can harvest `reward_index`:
+/// once claims are open and `min_claim_duration_seconds` after its previous claim.
+pub fn harvest_eligibility_ts(
+    reward_info: &RewardInfo,
+    user_state: &UserState,
+    reward_index: usize,
+) -> u64 {
+    cmp::max(
+        reward_info.claims_open_ts,
+        user_state.last_claim_ts[reward_index]
+            .saturating_add(reward_info.min_claim_duration_seconds),
+    )
+}
+
+pub fn harvest_eligibility(
+    farm_state: &FarmState,
+    user_state: &UserState,
+    ts: u64,
+) -> HarvestEligibility {
+    HarvestEligibility {
+        version: HARVEST_ELIGIBILITY_VERSION,
+        ts,
+        eligibility_ts: (0..farm_state.num_reward_tokens as usize)
+            .map(|reward_index| {
+                harvest_eligibility_ts(
+                    &farm_state.reward_infos[reward_index],
+                    user_state,
+                    reward_index,
+                )
+            })
+            .collect(),
+    }
+}
+
+/// Part of `reward` forfeited when claiming `since_accrual_start` after the unclaimed
+/// rewards of the user grew from zero: the full `early_claim_penalty_bps` right away,
+/// decaying linearly to 0 at the end of `penalty_decay_window_s`.
+pub fn early_claim_penalty(reward_info: &RewardInfo, reward: u64, since_accrual_start: u64) -> u64 {
+    let window = reward_info.penalty_decay_window_s;
+    if reward_info.early_claim_penalty_bps == 0 || window == 0 || since_accrual_start >= window {
+        return 0;
+    }
+
+    let penalty_bps = u64_mul_div(
+        reward_info.early_claim_penalty_bps,
+        window - since_accrual_start,
+        window,
+    );
+    u64_mul_div(reward, penalty_bps, BPS_DIV_FACTOR)
+}
+
 pub fn user_refresh_reward(
     farm_state: &mut FarmState,
     user_state: &mut UserState,
     reward_index: usize,
 ) -> Result<()> {
-    xmsg!(
+    vmsg!(
         "farm_operations::user_refresh_reward reward_index {} Global stake {} User stake {} prev_reward_tally {} rpt {}",
         reward_index,
         farm_state.total_active_stake_scaled,
@@ -550,9 +1852,15 @@ pub fn user_refresh_reward(
     );
 
     let rewards_tally = user_state.get_rewards_tally_decimal(reward_index);
-    let reward_per_share = farm_state.reward_infos[reward_index].get_reward_per_share_decimal();
+    let reward_info = &farm_state.reward_infos[reward_index];
+    let reward_per_share = reward_info.get_reward_per_share_decimal();
 
-    let new_reward_tally: Decimal = if farm_state.is_delegated() {
+    let new_reward_tally: Decimal = if reward_info.reward_type() == RewardType::ConstantPerUser {
+        if user_state.is_active_user == 0 {
+            return Ok(());
+        }
+        reward_per_share
+    } else if farm_state.is_delegated() {
         reward_per_share * user_state.active_stake_scaled
     } else {
         reward_per_share * user_state.get_active_stake_decimal()
@@ -564,7 +1872,7 @@ pub fn user_refresh_reward(
 
     let new_reward_tally = rewards_tally + reward.into();
 
-    xmsg!(
+    vmsg!(
         "farm_operations::user_refresh_reward reward {}, new_reward_tally (scaled) {}",
         reward,
         new_reward_tally.to_scaled_val::<u128>().unwrap()
@@ -572,7 +1880,20 @@ pub fn user_refresh_reward(
 
     user_state.set_rewards_tally_decimal(reward_index, new_reward_tally);
 
-    user_state.rewards_issued_unclaimed[reward_index] += reward;
+    let accrual_ts = farm_state.reward_infos[reward_index].last_issuance_ts;
+    user_state.add_rewards_issued_unclaimed(reward_index, reward, accrual_ts);
+
+    #[cfg(feature = "settlement-events")]
+    if reward > 0 {
+        emit!(crate::events::RewardSettlementEvent {
+            farm_state: user_state.farm_state,
+            owner: user_state.owner,
+            user_id: user_state.user_id,
+            reward_index: reward_index as u8,
+            amount: reward,
+            reward_per_share_scaled: farm_state.reward_infos[reward_index].reward_per_share_scaled,
+        });
+    }
 
     Ok(())
 }
@@ -602,7 +1923,7 @@ fn user_refresh_stake(
     {
         let (amount_staked, active_stake_gained) =
             stake_ops::activate_pending_stake(user_state, farm_state)?;
-        xmsg!(
+        vmsg!(
             "farm_operations::user_refresh_stake amount_staked {} active_stake_gained (scaled) {}",
             amount_staked,
             active_stake_gained.to_scaled_val::<u128>().unwrap()
@@ -630,103 +1951,595 @@ pub fn user_refresh_state(
         user_refresh_stake(farm_state, user_state, current_ts)?;
     }
 
-    Ok(())
-}
+    refresh_active_user(farm_state, user_state)?;
 
-pub fn reward_user_once(
-    farm_state: &mut FarmState,
-    user_state: &mut UserState,
-    reward_index: u64,
-    amount: u64,
-) -> Result<()> {
-    farm_state.reward_infos[reward_index as usize].rewards_issued_unclaimed += amount;
-    farm_state.reward_infos[reward_index as usize].rewards_issued_cumulative += amount;
-    user_state.rewards_issued_unclaimed[reward_index as usize] += amount;
     Ok(())
 }
 
-pub fn unstake(
+/// Same as `user_refresh_state` restricted to the rewards set in `rewards_mask`, bit
+/// `i` for reward index `i`, 0 for all of them. Falls back to refreshing every reward
+/// when the refresh would activate pending stake or change the active user status,
+/// both need all the rewards caught up first. The other rewards are left for their
+/// next refresh, `harvest` catches up the harvested reward on its own.
+pub fn user_refresh_state_for_rewards(
     farm_state: &mut FarmState,
     user_state: &mut UserState,
     scope_price: Option<DatedPrice>,
-    requested_stake_withdrawal: Decimal,
-    ts: u64,
+    current_ts: u64,
+    rewards_mask: u16,
 ) -> Result<()> {
-    xmsg!(
-        "farm_operations::unstake amount of stake={}",
-        requested_stake_withdrawal
+    let num_reward_tokens = farm_state.num_reward_tokens as usize;
+    require!(
+        u32::from(rewards_mask) >> num_reward_tokens == 0,
+        FarmError::RewardIndexOutOfRange
     );
 
-    refresh_global_rewards(farm_state, scope_price, ts)?;
+    let activates_pending_stake = !farm_state.is_delegated()
+        && user_state.pending_deposit_stake_scaled > 0
+        && current_ts >= user_state.pending_deposit_stake_ts;
+    let changes_active_user =
+        is_active_user(farm_state, user_state) != (user_state.is_active_user != 0);
 
-    user_refresh_all_rewards(farm_state, user_state)?;
+    if rewards_mask == 0 || activates_pending_stake || changes_active_user {
+        return user_refresh_state(farm_state, user_state, scope_price, current_ts);
+    }
 
-    let stake_share_to_unstake = std::cmp::min(
-        requested_stake_withdrawal,
-        user_state.get_active_stake_decimal(),
-    );
-    require!(
-        stake_share_to_unstake > Decimal::zero(),
-        FarmError::NothingToUnstake
+    vmsg!(
+        "farm_operations::user_refresh_state_for_rewards rewards_mask={:#b}",
+        rewards_mask
     );
 
-    if user_state.pending_withdrawal_unstake_scaled > 0 {
-        if user_state.pending_withdrawal_unstake_ts <= ts {
-            xmsg!("farm_operations::unstake pending withdrawal elapsed already exist but not withdrawn yet");
-            return err!(FarmError::PendingWithdrawalNotWithdrawnYet);
+    for reward_index in 0..num_reward_tokens {
+        if rewards_mask & (1 << reward_index) == 0 {
+            continue;
+        }
+        refresh_global_reward(farm_state, scope_price, current_ts, reward_index)?;
+        if user_state.active_stake_scaled > 0 {
+            user_refresh_reward(farm_state, user_state, reward_index)?;
         }
-        xmsg!(
-            "farm_operations::unstake pending withdrawal already exist and will be extended.\
-                already pending withdrawal stake={}, added={}, old ts={}",
-            user_state.get_pending_withdrawal_unstake_decimal(),
-            stake_share_to_unstake,
-            user_state.pending_withdrawal_unstake_ts
-        );
     }
 
-    user_state.pending_withdrawal_unstake_ts = ts
-        .checked_add(farm_state.withdrawal_cooldown_period.into())
-        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    user_state.is_farm_delegated = farm_state.is_delegated() as u8;
 
-    let (token_amount_removed, added_pending_withdrawal_unstake, token_amount_penalty) =
-        stake_ops::unstake(user_state, farm_state, stake_share_to_unstake, ts)?;
+    Ok(())
+}
 
-    xmsg!(
-        "farm_operations::unstake added_pending_withdrawal_unstake={}, token_amount_unstaked={}",
-        added_pending_withdrawal_unstake,
-        token_amount_removed
-    );
+fn is_active_user(farm_state: &FarmState, user_state: &UserState) -> bool {
+    user_state.active_stake_scaled > 0
+        && user_active_amount(farm_state, user_state) >= farm_state.min_active_user_stake
+}
 
-    farm_state.slashed_amount_current += token_amount_penalty;
-    farm_state.slashed_amount_cumulative += token_amount_penalty;
+/// Counts `user_state` in `active_user_count` while its active stake is at least
+/// `min_active_user_stake`. The user rewards must be refreshed first, the
+/// `ConstantPerUser` rewards only accrue from the time the user becomes active.
+pub fn refresh_active_user(farm_state: &mut FarmState, user_state: &mut UserState) -> Result<()> {
+    let is_active = is_active_user(farm_state, user_state);
+    if is_active == (user_state.is_active_user != 0) {
+        return Ok(());
+    }
 
-    for i in 0..farm_state.num_reward_tokens as usize {
-        let reward_tally = &mut user_state.rewards_tally_scaled[i];
-        let reward_info = &farm_state.reward_infos[i];
+    if is_active {
+        farm_state.active_user_count += 1;
+        for reward_index in 0..farm_state.num_reward_tokens as usize {
+            let reward_info = &farm_state.reward_infos[reward_index];
+            if reward_info.reward_type() == RewardType::ConstantPerUser {
+                user_state.rewards_tally_scaled[reward_index] = reward_info.reward_per_share_scaled;
+            }
+        }
+    } else {
+        farm_state.active_user_count = farm_state
+            .active_user_count
+            .checked_sub(1)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    }
+    user_state.is_active_user = is_active as u8;
 
-        let reward_tally_decimal = Decimal::from_scaled_val(*reward_tally);
-        let tally_loss = stake_share_to_unstake * reward_info.get_reward_per_share_decimal();
+    vmsg!(
+        "farm_operations::refresh_active_user is_active={} active_user_count={}",
+        is_active,
+        farm_state.active_user_count
+    );
 
-        require_gt!(
-            reward_tally_decimal + Decimal::one(),
-            tally_loss,
-            FarmError::IntegerOverflow
-        );
-        let reward_tally_scaled: u128 = reward_tally_decimal.to_scaled_val().unwrap();
-        let tally_loss_scaled: u128 = tally_loss.to_scaled_val().unwrap();
-        let new_reward_tally_decimal_scaled = reward_tally_scaled.saturating_sub(tally_loss_scaled);
+    Ok(())
+}
 
-        *reward_tally = new_reward_tally_decimal_scaled;
+/// Records `batch_nonce` as applied once `rewards_vault_amount` is checked to cover
+/// the rewards owed plus `total_amount`, before crediting the users of a batch.
+pub fn start_credit_users_batch(
+    farm_state: &mut FarmState,
+    reward_index: usize,
+    batch_nonce: u64,
+    total_amount: u64,
+    rewards_vault_amount: u64,
+) -> Result<()> {
+    if batch_nonce <= farm_state.last_credit_batch_nonce {
+        xmsg!(
+            "Credit batch nonce {} <= last applied {}",
+            batch_nonce,
+            farm_state.last_credit_batch_nonce
+        );
+        return err!(FarmError::CreditBatchAlreadyApplied);
+    }
+
+    let RewardShortfall { rewards_owed, .. } =
+        reward_shortfall(&farm_state.reward_infos[reward_index], rewards_vault_amount)?;
+    let rewards_required = rewards_owed
+        .checked_add(total_amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    if rewards_required > rewards_vault_amount {
+        xmsg!(
+            "Rewards vault {} does not cover owed {} + credited {}",
+            rewards_vault_amount,
+            rewards_owed,
+            total_amount
+        );
+        return err!(FarmError::CreditExceedsRewardsVault);
+    }
+
+    farm_state.last_credit_batch_nonce = batch_nonce;
+    Ok(())
+}
+
+/// Irreversible, nothing resets `is_renounced`.
+pub fn renounce_position(
+    farm_state: &FarmState,
+    user_state: &mut UserState,
+    allow_harvest: bool,
+) -> Result<()> {
+    // The delegate authority sets the stake of delegated farms, mirror farms included
+    require!(!farm_state.is_delegated(), FarmError::RenounceNotSupported);
+    require!(!user_state.is_renounced(), FarmError::PositionRenounced);
+
+    user_state.is_renounced = 1;
+    user_state.renounced_harvest_allowed = allow_harvest as u8;
+
+    vmsg!(
+        "farm_operations::renounce_position user_id={} allow_harvest={}",
+        user_state.user_id,
+        allow_harvest
+    );
+
+    Ok(())
+}
+
+pub fn reward_user_once(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    reward_index: usize,
+    amount: u64,
+    ts: u64,
+) -> Result<()> {
+    farm_state.reward_infos[reward_index].rewards_issued_unclaimed += amount;
+    farm_state.reward_infos[reward_index].rewards_issued_cumulative += amount;
+    user_state.add_rewards_issued_unclaimed(reward_index, amount, ts);
+    Ok(())
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn commit_raffle(
+    farm_state: &mut FarmState,
+    raffle_state: &mut RaffleState,
+    farm_state_key: &Pubkey,
+    scope_price: Option<DatedPrice>,
+    reward_index: usize,
+    slot_target: u64,
+    prize_amount: u64,
+    slot: u64,
+    ts: u64,
+) -> Result<u64> {
+    require!(
+        farm_state.user_pages_enabled == 1,
+        FarmError::InvalidUserPage
+    );
+    require_gt!(slot_target, slot, FarmError::InvalidRaffleSlotTarget);
+    require!(prize_amount > 0, FarmError::InvalidConfigValue);
+
+    refresh_global_rewards(farm_state, scope_price, ts)?;
+
+    let reward_info = &mut farm_state.reward_infos[reward_index];
+    if prize_amount > reward_info.rewards_available {
+        xmsg!(
+            "Raffle prize {} above the rewards available {}",
+            prize_amount,
+            reward_info.rewards_available
+        );
+        return err!(FarmError::RafflePrizeExceedsRewardsAvailable);
+    }
+    reward_info.rewards_available -= prize_amount;
+
+    raffle_state.farm_state = *farm_state_key;
+    raffle_state.raffle_id = farm_state.num_raffles;
+    raffle_state.reward_index = reward_index as u64;
+    raffle_state.prize_amount = prize_amount;
+    raffle_state.slot_target = slot_target;
+    raffle_state.num_users = farm_state.num_users;
+    raffle_state.status = RaffleStatus::Committed as u8;
+
+    farm_state.num_raffles = farm_state
+        .num_raffles
+        .checked_add(1)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    vmsg!(
+        "farm_operations::commit_raffle raffle_id={} reward_index={} prize={} slot_target={}",
+        raffle_state.raffle_id,
+        reward_index,
+        prize_amount,
+        slot_target
+    );
+
+    refresh_projected_depletion_ts(farm_state, reward_index, scope_price, ts)
+}
+
+pub fn draw_raffle_seed(
+    raffle_state: &mut RaffleState,
+    raffle_state_key: &Pubkey,
+    block_hash: &[u8; 32],
+) -> Result<()> {
+    require!(
+        raffle_state.get_status() == RaffleStatus::Committed,
+        FarmError::InvalidRaffleStatus
+    );
+    raffle_state.seed = hashv(&[block_hash, raffle_state_key.as_ref()]).to_bytes();
+    raffle_state.status = RaffleStatus::Drawing as u8;
+    Ok(())
+}
+
+/// Gives the prize back to the reward when the seed can no longer be drawn.
+pub fn cancel_raffle(farm_state: &mut FarmState, raffle_state: &mut RaffleState) -> Result<()> {
+    require!(
+        raffle_state.get_status() == RaffleStatus::Committed,
+        FarmError::InvalidRaffleStatus
+    );
+    return_raffle_prize(farm_state, raffle_state)?;
+    raffle_state.status = RaffleStatus::Cancelled as u8;
+    Ok(())
+}
+
+fn return_raffle_prize(farm_state: &mut FarmState, raffle_state: &RaffleState) -> Result<()> {
+    let reward_info = &mut farm_state.reward_infos[raffle_state.reward_index as usize];
+    reward_info.rewards_available = reward_info
+        .rewards_available
+        .checked_add(raffle_state.prize_amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    Ok(())
+}
+
+/// Walks the user `raffle_state.next_user_id`, which must be `user_state` or a
+/// tombstone of its page when `user_state` is `None`.
+///
+/// Users whose active stake changed since the commit are skipped: the seed is public
+/// once drawn, their stake could otherwise be tuned to win.
+pub fn walk_raffle_user(
+    farm_state: &FarmState,
+    raffle_state: &mut RaffleState,
+    user_state_key: &Pubkey,
+    user_state: Option<&UserState>,
+) -> Result<()> {
+    require!(
+        raffle_state.get_status() == RaffleStatus::Drawing,
+        FarmError::InvalidRaffleStatus
+    );
+
+    if let Some(user_state) = user_state {
+        require_eq!(
+            user_state.user_id,
+            raffle_state.next_user_id,
+            FarmError::UnexpectedAccount
+        );
+        let stake = user_active_amount(farm_state, user_state);
+        if stake > 0 && user_state.raffles_at_last_stake_change <= raffle_state.raffle_id {
+            raffle_state.cumulative_stake += u128::from(stake);
+
+            let draw = hashv(&[&raffle_state.seed, &user_state.user_id.to_le_bytes()]).to_bytes();
+            let draw = u128::from_le_bytes(draw[..16].try_into().unwrap());
+            if draw % raffle_state.cumulative_stake < u128::from(stake) {
+                raffle_state.winner = *user_state_key;
+            }
+        }
     }
 
+    raffle_state.next_user_id += 1;
+
     Ok(())
 }
 
+/// Ends the walk once every user was visited. Without eligible stake the prize goes
+/// back to the reward and the raffle is settled without winner.
+pub fn finish_raffle_walk(
+    farm_state: &mut FarmState,
+    raffle_state: &mut RaffleState,
+) -> Result<()> {
+    require!(
+        raffle_state.get_status() == RaffleStatus::Drawing,
+        FarmError::InvalidRaffleStatus
+    );
+    if raffle_state.next_user_id < raffle_state.num_users {
+        return Ok(());
+    }
+
+    if raffle_state.cumulative_stake == 0 {
+        vmsg!("farm_operations::finish_raffle_walk no eligible stake, prize returned");
+        return_raffle_prize(farm_state, raffle_state)?;
+        raffle_state.status = RaffleStatus::Settled as u8;
+    } else {
+        vmsg!(
+            "farm_operations::finish_raffle_walk winner={} cumulative_stake={}",
+            raffle_state.winner,
+            raffle_state.cumulative_stake
+        );
+        raffle_state.status = RaffleStatus::Drawn as u8;
+    }
+
+    Ok(())
+}
+
+pub fn credit_raffle_winner(
+    farm_state: &mut FarmState,
+    raffle_state: &mut RaffleState,
+    user_state: &mut UserState,
+    ts: u64,
+) -> Result<()> {
+    require!(
+        raffle_state.get_status() == RaffleStatus::Drawn,
+        FarmError::InvalidRaffleStatus
+    );
+    reward_user_once(
+        farm_state,
+        user_state,
+        raffle_state.reward_index as usize,
+        raffle_state.prize_amount,
+        ts,
+    )?;
+    raffle_state.status = RaffleStatus::Settled as u8;
+    Ok(())
+}
+
+pub fn unstake(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    scope_price: Option<DatedPrice>,
+    requested_stake_withdrawal: Decimal,
+    ts: u64,
+) -> Result<UnstakeEffects> {
+    vmsg!(
+        "farm_operations::unstake amount of stake={}",
+        requested_stake_withdrawal
+    );
+
+    require!(!user_state.is_renounced(), FarmError::PositionRenounced);
+    require!(ts >= user_state.locked_until_ts, FarmError::UserStakeLocked);
+
+    refresh_global_rewards(farm_state, scope_price, ts)?;
+
+    user_refresh_all_rewards(farm_state, user_state)?;
+
+    let stake_share_to_unstake = std::cmp::min(
+        requested_stake_withdrawal,
+        user_state.get_active_stake_decimal(),
+    );
+    require!(
+        stake_share_to_unstake > Decimal::zero(),
+        FarmError::NothingToUnstake
+    );
+
+    if user_state.pending_withdrawal_unstake_scaled > 0 {
+        if user_state.pending_withdrawal_unstake_ts <= ts {
+            xmsg!("farm_operations::unstake pending withdrawal elapsed already exist but not withdrawn yet");
+            return err!(FarmError::PendingWithdrawalNotWithdrawnYet);
+        }
+        vmsg!(
+            "farm_operations::unstake pending withdrawal already exist and will be extended.\
+                already pending withdrawal stake={}, added={}, old ts={}",
+            user_state.get_pending_withdrawal_unstake_decimal(),
+            stake_share_to_unstake,
+            user_state.pending_withdrawal_unstake_ts
+        );
+    }
+
+    user_state.pending_withdrawal_unstake_ts = ts
+        .checked_add(farm_state.withdrawal_cooldown_period.into())
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    let (
+        token_amount_removed,
+        added_pending_withdrawal_unstake,
+        token_amount_penalty,
+        token_amount_unlocked,
+    ) = stake_ops::unstake(user_state, farm_state, stake_share_to_unstake, ts)?;
+
+    vmsg!(
+        "farm_operations::unstake added_pending_withdrawal_unstake={}, token_amount_unstaked={}, token_amount_unlocked={}",
+        added_pending_withdrawal_unstake,
+        token_amount_removed,
+        token_amount_unlocked
+    );
+
+    if farm_state.has_fee_vault() {
+        farm_state.fees_pending_amount += token_amount_penalty;
+        farm_state.fees_cumulative_amount += token_amount_penalty;
+    } else {
+        farm_state.slashed_amount_current += token_amount_penalty;
+        farm_state.slashed_amount_cumulative += token_amount_penalty;
+    }
+
+    update_user_rewards_tally_on_stake_decrease(farm_state, user_state, stake_share_to_unstake)?;
+
+    refresh_active_user(farm_state, user_state)?;
+
+    Ok(UnstakeEffects {
+        amount_to_pending_withdrawal: token_amount_removed,
+        amount_unlocked: token_amount_unlocked,
+        amount_locked: token_amount_removed + token_amount_penalty - token_amount_unlocked,
+        amount_penalty: token_amount_penalty,
+    })
+}
+
+pub fn transfer_stake_internal(
+    farm_state: &mut FarmState,
+    from_user_state: &mut UserState,
+    to_user_state: &mut UserState,
+    scope_price: Option<DatedPrice>,
+    requested_stake_transfer: Decimal,
+    ts: u64,
+) -> Result<Decimal> {
+    vmsg!(
+        "farm_operations::transfer_stake_internal amount of stake={}",
+        requested_stake_transfer
+    );
+
+    require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !from_user_state.is_renounced(),
+        FarmError::PositionRenounced
+    );
+    require!(
+        ts >= from_user_state.locked_until_ts,
+        FarmError::UserStakeLocked
+    );
+
+    refresh_global_rewards(farm_state, scope_price, ts)?;
+
+    user_refresh_all_rewards(farm_state, from_user_state)?;
+    user_refresh_stake(farm_state, from_user_state, ts)?;
+    user_refresh_all_rewards(farm_state, to_user_state)?;
+    user_refresh_stake(farm_state, to_user_state, ts)?;
+
+    let stake_share_to_transfer = std::cmp::min(
+        requested_stake_transfer,
+        from_user_state.get_active_stake_decimal(),
+    );
+    let to_position_amount = user_position_amount(farm_state, to_user_state);
+    if to_position_amount == 0 {
+        to_user_state.penalty_terms_version = from_user_state.penalty_terms_version;
+    }
+    require!(
+        stake_share_to_transfer > Decimal::zero(),
+        FarmError::NothingToUnstake
+    );
+    let amount_transferred = active_stake_to_amount(farm_state, stake_share_to_transfer);
+
+    update_user_rewards_tally_on_stake_decrease(
+        farm_state,
+        from_user_state,
+        stake_share_to_transfer,
+    )?;
+    from_user_state.set_active_stake_decimal(
+        from_user_state.get_active_stake_decimal() - stake_share_to_transfer,
+    );
+
+    to_user_state.set_active_stake_decimal(
+        to_user_state.get_active_stake_decimal() + stake_share_to_transfer,
+    );
+    update_user_rewards_tally_on_stake_increase(
+        farm_state,
+        to_user_state,
+        stake_share_to_transfer,
+    )?;
+
+    // The moved amount tops up the destination lock like a stake would
+    to_user_state.set_last_stake_ts(
+        farm_state.get_locking_mode(),
+        topped_up_lock_start(
+            farm_state.get_lock_top_up_policy(),
+            to_user_state.last_stake_ts,
+            to_position_amount,
+            amount_transferred,
+            ts,
+        ),
+    );
+
+    refresh_active_user(farm_state, from_user_state)?;
+    refresh_active_user(farm_state, to_user_state)?;
+
+    vmsg!(
+        "farm_operations::transfer_stake_internal AFTER: from_active_stake_scaled={} to_active_stake_scaled={}",
+        from_user_state.active_stake_scaled,
+        to_user_state.active_stake_scaled
+    );
+
+    Ok(stake_share_to_transfer)
+}
+
+pub fn split_stake(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    new_user_state: &mut UserState,
+    scope_price: Option<DatedPrice>,
+    requested_stake_split: Decimal,
+    lock_end_ts: u64,
+    ts: u64,
+) -> Result<Decimal> {
+    vmsg!(
+        "farm_operations::split_stake amount of stake={} lock_end_ts={}",
+        requested_stake_split,
+        lock_end_ts
+    );
+
+    if lock_end_ts != 0 {
+        let max_lock_end_ts = ts
+            .checked_add(farm_state.locking_duration)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+        if lock_end_ts <= ts || lock_end_ts > max_lock_end_ts {
+            xmsg!(
+                "farm_operations::split_stake ERROR: lock_end_ts={} must be in ({}, {}]",
+                lock_end_ts,
+                ts,
+                max_lock_end_ts
+            );
+            return err!(FarmError::InvalidLockingTimestamps);
+        }
+    }
+
+    let stake_split = transfer_stake_internal(
+        farm_state,
+        user_state,
+        new_user_state,
+        scope_price,
+        requested_stake_split,
+        ts,
+    )?;
+
+    new_user_state.locked_until_ts = lock_end_ts;
+
+    Ok(stake_split)
+}
+
+/// `ts` is in the farm `time_unit`, `unix_ts` in seconds for the user withdrawal
+/// limit. Fails while withdrawals are queued, they are paid out first.
 pub fn withdraw_unstaked_deposits(
     farm_state: &mut FarmState,
     user_state: &mut UserState,
     ts: u64,
+    unix_ts: u64,
 ) -> Result<WithdrawEffects> {
+    let amount_to_withdraw = remove_elapsed_pending_withdrawal(farm_state, user_state, ts)?;
+
+    require!(
+        farm_state.num_queued_withdrawals == 0,
+        FarmError::WithdrawalQueueNotEmpty
+    );
+    let remaining_allowance = user_withdrawal_allowance(farm_state, unix_ts);
+    if amount_to_withdraw > remaining_allowance {
+        xmsg!(
+            "Withdrawal of {} exceeds the remaining allowance {}, window resets at {}",
+            amount_to_withdraw,
+            remaining_allowance,
+            user_withdrawal_window_reset_ts(farm_state)
+        );
+        return err!(FarmError::UserWithdrawalLimitExceeded);
+    }
+    consume_user_withdrawal_allowance(farm_state, amount_to_withdraw);
+
+    Ok(WithdrawEffects { amount_to_withdraw })
+}
+
+fn remove_elapsed_pending_withdrawal(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    ts: u64,
+) -> Result<u64> {
+    require!(!user_state.is_renounced(), FarmError::PositionRenounced);
     require!(
         user_state.pending_withdrawal_unstake_ts <= ts,
         FarmError::UnstakeNotElapsed
@@ -736,11 +2549,421 @@ pub fn withdraw_unstaked_deposits(
         FarmError::NothingToWithdraw
     );
 
-    let amount_to_withdraw = stake_ops::remove_pending_withdrawal_stake(user_state, farm_state)?;
+    stake_ops::remove_pending_withdrawal_stake(user_state, farm_state).map_err(Into::into)
+}
 
-    Ok(WithdrawEffects { amount_to_withdraw })
+/// Moves the elapsed pending withdrawal of `user_state` out of the pending stake, to
+/// be queued. Only withdrawals `withdraw_unstaked_deposits` would refuse can be queued.
+pub fn queue_withdrawal(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    ts: u64,
+    unix_ts: u64,
+) -> Result<u64> {
+    let amount = remove_elapsed_pending_withdrawal(farm_state, user_state, ts)?;
+
+    let blocked = farm_state.num_queued_withdrawals > 0
+        || amount > user_withdrawal_allowance(farm_state, unix_ts);
+    require!(blocked, FarmError::WithdrawalNotBlocked);
+
+    farm_state.queued_withdrawal_amount = farm_state
+        .queued_withdrawal_amount
+        .checked_add(amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    farm_state.num_queued_withdrawals += 1;
+
+    vmsg!(
+        "farm_operations::queue_withdrawal amount={} num_queued_withdrawals={}",
+        amount,
+        farm_state.num_queued_withdrawals
+    );
+
+    Ok(amount)
+}
+
+/// Gives the queued `amount` back to `user_state` as pending withdrawal, withdrawable
+/// once its current `pending_withdrawal_unstake_ts` elapsed.
+pub fn cancel_queued_withdrawal(
+    farm_state: &mut FarmState,
+    user_state: &mut UserState,
+    amount: u64,
+) -> Result<()> {
+    farm_state.queued_withdrawal_amount = farm_state
+        .queued_withdrawal_amount
+        .checked_sub(amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    farm_state.num_queued_withdrawals = farm_state
+        .num_queued_withdrawals
+        .checked_sub(1)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    stake_ops::add_pending_withdrawal_stake(user_state, farm_state, amount)?;
+
+    vmsg!(
+        "farm_operations::cancel_queued_withdrawal amount={} num_queued_withdrawals={}",
+        amount,
+        farm_state.num_queued_withdrawals
+    );
+
+    Ok(())
+}
+
+/// Amount paid out now of the queued withdrawal of `amount` at the head of the queue,
+/// within the user withdrawal allowance. The withdrawal leaves the queue once paid out
+/// in full, otherwise the rest waits for the next window.
+pub fn pay_queued_withdrawal(farm_state: &mut FarmState, amount: u64, unix_ts: u64) -> Result<u64> {
+    let paid = cmp::min(amount, user_withdrawal_allowance(farm_state, unix_ts));
+    consume_user_withdrawal_allowance(farm_state, paid);
+    farm_state.queued_withdrawal_amount = farm_state
+        .queued_withdrawal_amount
+        .checked_sub(paid)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    if paid == amount {
+        farm_state.num_queued_withdrawals = farm_state
+            .num_queued_withdrawals
+            .checked_sub(1)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    }
+
+    vmsg!(
+        "farm_operations::pay_queued_withdrawal amount={} paid={} window_reset_ts={}",
+        amount,
+        paid,
+        user_withdrawal_window_reset_ts(farm_state)
+    );
+
+    Ok(paid)
+}
+
+/// Allowance left in the current user withdrawal window, rolling over to a new window
+/// once the current one elapsed. `u64::MAX` without a limit.
+fn user_withdrawal_allowance(farm_state: &mut FarmState, unix_ts: u64) -> u64 {
+    if farm_state.max_user_withdrawal_per_window == 0 {
+        return u64::MAX;
+    }
+
+    if unix_ts >= user_withdrawal_window_reset_ts(farm_state) {
+        farm_state.user_withdrawal_window_start_ts = unix_ts;
+        farm_state.user_withdrawn_in_window = 0;
+    }
+
+    farm_state
+        .max_user_withdrawal_per_window
+        .saturating_sub(farm_state.user_withdrawn_in_window)
+}
+
+fn user_withdrawal_window_reset_ts(farm_state: &FarmState) -> u64 {
+    farm_state
+        .user_withdrawal_window_start_ts
+        .saturating_add(farm_state.user_withdrawal_window_s)
+}
+
+fn consume_user_withdrawal_allowance(farm_state: &mut FarmState, amount: u64) {
+    if farm_state.max_user_withdrawal_per_window > 0 {
+        farm_state.user_withdrawn_in_window += amount;
+    }
+}
+
+/// Converts an amount read from the reward curve into reward tokens, applying the
+/// reward type, the rps decimals and the oracle price.
+pub(crate) fn scale_curve_amount(
+    farm_state: &FarmState,
+    reward_info: &RewardInfo,
+    curve_amt: u128,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<u128> {
+    let reward_type_amt = match reward_info.reward_type() {
+        RewardType::Proportional => curve_amt,
+        RewardType::Constant => curve_amt * u128::from(farm_state.total_staked_amount),
+        RewardType::ConstantPerUser => curve_amt * u128::from(farm_state.active_user_count),
+    };
+
+    let decimal_adjusted_amt =
+        reward_type_amt / u128::from(ten_pow(reward_info.rewards_per_second_decimals.into()));
+
+    if farm_state.scope_oracle_price_id == u64::MAX {
+        return Ok(decimal_adjusted_amt);
+    }
+
+    let price = scope_price.ok_or(FarmError::MissingScopePrices)?;
+    if ts - price.unix_timestamp > farm_state.scope_oracle_max_age {
+        xmsg!(
+            "ts={} price_ts={} max_age={}",
+            ts,
+            price.unix_timestamp,
+            farm_state.scope_oracle_max_age
+        );
+        return Err(FarmError::ScopeOraclePriceTooOld.into());
+    }
+
+    vmsg!("Price: {:?}", price);
+    let px = price.price.value as u128;
+    let factor = ten_pow(price.price.exp as usize) as u128;
+    Ok(decimal_adjusted_amt * px / factor)
+}
+
+pub(crate) fn is_priced(farm_state: &FarmState, scope_price: Option<DatedPrice>, ts: u64) -> bool {
+    let price_is_fresh = matches!(
+        scope_price,
+        Some(price) if ts.saturating_sub(price.unix_timestamp) <= farm_state.scope_oracle_max_age
+    );
+    farm_state.scope_oracle_price_id == u64::MAX || price_is_fresh
+}
+
+/// Same as `reward_runway`, `None` when the farm has an oracle and `scope_price` is
+/// missing or too old.
+pub fn reward_runway_if_priced(
+    farm_state: &FarmState,
+    reward_index: usize,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<Option<u64>> {
+    if !is_priced(farm_state, scope_price, ts) {
+        return Ok(None);
+    }
+
+    reward_runway(farm_state, reward_index, scope_price, ts).map(Some)
+}
+
+/// Reward tokens issued per time unit at the current rate of the curve and the
+/// current TVL, before the `rewards_available` cap. `None` when the farm has an
+/// oracle and `scope_price` is missing or too old.
+pub fn reward_issuance_rate_if_priced(
+    farm_state: &FarmState,
+    reward_index: usize,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<Option<u64>> {
+    if !is_priced(farm_state, scope_price, ts) {
+        return Ok(None);
+    }
+
+    let issued_per_time_unit = reward_issuance_rate(farm_state, reward_index, scope_price, ts)?;
+    Ok(Some(issued_per_time_unit.try_into().unwrap_or(u64::MAX)))
+}
+
+fn reward_issuance_rate(
+    farm_state: &FarmState,
+    reward_index: usize,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<u128> {
+    let reward_info = &farm_state.reward_infos[reward_index];
+    let current_rps = reward_info.reward_schedule_curve.get_current_rps(ts)?;
+    scale_curve_amount(
+        farm_state,
+        reward_info,
+        u128::from(current_rps),
+        scope_price,
+        ts,
+    )
+}
+
+/// How much of the emissions left on the curve `rewards_available` covers, scaled to
+/// the current TVL like `reward_runway`. `None` when the curve never stops issuing, or
+/// when the farm has an oracle and `scope_price` is missing or too old.
+pub fn reward_funding_coverage(
+    farm_state: &FarmState,
+    reward_index: usize,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<Option<RewardFundingCoverage>> {
+    if !is_priced(farm_state, scope_price, ts) {
+        return Ok(None);
+    }
+    let reward_info = &farm_state.reward_infos[reward_index];
+    let Some(curve_remaining) = reward_info
+        .reward_schedule_curve
+        .get_remaining_amount_scheduled(ts)?
+    else {
+        return Ok(None);
+    };
+
+    let scheduled_remaining = scale_curve_amount(
+        farm_state,
+        reward_info,
+        u128::from(curve_remaining),
+        scope_price,
+        ts,
+    )?;
+    let coverage_bps = if scheduled_remaining == 0 {
+        u64::MAX
+    } else {
+        (u128::from(reward_info.rewards_available) * u128::from(BPS_DIV_FACTOR)
+            / scheduled_remaining)
+            .try_into()
+            .unwrap_or(u64::MAX)
+    };
+
+    vmsg!(
+        "farm_operations::reward_funding_coverage reward_index={} scheduled_remaining={} coverage_bps={}",
+        reward_index,
+        scheduled_remaining,
+        coverage_bps
+    );
+
+    Ok(Some(RewardFundingCoverage {
+        scheduled_remaining,
+        coverage_bps,
+    }))
+}
+
+pub fn refresh_projected_depletion_ts(
+    farm_state: &mut FarmState,
+    reward_index: usize,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<u64> {
+    let projected_depletion_ts =
+        match reward_runway_if_priced(farm_state, reward_index, scope_price, ts)? {
+            Some(u64::MAX) => u64::MAX,
+            Some(runway) => ts.saturating_add(runway),
+            None => 0,
+        };
+
+    vmsg!(
+        "farm_operations::refresh_projected_depletion_ts reward_index={} projected_depletion_ts={}",
+        reward_index,
+        projected_depletion_ts
+    );
+
+    farm_state.reward_infos[reward_index].projected_depletion_ts = projected_depletion_ts;
+
+    Ok(projected_depletion_ts)
+}
+
+/// Time units until `rewards_available` runs out at the current rate of the curve and
+/// the current TVL, `u64::MAX` when nothing is being issued. Later curve points and
+/// stake changes are not accounted for.
+pub fn reward_runway(
+    farm_state: &FarmState,
+    reward_index: usize,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<u64> {
+    let reward_info = &farm_state.reward_infos[reward_index];
+    if reward_info.shortfall != 0 {
+        return Ok(u64::MAX);
+    }
+    let issued_per_time_unit = reward_issuance_rate(farm_state, reward_index, scope_price, ts)?;
+
+    if issued_per_time_unit == 0 {
+        return Ok(u64::MAX);
+    }
+
+    Ok(
+        (u128::from(reward_info.rewards_available) / issued_per_time_unit)
+            .try_into()
+            .unwrap_or(u64::MAX),
+    )
 }
 
+pub fn farm_status(
+    farm_state: &FarmState,
+    scope_price: Option<DatedPrice>,
+    ts: u64,
+) -> Result<FarmStatus> {
+    let has_oracle = farm_state.scope_oracle_price_id != u64::MAX;
+    let fresh_price = scope_price
+        .filter(|price| ts.saturating_sub(price.unix_timestamp) <= farm_state.scope_oracle_max_age);
+
+    let total_staked_value = match fresh_price {
+        Some(price) if has_oracle => Some(quote_value(farm_state.total_staked_amount, &price)),
+        _ => None,
+    };
+
+    let staked_in_cap_units = if has_oracle {
+        total_staked_value
+    } else {
+        Some(farm_state.total_staked_amount)
+    };
+    let deposit_cap_utilization_bps = match staked_in_cap_units {
+        Some(staked) if farm_state.deposit_cap_amount > 0 => Some(
+            (u128::from(staked) * u128::from(BPS_DIV_FACTOR)
+                / u128::from(farm_state.deposit_cap_amount))
+            .try_into()
+            .unwrap_or(u64::MAX),
+        ),
+        _ => None,
+    };
+
+    let mut rewards = Vec::with_capacity(farm_state.num_reward_tokens as usize);
+    for reward_index in 0..farm_state.num_reward_tokens as usize {
+        let reward_info = &farm_state.reward_infos[reward_index];
+        rewards.push(RewardStatus {
+            mint: reward_info.token.mint,
+            reward_type: reward_info.reward_type,
+            current_rps: reward_info.reward_schedule_curve.get_current_rps(ts)?,
+            rps_decimals: reward_info.rewards_per_second_decimals,
+            rewards_available: reward_info.rewards_available,
+            runway: reward_runway_if_priced(farm_state, reward_index, scope_price, ts)?,
+            projected_depletion_ts: reward_info.projected_depletion_ts,
+            shortfall: reward_info.shortfall != 0,
+            issuance_rate: reward_issuance_rate_if_priced(
+                farm_state,
+                reward_index,
+                scope_price,
+                ts,
+            )?,
+        });
+    }
+
+    let pending_farm_admin = (farm_state.pending_farm_admin != Pubkey::default()
+        && farm_state.pending_farm_admin != farm_state.farm_admin)
+        .then_some(farm_state.pending_farm_admin);
+
+    Ok(FarmStatus {
+        version: FARM_STATUS_VERSION,
+        ts,
+        total_staked_amount: farm_state.total_staked_amount,
+        total_staked_value,
+        deposit_cap_amount: farm_state.deposit_cap_amount,
+        deposit_cap_utilization_bps,
+        pause_flags: farm_state.pause_flags,
+        is_farm_frozen: farm_state.is_farm_frozen != 0,
+        pending_farm_admin,
+        rewards,
+        active_user_count: farm_state.active_user_count,
+        delegated_stake_by_authority: farm_state.delegated_stake_by_authority(),
+    })
+}
+
+/// Value of `amount` staked tokens in the quote unit of the oracle, rounded down like
+/// the deposit cap check.
+fn quote_value(amount: u64, price: &DatedPrice) -> u64 {
+    (u128::from(amount) * u128::from(price.price.value)
+        / u128::from(ten_pow(price.price.exp as usize)))
+    .try_into()
+    .unwrap_or(u64::MAX)
+}
+
+pub fn farm_tvl(farm_state: &FarmState, scope_price: Option<DatedPrice>, ts: u64) -> FarmTvl {
+    let price = scope_price.filter(|price| {
+        farm_state.scope_oracle_price_id != u64::MAX
+            && ts.saturating_sub(price.unix_timestamp) <= farm_state.scope_oracle_max_age
+    });
+
+    FarmTvl {
+        version: FARM_TVL_VERSION,
+        ts,
+        total_staked_amount: farm_state.total_staked_amount,
+        total_staked_value: price.map(|price| quote_value(farm_state.total_staked_amount, &price)),
+        price: price.map(|price| TvlPrice {
+            value: price.price.value,
+            exp: price.price.exp,
+            unix_timestamp: price.unix_timestamp,
+        }),
+    }
+}
+
+/// A no-op when no time elapsed since `last_issuance_ts`, whatever the reward type and
+/// curve: it returns before reading the curve, the oracle price or the stake, and
+/// before writing any field.
+///
+/// Nothing is issued while the farm has no active stake, the first staker gets no
+/// emissions of the empty period: they stay in `rewards_available`, extending the
+/// runway, or go to `rewards_diverted_to_treasury` for the `Proportional` rewards of
+/// farms with `zero_stake_emissions_to_treasury`.
 pub fn refresh_global_reward(
     farm_state: &mut FarmState,
     scope_price: Option<DatedPrice>,
@@ -749,11 +2972,21 @@ pub fn refresh_global_reward(
 ) -> Result<()> {
     let reward_info = farm_state.reward_infos[reward_index];
 
+    // Must stay first: refreshing twice in the same slot or second leaves the farm
+    // byte-identical, even with a stale price or a shortfall
     if ts == reward_info.last_issuance_ts {
         return Ok(());
     }
 
-    if farm_state.total_active_stake_scaled == 0 {
+    let is_per_user = reward_info.reward_type() == RewardType::ConstantPerUser;
+    let no_stake = farm_state.total_active_stake_scaled == 0
+        || (is_per_user && farm_state.active_user_count == 0);
+    // The other reward types scale with the stake, so they emit nothing to divert
+    let divert_to_treasury = no_stake
+        && farm_state.zero_stake_emissions_to_treasury != 0
+        && reward_info.reward_type() == RewardType::Proportional;
+
+    if reward_info.shortfall != 0 || (no_stake && !divert_to_treasury) {
         farm_state.reward_infos[reward_index].last_issuance_ts = ts;
         return Ok(());
     }
@@ -764,41 +2997,14 @@ pub fn refresh_global_reward(
             .get_cumulative_amount_issued_since_last_ts(reward_info.last_issuance_ts, ts)?)
             as u128;
 
-        let reward_type_amt = match reward_info.reward_type() {
-            RewardType::Proportional => cumulative_amt,
-            RewardType::Constant => cumulative_amt * u128::from(farm_state.total_staked_amount),
-        };
-
-        let decimal_adjusted_amt =
-            reward_type_amt / u128::from(ten_pow(reward_info.rewards_per_second_decimals.into()));
+        let oracle_adjusted_amt =
+            scale_curve_amount(farm_state, &reward_info, cumulative_amt, scope_price, ts)?;
 
-        let oracle_adjusted_amt = if farm_state.scope_oracle_price_id == u64::MAX {
-            decimal_adjusted_amt
-        } else {
-            let price = scope_price.ok_or(FarmError::MissingScopePrices)?;
-            if ts - price.unix_timestamp > farm_state.scope_oracle_max_age {
-                xmsg!(
-                    "ts={} price_ts={} max_age={}",
-                    ts,
-                    price.unix_timestamp,
-                    farm_state.scope_oracle_max_age
-                );
-                return Err(FarmError::ScopeOraclePriceTooOld.into());
-            } else {
-                xmsg!("Price: {:?}", price);
-                let decimal_adjusted_amt = decimal_adjusted_amt as u128;
-                let px = price.price.value as u128;
-                let factor = ten_pow(price.price.exp as usize) as u128;
-                decimal_adjusted_amt * px / factor
-            }
-        };
-
-        xmsg!(
-            "time_passed={} reward_type={:?} cumulative_amt={} decimal_adjusted_amt={} oracle_adjusted_amt={} ",
+        vmsg!(
+            "time_passed={} reward_type={:?} cumulative_amt={} oracle_adjusted_amt={} ",
             ts - reward_info.last_issuance_ts,
             reward_info.reward_type(),
             cumulative_amt,
-            decimal_adjusted_amt,
             oracle_adjusted_amt,
         );
 
@@ -811,7 +3017,24 @@ pub fn refresh_global_reward(
 
     let rewards = cmp::min(amount, reward_info.rewards_available);
 
-    xmsg!(
+    if divert_to_treasury {
+        vmsg!(
+            "farm_operations::refresh_global_reward diverting_reward={} last_ts={} ts={}",
+            rewards,
+            reward_info.last_issuance_ts,
+            ts
+        );
+        let reward_info = &mut farm_state.reward_infos[reward_index];
+        reward_info.last_issuance_ts = ts;
+        reward_info.rewards_available -= rewards;
+        reward_info.rewards_diverted_to_treasury = reward_info
+            .rewards_diverted_to_treasury
+            .checked_add(rewards)
+            .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+        return Ok(());
+    }
+
+    vmsg!(
         "farm_operations::refresh_global_reward issuing_reward={} last_ts={} ts={}",
         rewards,
         reward_info.last_issuance_ts,
@@ -841,7 +3064,9 @@ pub fn refresh_global_reward(
         let mut reward_per_share =
             farm_state.reward_infos[reward_index].get_reward_per_share_decimal();
 
-        let added_reward_per_share = if farm_state.is_delegated() {
+        let added_reward_per_share = if is_per_user {
+            Decimal::from(rewards) / u128::from(farm_state.active_user_count)
+        } else if farm_state.is_delegated() {
             Decimal::from(rewards) / farm_state.total_active_stake_scaled
         } else {
             Decimal::from(rewards) / farm_state.get_total_active_stake_decimal()
@@ -855,12 +3080,94 @@ pub fn refresh_global_reward(
     Ok(())
 }
 
+/// Appends a snapshot to `farm_history` when `history_snapshot_interval` elapsed since
+/// the last one, overwriting the oldest once full. The rewards must be refreshed first.
+pub fn record_farm_history(farm_state: &FarmState, farm_history: &mut FarmHistory, ts: u64) {
+    if farm_state.history_snapshot_interval == 0 {
+        return;
+    }
+    if let Some(last_snapshot) = farm_history.last_snapshot() {
+        if ts
+            < last_snapshot
+                .ts
+                .saturating_add(farm_state.history_snapshot_interval)
+        {
+            return;
+        }
+    }
+
+    let mut rewards_issued = [0; MAX_REWARDS_TOKENS];
+    for (reward_index, reward_info) in farm_state.reward_infos.iter().enumerate() {
+        let last_issued = &mut farm_history.last_rewards_issued_cumulative[reward_index];
+        rewards_issued[reward_index] = reward_info
+            .rewards_issued_cumulative
+            .saturating_sub(*last_issued);
+        *last_issued = reward_info.rewards_issued_cumulative;
+    }
+
+    let index = farm_history.next_index as usize;
+    farm_history.snapshots[index] = FarmHistorySnapshot {
+        ts,
+        total_staked_amount: farm_state.total_staked_amount,
+        rewards_issued,
+    };
+    farm_history.next_index = ((index + 1) % FARM_HISTORY_CAPACITY) as u64;
+    farm_history.num_snapshots += 1;
+
+    vmsg!(
+        "farm_operations::record_farm_history ts={} total_staked_amount={} num_snapshots={}",
+        ts,
+        farm_state.total_staked_amount,
+        farm_history.num_snapshots
+    );
+}
+
+/// Snapshots of `farm_history` from the `start`-th oldest one kept, at most
+/// `MAX_FARM_HISTORY_PAGE_SIZE` of them, with the reward issuance truncated to the
+/// rewards of the farm.
+pub fn farm_history_page(
+    farm_state: &FarmState,
+    farm_history: &FarmHistory,
+    start: u32,
+    limit: u32,
+) -> FarmHistoryPage {
+    let num_kept = cmp::min(farm_history.num_snapshots, FARM_HISTORY_CAPACITY as u64) as usize;
+    let oldest_index = if num_kept < FARM_HISTORY_CAPACITY {
+        0
+    } else {
+        farm_history.next_index as usize
+    };
+    let start = cmp::min(start as usize, num_kept);
+    let end = cmp::min(
+        start + cmp::min(limit as usize, MAX_FARM_HISTORY_PAGE_SIZE),
+        num_kept,
+    );
+
+    let snapshots = (start..end)
+        .map(|i| {
+            let snapshot = &farm_history.snapshots[(oldest_index + i) % FARM_HISTORY_CAPACITY];
+            FarmHistoryEntry {
+                ts: snapshot.ts,
+                total_staked_amount: snapshot.total_staked_amount,
+                rewards_issued: snapshot.rewards_issued[..farm_state.num_reward_tokens as usize]
+                    .to_vec(),
+            }
+        })
+        .collect();
+
+    FarmHistoryPage {
+        version: FARM_HISTORY_VERSION,
+        num_snapshots: farm_history.num_snapshots,
+        snapshots,
+    }
+}
+
 pub fn refresh_global_rewards(
     farm_state: &mut FarmState,
     scope_price: Option<DatedPrice>,
     ts: u64,
 ) -> Result<()> {
-    xmsg!("farm_operations::refresh_global_rewards ts={}", ts);
+    vmsg!("farm_operations::refresh_global_rewards ts={}", ts);
 
     for reward_index in 0..farm_state.num_reward_tokens as usize {
         refresh_global_reward(farm_state, scope_price, ts, reward_index)?;
@@ -870,17 +3177,27 @@ pub fn refresh_global_rewards(
 }
 
 pub fn deposit_to_farm_vault(farm_state: &mut FarmState, amount: u64) -> Result<()> {
-    xmsg!("farm_operations::deposit_to_farm_vault amount={}", amount);
+    vmsg!("farm_operations::deposit_to_farm_vault amount={}", amount);
     stake_ops::increase_total_amount(farm_state, amount).map_err(Into::into)
 }
 
-pub fn withdraw_from_farm_vault(farm_state: &mut FarmState, amount: u64) -> Result<u64> {
-    xmsg!(
-        "farm_operations::withdraw_from_farm_vault amount={}",
-        amount
+pub fn withdraw_from_farm_vault(
+    farm_state: &mut FarmState,
+    amount: u64,
+    override_limit: bool,
+    unix_ts: u64,
+) -> Result<u64> {
+    vmsg!(
+        "farm_operations::withdraw_from_farm_vault amount={} override_limit={}",
+        amount,
+        override_limit
     );
     let res = stake_ops::withdraw_farm(farm_state, amount)?;
 
+    if farm_state.max_admin_withdrawal_per_window > 0 && !override_limit {
+        consume_admin_withdrawal_allowance(farm_state, res.amount_to_withdraw, unix_ts)?;
+    }
+
     if res.farm_to_freeze {
         farm_state.is_farm_frozen = true as u8;
     }
@@ -888,11 +3205,217 @@ pub fn withdraw_from_farm_vault(farm_state: &mut FarmState, amount: u64) -> Resu
     Ok(res.amount_to_withdraw)
 }
 
+fn consume_admin_withdrawal_allowance(
+    farm_state: &mut FarmState,
+    amount: u64,
+    unix_ts: u64,
+) -> Result<()> {
+    let window_end_ts = farm_state
+        .admin_withdrawal_window_start_ts
+        .saturating_add(farm_state.admin_withdrawal_window_s);
+    if unix_ts >= window_end_ts {
+        farm_state.admin_withdrawal_window_start_ts = unix_ts;
+        farm_state.admin_withdrawn_in_window = 0;
+    }
+
+    let window_reset_ts = farm_state
+        .admin_withdrawal_window_start_ts
+        .saturating_add(farm_state.admin_withdrawal_window_s);
+    let remaining_allowance = farm_state
+        .max_admin_withdrawal_per_window
+        .saturating_sub(farm_state.admin_withdrawn_in_window);
+
+    if amount > remaining_allowance {
+        xmsg!(
+            "Admin withdrawal of {} exceeds the remaining allowance {}, window resets at {}",
+            amount,
+            remaining_allowance,
+            window_reset_ts
+        );
+        return err!(FarmError::AdminWithdrawalLimitExceeded);
+    }
+
+    farm_state.admin_withdrawn_in_window += amount;
+
+    vmsg!(
+        "farm_operations::consume_admin_withdrawal_allowance amount={} remaining={} window_reset_ts={}",
+        amount,
+        remaining_allowance - amount,
+        window_reset_ts
+    );
+
+    Ok(())
+}
+
+pub fn reward_shortfall(
+    reward_info: &RewardInfo,
+    rewards_vault_amount: u64,
+) -> Result<RewardShortfall> {
+    let rewards_owed = reward_info
+        .rewards_available
+        .checked_add(reward_info.rewards_issued_unclaimed)
+        .and_then(|owed| owed.checked_add(reward_info.rewards_diverted_to_treasury))
+        .and_then(|owed| owed.checked_add(reward_info.referral_fees_unclaimed))
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    Ok(RewardShortfall {
+        rewards_vault_amount,
+        rewards_owed,
+        deficit: rewards_owed.saturating_sub(rewards_vault_amount),
+    })
+}
+
+pub fn report_reward_shortfall(
+    farm_state: &mut FarmState,
+    scope_price: Option<DatedPrice>,
+    reward_index: usize,
+    rewards_vault_amount: u64,
+    ts: u64,
+) -> Result<RewardShortfall> {
+    refresh_global_rewards(farm_state, scope_price, ts)?;
+
+    let reward_info = &mut farm_state.reward_infos[reward_index];
+    let shortfall = reward_shortfall(reward_info, rewards_vault_amount)?;
+
+    if shortfall.deficit == 0 {
+        xmsg!(
+            "No shortfall: vault={} owed={}",
+            shortfall.rewards_vault_amount,
+            shortfall.rewards_owed
+        );
+        return err!(FarmError::NoRewardShortfall);
+    }
+
+    vmsg!(
+        "farm_operations::report_reward_shortfall reward_index={} {:?}",
+        reward_index,
+        shortfall
+    );
+
+    reward_info.shortfall = 1;
+
+    Ok(shortfall)
+}
+
+/// Takes `referral_fee_bps` of the treasury fee of a harvest for the referrer, the
+/// amount stays in `rewards_vault` until claimed. Returns the treasury fee left.
+pub fn credit_referral_fee(
+    reward_info: &mut RewardInfo,
+    referrer_state: &mut ReferrerState,
+    reward_index: usize,
+    reward_treasury: u64,
+    referral_fee_bps: u64,
+) -> Result<u64> {
+    let amount = u64_mul_div(reward_treasury, referral_fee_bps, BPS_DIV_FACTOR);
+    vmsg!(
+        "farm_operations::credit_referral_fee reward_index={} amount={} of {}",
+        reward_index,
+        amount,
+        reward_treasury
+    );
+
+    referrer_state.fees_accrued[reward_index] = referrer_state.fees_accrued[reward_index]
+        .checked_add(amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    reward_info.referral_fees_unclaimed = reward_info
+        .referral_fees_unclaimed
+        .checked_add(amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    Ok(reward_treasury - amount)
+}
+
+pub fn claim_referral_fees(
+    reward_info: &mut RewardInfo,
+    referrer_state: &mut ReferrerState,
+    reward_index: usize,
+) -> Result<u64> {
+    let amount = referrer_state.fees_accrued[reward_index];
+    vmsg!(
+        "farm_operations::claim_referral_fees reward_index={} amount={}",
+        reward_index,
+        amount
+    );
+
+    reward_info.referral_fees_unclaimed = reward_info
+        .referral_fees_unclaimed
+        .checked_sub(amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    referrer_state.fees_accrued[reward_index] = 0;
+    referrer_state.fees_claimed_cumulative[reward_index] = referrer_state.fees_claimed_cumulative
+        [reward_index]
+        .checked_add(amount)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    Ok(amount)
+}
+
 pub fn withdraw_slashed_amount(farm_state: &mut FarmState) -> Result<u64> {
     let amount = farm_state.slashed_amount_current;
     farm_state.slashed_amount_current = 0;
     Ok(amount)
 }
+
+/// Refreshes the rewards, then hands the emissions diverted to the treasury over to
+/// the caller for the transfer.
+pub fn sweep_diverted_rewards(
+    farm_state: &mut FarmState,
+    scope_price: Option<DatedPrice>,
+    reward_index: usize,
+    ts: u64,
+) -> Result<u64> {
+    refresh_global_rewards(farm_state, scope_price, ts)?;
+
+    let reward_info = &mut farm_state.reward_infos[reward_index];
+    let amount = reward_info.rewards_diverted_to_treasury;
+    reward_info.rewards_diverted_to_treasury = 0;
+    vmsg!(
+        "farm_operations::sweep_diverted_rewards reward_index={} amount={}",
+        reward_index,
+        amount
+    );
+    Ok(amount)
+}
+
+pub fn sweep_farm_fees(farm_state: &mut FarmState) -> Result<u64> {
+    let amount = farm_state.fees_pending_amount;
+    farm_state.fees_pending_amount = 0;
+    vmsg!("farm_operations::sweep_farm_fees amount={}", amount);
+    Ok(amount)
+}
+
+fn update_user_rewards_tally_on_stake_decrease(
+    farm_state: &FarmState,
+    user_state: &mut UserState,
+    removed_shares: Decimal,
+) -> Result<()> {
+    user_state.raffles_at_last_stake_change = farm_state.num_raffles;
+
+    for i in 0..farm_state.num_reward_tokens as usize {
+        let reward_tally = &mut user_state.rewards_tally_scaled[i];
+        let reward_info = &farm_state.reward_infos[i];
+        if reward_info.reward_type() == RewardType::ConstantPerUser {
+            continue;
+        }
+
+        let reward_tally_decimal = Decimal::from_scaled_val(*reward_tally);
+        let tally_loss = removed_shares * reward_info.get_reward_per_share_decimal();
+
+        require_gt!(
+            reward_tally_decimal + Decimal::one(),
+            tally_loss,
+            FarmError::IntegerOverflow
+        );
+        let reward_tally_scaled: u128 = reward_tally_decimal.to_scaled_val().unwrap();
+        let tally_loss_scaled: u128 = tally_loss.to_scaled_val().unwrap();
+        let new_reward_tally_decimal_scaled = reward_tally_scaled.saturating_sub(tally_loss_scaled);
+
+        *reward_tally = new_reward_tally_decimal_scaled;
+    }
+
+    Ok(())
+}
+
 fn update_user_rewards_tally_on_stake_increase(
     farm_state: &mut FarmState,
     user_state: &mut UserState,
@@ -901,13 +3424,17 @@ fn update_user_rewards_tally_on_stake_increase(
     if added_shares == Decimal::zero() {
         return Ok(());
     }
+    user_state.raffles_at_last_stake_change = farm_state.num_raffles;
 
-    xmsg!(
+    vmsg!(
         "farm_operations::update_user_rewards_tally_on_stake_increase amount(scaled)={}",
         added_shares.to_scaled_val::<u128>().unwrap()
     );
     for index in 0..farm_state.num_reward_tokens as usize {
         let reward_info = farm_state.reward_infos[index];
+        if reward_info.reward_type() == RewardType::ConstantPerUser {
+            continue;
+        }
 
         let rewards_tally = user_state.get_rewards_tally_decimal(index);
         let reward_per_share = reward_info.get_reward_per_share_decimal();
@@ -918,7 +3445,7 @@ fn update_user_rewards_tally_on_stake_increase(
             .to_scaled_val()
             .map_err(|_| dbg_msg!(FarmError::IntegerOverflow))?;
 
-        xmsg!(
+        vmsg!(
             "farm_operations::update_user_rewards_tally_on_stake_increase reward_index={} new_reward_tally(scaled)={}",
             index,
             new_reward_tally.to_scaled_val::<u128>().unwrap()
@@ -1,20 +1,33 @@
-use crate::state::TimeUnit;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::emit_event;
+use crate::events::{RewardDepletionProjectionEvent, RewardFundingCoverageEvent};
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::token_operations;
+use crate::utils::constraints::{
+    check_instruction_enabled, check_remaining_accounts, check_reward_index,
+};
 use crate::utils::consts::BASE_SEED_FARM_VAULTS_AUTHORITY;
 use crate::utils::scope::load_scope_price;
 use crate::FarmState;
-use crate::{farm_operations, types::AddRewardEffects, FarmError};
+use crate::{farm_operations, types::AddRewardEffects, FarmError, GlobalConfig};
+use crate::{vmsg, xmsg};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{self, Mint, Token, TokenAccount, Transfer};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
+/// Only the amount received by `reward_vault` is added, less than `amount` when the
+/// reward mint charges a transfer fee.
+///
+/// Warns, without failing, when the funding covers less of the emissions left on the
+/// curve than `GlobalConfig::min_reward_funding_coverage_bps`.
 pub fn process(ctx: Context<AddReward>, amount: u64, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, ProgramInstruction::AddRewards)?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
     let time_unit = farm_state.time_unit;
     let reward_mint = &mut ctx.accounts.reward_mint;
     let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
-    msg!(
+    let reward_index = check_reward_index(farm_state, reward_index)?;
+    vmsg!(
         "AddReward farm_state {:?} amount {}, reward_index {} ts {}",
         ctx.accounts.farm_state.key(),
         amount,
@@ -22,56 +35,106 @@ pub fn process(ctx: Context<AddReward>, amount: u64, reward_index: u64) -> Resul
         TimeUnit::now_from_clock(time_unit, &Clock::get()?)
     );
 
-    let AddRewardEffects { reward_amount } = farm_operations::add_reward(
+    let reward_vault_amount = ctx.accounts.reward_vault.amount;
+    token_operations::transfer_token_from_user(
+        &farm_state.reward_infos[reward_index].token,
+        amount,
+        &ctx.accounts.payer_reward_token_ata.to_account_info(),
+        &ctx.accounts.reward_vault.to_account_info(),
+        &ctx.accounts.payer.to_account_info(),
+        Some(reward_mint.to_account_info()),
+        &ctx.accounts.token_program,
+    )?;
+    let amount_received =
+        token_operations::received_amount(&mut ctx.accounts.reward_vault, reward_vault_amount)?;
+
+    let AddRewardEffects {
+        reward_amount,
+        projected_depletion_ts,
+        funding_coverage,
+    } = farm_operations::add_reward(
         farm_state,
         scope_price,
         reward_mint.key(),
-        reward_index as usize,
-        amount,
+        reward_index,
+        amount_received,
+        reward_vault_amount,
         TimeUnit::now_from_clock(time_unit, &Clock::get()?),
     )?;
 
-    msg!(
+    vmsg!(
         "add {} to reward {:?} index {}",
         reward_amount,
         reward_mint.key(),
         reward_index
     );
 
-    token::transfer(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            Transfer {
-                from: ctx
-                    .accounts
-                    .payer_reward_token_ata
-                    .to_account_info()
-                    .clone(),
-                to: ctx.accounts.reward_vault.to_account_info().clone(),
-                authority: ctx.accounts.payer.to_account_info().clone(),
-            },
-        ),
-        reward_amount,
-    )?;
+    emit_event!(
+        ctx,
+        RewardDepletionProjectionEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            reward_index: reward_index as u64,
+            reward_mint: reward_mint.key(),
+            rewards_available: farm_state.reward_infos[reward_index].rewards_available,
+            projected_depletion_ts,
+        }
+    );
+
+    let min_coverage_bps = ctx
+        .accounts
+        .global_config
+        .load()?
+        .min_reward_funding_coverage_bps;
+    let mut below_threshold = false;
+    if let Some(coverage) = &funding_coverage {
+        if coverage.coverage_bps < min_coverage_bps {
+            xmsg!(
+                "WARNING: reward {} funding covers {} bps of the scheduled emissions, below the {} bps minimum",
+                reward_index,
+                coverage.coverage_bps,
+                min_coverage_bps
+            );
+            below_threshold = true;
+        }
+    }
+
+    emit_event!(
+        ctx,
+        RewardFundingCoverageEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            reward_index: reward_index as u64,
+            reward_mint: reward_mint.key(),
+            rewards_available: farm_state.reward_infos[reward_index].rewards_available,
+            rewards_vault_amount: ctx.accounts.reward_vault.amount,
+            scheduled_remaining: funding_coverage
+                .as_ref()
+                .map(|coverage| coverage.scheduled_remaining),
+            coverage_bps: funding_coverage.map(|coverage| coverage.coverage_bps),
+            below_threshold,
+        }
+    );
 
     Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 #[instruction(amount: u64, reward_index: u64)]
 pub struct AddReward<'info> {
     #[account(mut)]
     pub payer: Signer<'info>,
 
-    #[account(mut)]
+    #[account(mut,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
     pub farm_state: AccountLoader<'info, FarmState>,
 
     #[account(mut,
         token::mint = reward_mint,
         token::authority = farm_vaults_authority,
-        constraint = reward_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault,
+        constraint = reward_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
     )]
-    pub reward_vault: Box<Account<'info, TokenAccount>>,
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
@@ -83,11 +146,14 @@ pub struct AddReward<'info> {
         constraint = payer_reward_token_ata.mint == reward_mint.key() @ FarmError::RewardAtaRewardMintMissmatch,
         constraint = payer_reward_token_ata.owner == payer.key() @ FarmError::RewardAtaOwnerNotPayer,
     )]
-    pub payer_reward_token_ata: Box<Account<'info, TokenAccount>>,
+    pub payer_reward_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    pub reward_mint: Account<'info, Mint>,
+    pub reward_mint: InterfaceAccount<'info, Mint>,
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -0,0 +1,60 @@
+use crate::emit_event;
+use crate::events::CancelPendingFarmAdminEvent;
+use crate::state::{GlobalConfig, ProgramInstruction};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::vmsg;
+use crate::{FarmError, FarmState};
+use anchor_lang::prelude::*;
+
+/// Resets the pending admin to the current admin, which is how a farm without a
+/// pending transfer is initialized.
+pub fn process(ctx: Context<CancelPendingFarmAdmin>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::CancelPendingFarmAdmin,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let global_config = ctx.accounts.global_config.load()?;
+    let authority = ctx.accounts.authority.key();
+
+    require!(
+        authority == farm_state.farm_admin || authority == global_config.global_admin,
+        FarmError::InvalidAdminAuthority
+    );
+
+    let cancelled_pending_farm_admin = farm_state.pending_farm_admin;
+    farm_state.pending_farm_admin = farm_state.farm_admin;
+
+    vmsg!(
+        "CancelPendingFarmAdmin farm {} cancelled {} by {}",
+        ctx.accounts.farm_state.key(),
+        cancelled_pending_farm_admin,
+        authority
+    );
+
+    emit_event!(
+        ctx,
+        CancelPendingFarmAdminEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            authority,
+            cancelled_pending_farm_admin,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct CancelPendingFarmAdmin<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        has_one = global_config,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
@@ -0,0 +1,69 @@
+use crate::emit_event;
+use crate::events::QueuedWithdrawalCancelledEvent;
+use crate::farm_operations;
+use crate::state::{GlobalConfig, ProgramInstruction, WithdrawalQueue};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::{FarmError, FarmState, UserState};
+use anchor_lang::prelude::*;
+
+/// Takes the queued withdrawal of the user out of the queue, back to its pending
+/// withdrawal. Whatever was already paid out of it stays paid.
+pub fn process(ctx: Context<CancelQueuedWithdrawal>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::CancelQueuedWithdrawal,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+
+    let user_state_key = ctx.accounts.user_state.key();
+    let withdrawals = &mut ctx.accounts.withdrawal_queue.withdrawals;
+    let position = withdrawals
+        .iter()
+        .position(|withdrawal| withdrawal.user_state == user_state_key)
+        .ok_or(FarmError::NoQueuedWithdrawal)?;
+    let withdrawal = withdrawals.remove(position);
+
+    farm_operations::cancel_queued_withdrawal(farm_state, user_state, withdrawal.amount)?;
+
+    emit_event!(
+        ctx,
+        QueuedWithdrawalCancelledEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            user_state: user_state_key,
+            owner: ctx.accounts.owner.key(),
+            amount: withdrawal.amount,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct CancelQueuedWithdrawal<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut,
+        has_one = global_config,
+        has_one = withdrawal_queue,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_WITHDRAWAL_QUEUE, farm_state.key().as_ref()],
+        bump = withdrawal_queue.bump,
+    )]
+    pub withdrawal_queue: Account<'info, WithdrawalQueue>,
+}
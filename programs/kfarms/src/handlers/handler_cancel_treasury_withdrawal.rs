@@ -0,0 +1,44 @@
+use crate::emit_event;
+use crate::events::TreasuryWithdrawalCancelledEvent;
+use crate::state::{GlobalConfig, ProgramInstruction, TreasuryWithdrawalRequest};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use anchor_lang::prelude::*;
+
+pub fn process(ctx: Context<CancelTreasuryWithdrawal>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::CancelTreasuryWithdrawal,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let request = ctx.accounts.withdrawal_request.load()?;
+
+    emit_event!(
+        ctx,
+        TreasuryWithdrawalCancelledEvent {
+            global_config: ctx.accounts.global_config.key(),
+            request: ctx.accounts.withdrawal_request.key(),
+            request_id: request.request_id,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct CancelTreasuryWithdrawal<'info> {
+    #[account(mut)]
+    pub global_admin: Signer<'info>,
+
+    #[account(
+        has_one = global_admin
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        close = global_admin,
+        has_one = global_config,
+    )]
+    pub withdrawal_request: AccountLoader<'info, TreasuryWithdrawalRequest>,
+}
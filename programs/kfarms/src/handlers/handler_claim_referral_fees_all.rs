@@ -0,0 +1,139 @@
+use crate::err_ctx;
+use crate::farm_operations;
+use crate::gen_signer_seeds_two;
+use crate::state::ProgramInstruction;
+use crate::token_operations;
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, ReferrerState};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+
+/// Drains every non-zero referral fee bucket of `referrer_state`.
+///
+/// `remaining_accounts` must hold one `(rewards_vault, referrer_reward_ata)` pair
+/// per non-zero bucket, in ascending reward index order, followed by the reward mint
+/// for Token-2022 rewards.
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, ClaimReferralFeesAll<'info>>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::ClaimReferralFeesAll,
+    )?;
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let referrer_state = &mut ctx.accounts.referrer_state.load_mut()?;
+    let referrer = ctx.accounts.referrer.key();
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+        BASE_SEED_FARM_VAULTS_AUTHORITY,
+        farm_state_key,
+        farm_state.farm_vaults_authority_bump as u8
+    );
+
+    let mut remaining_accounts = ctx.remaining_accounts.iter();
+    let mut total_claimed_buckets = 0;
+
+    for reward_index in 0..farm_state.num_reward_tokens as usize {
+        if referrer_state.fees_accrued[reward_index] == 0 {
+            continue;
+        }
+
+        let reward_info = &farm_state.reward_infos[reward_index];
+        let rewards_vault = remaining_accounts
+            .next()
+            .ok_or(FarmError::MissingRemainingAccounts)?;
+        let referrer_reward_ata = remaining_accounts
+            .next()
+            .ok_or(FarmError::MissingRemainingAccounts)?;
+        let reward_mint = if reward_info.token.is_spl_token() {
+            None
+        } else {
+            Some(
+                remaining_accounts
+                    .next()
+                    .ok_or(FarmError::MissingRemainingAccounts)?
+                    .clone(),
+            )
+        };
+
+        require_keys_eq!(
+            rewards_vault.key(),
+            reward_info.rewards_vault,
+            FarmError::RewardVaultMismatch
+        );
+
+        let destination = InterfaceAccount::<TokenAccount>::try_from(referrer_reward_ata)?;
+        require_keys_eq!(
+            destination.mint,
+            reward_info.token.mint,
+            FarmError::UserAtaRewardVaultMintMissmatch
+        );
+        require_keys_eq!(destination.owner, referrer, FarmError::UnexpectedAccount);
+
+        let amount = farm_operations::claim_referral_fees(
+            &mut farm_state.reward_infos[reward_index],
+            referrer_state,
+            reward_index,
+        )?;
+
+        vmsg!(
+            "ClaimReferralFees referrer {} reward_index {} amount {}",
+            referrer,
+            reward_index,
+            amount
+        );
+
+        token_operations::transfer_token_from_vault(
+            &farm_state.reward_infos[reward_index].token,
+            amount,
+            signer_seeds,
+            referrer_reward_ata,
+            rewards_vault,
+            &ctx.accounts.farm_vaults_authority,
+            reward_mint,
+            &ctx.accounts.token_program,
+        )?;
+
+        total_claimed_buckets += 1;
+    }
+
+    if let Some(extra_account) = remaining_accounts.next() {
+        return err_ctx!(
+            FarmError::UnexpectedAccount,
+            extra_account = extra_account.key(),
+            claimed_buckets = total_claimed_buckets,
+        );
+    }
+
+    require!(total_claimed_buckets > 0, FarmError::NothingToWithdraw);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct ClaimReferralFeesAll<'info> {
+    pub referrer: Signer<'info>,
+
+    #[account(mut,
+        has_one = referrer,
+        has_one = farm_state,
+    )]
+    pub referrer_state: AccountLoader<'info, ReferrerState>,
+
+    #[account(mut,
+        has_one = farm_vaults_authority,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_vaults_authority: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
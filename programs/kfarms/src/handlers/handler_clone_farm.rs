@@ -0,0 +1,125 @@
+use crate::state::{FarmRegistry, GlobalConfig, ProgramInstruction, TimeUnit};
+use crate::state::{RewardInfo, TokenInfo};
+use crate::token_operations;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::utils::registry;
+use crate::vmsg;
+use crate::{farm_operations, FarmError, FarmState};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Initializes a new farm on the template's token with fresh vaults, copying the
+/// template configuration except for its identity (admins, authorities, strategy,
+/// spill address) and its rewards, which are left uninitialized.
+pub fn process(ctx: Context<CloneFarm>) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, ProgramInstruction::CloneFarm)?;
+    check_remaining_accounts(&ctx)?;
+
+    let template_farm_state = ctx.accounts.template_farm_state.load()?;
+    let mut farm_state = ctx.accounts.farm_state.load_init()?;
+    let global_config = &mut ctx.accounts.global_config.load_mut()?;
+
+    farm_operations::check_farm_token_mint(
+        global_config,
+        &ctx.accounts.token_mint.key(),
+        ctx.accounts.token_mint.freeze_authority.into(),
+    )?;
+    token_operations::check_mint_extensions(
+        global_config,
+        &ctx.accounts.token_mint.to_account_info(),
+    )?;
+    let transfer_hook_program = token_operations::check_farm_token_transfer_hook(
+        global_config,
+        &ctx.accounts.token_mint.to_account_info(),
+    )?;
+
+    farm_operations::register_new_farm(global_config)?;
+
+    if let Some(farm_registry) = &mut ctx.accounts.farm_registry {
+        registry::append_farm_to_registry(
+            farm_registry,
+            ctx.accounts.farm_state.key(),
+            &ctx.accounts.farm_admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
+
+    farm_state.farm_admin = ctx.accounts.farm_admin.key();
+    farm_state.pending_farm_admin = ctx.accounts.farm_admin.key();
+    farm_state.global_config = ctx.accounts.global_config.key();
+    farm_state.farm_vaults_authority = ctx.accounts.farm_vaults_authority.key();
+    farm_state.farm_vaults_authority_bump = ctx.bumps.farm_vaults_authority.into();
+    farm_state.reward_infos = [RewardInfo::default(); 10];
+    farm_state.scope_oracle_price_id = u64::MAX;
+
+    farm_state.token = TokenInfo {
+        mint: ctx.accounts.token_mint.key(),
+        decimals: ctx.accounts.token_mint.decimals as u64,
+        token_program: ctx.accounts.token_program.key(),
+        transfer_hook_program,
+        _padding: [0; 2],
+    };
+    farm_state.farm_vault = ctx.accounts.farm_vault.key();
+    farm_state.delegate_authority = Pubkey::default();
+
+    farm_operations::copy_farm_config(&template_farm_state, &mut farm_state);
+
+    vmsg!(
+        "Clone farm {:?} from template {:?} ts {}",
+        ctx.accounts.farm_state.to_account_info().key(),
+        ctx.accounts.template_farm_state.key(),
+        TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?)
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloneFarm<'info> {
+    #[account(mut)]
+    pub farm_admin: Signer<'info>,
+
+    #[account(zero)]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(mut)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(
+        has_one = global_config,
+        constraint = !template_farm_state.load()?.is_delegated() @ FarmError::FarmDelegated,
+    )]
+    pub template_farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(init,
+        payer = farm_admin,
+        seeds = [BASE_SEED_FARM_VAULT, farm_state.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = farm_vaults_authority,
+    )]
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_vaults_authority: AccountInfo<'info>,
+
+    #[account(
+        constraint = token_mint.key() == template_farm_state.load()?.token.mint @ FarmError::TokenFarmTokenMintMissmatch,
+    )]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = template_farm_state.load()?.token.token_program() @ FarmError::UnexpectedAccount)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(mut,
+        has_one = global_config,
+    )]
+    pub farm_registry: Option<Box<Account<'info, FarmRegistry>>>,
+}
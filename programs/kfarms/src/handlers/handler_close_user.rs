@@ -0,0 +1,62 @@
+use crate::farm_operations;
+use crate::state::ProgramInstruction;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserPage, UserState};
+use anchor_lang::prelude::*;
+
+/// Closes a user with nothing staked, pending or left to claim, its rent going back
+/// to the owner. On farms with user pages its slot is tombstoned.
+pub fn process(ctx: Context<CloseUser>) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, ProgramInstruction::CloseUser)?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let user_state = ctx.accounts.user_state.load()?;
+
+    farm_operations::check_user_closable(&user_state)?;
+
+    if farm_state.user_pages_enabled == 1 {
+        let user_page = ctx
+            .accounts
+            .user_page
+            .as_ref()
+            .ok_or(FarmError::InvalidUserPage)?;
+        farm_operations::remove_user_from_page(
+            &mut user_page.load_mut()?,
+            &ctx.accounts.farm_state.key(),
+            &ctx.accounts.user_state.key(),
+            user_state.user_id,
+        )?;
+    }
+
+    vmsg!(
+        "CloseUser: user {} farm {} user_id {}",
+        ctx.accounts.user_state.key(),
+        ctx.accounts.farm_state.key(),
+        user_state.user_id
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CloseUser<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+        close = owner,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut)]
+    pub user_page: Option<AccountLoader<'info, UserPage>>,
+}
@@ -0,0 +1,106 @@
+use crate::emit_event;
+use crate::events::{RaffleCommittedEvent, RewardDepletionProjectionEvent};
+use crate::farm_operations;
+use crate::state::{ProgramInstruction, RaffleState, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::utils::scope::load_scope_price;
+use crate::{FarmError, FarmState, GlobalConfig};
+use anchor_lang::prelude::*;
+
+/// Sets `prize_amount` of `reward_index` aside for a raffle among the current users of
+/// the farm, drawn with the hash of the first block at or after `slot_target`, see
+/// `settle_raffle`.
+pub fn process(
+    ctx: Context<CommitRaffle>,
+    reward_index: u64,
+    slot_target: u64,
+    prize_amount: u64,
+) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::CommitRaffle,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+    let raffle_state = &mut ctx.accounts.raffle_state.load_init()?;
+
+    let clock = Clock::get()?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &clock);
+    let reward_index = reward_index as usize;
+
+    let projected_depletion_ts = farm_operations::commit_raffle(
+        farm_state,
+        raffle_state,
+        &farm_state_key,
+        scope_price,
+        reward_index,
+        slot_target,
+        prize_amount,
+        clock.slot,
+        ts,
+    )?;
+    raffle_state.bump = ctx.bumps.raffle_state.into();
+
+    emit_event!(
+        ctx,
+        RaffleCommittedEvent {
+            farm_state: farm_state_key,
+            raffle_state: ctx.accounts.raffle_state.key(),
+            raffle_id: raffle_state.raffle_id,
+            reward_index: reward_index as u64,
+            prize_amount,
+            slot_target,
+        }
+    );
+
+    emit_event!(
+        ctx,
+        RewardDepletionProjectionEvent {
+            farm_state: farm_state_key,
+            reward_index: reward_index as u64,
+            reward_mint: farm_state.reward_infos[reward_index].token.mint,
+            rewards_available: farm_state.reward_infos[reward_index].rewards_available,
+            projected_depletion_ts,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(reward_index: u64)]
+pub struct CommitRaffle<'info> {
+    #[account(mut)]
+    pub farm_admin: Signer<'info>,
+
+    #[account(mut,
+        has_one = farm_admin,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        seeds = [
+            BASE_SEED_RAFFLE_STATE,
+            farm_state.key().as_ref(),
+            &farm_state.load()?.num_raffles.to_le_bytes(),
+        ],
+        bump,
+        payer = farm_admin,
+        space = SIZE_RAFFLE_STATE,
+    )]
+    pub raffle_state: AccountLoader<'info, RaffleState>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
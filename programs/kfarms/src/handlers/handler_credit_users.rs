@@ -0,0 +1,108 @@
+use crate::emit_event;
+use crate::events::CreditUsersEvent;
+use crate::state::{ProgramInstruction, TimeUnit, UserState};
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::constraints::check_reward_index;
+use crate::vmsg;
+use crate::{dbg_msg, farm_operations, FarmError, FarmState, GlobalConfig};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+/// Credits `amounts[i]` of `reward_index` to the unclaimed rewards of the i-th user
+/// state of `remaining_accounts`, without moving tokens: `rewards_vault` must already
+/// hold them on top of the rewards owed.
+///
+/// `batch_nonce` must be above the nonce of the last batch applied, so that a
+/// retried batch cannot be credited twice.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, CreditUsers<'info>>,
+    reward_index: u64,
+    batch_nonce: u64,
+    amounts: Vec<u64>,
+) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, ProgramInstruction::CreditUsers)?;
+    require!(
+        !amounts.is_empty() && amounts.len() == ctx.remaining_accounts.len(),
+        FarmError::MissingRemainingAccounts
+    );
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let mut farm_state = ctx.accounts.farm_state.load_mut()?;
+    let reward_index = check_reward_index(&farm_state, reward_index)?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+
+    let total_amount = amounts
+        .iter()
+        .try_fold(0u64, |total, amount| total.checked_add(*amount))
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+
+    farm_operations::start_credit_users_batch(
+        &mut farm_state,
+        reward_index,
+        batch_nonce,
+        total_amount,
+        ctx.accounts.rewards_vault.amount,
+    )?;
+
+    for (user_state_info, amount) in ctx.remaining_accounts.iter().zip(amounts.iter()) {
+        require!(user_state_info.is_writable, FarmError::UnexpectedAccount);
+        let user_state_loader = AccountLoader::<UserState>::try_from(user_state_info)?;
+        let mut user_state = user_state_loader.load_mut()?;
+        require_keys_eq!(
+            user_state.farm_state,
+            farm_state_key,
+            FarmError::UnexpectedAccount
+        );
+
+        farm_operations::reward_user_once(
+            &mut farm_state,
+            &mut user_state,
+            reward_index,
+            *amount,
+            ts,
+        )?;
+    }
+
+    vmsg!(
+        "CreditUsers farm {} reward_index {} batch {} users {} total {}",
+        farm_state_key,
+        reward_index,
+        batch_nonce,
+        amounts.len(),
+        total_amount
+    );
+
+    emit_event!(
+        ctx,
+        CreditUsersEvent {
+            farm_state: farm_state_key,
+            reward_index: reward_index as u64,
+            batch_nonce,
+            num_users: amounts.len() as u64,
+            total_amount,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(reward_index: u64)]
+pub struct CreditUsers<'info> {
+    pub farm_admin: Signer<'info>,
+
+    #[account(mut,
+        has_one = farm_admin,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(
+        constraint = rewards_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
+    )]
+    pub rewards_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+}
@@ -1,29 +1,47 @@
 use crate::farm_operations;
-use crate::token_operations::transfer_from_user;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::state::ProgramInstruction;
+use crate::token_operations;
+use crate::utils::constraints::check_instruction_enabled;
 use crate::utils::consts::*;
-use crate::{FarmError, FarmState};
+use crate::{FarmError, FarmState, GlobalConfig};
 use anchor_lang::prelude::*;
 use anchor_lang::ToAccountInfo;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-pub fn process(ctx: Context<DepositToFarmVault>, amount: u64) -> Result<()> {
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, DepositToFarmVault<'info>>,
+    amount: u64,
+) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::DepositToFarmVault,
+    )?;
     require!(amount != 0, FarmError::DepositZero);
-    check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let transfer_hook_accounts =
+        token_operations::transfer_hook_accounts(&farm_state.token, ctx.remaining_accounts)?;
 
     require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
 
-    farm_operations::deposit_to_farm_vault(farm_state, amount)?;
-
-    transfer_from_user(
+    let farm_vault_amount = ctx.accounts.farm_vault.amount;
+    token_operations::transfer_token_from_user_with_hook(
+        &farm_state.token,
         amount,
         &ctx.accounts.depositor_ata.to_account_info(),
         &ctx.accounts.farm_vault.to_account_info(),
         &ctx.accounts.depositor,
+        ctx.accounts
+            .token_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
         &ctx.accounts.token_program,
+        transfer_hook_accounts,
     )?;
+    let amount_received =
+        token_operations::received_amount(&mut ctx.accounts.farm_vault, farm_vault_amount)?;
+
+    farm_operations::deposit_to_farm_vault(farm_state, amount_received)?;
 
     Ok(())
 }
@@ -45,13 +63,19 @@ pub struct DepositToFarmVault<'info> {
         constraint = farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
         constraint = farm_vault.mint == farm_state.load_mut()?.token.mint @ FarmError::TokenFarmTokenMintMissmatch,
     )]
-    pub farm_vault: Box<Account<'info, TokenAccount>>,
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
         constraint = depositor_ata.mint == farm_state.load()?.token.mint @ FarmError::UserAtaFarmTokenMintMissmatch,
         token::authority = depositor,
     )]
-    pub depositor_ata: Box<Account<'info, TokenAccount>>,
+    pub depositor_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Required for Token-2022 farm tokens
+    pub token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
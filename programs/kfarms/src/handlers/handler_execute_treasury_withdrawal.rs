@@ -0,0 +1,102 @@
+use crate::emit_event;
+use crate::events::TreasuryWithdrawalExecutedEvent;
+use crate::state::{GlobalConfig, ProgramInstruction, TreasuryWithdrawalRequest};
+use crate::token_operations;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::FarmError;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+pub fn process(ctx: Context<ExecuteTreasuryWithdrawal>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::ExecuteTreasuryWithdrawal,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let request = ctx.accounts.withdrawal_request.load()?;
+    let now: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+
+    require_gte!(
+        now,
+        request.executable_ts,
+        FarmError::TreasuryWithdrawalNotExecutableYet
+    );
+
+    let global_config_key = ctx.accounts.global_config.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        BASE_SEED_TREASURY_VAULTS_AUTHORITY,
+        global_config_key.as_ref(),
+        &[ctx.bumps.treasury_vault_authority],
+    ]];
+
+    token_operations::transfer_checked_from_vault(
+        request.amount,
+        ctx.accounts.reward_mint.decimals,
+        signer_seeds,
+        &ctx.accounts
+            .withdraw_destination_token_account
+            .to_account_info(),
+        &ctx.accounts.reward_treasury_vault.to_account_info(),
+        &ctx.accounts.treasury_vault_authority,
+        &ctx.accounts.reward_mint.to_account_info(),
+        &ctx.accounts.token_program,
+    )?;
+
+    emit_event!(
+        ctx,
+        TreasuryWithdrawalExecutedEvent {
+            global_config: global_config_key,
+            request: ctx.accounts.withdrawal_request.key(),
+            request_id: request.request_id,
+            amount: request.amount,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct ExecuteTreasuryWithdrawal<'info> {
+    #[account(mut)]
+    pub global_admin: Signer<'info>,
+
+    #[account(
+        has_one = global_admin
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        close = global_admin,
+        has_one = global_config,
+        constraint = withdrawal_request.load()?.mint == reward_mint.key() @ FarmError::UnexpectedAccount,
+        constraint = withdrawal_request.load()?.destination == withdraw_destination_token_account.key() @ FarmError::UnexpectedAccount,
+    )]
+    pub withdrawal_request: AccountLoader<'info, TreasuryWithdrawalRequest>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_REWARD_TREASURY_VAULT, global_config.key().as_ref(), reward_mint.key().as_ref()],
+        bump,
+        token::mint = reward_mint,
+        token::authority = treasury_vault_authority,
+    )]
+    pub reward_treasury_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_TREASURY_VAULTS_AUTHORITY, global_config.key().as_ref()],
+        bump,
+    )]
+    pub treasury_vault_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        token::mint = reward_mint,
+    )]
+    pub withdraw_destination_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = *reward_mint.to_account_info().owner @ FarmError::UnexpectedAccount)]
+    pub token_program: Interface<'info, TokenInterface>,
+}
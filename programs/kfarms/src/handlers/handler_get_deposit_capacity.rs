@@ -0,0 +1,39 @@
+use crate::state::TimeUnit;
+use crate::types::{DepositCapacity, DepositCapacityConstraint};
+use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::scope::load_scope_price;
+use crate::FarmState;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+
+/// Return data: Borsh `DepositCapacity`, computed with the same function `stake`
+/// uses to enforce the deposit cap.
+pub fn process(ctx: Context<GetDepositCapacity>) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, &farm_state)?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+
+    let capacity = match farm_state.remaining_deposit_capacity(scope_price, ts)? {
+        Some(remaining) => DepositCapacity {
+            binding_constraint: DepositCapacityConstraint::FarmDepositCap,
+            remaining_farm_cap: remaining,
+        },
+        None => DepositCapacity {
+            binding_constraint: DepositCapacityConstraint::None,
+            remaining_farm_cap: u64::MAX,
+        },
+    };
+
+    set_return_data(&capacity.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetDepositCapacity<'info> {
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+}
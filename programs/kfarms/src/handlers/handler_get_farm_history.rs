@@ -0,0 +1,34 @@
+use crate::farm_operations;
+use crate::state::FarmHistory;
+use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::consts::BASE_SEED_FARM_HISTORY;
+use crate::FarmState;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+
+/// Return data: Borsh `FarmHistoryPage`, see `types.rs` for the layout. Pages hold at
+/// most `MAX_FARM_HISTORY_PAGE_SIZE` snapshots starting at the `start`-th oldest, so
+/// that they fit in the 1024 bytes of return data. An empty page marks the end.
+pub fn process(ctx: Context<GetFarmHistory>, start: u32, limit: u32) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let farm_history = ctx.accounts.farm_history.load()?;
+
+    let page = farm_operations::farm_history_page(&farm_state, &farm_history, start, limit);
+
+    set_return_data(&page.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetFarmHistory<'info> {
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_HISTORY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_history: AccountLoader<'info, FarmHistory>,
+}
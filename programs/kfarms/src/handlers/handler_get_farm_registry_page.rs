@@ -0,0 +1,29 @@
+use crate::state::{FarmRegistry, FarmRegistryEntry};
+use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::consts::MAX_FARM_REGISTRY_PAGE_SIZE;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+
+/// Return data: Borsh `Vec<FarmRegistryEntry>` holding at most
+/// `MAX_FARM_REGISTRY_PAGE_SIZE` entries starting at `start`, so that a page
+/// always fits in the 1024 bytes of return data. An empty page marks the end.
+pub fn process(ctx: Context<GetFarmRegistryPage>, start: u32, limit: u32) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let farms = &ctx.accounts.farm_registry.farms;
+    let start = std::cmp::min(start as usize, farms.len());
+    let end = std::cmp::min(
+        start + std::cmp::min(limit as usize, MAX_FARM_REGISTRY_PAGE_SIZE),
+        farms.len(),
+    );
+
+    let page: Vec<FarmRegistryEntry> = farms[start..end].to_vec();
+    set_return_data(&page.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetFarmRegistryPage<'info> {
+    pub farm_registry: Account<'info, FarmRegistry>,
+}
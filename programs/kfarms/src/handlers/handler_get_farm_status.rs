@@ -0,0 +1,29 @@
+use crate::farm_operations;
+use crate::state::TimeUnit;
+use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::scope::load_scope_price;
+use crate::FarmState;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+
+/// Return data: Borsh `FarmStatus`, see `types.rs` for the layout.
+pub fn process(ctx: Context<GetFarmStatus>) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, &farm_state)?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+
+    let status = farm_operations::farm_status(&farm_state, scope_price, ts)?;
+
+    set_return_data(&status.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetFarmStatus<'info> {
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+}
@@ -0,0 +1,35 @@
+use crate::farm_operations;
+use crate::state::TimeUnit;
+use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::scope::load_scope_price;
+use crate::FarmState;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+
+/// Return data: Borsh `FarmTvl`, see `types.rs` for the layout.
+///
+/// `scope_prices` is optional even on oracle farms, the value is then left out.
+pub fn process(ctx: Context<GetFarmTvl>) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let scope_price = if ctx.accounts.scope_prices.is_some() {
+        load_scope_price(&ctx.accounts.scope_prices, &farm_state)?
+    } else {
+        None
+    };
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+
+    let tvl = farm_operations::farm_tvl(&farm_state, scope_price, ts);
+
+    set_return_data(&tvl.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetFarmTvl<'info> {
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+}
@@ -0,0 +1,31 @@
+use crate::farm_operations;
+use crate::state::TimeUnit;
+use crate::utils::constraints::check_remaining_accounts;
+use crate::{FarmState, UserState};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+
+/// Return data: Borsh `HarvestEligibility`, see `types.rs` for the layout.
+///
+/// Computed by the same `harvest_eligibility_ts` that `harvest` enforces.
+pub fn process(ctx: Context<GetHarvestEligibility>) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let user_state = ctx.accounts.user_state.load()?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+
+    let eligibility = farm_operations::harvest_eligibility(&farm_state, &user_state, ts);
+
+    set_return_data(&eligibility.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetHarvestEligibility<'info> {
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(has_one = farm_state)]
+    pub user_state: AccountLoader<'info, UserState>,
+}
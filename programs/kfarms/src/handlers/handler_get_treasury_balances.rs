@@ -0,0 +1,78 @@
+use crate::state::GlobalConfig;
+use crate::types::{TreasuryBalance, TreasuryBalances, TREASURY_BALANCES_VERSION};
+use crate::utils::consts::*;
+use crate::FarmError;
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_spl::token_interface::TokenAccount;
+
+/// Return data: Borsh `TreasuryBalances` of the treasury vaults passed in
+/// `remaining_accounts`, at most `MAX_TREASURY_BALANCES_PAGE_SIZE` of them so that the
+/// result fits in the 1024 bytes of return data.
+///
+/// Fails on any account that is not the treasury vault of `global_config` for its
+/// mint, or that is given twice.
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, GetTreasuryBalances<'info>>) -> Result<()> {
+    require!(
+        !ctx.remaining_accounts.is_empty()
+            && ctx.remaining_accounts.len() <= MAX_TREASURY_BALANCES_PAGE_SIZE,
+        FarmError::MissingRemainingAccounts
+    );
+
+    let global_config_key = ctx.accounts.global_config.key();
+    let (treasury_vault_authority, _) = Pubkey::find_program_address(
+        &[
+            BASE_SEED_TREASURY_VAULTS_AUTHORITY,
+            global_config_key.as_ref(),
+        ],
+        ctx.program_id,
+    );
+
+    let mut balances: Vec<TreasuryBalance> = Vec::with_capacity(ctx.remaining_accounts.len());
+
+    for treasury_vault_info in ctx.remaining_accounts {
+        let treasury_vault = InterfaceAccount::<TokenAccount>::try_from(treasury_vault_info)?;
+        let (expected_vault, _) = Pubkey::find_program_address(
+            &[
+                BASE_SEED_REWARD_TREASURY_VAULT,
+                global_config_key.as_ref(),
+                treasury_vault.mint.as_ref(),
+            ],
+            ctx.program_id,
+        );
+        require_keys_eq!(
+            treasury_vault_info.key(),
+            expected_vault,
+            FarmError::UnexpectedAccount
+        );
+        require_keys_eq!(
+            treasury_vault.owner,
+            treasury_vault_authority,
+            FarmError::UnexpectedAccount
+        );
+        require!(
+            balances
+                .iter()
+                .all(|balance| balance.mint != treasury_vault.mint),
+            FarmError::UnexpectedAccount
+        );
+
+        balances.push(TreasuryBalance {
+            mint: treasury_vault.mint,
+            amount: treasury_vault.amount,
+        });
+    }
+
+    let result = TreasuryBalances {
+        version: TREASURY_BALANCES_VERSION,
+        balances,
+    };
+    set_return_data(&result.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetTreasuryBalances<'info> {
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
@@ -0,0 +1,67 @@
+use crate::types::{UpgradeSnapshot, UpgradeSnapshots, UPGRADE_SNAPSHOTS_VERSION};
+use crate::utils::consts::*;
+use crate::{FarmError, FarmState, GlobalConfig};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+
+/// Return data: Borsh `UpgradeSnapshots` of the farms of `global_config` passed in
+/// `remaining_accounts`, at most `MAX_UPGRADE_SNAPSHOTS_PAGE_SIZE` of them so that the
+/// result fits in the 1024 bytes of return data.
+///
+/// A farm is ready for the upgrade once it carries `expected_marker` and nothing
+/// changed its accumulators since `snapshot_farm_for_upgrade`.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, GetUpgradeSnapshots<'info>>,
+    expected_marker: u64,
+) -> Result<()> {
+    require!(
+        !ctx.remaining_accounts.is_empty()
+            && ctx.remaining_accounts.len() <= MAX_UPGRADE_SNAPSHOTS_PAGE_SIZE,
+        FarmError::MissingRemainingAccounts
+    );
+
+    let global_config_key = ctx.accounts.global_config.key();
+    let mut farms: Vec<UpgradeSnapshot> = Vec::with_capacity(ctx.remaining_accounts.len());
+
+    for farm_state_info in ctx.remaining_accounts {
+        let farm_state_loader = AccountLoader::<FarmState>::try_from(farm_state_info)?;
+        let farm_state = farm_state_loader.load()?;
+        require_keys_eq!(
+            farm_state.global_config,
+            global_config_key,
+            FarmError::UnexpectedAccount
+        );
+        require!(
+            farms
+                .iter()
+                .all(|farm| farm.farm_state != farm_state_info.key()),
+            FarmError::UnexpectedAccount
+        );
+
+        farms.push(UpgradeSnapshot {
+            farm_state: farm_state_info.key(),
+            marker: farm_state.upgrade_snapshot_marker,
+            snapshot_slot: farm_state.upgrade_snapshot_slot,
+            accumulators_unchanged: farm_state.upgrade_snapshot_marker != 0
+                && farm_state.accumulators_hash() == farm_state.upgrade_snapshot_accumulators_hash,
+            pause_until_slot: farm_state.upgrade_pause_until_slot,
+        });
+    }
+
+    let result = UpgradeSnapshots {
+        version: UPGRADE_SNAPSHOTS_VERSION,
+        slot: Clock::get()?.slot,
+        all_ready: farms
+            .iter()
+            .all(|farm| farm.marker == expected_marker && farm.accumulators_unchanged),
+        farms,
+    };
+    set_return_data(&result.try_to_vec()?);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct GetUpgradeSnapshots<'info> {
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
@@ -0,0 +1,372 @@
+use std::slice::Iter;
+
+use crate::emit_event;
+use crate::err_ctx;
+use crate::events::{HarvestEvent, HarvestFeeOverrideClampedEvent};
+use crate::farm_operations;
+use crate::gen_signer_seeds_two;
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
+use crate::token_operations;
+use crate::types::HarvestEffects;
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::consts::*;
+use crate::utils::scope::load_scope_price;
+use crate::{vmsg, xmsg};
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_spl::token_interface::{TokenAccount, TokenInterface};
+use scope::DatedPrice;
+
+/// Harvests the rewards of `owner` in several farms at once, one group of
+/// `remaining_accounts` per entry of `reward_masks`, whose bit `i` selects the reward
+/// index `i`. Each group holds `farm_state, user_state, global_config,
+/// farm_vaults_authority`, then for each selected reward in ascending order
+/// `rewards_vault, rewards_treasury_vault, user_reward_ata`, followed by the reward
+/// mint for Token-2022 rewards.
+///
+/// A group that cannot be harvested right now, e.g. paused, not claimable yet, with a
+/// reward split or under another token program, is skipped as a whole before any
+/// change. Malformed groups fail the instruction.
+///
+/// Return data: `u16` little-endian bitmask of the skipped groups.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, HarvestAcrossFarms<'info>>,
+    reward_masks: Vec<u16>,
+) -> Result<()> {
+    require!(
+        !reward_masks.is_empty(),
+        FarmError::MissingRemainingAccounts
+    );
+    require_gte!(
+        MAX_HARVEST_ACROSS_FARMS_GROUPS,
+        reward_masks.len(),
+        FarmError::TooManyHarvestGroups
+    );
+
+    let mut remaining_accounts = ctx.remaining_accounts.iter();
+    let mut skipped_groups: u16 = 0;
+
+    for (group_index, reward_mask) in reward_masks.iter().enumerate() {
+        if !harvest_group(&ctx, &mut remaining_accounts, group_index, *reward_mask)? {
+            skipped_groups |= 1 << group_index;
+        }
+    }
+
+    if let Some(extra_account) = remaining_accounts.next() {
+        return err_ctx!(
+            FarmError::UnexpectedAccount,
+            extra_account = extra_account.key(),
+            num_groups = reward_masks.len(),
+        );
+    }
+
+    vmsg!(
+        "HarvestAcrossFarms owner {} groups {} skipped {:#b}",
+        ctx.accounts.owner.key(),
+        reward_masks.len(),
+        skipped_groups
+    );
+
+    set_return_data(&skipped_groups.to_le_bytes());
+
+    Ok(())
+}
+
+struct RewardAccounts<'a, 'info> {
+    reward_index: usize,
+    rewards_vault_amount: u64,
+    reward_mint: Pubkey,
+    rewards_vault: &'a AccountInfo<'info>,
+    rewards_treasury_vault: &'a AccountInfo<'info>,
+    user_reward_ata: &'a AccountInfo<'info>,
+    reward_mint_info: Option<&'a AccountInfo<'info>>,
+}
+
+/// Harvests one group, `false` when it was skipped.
+fn harvest_group<'a, 'info>(
+    ctx: &Context<'_, '_, '_, 'info, HarvestAcrossFarms<'info>>,
+    remaining_accounts: &mut Iter<'a, AccountInfo<'info>>,
+    group_index: usize,
+    reward_mask: u16,
+) -> Result<bool> {
+    let owner = ctx.accounts.owner.key();
+
+    let farm_state_info = next_account(remaining_accounts)?;
+    let user_state_info = next_account(remaining_accounts)?;
+    let global_config_info = next_account(remaining_accounts)?;
+    let farm_vaults_authority = next_account(remaining_accounts)?;
+
+    let farm_state_loader = AccountLoader::<FarmState>::try_from(farm_state_info)?;
+    let user_state_loader = AccountLoader::<UserState>::try_from(user_state_info)?;
+    let global_config_loader = AccountLoader::<GlobalConfig>::try_from(global_config_info)?;
+    let farm_state_key = farm_state_info.key();
+    let farm_state = &mut farm_state_loader.load_mut()?;
+    let user_state = &mut user_state_loader.load_mut()?;
+
+    require_keys_eq!(
+        farm_state.global_config,
+        global_config_info.key(),
+        FarmError::UnexpectedAccount
+    );
+    require_keys_eq!(
+        farm_state.farm_vaults_authority,
+        farm_vaults_authority.key(),
+        FarmError::UnexpectedAccount
+    );
+    require_keys_eq!(
+        user_state.farm_state,
+        farm_state_key,
+        FarmError::UnexpectedAccount
+    );
+    require_keys_eq!(user_state.owner, owner, FarmError::UnexpectedAccount);
+    require!(
+        reward_mask != 0 && u32::from(reward_mask) >> farm_state.num_reward_tokens == 0,
+        FarmError::RewardIndexOutOfRange
+    );
+
+    let mut reward_accounts = Vec::with_capacity(reward_mask.count_ones() as usize);
+    for reward_index in 0..farm_state.num_reward_tokens as usize {
+        if reward_mask & (1 << reward_index) == 0 {
+            continue;
+        }
+        let reward_info = &farm_state.reward_infos[reward_index];
+
+        let rewards_vault_info = next_account(remaining_accounts)?;
+        let rewards_treasury_vault_info = next_account(remaining_accounts)?;
+        let user_reward_ata_info = next_account(remaining_accounts)?;
+        let reward_mint_info = if reward_info.token.is_spl_token() {
+            None
+        } else {
+            Some(next_account(remaining_accounts)?)
+        };
+
+        require_keys_eq!(
+            rewards_vault_info.key(),
+            reward_info.rewards_vault,
+            FarmError::RewardVaultMismatch
+        );
+        let rewards_vault = InterfaceAccount::<TokenAccount>::try_from(rewards_vault_info)?;
+        require!(
+            rewards_vault.delegate.is_none(),
+            FarmError::RewardsVaultHasDelegate
+        );
+        require!(
+            rewards_vault.close_authority.is_none(),
+            FarmError::RewardsVaultHasCloseAuthority
+        );
+
+        let (expected_treasury_vault, _) = Pubkey::find_program_address(
+            &[
+                BASE_SEED_REWARD_TREASURY_VAULT,
+                global_config_info.key().as_ref(),
+                reward_info.token.mint.as_ref(),
+            ],
+            ctx.program_id,
+        );
+        require_keys_eq!(
+            rewards_treasury_vault_info.key(),
+            expected_treasury_vault,
+            FarmError::UnexpectedAccount
+        );
+        let rewards_treasury_vault =
+            InterfaceAccount::<TokenAccount>::try_from(rewards_treasury_vault_info)?;
+        require!(
+            rewards_treasury_vault.delegate.is_none(),
+            FarmError::RewardsTreasuryVaultHasDelegate
+        );
+        require!(
+            rewards_treasury_vault.close_authority.is_none(),
+            FarmError::RewardsTreasuryVaultHasCloseAuthority
+        );
+
+        let user_reward_ata = InterfaceAccount::<TokenAccount>::try_from(user_reward_ata_info)?;
+        require_keys_eq!(
+            user_reward_ata.mint,
+            reward_info.token.mint,
+            FarmError::UserAtaRewardVaultMintMissmatch
+        );
+        require!(
+            user_state
+                .reward_destination(reward_index)
+                .map_or(user_reward_ata.owner == owner, |destination| {
+                    destination == user_reward_ata_info.key()
+                }),
+            FarmError::InvalidRewardDestination
+        );
+
+        reward_accounts.push(RewardAccounts {
+            reward_index,
+            rewards_vault_amount: rewards_vault.amount,
+            reward_mint: reward_info.token.mint,
+            rewards_vault: rewards_vault_info,
+            rewards_treasury_vault: rewards_treasury_vault_info,
+            user_reward_ata: user_reward_ata_info,
+            reward_mint_info,
+        });
+    }
+
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+    let scope_price = match check_group_harvestable(
+        ctx,
+        &global_config_loader,
+        farm_state,
+        user_state,
+        &reward_accounts,
+        ts,
+    ) {
+        Ok(scope_price) => scope_price,
+        Err(err) => {
+            xmsg!(
+                "HarvestAcrossFarms group {} farm_state {} skipped: {}",
+                group_index,
+                farm_state_key,
+                err
+            );
+            return Ok(false);
+        }
+    };
+
+    let global_config = global_config_loader.load()?;
+    let signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+        BASE_SEED_FARM_VAULTS_AUTHORITY,
+        farm_state_key,
+        farm_state.farm_vaults_authority_bump as u8
+    );
+
+    for accounts in reward_accounts {
+        let reward_index = accounts.reward_index;
+        let HarvestEffects {
+            reward_user,
+            reward_treasury,
+            reward_penalty,
+            reward_split,
+            treasury_fee_bps,
+            fee_override_clamped,
+        } = farm_operations::harvest(
+            farm_state,
+            user_state,
+            &global_config,
+            scope_price,
+            reward_index,
+            accounts.rewards_vault_amount,
+            ts,
+        )?;
+
+        let reward_token = &farm_state.reward_infos[reward_index].token;
+        if reward_user > 0 {
+            token_operations::transfer_token_from_vault(
+                reward_token,
+                reward_user,
+                signer_seeds,
+                accounts.user_reward_ata,
+                accounts.rewards_vault,
+                farm_vaults_authority,
+                accounts.reward_mint_info.cloned(),
+                &ctx.accounts.token_program,
+            )?;
+        }
+        if reward_treasury > 0 {
+            token_operations::transfer_token_from_vault(
+                reward_token,
+                reward_treasury,
+                signer_seeds,
+                accounts.rewards_treasury_vault,
+                accounts.rewards_vault,
+                farm_vaults_authority,
+                accounts.reward_mint_info.cloned(),
+                &ctx.accounts.token_program,
+            )?;
+        }
+
+        emit_event!(
+            ctx,
+            HarvestEvent {
+                farm_state: farm_state_key,
+                user_state: user_state_info.key(),
+                owner,
+                reward_index: reward_index as u64,
+                reward_mint: accounts.reward_mint,
+                reward_user,
+                reward_treasury,
+                reward_penalty,
+                reward_split,
+                ts,
+            }
+        );
+
+        if fee_override_clamped {
+            emit_event!(
+                ctx,
+                HarvestFeeOverrideClampedEvent {
+                    farm_state: farm_state_key,
+                    harvest_fee_bps_override: farm_state.harvest_fee_bps_override,
+                    treasury_fee_bps,
+                }
+            );
+        }
+    }
+
+    Ok(true)
+}
+
+/// Everything that would make `farm_operations::harvest` or the transfers of the group
+/// fail, checked before any change. Returns the price to harvest with.
+fn check_group_harvestable(
+    ctx: &Context<'_, '_, '_, '_, HarvestAcrossFarms<'_>>,
+    global_config: &AccountLoader<GlobalConfig>,
+    farm_state: &FarmState,
+    user_state: &UserState,
+    reward_accounts: &[RewardAccounts],
+    ts: u64,
+) -> Result<Option<DatedPrice>> {
+    check_instruction_enabled(global_config, ProgramInstruction::HarvestAcrossFarms)?;
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::Harvest),
+        FarmError::FarmOperationPaused
+    );
+
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+    require!(
+        farm_operations::is_priced(farm_state, scope_price, ts),
+        FarmError::ScopeOraclePriceTooOld
+    );
+
+    for accounts in reward_accounts {
+        let reward_info = &farm_state.reward_infos[accounts.reward_index];
+        require_keys_eq!(
+            ctx.accounts.token_program.key(),
+            reward_info.token.token_program(),
+            FarmError::UnexpectedAccount
+        );
+        // The split destination accounts are only taken by harvest_reward
+        require!(
+            !reward_info.has_split(),
+            FarmError::MissingRewardSplitAccounts
+        );
+        farm_operations::check_harvest_allowed(farm_state, user_state, accounts.reward_index, ts)?;
+    }
+
+    Ok(scope_price)
+}
+
+fn next_account<'a, 'info>(
+    remaining_accounts: &mut Iter<'a, AccountInfo<'info>>,
+) -> Result<&'a AccountInfo<'info>> {
+    remaining_accounts
+        .next()
+        .ok_or_else(|| error!(FarmError::MissingRemainingAccounts))
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct HarvestAcrossFarms<'info> {
+    pub owner: Signer<'info>,
+
+    /// Shared by the groups, required for the farms with an oracle
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    /// Program of every reward harvested, groups with rewards of another program are
+    /// skipped
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -0,0 +1,314 @@
+use crate::emit_event;
+use crate::err_ctx;
+use crate::events::{HarvestEvent, HarvestFeeOverrideClampedEvent, StakeEvent};
+use crate::farm_operations;
+use crate::gen_signer_seeds_two;
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
+use crate::token_operations;
+use crate::types::{HarvestEffects, StakeEffects};
+use crate::utils::constraints::{
+    check_instruction_enabled, check_remaining_accounts, check_reward_index,
+};
+use crate::utils::consts::*;
+use crate::utils::scope::load_scope_price;
+use crate::{vmsg, xmsg};
+use crate::{FarmError, FarmState, GlobalConfig, UserPage, UserState};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Harvests `reward_index` of `farm_state` and stakes the user part in `target_farm_state`,
+/// whose token must be the reward mint. The rewards go straight from the rewards vault to
+/// the target farm vault, the target user state is created when it does not exist yet.
+/// Only the amount received by the target farm vault is staked.
+pub fn process(ctx: Context<HarvestAndStakeOther>, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::HarvestAndStakeOther,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    let global_config = &ctx.accounts.global_config.load()?;
+    let target_farm_state = &mut ctx.accounts.target_farm_state.load_mut()?;
+
+    let reward_index = check_reward_index(farm_state, reward_index)?;
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::Harvest),
+        FarmError::FarmOperationPaused
+    );
+    require!(!target_farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !target_farm_state.is_paused(FarmPauseFlag::Stake),
+        FarmError::FarmOperationPaused
+    );
+    if target_farm_state.has_stake_hook() {
+        xmsg!("Target farm has a stake hook, use stake instead");
+        return err_ctx!(
+            FarmError::InvalidHookAccounts,
+            target_farm_state = ctx.accounts.target_farm_state.key(),
+            hook_program = target_farm_state.hook_program,
+        );
+    }
+
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+    let target_scope_price =
+        load_scope_price(&ctx.accounts.target_scope_prices, target_farm_state)?;
+
+    let clock = Clock::get()?;
+    farm_operations::check_not_upgrade_paused(target_farm_state, clock.slot)?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &clock);
+    let target_ts = TimeUnit::now_from_clock(target_farm_state.time_unit, &clock);
+
+    // The split destination accounts are only taken by harvest_reward
+    require!(
+        !farm_state.reward_infos[reward_index].has_split(),
+        FarmError::MissingRewardSplitAccounts
+    );
+
+    let HarvestEffects {
+        reward_user,
+        reward_treasury,
+        reward_penalty,
+        treasury_fee_bps,
+        fee_override_clamped,
+        ..
+    } = farm_operations::harvest(
+        farm_state,
+        user_state,
+        global_config,
+        scope_price,
+        reward_index,
+        ctx.accounts.rewards_vault.amount,
+        ts,
+    )?;
+    require!(reward_user > 0, FarmError::StakeZero);
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+        BASE_SEED_FARM_VAULTS_AUTHORITY,
+        farm_state_key,
+        farm_state.farm_vaults_authority_bump as u8
+    );
+    let reward_mint = ctx
+        .accounts
+        .reward_mint
+        .as_ref()
+        .map(|mint| mint.to_account_info());
+
+    let target_farm_vault_amount = ctx.accounts.target_farm_vault.amount;
+    token_operations::transfer_token_from_vault(
+        &farm_state.reward_infos[reward_index].token,
+        reward_user,
+        signer_seeds,
+        &ctx.accounts.target_farm_vault.to_account_info(),
+        &ctx.accounts.rewards_vault.to_account_info(),
+        &ctx.accounts.farm_vaults_authority,
+        reward_mint.clone(),
+        &ctx.accounts.token_program,
+    )?;
+    let amount_received = token_operations::received_amount(
+        &mut ctx.accounts.target_farm_vault,
+        target_farm_vault_amount,
+    )?;
+
+    let target_user_state_info = ctx.accounts.target_user_state.to_account_info();
+    let is_new_target_user = target_user_state_info.try_borrow_data()?[..8] == [0; 8];
+    let target_user_state = &mut if is_new_target_user {
+        ctx.accounts.target_user_state.load_init()?
+    } else {
+        ctx.accounts.target_user_state.load_mut()?
+    };
+
+    if is_new_target_user {
+        vmsg!(
+            "HarvestAndStakeOther: creating user {} on farm {}",
+            ctx.accounts.target_user_state.key(),
+            ctx.accounts.target_farm_state.key()
+        );
+        if target_farm_state.user_pages_enabled == 1 {
+            let user_page = ctx
+                .accounts
+                .target_user_page
+                .as_ref()
+                .ok_or(FarmError::InvalidUserPage)?;
+            farm_operations::add_user_to_page(
+                target_farm_state,
+                &mut user_page.load_mut()?,
+                &ctx.accounts.target_farm_state.key(),
+                &ctx.accounts.target_user_state.key(),
+                target_farm_state.num_users,
+            )?;
+        }
+        target_user_state.bump = ctx.bumps.target_user_state.into();
+        target_user_state.delegatee = ctx.accounts.owner.key();
+        farm_operations::initialize_user(
+            target_farm_state,
+            target_user_state,
+            &ctx.accounts.owner.key(),
+            &ctx.accounts.target_farm_state.key(),
+            target_ts,
+        )?;
+    }
+
+    let StakeEffects {
+        amount_to_stake, ..
+    } = farm_operations::stake(
+        target_farm_state,
+        target_user_state,
+        target_scope_price,
+        amount_received,
+        false,
+        target_ts,
+    )?;
+
+    vmsg!(
+        "HarvestAndStakeOther owner {} reward_user {} reward_treasury {} reward_penalty {}",
+        ctx.accounts.owner.key(),
+        reward_user,
+        reward_treasury,
+        reward_penalty
+    );
+
+    if reward_treasury > 0 {
+        token_operations::transfer_token_from_vault(
+            &farm_state.reward_infos[reward_index].token,
+            reward_treasury,
+            signer_seeds,
+            &ctx.accounts.rewards_treasury_vault.to_account_info(),
+            &ctx.accounts.rewards_vault.to_account_info(),
+            &ctx.accounts.farm_vaults_authority,
+            reward_mint,
+            &ctx.accounts.token_program,
+        )?;
+    }
+
+    emit_event!(
+        ctx,
+        HarvestEvent {
+            farm_state: farm_state_key,
+            user_state: ctx.accounts.user_state.key(),
+            owner: ctx.accounts.owner.key(),
+            reward_index: reward_index as u64,
+            reward_mint: ctx.accounts.rewards_vault.mint,
+            reward_user,
+            reward_treasury,
+            reward_penalty,
+            reward_split: 0,
+            ts,
+        }
+    );
+
+    if fee_override_clamped {
+        emit_event!(
+            ctx,
+            HarvestFeeOverrideClampedEvent {
+                farm_state: farm_state_key,
+                harvest_fee_bps_override: farm_state.harvest_fee_bps_override,
+                treasury_fee_bps,
+            }
+        );
+    }
+
+    emit_event!(
+        ctx,
+        StakeEvent {
+            farm_state: ctx.accounts.target_farm_state.key(),
+            user_state: ctx.accounts.target_user_state.key(),
+            owner: ctx.accounts.owner.key(),
+            amount: amount_to_stake,
+            active_stake_scaled: target_user_state.active_stake_scaled,
+            ts: target_ts,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(reward_index: u64)]
+pub struct HarvestAndStakeOther<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut,
+        has_one = global_config,
+        has_one = farm_vaults_authority,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        seeds = [
+            BASE_SEED_REWARD_VAULT,
+            farm_state.key().as_ref(),
+            rewards_vault.mint.as_ref(),
+            farm_state.load()?.reward_vault_seed_suffix(reward_index as usize, &rewards_vault.mint),
+        ],
+        bump,
+        constraint = rewards_vault.delegate.is_none() @ FarmError::RewardsVaultHasDelegate,
+        constraint = rewards_vault.close_authority.is_none() @ FarmError::RewardsVaultHasCloseAuthority,
+        constraint = rewards_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
+    )]
+    pub rewards_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_REWARD_TREASURY_VAULT.as_ref(), global_config.key().as_ref(), rewards_vault.mint.as_ref()],
+        bump,
+        constraint = rewards_treasury_vault.delegate.is_none() @ FarmError::RewardsTreasuryVaultHasDelegate,
+        constraint = rewards_treasury_vault.close_authority.is_none() @ FarmError::RewardsTreasuryVaultHasCloseAuthority,
+    )]
+    pub rewards_treasury_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_vaults_authority: AccountInfo<'info>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    #[account(mut,
+        has_one = global_config,
+        constraint = target_farm_state.key() != farm_state.key() @ FarmError::UnexpectedAccount,
+        constraint = target_farm_state.load()?.token.mint == rewards_vault.mint @ FarmError::TokenFarmTokenMintMissmatch,
+    )]
+    pub target_farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(init_if_needed,
+        seeds = [BASE_SEED_USER_STATE, target_farm_state.key().as_ref(), owner.key().as_ref()],
+        bump,
+        payer = owner,
+        space = SIZE_USER_STATE,
+    )]
+    pub target_user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_FARM_VAULT, target_farm_state.key().as_ref(), rewards_vault.mint.as_ref()],
+        bump,
+        constraint = target_farm_vault.key() == target_farm_state.load()?.farm_vault @ FarmError::UnexpectedAccount,
+        constraint = target_farm_vault.delegate.is_none() @ FarmError::FarmVaultHasDelegate,
+        constraint = target_farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
+    )]
+    pub target_farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub target_scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    #[account(mut)]
+    pub target_user_page: Option<AccountLoader<'info, UserPage>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+
+    /// Required for Token-2022 rewards
+    pub reward_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+}
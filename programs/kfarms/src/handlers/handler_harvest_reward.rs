@@ -1,16 +1,34 @@
+use crate::emit_event;
+use crate::err_ctx;
+use crate::events::{HarvestEvent, HarvestFeeOverrideClampedEvent};
 use crate::farm_operations;
 use crate::gen_signer_seeds_two;
-use crate::state::TimeUnit;
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
 use crate::token_operations;
 use crate::types::HarvestEffects;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::{
+    check_instruction_enabled, check_remaining_accounts, check_reward_index,
+};
 use crate::utils::consts::*;
 use crate::utils::scope::load_scope_price;
-use crate::{FarmError, FarmState, GlobalConfig, UserState};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, ReferrerState, UserState};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, AssociatedToken};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
+/// Return data: the reward amount transferred to the user, net of the treasury
+/// fee and of the reward split, as a little-endian `u64` (8 bytes).
+///
+/// Rewards with a split also need the split accounts, the destination ATA is created
+/// when missing, paid by `owner`. With a `referrer_state`, its share of the treasury
+/// fee stays in `rewards_vault` until the referrer claims it.
 pub fn process(ctx: Context<HarvestReward>, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::HarvestReward,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
@@ -20,34 +38,56 @@ pub fn process(ctx: Context<HarvestReward>, reward_index: u64) -> Result<()> {
     let user_state = &mut ctx.accounts.user_state.load_mut()?;
     let global_config = &ctx.accounts.global_config.load()?;
 
+    let reward_index = check_reward_index(farm_state, reward_index)?;
     require!(
-        reward_index < farm_state.num_reward_tokens,
-        FarmError::RewardIndexOutOfRange
+        !farm_state.is_paused(FarmPauseFlag::Harvest),
+        FarmError::FarmOperationPaused
     );
 
-    msg!(
+    let ts = TimeUnit::now_from_clock(time_unit, &Clock::get()?);
+    vmsg!(
         "HarvestReward user_state {:?}, farm_state {:?} ts {}",
         ctx.accounts.user_state.key(),
         ctx.accounts.farm_state.key(),
-        TimeUnit::now_from_clock(time_unit, &Clock::get()?)
+        ts
     );
     let HarvestEffects {
         reward_user,
         reward_treasury,
+        reward_penalty,
+        reward_split,
+        treasury_fee_bps,
+        fee_override_clamped,
     } = farm_operations::harvest(
         farm_state,
         user_state,
         global_config,
         scope_price,
-        reward_index as usize,
-        TimeUnit::now_from_clock(time_unit, &Clock::get()?),
+        reward_index,
+        ctx.accounts.rewards_vault.amount,
+        ts,
     )?;
 
-    msg!(
-        "owner {:?} amount_user {:?}, amount_treasury {:?}",
+    let reward_treasury = match &ctx.accounts.referrer_state {
+        Some(referrer_state) if global_config.referral_fee_bps > 0 => {
+            farm_operations::credit_referral_fee(
+                &mut farm_state.reward_infos[reward_index],
+                &mut referrer_state.load_mut()?,
+                reward_index,
+                reward_treasury,
+                global_config.referral_fee_bps,
+            )?
+        }
+        _ => reward_treasury,
+    };
+
+    vmsg!(
+        "owner {:?} amount_user {:?}, amount_treasury {:?}, amount_penalty {:?}, amount_split {:?}",
         user_state.owner,
         reward_user,
-        reward_treasury
+        reward_treasury,
+        reward_penalty,
+        reward_split
     );
 
     let farm_state_key = ctx.accounts.farm_state.key();
@@ -58,31 +98,114 @@ pub fn process(ctx: Context<HarvestReward>, reward_index: u64) -> Result<()> {
         farm_state.farm_vaults_authority_bump as u8
     );
 
+    let reward_mint_info = ctx
+        .accounts
+        .reward_mint
+        .as_ref()
+        .map(|mint| mint.to_account_info());
+
     if reward_user > 0 {
-        token_operations::transfer_from_vault(
+        token_operations::transfer_token_from_vault(
+            &farm_state.reward_infos[reward_index].token,
             reward_user,
             signer_seeds,
             &ctx.accounts.user_reward_ata.to_account_info(),
             &ctx.accounts.rewards_vault.to_account_info(),
             &ctx.accounts.farm_vaults_authority,
+            reward_mint_info.clone(),
             &ctx.accounts.token_program,
         )?;
     }
 
     if reward_treasury > 0 {
-        token_operations::transfer_from_vault(
+        token_operations::transfer_token_from_vault(
+            &farm_state.reward_infos[reward_index].token,
             reward_treasury,
             signer_seeds,
             &ctx.accounts.rewards_treasury_vault.to_account_info(),
             &ctx.accounts.rewards_vault.to_account_info(),
             &ctx.accounts.farm_vaults_authority,
+            reward_mint_info.clone(),
+            &ctx.accounts.token_program,
+        )?;
+    }
+
+    if reward_split > 0 {
+        let (
+            Some(split_destination_owner),
+            Some(split_destination_ata),
+            Some(reward_mint),
+            Some(associated_token_program),
+            Some(system_program),
+        ) = (
+            &ctx.accounts.split_destination,
+            &ctx.accounts.split_destination_ata,
+            &ctx.accounts.reward_mint,
+            &ctx.accounts.associated_token_program,
+            &ctx.accounts.system_program,
+        )
+        else {
+            return err_ctx!(
+                FarmError::MissingRewardSplitAccounts,
+                farm_state = farm_state_key,
+                reward_index = reward_index,
+                reward_split = reward_split,
+            );
+        };
+        token_operations::create_ata_if_needed(
+            &ctx.accounts.owner.to_account_info(),
+            split_destination_ata,
+            split_destination_owner,
+            &reward_mint.to_account_info(),
+            &system_program.to_account_info(),
+            &ctx.accounts.token_program,
+            &associated_token_program.to_account_info(),
+        )?;
+        token_operations::transfer_token_from_vault(
+            &farm_state.reward_infos[reward_index].token,
+            reward_split,
+            signer_seeds,
+            split_destination_ata,
+            &ctx.accounts.rewards_vault.to_account_info(),
+            &ctx.accounts.farm_vaults_authority,
+            Some(reward_mint.to_account_info()),
             &ctx.accounts.token_program,
         )?;
     }
 
+    emit_event!(
+        ctx,
+        HarvestEvent {
+            farm_state: farm_state_key,
+            user_state: ctx.accounts.user_state.key(),
+            owner: ctx.accounts.owner.key(),
+            reward_index: reward_index as u64,
+            reward_mint: ctx.accounts.rewards_vault.mint,
+            reward_user,
+            reward_treasury,
+            reward_penalty,
+            reward_split,
+            ts,
+        }
+    );
+
+    if fee_override_clamped {
+        emit_event!(
+            ctx,
+            HarvestFeeOverrideClampedEvent {
+                farm_state: farm_state_key,
+                harvest_fee_bps_override: farm_state.harvest_fee_bps_override,
+                treasury_fee_bps,
+            }
+        );
+    }
+
+    set_return_data(&reward_user.to_le_bytes());
+
     Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 #[instruction(reward_index: u64)]
 pub struct HarvestReward<'info> {
@@ -98,34 +221,46 @@ pub struct HarvestReward<'info> {
     #[account(
         mut,
         has_one = global_config,
-        has_one = farm_vaults_authority
+        has_one = farm_vaults_authority,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
     )]
     pub farm_state: AccountLoader<'info, FarmState>,
 
     pub global_config: AccountLoader<'info, GlobalConfig>,
 
+    /// The user reward destination when set, whatever its owner: a PDA such as a
+    /// multisig vault works. An account of `owner` otherwise
     #[account(mut,
-        has_one = owner,
         constraint = user_reward_ata.mint == rewards_vault.mint @ FarmError::UserAtaRewardVaultMintMissmatch,
+        constraint = user_state
+            .load()?
+            .reward_destination(reward_index as usize)
+            .map_or(user_reward_ata.owner == owner.key(), |destination| destination == user_reward_ata.key())
+            @ FarmError::InvalidRewardDestination,
     )]
-    pub user_reward_ata: Box<Account<'info, TokenAccount>>,
+    pub user_reward_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
-        seeds = [BASE_SEED_REWARD_VAULT, farm_state.key().as_ref(), rewards_vault.mint.as_ref()],
+        seeds = [
+            BASE_SEED_REWARD_VAULT,
+            farm_state.key().as_ref(),
+            rewards_vault.mint.as_ref(),
+            farm_state.load()?.reward_vault_seed_suffix(reward_index as usize, &rewards_vault.mint),
+        ],
         bump,
         constraint = rewards_vault.delegate.is_none() @ FarmError::RewardsVaultHasDelegate,
         constraint = rewards_vault.close_authority.is_none() @ FarmError::RewardsVaultHasCloseAuthority,
         constraint = rewards_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
     )]
-    pub rewards_vault: Box<Account<'info, TokenAccount>>,
+    pub rewards_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
         seeds = [BASE_SEED_REWARD_TREASURY_VAULT.as_ref(), global_config.key().as_ref(), rewards_vault.mint.as_ref()],
         bump,
-        constraint = rewards_vault.delegate.is_none() @ FarmError::RewardsTreasuryVaultHasDelegate,
-        constraint = rewards_vault.close_authority.is_none() @ FarmError::RewardsTreasuryVaultHasCloseAuthority,
+        constraint = rewards_treasury_vault.delegate.is_none() @ FarmError::RewardsTreasuryVaultHasDelegate,
+        constraint = rewards_treasury_vault.close_authority.is_none() @ FarmError::RewardsTreasuryVaultHasCloseAuthority,
     )]
-    pub rewards_treasury_vault: Box<Account<'info, TokenAccount>>,
+    pub rewards_treasury_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
@@ -135,5 +270,34 @@ pub struct HarvestReward<'info> {
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Required when the reward has a split: `RewardInfo::split_destination`, its ATA for
+    /// the reward mint, and the accounts to create it. `reward_mint` is also required
+    /// for Token-2022 rewards
+    #[account(
+        address = farm_state.load()?.reward_infos[reward_index as usize].split_destination @ FarmError::UnexpectedAccount,
+    )]
+    pub split_destination: Option<AccountInfo<'info>>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(
+            &farm_state.load()?.reward_infos[reward_index as usize].split_destination,
+            &rewards_vault.mint,
+            &token_program.key(),
+        ) @ FarmError::UnexpectedAccount,
+    )]
+    pub split_destination_ata: Option<AccountInfo<'info>>,
+    #[account(
+        address = rewards_vault.mint @ FarmError::UnexpectedAccount,
+    )]
+    pub reward_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+    pub associated_token_program: Option<Program<'info, AssociatedToken>>,
+    pub system_program: Option<Program<'info, System>>,
+
+    /// Referrer of the harvest, credited `GlobalConfig::referral_fee_bps` of the
+    /// treasury fee
+    #[account(mut,
+        constraint = referrer_state.load()?.farm_state == farm_state.key() @ FarmError::UnexpectedAccount,
+    )]
+    pub referrer_state: Option<AccountLoader<'info, ReferrerState>>,
 }
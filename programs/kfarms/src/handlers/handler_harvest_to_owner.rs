@@ -0,0 +1,294 @@
+use crate::emit_event;
+use crate::err_ctx;
+use crate::events::{HarvestEvent, HarvestFeeOverrideClampedEvent, HarvestToOwnerEvent};
+use crate::farm_operations;
+use crate::gen_signer_seeds_two;
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
+use crate::token_operations;
+use crate::types::HarvestEffects;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::utils::scope::load_scope_price;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, AssociatedToken};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Permissionless harvest of `reward_index`, always paid to the canonical ATA of the
+/// user state owner, ignoring any reward destination. The ATA is created when missing,
+/// paid by `payer`.
+///
+/// Return data: same as `harvest_reward`.
+pub fn process(ctx: Context<HarvestToOwner>, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::HarvestToOwner,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let time_unit = farm_state.time_unit;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    let global_config = &ctx.accounts.global_config.load()?;
+
+    let reward_index = reward_index as usize;
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::Harvest),
+        FarmError::FarmOperationPaused
+    );
+
+    let owner = ctx.accounts.owner.key();
+    let harvester = ctx.accounts.payer.key();
+    let is_third_party = harvester != owner;
+
+    let ts = TimeUnit::now_from_clock(time_unit, &Clock::get()?);
+    vmsg!(
+        "HarvestToOwner user_state {:?}, farm_state {:?} harvester {:?} ts {}",
+        ctx.accounts.user_state.key(),
+        ctx.accounts.farm_state.key(),
+        harvester,
+        ts
+    );
+    let HarvestEffects {
+        reward_user,
+        reward_treasury,
+        reward_penalty,
+        reward_split,
+        treasury_fee_bps,
+        fee_override_clamped,
+    } = farm_operations::harvest(
+        farm_state,
+        user_state,
+        global_config,
+        scope_price,
+        reward_index,
+        ctx.accounts.rewards_vault.amount,
+        ts,
+    )?;
+
+    vmsg!(
+        "owner {:?} amount_user {:?}, amount_treasury {:?}, amount_penalty {:?}, amount_split {:?}",
+        owner,
+        reward_user,
+        reward_treasury,
+        reward_penalty,
+        reward_split
+    );
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+
+    let signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+        BASE_SEED_FARM_VAULTS_AUTHORITY,
+        farm_state_key,
+        farm_state.farm_vaults_authority_bump as u8
+    );
+
+    let reward_token = &farm_state.reward_infos[reward_index].token;
+    let reward_mint_info = ctx.accounts.reward_mint.to_account_info();
+
+    if reward_user > 0 {
+        token_operations::create_ata_if_needed(
+            &ctx.accounts.payer.to_account_info(),
+            &ctx.accounts.owner_reward_ata,
+            &ctx.accounts.owner,
+            &reward_mint_info,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program.to_account_info(),
+        )?;
+        token_operations::transfer_token_from_vault(
+            reward_token,
+            reward_user,
+            signer_seeds,
+            &ctx.accounts.owner_reward_ata,
+            &ctx.accounts.rewards_vault.to_account_info(),
+            &ctx.accounts.farm_vaults_authority,
+            Some(reward_mint_info.clone()),
+            &ctx.accounts.token_program,
+        )?;
+    }
+
+    if reward_treasury > 0 {
+        token_operations::transfer_token_from_vault(
+            reward_token,
+            reward_treasury,
+            signer_seeds,
+            &ctx.accounts.rewards_treasury_vault.to_account_info(),
+            &ctx.accounts.rewards_vault.to_account_info(),
+            &ctx.accounts.farm_vaults_authority,
+            Some(reward_mint_info.clone()),
+            &ctx.accounts.token_program,
+        )?;
+    }
+
+    if reward_split > 0 {
+        let (Some(split_destination_owner), Some(split_destination_ata)) = (
+            &ctx.accounts.split_destination,
+            &ctx.accounts.split_destination_ata,
+        ) else {
+            return err_ctx!(
+                FarmError::MissingRewardSplitAccounts,
+                farm_state = farm_state_key,
+                reward_index = reward_index,
+                reward_split = reward_split,
+            );
+        };
+        token_operations::create_ata_if_needed(
+            &ctx.accounts.payer.to_account_info(),
+            split_destination_ata,
+            split_destination_owner,
+            &reward_mint_info,
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program.to_account_info(),
+        )?;
+        token_operations::transfer_token_from_vault(
+            reward_token,
+            reward_split,
+            signer_seeds,
+            split_destination_ata,
+            &ctx.accounts.rewards_vault.to_account_info(),
+            &ctx.accounts.farm_vaults_authority,
+            Some(reward_mint_info.clone()),
+            &ctx.accounts.token_program,
+        )?;
+    }
+
+    emit_event!(
+        ctx,
+        HarvestEvent {
+            farm_state: farm_state_key,
+            user_state: ctx.accounts.user_state.key(),
+            owner,
+            reward_index: reward_index as u64,
+            reward_mint: ctx.accounts.rewards_vault.mint,
+            reward_user,
+            reward_treasury,
+            reward_penalty,
+            reward_split,
+            ts,
+        }
+    );
+
+    emit_event!(
+        ctx,
+        HarvestToOwnerEvent {
+            farm_state: farm_state_key,
+            user_state: ctx.accounts.user_state.key(),
+            owner,
+            reward_index: reward_index as u64,
+            harvester,
+            is_third_party,
+        }
+    );
+
+    if fee_override_clamped {
+        emit_event!(
+            ctx,
+            HarvestFeeOverrideClampedEvent {
+                farm_state: farm_state_key,
+                harvest_fee_bps_override: farm_state.harvest_fee_bps_override,
+                treasury_fee_bps,
+            }
+        );
+    }
+
+    set_return_data(&reward_user.to_le_bytes());
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(reward_index: u64)]
+pub struct HarvestToOwner<'info> {
+    /// Anyone, the owner included
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// `UserState::owner`
+    #[account(address = user_state.load()?.owner @ FarmError::UnexpectedAccount)]
+    pub owner: AccountInfo<'info>,
+
+    #[account(mut,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(
+        mut,
+        has_one = global_config,
+        has_one = farm_vaults_authority,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    /// The ATA of `owner` for the reward mint, created when missing
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(
+            &owner.key(),
+            &rewards_vault.mint,
+            &token_program.key(),
+        ) @ FarmError::UnexpectedAccount,
+    )]
+    pub owner_reward_ata: AccountInfo<'info>,
+
+    #[account(mut,
+        seeds = [
+            BASE_SEED_REWARD_VAULT,
+            farm_state.key().as_ref(),
+            rewards_vault.mint.as_ref(),
+            farm_state.load()?.reward_vault_seed_suffix(reward_index as usize, &rewards_vault.mint),
+        ],
+        bump,
+        constraint = rewards_vault.delegate.is_none() @ FarmError::RewardsVaultHasDelegate,
+        constraint = rewards_vault.close_authority.is_none() @ FarmError::RewardsVaultHasCloseAuthority,
+        constraint = rewards_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
+    )]
+    pub rewards_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_REWARD_TREASURY_VAULT.as_ref(), global_config.key().as_ref(), rewards_vault.mint.as_ref()],
+        bump,
+        constraint = rewards_treasury_vault.delegate.is_none() @ FarmError::RewardsTreasuryVaultHasDelegate,
+        constraint = rewards_treasury_vault.close_authority.is_none() @ FarmError::RewardsTreasuryVaultHasCloseAuthority,
+    )]
+    pub rewards_treasury_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_vaults_authority: AccountInfo<'info>,
+
+    #[account(
+        address = rewards_vault.mint @ FarmError::UnexpectedAccount,
+    )]
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+
+    /// Required when the reward has a split, see `HarvestReward`
+    #[account(
+        address = farm_state.load()?.reward_infos[reward_index as usize].split_destination @ FarmError::UnexpectedAccount,
+    )]
+    pub split_destination: Option<AccountInfo<'info>>,
+    #[account(mut,
+        address = get_associated_token_address_with_program_id(
+            &farm_state.load()?.reward_infos[reward_index as usize].split_destination,
+            &rewards_vault.mint,
+            &token_program.key(),
+        ) @ FarmError::UnexpectedAccount,
+    )]
+    pub split_destination_ata: Option<AccountInfo<'info>>,
+}
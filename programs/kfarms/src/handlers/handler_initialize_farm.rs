@@ -1,15 +1,48 @@
-use crate::state::{GlobalConfig, TimeUnit};
+use crate::state::{FarmRegistry, GlobalConfig, ProgramInstruction, TimeUnit};
 use crate::state::{RewardInfo, TokenInfo};
-use crate::utils::constraints::check_remaining_accounts;
+use crate::token_operations;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::consts::*;
-use crate::FarmState;
+use crate::utils::registry;
+use crate::vmsg;
+use crate::{farm_operations, FarmState};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 pub fn process(ctx: Context<InitializeFarm>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeFarm,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let mut farm_state = ctx.accounts.farm_state.load_init()?;
+    let global_config = &mut ctx.accounts.global_config.load_mut()?;
+
+    farm_operations::check_farm_token_mint(
+        global_config,
+        &ctx.accounts.token_mint.key(),
+        ctx.accounts.token_mint.freeze_authority.into(),
+    )?;
+    token_operations::check_mint_extensions(
+        global_config,
+        &ctx.accounts.token_mint.to_account_info(),
+    )?;
+    let transfer_hook_program = token_operations::check_farm_token_transfer_hook(
+        global_config,
+        &ctx.accounts.token_mint.to_account_info(),
+    )?;
+
+    farm_operations::register_new_farm(global_config)?;
+
+    if let Some(farm_registry) = &mut ctx.accounts.farm_registry {
+        registry::append_farm_to_registry(
+            farm_registry,
+            ctx.accounts.farm_state.key(),
+            &ctx.accounts.farm_admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
 
     farm_state.farm_admin = ctx.accounts.farm_admin.key();
     farm_state.pending_farm_admin = ctx.accounts.farm_admin.key();
@@ -22,12 +55,16 @@ pub fn process(ctx: Context<InitializeFarm>) -> Result<()> {
     farm_state.token = TokenInfo {
         mint: ctx.accounts.token_mint.key(),
         decimals: ctx.accounts.token_mint.decimals as u64,
-        _padding: [0; 10],
+        token_program: ctx.accounts.token_program.key(),
+        transfer_hook_program,
+        _padding: [0; 2],
     };
     farm_state.farm_vault = ctx.accounts.farm_vault.key();
     farm_state.delegate_authority = Pubkey::default();
 
-    msg!(
+    farm_operations::apply_global_farm_defaults(global_config, &mut farm_state);
+
+    vmsg!(
         "Initialize farm {:?} ts {}",
         ctx.accounts.farm_state.to_account_info().key(),
         TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?)
@@ -44,6 +81,7 @@ pub struct InitializeFarm<'info> {
     #[account(zero)]
     pub farm_state: AccountLoader<'info, FarmState>,
 
+    #[account(mut)]
     pub global_config: AccountLoader<'info, GlobalConfig>,
 
     #[account(init,
@@ -53,7 +91,7 @@ pub struct InitializeFarm<'info> {
         token::mint = token_mint,
         token::authority = farm_vaults_authority,
     )]
-    pub farm_vault: Box<Account<'info, TokenAccount>>,
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
@@ -61,10 +99,15 @@ pub struct InitializeFarm<'info> {
     )]
     pub farm_vaults_authority: AccountInfo<'info>,
 
-    pub token_mint: Box<Account<'info, Mint>>,
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
     pub system_program: Program<'info, System>,
 
     pub rent: Sysvar<'info, Rent>,
+
+    #[account(mut,
+        has_one = global_config,
+    )]
+    pub farm_registry: Option<Box<Account<'info, FarmRegistry>>>,
 }
@@ -1,14 +1,32 @@
-use crate::state::{GlobalConfig, TimeUnit};
+use crate::state::{FarmRegistry, GlobalConfig, ProgramInstruction, TimeUnit};
 use crate::state::{RewardInfo, TokenInfo};
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::consts::*;
-use crate::FarmState;
+use crate::utils::registry;
+use crate::vmsg;
+use crate::{farm_operations, FarmState};
 use anchor_lang::prelude::*;
 
 pub fn process(ctx: Context<InitializeFarmDelegated>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeFarmDelegated,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let mut farm_state = ctx.accounts.farm_state.load_init()?;
+    let global_config = &mut ctx.accounts.global_config.load_mut()?;
+
+    farm_operations::register_new_farm(global_config)?;
+
+    if let Some(farm_registry) = &mut ctx.accounts.farm_registry {
+        registry::append_farm_to_registry(
+            farm_registry,
+            ctx.accounts.farm_state.key(),
+            &ctx.accounts.farm_admin.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+        )?;
+    }
     let time_unit = farm_state.time_unit;
 
     farm_state.farm_admin = ctx.accounts.farm_admin.key();
@@ -24,7 +42,7 @@ pub fn process(ctx: Context<InitializeFarmDelegated>) -> Result<()> {
     farm_state.delegate_authority = ctx.accounts.farm_delegate.key();
     farm_state.is_farm_delegated = true as u8;
 
-    msg!(
+    vmsg!(
         "InitializeFarmDelegated {:?} ts {}",
         ctx.accounts.farm_state.to_account_info().key(),
         TimeUnit::now_from_clock(time_unit, &Clock::get()?)
@@ -44,6 +62,7 @@ pub struct InitializeFarmDelegated<'info> {
     #[account(zero)]
     pub farm_state: AccountLoader<'info, FarmState>,
 
+    #[account(mut)]
     pub global_config: AccountLoader<'info, GlobalConfig>,
 
     #[account(
@@ -55,4 +74,9 @@ pub struct InitializeFarmDelegated<'info> {
     pub system_program: Program<'info, System>,
 
     pub rent: Sysvar<'info, Rent>,
+
+    #[account(mut,
+        has_one = global_config,
+    )]
+    pub farm_registry: Option<Box<Account<'info, FarmRegistry>>>,
 }
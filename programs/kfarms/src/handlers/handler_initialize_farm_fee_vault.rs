@@ -0,0 +1,72 @@
+use crate::state::{GlobalConfig, ProgramInstruction};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::{FarmError, FarmState};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+pub fn process(ctx: Context<InitializeFarmFeeVault>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeFarmFeeVault,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+
+    require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !farm_state.has_fee_vault(),
+        FarmError::FeeVaultAlreadyInitialized
+    );
+
+    farm_state.fee_vault = ctx.accounts.fee_vault.key();
+
+    vmsg!(
+        "InitializeFarmFeeVault: farm {} fee_vault {}",
+        ctx.accounts.farm_state.key(),
+        ctx.accounts.fee_vault.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeFarmFeeVault<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut,
+        has_one = global_config,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(has_one = treasury_vaults_authority)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        payer = payer,
+        seeds = [BASE_SEED_FARM_FEE_VAULT, farm_state.key().as_ref(), token_mint.key().as_ref()],
+        bump,
+        token::mint = token_mint,
+        token::authority = treasury_vaults_authority,
+    )]
+    pub fee_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_TREASURY_VAULTS_AUTHORITY, global_config.key().as_ref()],
+        bump,
+    )]
+    pub treasury_vaults_authority: AccountInfo<'info>,
+
+    #[account(
+        constraint = token_mint.key() == farm_state.load()?.token.mint @ FarmError::TokenFarmTokenMintMissmatch,
+    )]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    #[account(address = farm_state.load()?.token.token_program() @ FarmError::UnexpectedAccount)]
+    pub token_program: Interface<'info, TokenInterface>,
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
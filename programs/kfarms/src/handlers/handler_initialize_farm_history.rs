@@ -0,0 +1,55 @@
+use crate::state::{FarmHistory, ProgramInstruction};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig};
+use anchor_lang::prelude::*;
+
+/// Snapshots start once `history_snapshot_interval` is set on the farm, the first
+/// one only counting the rewards issued from now on.
+pub fn process(ctx: Context<InitializeFarmHistory>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeFarmHistory,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let farm_history = &mut ctx.accounts.farm_history.load_init()?;
+
+    farm_history.farm_state = ctx.accounts.farm_state.key();
+    for (reward_index, reward_info) in farm_state.reward_infos.iter().enumerate() {
+        farm_history.last_rewards_issued_cumulative[reward_index] =
+            reward_info.rewards_issued_cumulative;
+    }
+
+    vmsg!(
+        "InitializeFarmHistory: history {} farm {}",
+        ctx.accounts.farm_history.key(),
+        ctx.accounts.farm_state.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeFarmHistory<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        seeds = [BASE_SEED_FARM_HISTORY, farm_state.key().as_ref()],
+        bump,
+        payer = payer,
+        space = SIZE_FARM_HISTORY,
+    )]
+    pub farm_history: AccountLoader<'info, FarmHistory>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
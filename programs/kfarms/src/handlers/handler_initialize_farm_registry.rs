@@ -0,0 +1,45 @@
+use crate::state::{FarmRegistry, GlobalConfig, ProgramInstruction};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::vmsg;
+use anchor_lang::prelude::*;
+
+pub fn process(ctx: Context<InitializeFarmRegistry>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeFarmRegistry,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_registry = &mut ctx.accounts.farm_registry;
+    farm_registry.global_config = ctx.accounts.global_config.key();
+    farm_registry.bump = ctx.bumps.farm_registry;
+    farm_registry.farms = Vec::new();
+
+    vmsg!(
+        "InitializeFarmRegistry {} global_config {}",
+        farm_registry.key(),
+        ctx.accounts.global_config.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeFarmRegistry<'info> {
+    #[account(mut)]
+    pub global_admin: Signer<'info>,
+
+    #[account(has_one = global_admin)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        seeds = [BASE_SEED_FARM_REGISTRY, global_config.key().as_ref()],
+        bump,
+        payer = global_admin,
+        space = FarmRegistry::space(0),
+    )]
+    pub farm_registry: Account<'info, FarmRegistry>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,50 @@
+use crate::state::ProgramInstruction;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, ReferrerState};
+use anchor_lang::prelude::*;
+
+pub fn process(ctx: Context<InitializeReferrerState>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeReferrerState,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let referrer_state = &mut ctx.accounts.referrer_state.load_init()?;
+
+    referrer_state.farm_state = ctx.accounts.farm_state.key();
+    referrer_state.referrer = ctx.accounts.referrer.key();
+    referrer_state.bump = ctx.bumps.referrer_state.into();
+
+    vmsg!(
+        "InitializeReferrerState: referrer {} farm {}",
+        ctx.accounts.referrer.key(),
+        ctx.accounts.farm_state.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeReferrerState<'info> {
+    #[account(mut)]
+    pub referrer: Signer<'info>,
+
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        seeds = [BASE_SEED_REFERRER_STATE, farm_state.key().as_ref(), referrer.key().as_ref()],
+        bump,
+        payer = referrer,
+        space = SIZE_REFERRER_STATE,
+    )]
+    pub referrer_state: AccountLoader<'info, ReferrerState>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
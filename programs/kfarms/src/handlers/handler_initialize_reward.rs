@@ -1,27 +1,39 @@
-use crate::state::GlobalConfig;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::state::{GlobalConfig, ProgramInstruction};
+use crate::token_operations;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::consts::*;
-use crate::FarmState;
+use crate::vmsg;
 use crate::{farm_operations, state::TimeUnit};
+use crate::{FarmError, FarmState};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 pub fn process(ctx: Context<InitializeReward>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeReward,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
     let time_unit = farm_state.time_unit;
     let reward_mint = &mut ctx.accounts.reward_mint;
+    token_operations::check_mint_extensions(
+        &ctx.accounts.global_config.load()?,
+        &reward_mint.to_account_info(),
+    )?;
+    token_operations::check_reward_transfer_hook(&reward_mint.to_account_info())?;
 
     farm_operations::initialize_reward(
         farm_state,
         ctx.accounts.reward_vault.key(),
         reward_mint.key(),
         reward_mint.decimals,
+        ctx.accounts.token_program.key(),
         TimeUnit::now_from_clock(time_unit, &Clock::get()?),
     )?;
 
-    msg!(
+    vmsg!(
         "InitializeReward {:?} farm_state {:?} ts {}",
         ctx.accounts.reward_mint.key(),
         ctx.accounts.farm_state.key(),
@@ -40,7 +52,10 @@ pub struct InitializeReward<'info> {
         mut,
         has_one = farm_admin,
         has_one = global_config,
-        has_one = farm_vaults_authority
+        has_one = farm_vaults_authority,
+        // Checked before `reward_vault` is created, which would fail on its own for a
+        // mint already used by the farm, but with a system program error
+        constraint = farm_state.load()?.can_initialize_reward_mint(&reward_mint.key()) @ FarmError::RewardAlreadyInitialized,
     )]
     pub farm_state: AccountLoader<'info, FarmState>,
 
@@ -49,12 +64,17 @@ pub struct InitializeReward<'info> {
 
     #[account(init,
         payer = farm_admin,
-        seeds = [BASE_SEED_REWARD_VAULT, farm_state.key().as_ref(), reward_mint.key().as_ref()],
+        seeds = [
+            BASE_SEED_REWARD_VAULT,
+            farm_state.key().as_ref(),
+            reward_mint.key().as_ref(),
+            farm_state.load()?.new_reward_vault_seed_suffix(&reward_mint.key()),
+        ],
         bump,
         token::mint = reward_mint,
         token::authority = farm_vaults_authority,
     )]
-    pub reward_vault: Box<Account<'info, TokenAccount>>,
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(init_if_needed,
         payer = farm_admin,
@@ -63,15 +83,15 @@ pub struct InitializeReward<'info> {
         token::mint = reward_mint,
         token::authority = treasury_vaults_authority,
     )]
-    pub reward_treasury_vault: Box<Account<'info, TokenAccount>>,
+    pub reward_treasury_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     pub farm_vaults_authority: AccountInfo<'info>,
 
     pub treasury_vaults_authority: AccountInfo<'info>,
 
-    pub reward_mint: Box<Account<'info, Mint>>,
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
 
     pub system_program: Program<'info, System>,
 
@@ -1,11 +1,16 @@
 use crate::farm_operations;
-use crate::state::TimeUnit;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::consts::*;
-use crate::{FarmError, FarmState, UserState};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserPage, UserState};
 use anchor_lang::prelude::*;
 
 pub fn process(ctx: Context<InitializeUser>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeUser,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
@@ -15,7 +20,7 @@ pub fn process(ctx: Context<InitializeUser>) -> Result<()> {
     let owner = ctx.accounts.owner.key();
     let user_state_bump = ctx.bumps.user_state.into();
 
-    msg!(
+    vmsg!(
         "InitializeUser: user {} farm {} ts {}",
         ctx.accounts.user_state.key(),
         ctx.accounts.farm_state.key(),
@@ -38,14 +43,37 @@ pub fn process(ctx: Context<InitializeUser>) -> Result<()> {
             owner,
             FarmError::UserDelegatedFarmNonDelegatedMissmatch
         );
-    } else {
+    } else if farm_state.is_mirror() {
+        // Anyone can create the user state, its stake is copied from the primary farm
         require_keys_eq!(
-            farm_state.delegate_authority,
-            ctx.accounts.authority.key(),
+            owner,
+            ctx.accounts.delegatee.key(),
+            FarmError::UserDelegatedFarmNonDelegatedMissmatch
+        );
+    } else {
+        require!(
+            farm_state
+                .delegate_authority_index(&ctx.accounts.authority.key())
+                .is_some(),
             FarmError::AuthorityFarmDelegateMissmatch
         );
     }
 
+    if farm_state.user_pages_enabled == 1 {
+        let user_page = ctx
+            .accounts
+            .user_page
+            .as_ref()
+            .ok_or(FarmError::InvalidUserPage)?;
+        farm_operations::add_user_to_page(
+            farm_state,
+            &mut user_page.load_mut()?,
+            &ctx.accounts.farm_state.key(),
+            &ctx.accounts.user_state.key(),
+            farm_state.num_users,
+        )?;
+    }
+
     user_state.bump = user_state_bump;
     user_state.delegatee = ctx.accounts.delegatee.key();
 
@@ -85,4 +113,10 @@ pub struct InitializeUser<'info> {
 
     pub system_program: Program<'info, System>,
     pub rent: Sysvar<'info, Rent>,
+
+    #[account(mut)]
+    pub user_page: Option<AccountLoader<'info, UserPage>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -0,0 +1,56 @@
+use crate::state::ProgramInstruction;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserPage};
+use anchor_lang::prelude::*;
+
+pub fn process(ctx: Context<InitializeUserPage>, page_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeUserPage,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    require!(
+        farm_state.user_pages_enabled == 1,
+        FarmError::InvalidUserPage
+    );
+
+    let user_page = &mut ctx.accounts.user_page.load_init()?;
+    user_page.farm_state = ctx.accounts.farm_state.key();
+    user_page.page_index = page_index;
+
+    vmsg!(
+        "InitializeUserPage: page {} farm {} page_index {}",
+        ctx.accounts.user_page.key(),
+        ctx.accounts.farm_state.key(),
+        page_index
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(page_index: u64)]
+pub struct InitializeUserPage<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        seeds = [BASE_SEED_USER_PAGE, farm_state.key().as_ref(), &page_index.to_le_bytes()],
+        bump,
+        payer = payer,
+        space = SIZE_USER_PAGE,
+    )]
+    pub user_page: AccountLoader<'info, UserPage>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
@@ -0,0 +1,58 @@
+use crate::state::{GlobalConfig, ProgramInstruction, WithdrawalQueue};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::{FarmError, FarmState};
+use anchor_lang::prelude::*;
+
+/// Withdrawals blocked by `max_user_withdrawal_per_window` can be queued once the
+/// farm has a withdrawal queue, see `queue_withdrawal`.
+pub fn process(ctx: Context<InitializeWithdrawalQueue>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::InitializeWithdrawalQueue,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+
+    let withdrawal_queue = &mut ctx.accounts.withdrawal_queue;
+    withdrawal_queue.farm_state = ctx.accounts.farm_state.key();
+    withdrawal_queue.bump = ctx.bumps.withdrawal_queue;
+    withdrawal_queue.withdrawals = Vec::new();
+
+    farm_state.withdrawal_queue = withdrawal_queue.key();
+
+    vmsg!(
+        "InitializeWithdrawalQueue {} farm {}",
+        withdrawal_queue.key(),
+        ctx.accounts.farm_state.key()
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct InitializeWithdrawalQueue<'info> {
+    #[account(mut)]
+    pub farm_admin: Signer<'info>,
+
+    #[account(mut,
+        has_one = farm_admin,
+        has_one = global_config,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        seeds = [BASE_SEED_WITHDRAWAL_QUEUE, farm_state.key().as_ref()],
+        bump,
+        payer = farm_admin,
+        space = WithdrawalQueue::space(0),
+    )]
+    pub withdrawal_queue: Account<'info, WithdrawalQueue>,
+
+    pub system_program: Program<'info, System>,
+}
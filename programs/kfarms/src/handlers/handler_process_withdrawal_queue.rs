@@ -0,0 +1,194 @@
+use crate::emit_event;
+use crate::events::QueuedWithdrawalPaidEvent;
+use crate::farm_operations;
+use crate::state::{FarmPauseFlag, GlobalConfig, ProgramInstruction, WithdrawalQueue};
+use crate::token_operations;
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::{gen_signer_seeds_two, FarmError, FarmState, UserState};
+use anchor_lang::prelude::*;
+use anchor_spl::associated_token::{get_associated_token_address_with_program_id, AssociatedToken};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Permissionless, pays out the queued withdrawals in order, within the user
+/// withdrawal allowance, to the ATA of the current owner of each position. Stops at
+/// the first withdrawal the allowance does not cover in full, which is paid out in
+/// part and keeps its place.
+///
+/// `remaining_accounts`: the user state, its owner and the owner ATA of each of the
+/// first `min(max_items, queue length)` queued withdrawals, followed by the accounts
+/// of the farm token transfer hook, if any. The ATAs are created if needed, `payer`
+/// funding the rent.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, ProcessWithdrawalQueue<'info>>,
+    max_items: u8,
+) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::ProcessWithdrawalQueue,
+    )?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::WithdrawUnstakedDeposits),
+        FarmError::FarmOperationPaused
+    );
+
+    let num_items = std::cmp::min(
+        max_items as usize,
+        ctx.accounts.withdrawal_queue.withdrawals.len(),
+    );
+    require!(
+        ctx.remaining_accounts.len() >= 3 * num_items,
+        FarmError::MissingRemainingAccounts
+    );
+    let (user_accounts, transfer_hook_accounts) = ctx.remaining_accounts.split_at(3 * num_items);
+    let transfer_hook_accounts =
+        token_operations::transfer_hook_accounts(&farm_state.token, transfer_hook_accounts)?;
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+        BASE_SEED_FARM_VAULTS_AUTHORITY,
+        farm_state_key,
+        farm_state.farm_vaults_authority_bump as u8
+    );
+    let unix_ts: u64 = Clock::get()?.unix_timestamp.try_into().unwrap();
+
+    let mut num_paid_in_full = 0;
+    for (withdrawal, accounts) in ctx
+        .accounts
+        .withdrawal_queue
+        .withdrawals
+        .iter_mut()
+        .zip(user_accounts.chunks_exact(3))
+    {
+        let [user_state_info, owner, owner_ata] = accounts else {
+            unreachable!()
+        };
+        require_keys_eq!(
+            user_state_info.key(),
+            withdrawal.user_state,
+            FarmError::UnexpectedAccount
+        );
+        let user_state = AccountLoader::<UserState>::try_from(user_state_info)?;
+        require_keys_eq!(
+            owner.key(),
+            user_state.load()?.owner,
+            FarmError::UnexpectedAccount
+        );
+        require_keys_eq!(
+            owner_ata.key(),
+            get_associated_token_address_with_program_id(
+                &owner.key(),
+                &farm_state.token.mint,
+                &ctx.accounts.token_program.key(),
+            ),
+            FarmError::UnexpectedAccount
+        );
+
+        let amount =
+            farm_operations::pay_queued_withdrawal(farm_state, withdrawal.amount, unix_ts)?;
+        if amount == 0 {
+            break;
+        }
+        withdrawal.amount -= amount;
+
+        token_operations::create_ata_if_needed(
+            &ctx.accounts.payer.to_account_info(),
+            owner_ata,
+            owner,
+            &ctx.accounts.token_mint.to_account_info(),
+            &ctx.accounts.system_program.to_account_info(),
+            &ctx.accounts.token_program,
+            &ctx.accounts.associated_token_program.to_account_info(),
+        )?;
+        token_operations::transfer_token_from_vault_with_hook(
+            &farm_state.token,
+            amount,
+            signer_seeds,
+            owner_ata,
+            &ctx.accounts.farm_vault.to_account_info(),
+            &ctx.accounts.farm_vaults_authority,
+            Some(ctx.accounts.token_mint.to_account_info()),
+            &ctx.accounts.token_program,
+            transfer_hook_accounts,
+        )?;
+
+        emit_event!(
+            ctx,
+            QueuedWithdrawalPaidEvent {
+                farm_state: farm_state_key,
+                user_state: withdrawal.user_state,
+                owner: owner.key(),
+                amount,
+                amount_remaining: withdrawal.amount,
+            }
+        );
+
+        if withdrawal.amount > 0 {
+            break;
+        }
+        num_paid_in_full += 1;
+    }
+
+    ctx.accounts
+        .withdrawal_queue
+        .withdrawals
+        .drain(..num_paid_in_full);
+
+    vmsg!(
+        "ProcessWithdrawalQueue farm {} paid in full {} queued {}",
+        farm_state_key,
+        num_paid_in_full,
+        farm_state.num_queued_withdrawals
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct ProcessWithdrawalQueue<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    #[account(mut,
+        has_one = global_config,
+        has_one = farm_vault,
+        has_one = farm_vaults_authority,
+        has_one = withdrawal_queue,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_WITHDRAWAL_QUEUE, farm_state.key().as_ref()],
+        bump = withdrawal_queue.bump,
+    )]
+    pub withdrawal_queue: Box<Account<'info, WithdrawalQueue>>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_FARM_VAULT, farm_state.key().as_ref(), farm_state.load()?.token.mint.as_ref()],
+        bump,
+        constraint = farm_vault.delegate.is_none() @ FarmError::FarmVaultHasDelegate,
+        constraint = farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
+    )]
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_vaults_authority: AccountInfo<'info>,
+
+    #[account(
+        address = farm_state.load()?.token.mint @ FarmError::TokenFarmTokenMintMissmatch,
+    )]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+    pub associated_token_program: Program<'info, AssociatedToken>,
+    pub system_program: Program<'info, System>,
+}
@@ -0,0 +1,79 @@
+use crate::emit_event;
+use crate::events::TreasuryWithdrawalQueuedEvent;
+use crate::farm_operations;
+use crate::state::{GlobalConfig, ProgramInstruction, TreasuryWithdrawalRequest};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+
+pub fn process(ctx: Context<QueueTreasuryWithdrawal>, amount: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::QueueTreasuryWithdrawal,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let global_config = &mut ctx.accounts.global_config.load_mut()?;
+    let request = &mut ctx.accounts.withdrawal_request.load_init()?;
+
+    farm_operations::queue_treasury_withdrawal(
+        global_config,
+        request,
+        &ctx.accounts.global_config.key(),
+        &ctx.accounts.reward_mint.key(),
+        &ctx.accounts.withdraw_destination_token_account.key(),
+        amount,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+    )?;
+    request.bump = ctx.bumps.withdrawal_request.into();
+
+    emit_event!(
+        ctx,
+        TreasuryWithdrawalQueuedEvent {
+            global_config: ctx.accounts.global_config.key(),
+            request: ctx.accounts.withdrawal_request.key(),
+            request_id: request.request_id,
+            mint: request.mint,
+            destination: request.destination,
+            amount,
+            executable_ts: request.executable_ts,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct QueueTreasuryWithdrawal<'info> {
+    #[account(mut)]
+    pub global_admin: Signer<'info>,
+
+    #[account(mut,
+        has_one = global_admin
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(init,
+        seeds = [
+            BASE_SEED_TREASURY_WITHDRAWAL_REQUEST,
+            global_config.key().as_ref(),
+            &global_config.load()?.num_treasury_withdrawal_requests.to_le_bytes(),
+        ],
+        bump,
+        payer = global_admin,
+        space = SIZE_TREASURY_WITHDRAWAL_REQUEST,
+    )]
+    pub withdrawal_request: AccountLoader<'info, TreasuryWithdrawalRequest>,
+
+    #[account(
+        token::mint = reward_mint,
+    )]
+    pub withdraw_destination_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+}
@@ -0,0 +1,107 @@
+use crate::emit_event;
+use crate::events::WithdrawalQueuedEvent;
+use crate::farm_operations;
+use crate::state::{
+    FarmPauseFlag, GlobalConfig, ProgramInstruction, QueuedWithdrawal, TimeUnit, WithdrawalQueue,
+};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::utils::withdrawal_queue::append_queued_withdrawal;
+use crate::{FarmError, FarmState, UserState};
+use anchor_lang::prelude::*;
+
+/// Moves the elapsed pending withdrawal of the user to the end of the withdrawal
+/// queue, when `withdraw_unstaked_deposits` refuses it because of the user withdrawal
+/// limit or of the withdrawals already queued. One queued withdrawal per user, the
+/// owner funds the rent of its queue entry.
+pub fn process(ctx: Context<QueueWithdrawal>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::QueueWithdrawal,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    let time_unit = farm_state.time_unit;
+
+    require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::WithdrawUnstakedDeposits),
+        FarmError::FarmOperationPaused
+    );
+
+    let user_state_key = ctx.accounts.user_state.key();
+    require!(
+        !ctx.accounts
+            .withdrawal_queue
+            .withdrawals
+            .iter()
+            .any(|withdrawal| withdrawal.user_state == user_state_key),
+        FarmError::WithdrawalAlreadyQueued
+    );
+
+    let clock = Clock::get()?;
+    let request_ts: u64 = clock.unix_timestamp.try_into().unwrap();
+    let amount = farm_operations::queue_withdrawal(
+        farm_state,
+        user_state,
+        TimeUnit::now_from_clock(time_unit, &clock),
+        request_ts,
+    )?;
+
+    let queue_position = ctx.accounts.withdrawal_queue.withdrawals.len() as u64;
+    append_queued_withdrawal(
+        &mut ctx.accounts.withdrawal_queue,
+        QueuedWithdrawal {
+            user_state: user_state_key,
+            amount,
+            request_ts,
+        },
+        &ctx.accounts.owner.to_account_info(),
+        &ctx.accounts.system_program.to_account_info(),
+    )?;
+
+    emit_event!(
+        ctx,
+        WithdrawalQueuedEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            user_state: user_state_key,
+            owner: ctx.accounts.owner.key(),
+            amount,
+            request_ts,
+            queue_position,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct QueueWithdrawal<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut,
+        has_one = global_config,
+        has_one = withdrawal_queue,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_WITHDRAWAL_QUEUE, farm_state.key().as_ref()],
+        bump = withdrawal_queue.bump,
+    )]
+    pub withdrawal_queue: Account<'info, WithdrawalQueue>,
+
+    pub system_program: Program<'info, System>,
+}
@@ -1,11 +1,18 @@
 use crate::farm_operations;
-use crate::state::TimeUnit;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::BASE_SEED_FARM_HISTORY;
 use crate::utils::scope::load_scope_price;
+use crate::FarmError;
+use crate::FarmHistory;
 use crate::FarmState;
+use crate::GlobalConfig;
 use anchor_lang::prelude::*;
 
+/// Also records a `farm_history` snapshot when one is due, snapshots are skipped
+/// while the account is not passed.
 pub fn process(ctx: Context<RefreshFarm>) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, ProgramInstruction::RefreshFarm)?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
@@ -14,11 +21,15 @@ pub fn process(ctx: Context<RefreshFarm>) -> Result<()> {
 
     farm_state.is_farm_delegated = farm_state.is_delegated() as u8;
 
-    farm_operations::refresh_global_rewards(
-        farm_state,
-        scope_price,
-        TimeUnit::now_from_clock(time_unit, &Clock::get()?),
-    )?;
+    let clock = Clock::get()?;
+    let ts = TimeUnit::now_from_clock(time_unit, &clock);
+    farm_operations::refresh_global_rewards(farm_state, scope_price, ts)?;
+    farm_state.last_refresh_slot = clock.slot;
+
+    if let Some(farm_history) = &ctx.accounts.farm_history {
+        farm_operations::record_farm_history(farm_state, &mut farm_history.load_mut()?, ts);
+    }
+
     Ok(())
 }
 
@@ -28,4 +39,13 @@ pub struct RefreshFarm<'info> {
     pub farm_state: AccountLoader<'info, FarmState>,
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_FARM_HISTORY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_history: Option<AccountLoader<'info, FarmHistory>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
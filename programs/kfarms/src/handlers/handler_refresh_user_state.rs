@@ -1,11 +1,18 @@
 use crate::farm_operations;
-use crate::state::TimeUnit;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::scope::load_scope_price;
-use crate::{FarmState, UserState};
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
 use anchor_lang::prelude::*;
 
-pub fn process(ctx: Context<RefreshUserState>) -> Result<()> {
+/// `refresh_user_state` catches up every reward, `refresh_user_state_masked` only the
+/// indices set in `rewards_mask`, 0 meaning all of them.
+pub fn process(
+    ctx: Context<RefreshUserState>,
+    rewards_mask: u16,
+    instruction: ProgramInstruction,
+) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, instruction)?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
@@ -13,11 +20,12 @@ pub fn process(ctx: Context<RefreshUserState>) -> Result<()> {
     let time_unit = farm_state.time_unit;
     let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
 
-    farm_operations::user_refresh_state(
+    farm_operations::user_refresh_state_for_rewards(
         farm_state,
         user_state,
         scope_price,
         TimeUnit::now_from_clock(time_unit, &Clock::get()?),
+        rewards_mask,
     )?;
 
     Ok(())
@@ -34,4 +42,7 @@ pub struct RefreshUserState<'info> {
     pub farm_state: AccountLoader<'info, FarmState>,
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -0,0 +1,52 @@
+use crate::emit_event;
+use crate::events::PositionRenouncedEvent;
+use crate::farm_operations;
+use crate::state::{FarmState, GlobalConfig, ProgramInstruction, UserState};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::FarmError;
+use anchor_lang::prelude::*;
+
+/// Locks the stake of `user_state` forever: unstake, withdraw_unstaked_deposits,
+/// transfer_stake_internal, split_stake and transfer_ownership are refused from then on,
+/// and harvests too unless `allow_harvest`. No authority can undo it.
+pub fn process(ctx: Context<RenouncePosition>, allow_harvest: bool) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::RenouncePosition,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = ctx.accounts.farm_state.load()?;
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+
+    farm_operations::renounce_position(&farm_state, user_state, allow_harvest)?;
+
+    emit_event!(
+        ctx,
+        PositionRenouncedEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            user_state: ctx.accounts.user_state.key(),
+            owner: ctx.accounts.owner.key(),
+            allow_harvest,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct RenouncePosition<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
@@ -0,0 +1,74 @@
+use crate::emit_event;
+use crate::events::RewardShortfallEvent;
+use crate::farm_operations;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::types::RewardShortfall;
+use crate::utils::constraints::{
+    check_instruction_enabled, check_remaining_accounts, check_reward_index,
+};
+use crate::utils::scope::load_scope_price;
+use crate::{FarmError, FarmState, GlobalConfig};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+/// Permissionless, fails with `NoRewardShortfall` unless `rewards_vault` holds less than
+/// the rewards still owed for `reward_index`.
+pub fn process(ctx: Context<ReportRewardShortfall>, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::ReportRewardShortfall,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+
+    let reward_index = check_reward_index(farm_state, reward_index)?;
+
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+    let RewardShortfall {
+        rewards_vault_amount,
+        rewards_owed,
+        deficit,
+    } = farm_operations::report_reward_shortfall(
+        farm_state,
+        scope_price,
+        reward_index,
+        ctx.accounts.rewards_vault.amount,
+        ts,
+    )?;
+
+    emit_event!(
+        ctx,
+        RewardShortfallEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            reward_index: reward_index as u64,
+            reward_mint: ctx.accounts.rewards_vault.mint,
+            rewards_vault_amount,
+            rewards_owed,
+            deficit,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(reward_index: u64)]
+pub struct ReportRewardShortfall<'info> {
+    #[account(mut,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(
+        constraint = rewards_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
+    )]
+    pub rewards_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+}
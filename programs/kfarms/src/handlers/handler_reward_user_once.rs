@@ -1,15 +1,24 @@
-use crate::state::UserState;
-use crate::utils::constraints::check_remaining_accounts;
-use crate::{farm_operations, FarmState};
+use crate::state::{ProgramInstruction, TimeUnit, UserState};
+use crate::utils::constraints::{
+    check_instruction_enabled, check_remaining_accounts, check_reward_index,
+};
+use crate::{farm_operations, FarmError, FarmState, GlobalConfig};
 use anchor_lang::prelude::*;
 
 pub fn process(ctx: Context<RewardUserOnce>, reward_index: u64, amount: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::RewardUserOnce,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let mut farm_state = ctx.accounts.farm_state.load_mut()?;
     let mut user_state = ctx.accounts.user_state.load_mut()?;
+    let reward_index = check_reward_index(&farm_state, reward_index)?;
 
-    farm_operations::reward_user_once(&mut farm_state, &mut user_state, reward_index, amount)?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+
+    farm_operations::reward_user_once(&mut farm_state, &mut user_state, reward_index, amount, ts)?;
 
     Ok(())
 }
@@ -26,4 +35,7 @@ pub struct RewardUserOnce<'info> {
         has_one = farm_state,
     )]
     pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -0,0 +1,69 @@
+use crate::state::ProgramInstruction;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::TokenAccount;
+
+/// Harvests of `reward_index` pay to `reward_destination`, or to an account of the
+/// owner again when it is not provided.
+///
+/// Only the mint of `reward_destination` is checked, not its owner, so token accounts
+/// owned by an off-curve address such as a multisig vault can be designated.
+/// `harvest_to_owner` ignores the destination and always pays the owner ATA.
+pub fn process(ctx: Context<SetRewardDestination>, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::SetRewardDestination,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    let reward_index = reward_index as usize;
+
+    let destination = ctx
+        .accounts
+        .reward_destination
+        .as_ref()
+        .map_or(Pubkey::default(), |reward_destination| {
+            reward_destination.key()
+        });
+
+    vmsg!(
+        "SetRewardDestination user_state {} reward_index {} {} -> {}",
+        ctx.accounts.user_state.key(),
+        reward_index,
+        user_state.reward_destinations[reward_index],
+        destination
+    );
+
+    user_state.reward_destinations[reward_index] = destination;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(reward_index: u64)]
+pub struct SetRewardDestination<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(
+        constraint = reward_destination.mint == farm_state.load()?.reward_infos[reward_index as usize].token.mint
+            @ FarmError::UserAtaRewardVaultMintMissmatch,
+    )]
+    pub reward_destination: Option<Box<InterfaceAccount<'info, TokenAccount>>>,
+}
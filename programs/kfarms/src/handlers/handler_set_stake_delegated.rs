@@ -1,20 +1,35 @@
+use crate::emit_event;
+use crate::events::DelegatedStakeChangedEvent;
 use crate::farm_operations;
-use crate::state::TimeUnit;
-use crate::utils::constraints::check_remaining_accounts;
-use crate::{FarmError, FarmState, UserState};
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
 use anchor_lang::prelude::*;
 
+/// `delegate_authority` is the farm delegate authority or one of its additional
+/// delegate authorities, the stake of the user is attributed to it.
 pub fn process(ctx: Context<SetStakeDelegated>, new_stake: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::SetStakeDelegated,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
     let time_unit = farm_state.time_unit;
 
     require!(farm_state.is_delegated(), FarmError::FarmNotDelegated);
+    require!(!farm_state.is_mirror(), FarmError::FarmIsMirror);
+
+    let authority_index = farm_state
+        .delegate_authority_index(&ctx.accounts.delegate_authority.key())
+        .ok_or(FarmError::AuthorityFarmDelegateMissmatch)?;
 
     let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    let previous_stake = user_state.active_stake_scaled as u64;
 
-    msg!(
+    vmsg!(
         "SetStakeDelegated: prev:{} -> new:{} ts:{}",
         user_state.active_stake_scaled,
         new_stake,
@@ -25,12 +40,26 @@ pub fn process(ctx: Context<SetStakeDelegated>, new_stake: u64) -> Result<()> {
         farm_state,
         user_state,
         new_stake,
+        authority_index,
         TimeUnit::now_from_clock(time_unit, &Clock::get()?),
     )?;
 
+    emit_event!(
+        ctx,
+        DelegatedStakeChangedEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            user_state: ctx.accounts.user_state.key(),
+            delegate_authority: ctx.accounts.delegate_authority.key(),
+            previous_stake,
+            new_stake,
+            authority_stake: farm_state.delegated_stake(authority_index),
+        }
+    );
+
     Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct SetStakeDelegated<'info> {
     pub delegate_authority: Signer<'info>,
@@ -40,8 +69,9 @@ pub struct SetStakeDelegated<'info> {
     )]
     pub user_state: AccountLoader<'info, UserState>,
 
-    #[account(mut,
-        has_one = delegate_authority,
-    )]
+    #[account(mut)]
     pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
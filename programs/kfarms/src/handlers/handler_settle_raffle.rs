@@ -0,0 +1,196 @@
+use crate::emit_event;
+use crate::err_ctx;
+use crate::events::RaffleSettledEvent;
+use crate::farm_operations;
+use crate::state::{ProgramInstruction, RaffleState, RaffleStatus, TimeUnit};
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::consts::*;
+use crate::utils::slot_hashes::{first_block_hash_from, SlotHashLookup};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserPage, UserState};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::sysvar::slot_hashes;
+
+/// Permissionless, advances `raffle_state` as far as the accounts given allow:
+///
+/// 1. draws the seed from the `slot_target` block hash, or cancels the raffle when the
+///    hash is no longer in `SlotHashes`
+/// 2. walks up to `MAX_RAFFLE_PAGES_PER_SETTLE` user pages from `next_user_id`,
+///    `remaining_accounts` holding each `user_page` followed by the user states of its
+///    non-tombstone slots, in order. A page can be cut short and resumed by the next call
+/// 3. once every user was walked, credits the prize to the unclaimed rewards of
+///    `winner_user_state`
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, SettleRaffle<'info>>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::SettleRaffle,
+    )?;
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let raffle_state_key = ctx.accounts.raffle_state.key();
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let raffle_state = &mut ctx.accounts.raffle_state.load_mut()?;
+
+    if raffle_state.get_status() == RaffleStatus::Committed {
+        match first_block_hash_from(&ctx.accounts.slot_hashes, raffle_state.slot_target)? {
+            SlotHashLookup::Found(block_hash) => {
+                farm_operations::draw_raffle_seed(raffle_state, &raffle_state_key, &block_hash)?
+            }
+            SlotHashLookup::NotYetProduced => {
+                return err_ctx!(
+                    FarmError::RaffleNotReady,
+                    raffle_state = raffle_state_key,
+                    slot_target = raffle_state.slot_target,
+                )
+            }
+            SlotHashLookup::Evicted => {
+                vmsg!(
+                    "SettleRaffle raffle {} slot {} hash evicted, cancelled",
+                    raffle_state_key,
+                    raffle_state.slot_target
+                );
+                farm_operations::cancel_raffle(farm_state, raffle_state)?;
+                emit_raffle_settled(&ctx, &farm_state_key, raffle_state)?;
+                return Ok(());
+            }
+        }
+    }
+
+    let mut remaining_accounts = ctx.remaining_accounts.iter();
+
+    if raffle_state.get_status() == RaffleStatus::Drawing {
+        let mut pages_walked = 0;
+        'pages: while raffle_state.next_user_id < raffle_state.num_users
+            && pages_walked < MAX_RAFFLE_PAGES_PER_SETTLE
+        {
+            let Some(user_page_info) = remaining_accounts.next() else {
+                break;
+            };
+            let user_page_loader = AccountLoader::<UserPage>::try_from(user_page_info)?;
+            let user_page = user_page_loader.load()?;
+            let (page_index, first_slot) = UserPage::location(raffle_state.next_user_id);
+            require_keys_eq!(
+                user_page.farm_state,
+                farm_state_key,
+                FarmError::InvalidUserPage
+            );
+            require_eq!(user_page.page_index, page_index, FarmError::InvalidUserPage);
+            pages_walked += 1;
+
+            for user_state_key in &user_page.user_states[first_slot..] {
+                if raffle_state.next_user_id == raffle_state.num_users {
+                    break 'pages;
+                }
+                if *user_state_key == Pubkey::default() {
+                    farm_operations::walk_raffle_user(
+                        farm_state,
+                        raffle_state,
+                        user_state_key,
+                        None,
+                    )?;
+                    continue;
+                }
+
+                let Some(user_state_info) = remaining_accounts.next() else {
+                    break 'pages;
+                };
+                require_keys_eq!(
+                    user_state_info.key(),
+                    *user_state_key,
+                    FarmError::UnexpectedAccount
+                );
+                let user_state_loader = AccountLoader::<UserState>::try_from(user_state_info)?;
+                farm_operations::walk_raffle_user(
+                    farm_state,
+                    raffle_state,
+                    user_state_key,
+                    Some(&user_state_loader.load()?),
+                )?;
+            }
+        }
+
+        vmsg!(
+            "SettleRaffle raffle {} walked {} pages, next_user_id {} of {}",
+            raffle_state_key,
+            pages_walked,
+            raffle_state.next_user_id,
+            raffle_state.num_users
+        );
+
+        farm_operations::finish_raffle_walk(farm_state, raffle_state)?;
+    }
+
+    if let Some(extra_account) = remaining_accounts.next() {
+        return err_ctx!(
+            FarmError::UnexpectedAccount,
+            extra_account = extra_account.key(),
+            raffle_state = raffle_state_key,
+            next_user_id = raffle_state.next_user_id,
+        );
+    }
+
+    if raffle_state.get_status() == RaffleStatus::Drawn {
+        if let Some(winner_user_state) = &ctx.accounts.winner_user_state {
+            let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+            farm_operations::credit_raffle_winner(
+                farm_state,
+                raffle_state,
+                &mut winner_user_state.load_mut()?,
+                ts,
+            )?;
+        }
+    }
+
+    if raffle_state.get_status() == RaffleStatus::Settled {
+        emit_raffle_settled(&ctx, &farm_state_key, raffle_state)?;
+    }
+
+    Ok(())
+}
+
+fn emit_raffle_settled(
+    ctx: &Context<'_, '_, '_, '_, SettleRaffle<'_>>,
+    farm_state_key: &Pubkey,
+    raffle_state: &RaffleState,
+) -> Result<()> {
+    emit_event!(
+        ctx,
+        RaffleSettledEvent {
+            farm_state: *farm_state_key,
+            raffle_state: ctx.accounts.raffle_state.key(),
+            raffle_id: raffle_state.raffle_id,
+            reward_index: raffle_state.reward_index,
+            prize_amount: raffle_state.prize_amount,
+            status: raffle_state.status,
+            winner: raffle_state.winner,
+        }
+    );
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct SettleRaffle<'info> {
+    #[account(mut)]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        has_one = farm_state,
+        constraint = raffle_state.load()?.get_status() != RaffleStatus::Settled
+            && raffle_state.load()?.get_status() != RaffleStatus::Cancelled
+            @ FarmError::InvalidRaffleStatus,
+    )]
+    pub raffle_state: AccountLoader<'info, RaffleState>,
+
+    #[account(address = slot_hashes::ID @ FarmError::UnexpectedAccount)]
+    pub slot_hashes: AccountInfo<'info>,
+
+    /// Required to credit the prize, `RaffleState::winner`
+    #[account(mut,
+        constraint = winner_user_state.key() == raffle_state.load()?.winner @ FarmError::UnexpectedAccount,
+    )]
+    pub winner_user_state: Option<AccountLoader<'info, UserState>>,
+}
@@ -0,0 +1,62 @@
+use crate::emit_event;
+use crate::events::FarmUpgradeSnapshotEvent;
+use crate::farm_operations;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::scope::load_scope_price;
+use crate::{FarmState, GlobalConfig};
+use anchor_lang::prelude::*;
+
+/// Refreshes the farm and records its accumulators with `marker` before a program
+/// upgrade, optionally pausing stake and unstake for `pause_slots`, see
+/// `get_upgrade_snapshots`.
+pub fn process(ctx: Context<SnapshotFarmForUpgrade>, marker: u64, pause_slots: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::SnapshotFarmForUpgrade,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+
+    farm_state.is_farm_delegated = farm_state.is_delegated() as u8;
+
+    let clock = Clock::get()?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &clock);
+    farm_operations::snapshot_farm_for_upgrade(
+        farm_state,
+        scope_price,
+        marker,
+        pause_slots,
+        clock.slot,
+        ts,
+    )?;
+
+    emit_event!(
+        ctx,
+        FarmUpgradeSnapshotEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            marker,
+            slot: clock.slot,
+            accumulators_hash: farm_state.upgrade_snapshot_accumulators_hash,
+            pause_until_slot: farm_state.upgrade_pause_until_slot,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct SnapshotFarmForUpgrade<'info> {
+    pub global_admin: Signer<'info>,
+
+    #[account(has_one = global_admin)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut, has_one = global_config)]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+}
@@ -0,0 +1,120 @@
+use crate::events::SplitStakeEvent;
+use crate::farm_operations;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::utils::scope::load_scope_price;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserPage, UserState};
+use anchor_lang::prelude::*;
+use decimal_wad::decimal::Decimal;
+
+pub fn process(
+    ctx: Context<SplitStake>,
+    stake_shares: Decimal,
+    new_user_id: u64,
+    lock_end_ts: u64,
+) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, ProgramInstruction::SplitStake)?;
+    require!(stake_shares != Decimal::zero(), FarmError::UnstakeZero);
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let time_unit = farm_state.time_unit;
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    let new_user_state = &mut ctx.accounts.new_user_state.load_init()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+    let owner = ctx.accounts.owner.key();
+    let ts = TimeUnit::now_from_clock(time_unit, &Clock::get()?);
+
+    vmsg!(
+        "SplitStake: user {} new_user {} new_user_id {} farm {} ts {}",
+        ctx.accounts.user_state.key(),
+        ctx.accounts.new_user_state.key(),
+        new_user_id,
+        ctx.accounts.farm_state.key(),
+        ts
+    );
+
+    if farm_state.user_pages_enabled == 1 {
+        let user_page = ctx
+            .accounts
+            .user_page
+            .as_ref()
+            .ok_or(FarmError::InvalidUserPage)?;
+        farm_operations::add_user_to_page(
+            farm_state,
+            &mut user_page.load_mut()?,
+            &ctx.accounts.farm_state.key(),
+            &ctx.accounts.new_user_state.key(),
+            farm_state.num_users,
+        )?;
+    }
+
+    new_user_state.bump = ctx.bumps.new_user_state.into();
+    new_user_state.delegatee = owner;
+
+    farm_operations::initialize_user(
+        farm_state,
+        new_user_state,
+        &owner,
+        &ctx.accounts.farm_state.key(),
+        ts,
+    )?;
+
+    let stake_split = farm_operations::split_stake(
+        farm_state,
+        user_state,
+        new_user_state,
+        scope_price,
+        stake_shares,
+        lock_end_ts,
+        ts,
+    )?;
+
+    emit!(SplitStakeEvent {
+        farm_state: ctx.accounts.farm_state.key(),
+        owner,
+        source_user_state: ctx.accounts.user_state.key(),
+        new_user_state: ctx.accounts.new_user_state.key(),
+        stake_shares_scaled: stake_split.to_scaled_val().unwrap(),
+        lock_end_ts,
+    });
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(stake_shares_scaled: u128, new_user_id: u64)]
+pub struct SplitStake<'info> {
+    #[account(mut)]
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(init,
+        seeds = [BASE_SEED_USER_STATE, farm_state.key().as_ref(), owner.key().as_ref(), &new_user_id.to_le_bytes()],
+        bump,
+        payer = owner,
+        space = SIZE_USER_STATE,
+    )]
+    pub new_user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut)]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    pub system_program: Program<'info, System>,
+    pub rent: Sysvar<'info, Rent>,
+
+    #[account(mut)]
+    pub user_page: Option<AccountLoader<'info, UserPage>>,
+}
@@ -1,21 +1,66 @@
+use crate::emit_event;
+use crate::events::StakeEvent;
 use crate::farm_operations;
-use crate::state::TimeUnit;
-use crate::token_operations::transfer_from_user;
+use crate::hook_operations::{self, StakeChangeHookArgs};
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
+use crate::token_operations;
 use crate::types::StakeEffects;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::check_instruction_enabled;
 use crate::utils::consts::*;
 use crate::utils::scope::load_scope_price;
-use crate::{FarmError, FarmState, UserState};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
 use anchor_lang::ToAccountInfo;
-use anchor_spl::token::{Mint, Token, TokenAccount};
-
-pub fn process(ctx: Context<Stake>, amount: u64) -> Result<()> {
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+use decimal_wad::decimal::Decimal;
+
+/// `stake` is `stake_with_options` without `allow_partial` nor `min_shares_out`.
+///
+/// With `allow_partial`, an amount above the remaining deposit capacity is reduced
+/// to it instead of failing, the rest stays in `user_ata`. Fails with
+/// `StakeSharesOutTooLow` when fewer than `min_shares_out` stake shares are credited,
+/// in the unscaled units of `amount`; zero disables the check.
+///
+/// Only the amount received by `farm_vault` is staked, less than the amount
+/// transferred when the farm token charges a transfer fee. The accounts of its
+/// transfer hook, if any, are passed as `remaining_accounts`.
+///
+/// Return data (24 bytes, little-endian): the stake shares gained as a scaled `u128`,
+/// pending when the farm has a deposit warmup period, followed by the `u64` amount
+/// actually staked.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, Stake<'info>>,
+    amount: u64,
+    allow_partial: bool,
+    min_shares_out: u64,
+    instruction: ProgramInstruction,
+) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, instruction)?;
     require!(amount != 0, FarmError::StakeZero);
-    check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
     let user_state = &mut ctx.accounts.user_state.load_mut()?;
+
+    let has_stake_hook = farm_state.has_stake_hook();
+    let transfer_hook_accounts = if has_stake_hook {
+        hook_operations::validate_hook_accounts(
+            farm_state,
+            &ctx.accounts.hook_program,
+            &ctx.accounts.hook_config,
+        )?;
+        // Both hooks are given all of `remaining_accounts`
+        if farm_state.token.has_transfer_hook() {
+            ctx.remaining_accounts
+        } else {
+            &[]
+        }
+    } else {
+        token_operations::transfer_hook_accounts(&farm_state.token, ctx.remaining_accounts)?
+    };
+
+    let old_stake_scaled = user_state.active_stake_scaled;
     let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
     let time_unit = farm_state.time_unit;
 
@@ -26,34 +71,93 @@ pub fn process(ctx: Context<Stake>, amount: u64) -> Result<()> {
     };
 
     require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::Stake),
+        FarmError::FarmOperationPaused
+    );
 
-    let StakeEffects { amount_to_stake } = farm_operations::stake(
+    let clock = Clock::get()?;
+    farm_operations::check_refreshed_in_slot(farm_state, clock.slot)?;
+    farm_operations::check_not_upgrade_paused(farm_state, clock.slot)?;
+    let ts = TimeUnit::now_from_clock(time_unit, &clock);
+    let amount_to_transfer = farm_operations::stake_amount_within_capacity(
         farm_state,
-        user_state,
         scope_price,
         amount,
-        TimeUnit::now_from_clock(time_unit, &Clock::get()?),
+        allow_partial,
+        ts,
     )?;
 
-    msg!(
-        "Stake {:} ts {:?}",
+    let farm_vault_amount = ctx.accounts.farm_vault.amount;
+    token_operations::transfer_token_from_user_with_hook(
+        &farm_state.token,
+        amount_to_transfer,
+        &ctx.accounts.user_ata.to_account_info(),
+        &ctx.accounts.farm_vault.to_account_info(),
+        &ctx.accounts.owner,
+        Some(ctx.accounts.token_mint.to_account_info()),
+        &ctx.accounts.token_program,
+        transfer_hook_accounts,
+    )?;
+    let amount_received =
+        token_operations::received_amount(&mut ctx.accounts.farm_vault, farm_vault_amount)?;
+
+    let StakeEffects {
         amount_to_stake,
-        TimeUnit::now_from_clock(time_unit, &Clock::get()?)
+        stake_gained_scaled,
+    } = farm_operations::stake(
+        farm_state,
+        user_state,
+        scope_price,
+        amount_received,
+        allow_partial,
+        ts,
+    )?;
+
+    if min_shares_out > 0 {
+        require!(
+            Decimal::from_scaled_val(stake_gained_scaled) >= Decimal::from(min_shares_out),
+            FarmError::StakeSharesOutTooLow
+        );
+    }
+
+    vmsg!("Stake {:} ts {:?}", amount_to_stake, ts);
+
+    emit_event!(
+        ctx,
+        StakeEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            user_state: ctx.accounts.user_state.key(),
+            owner: ctx.accounts.owner.key(),
+            amount: amount_to_stake,
+            active_stake_scaled: user_state.active_stake_scaled,
+            ts,
+        }
     );
 
-    if amount_to_stake > 0 {
-        transfer_from_user(
-            amount_to_stake,
-            &ctx.accounts.user_ata.to_account_info(),
-            &ctx.accounts.farm_vault.to_account_info(),
-            &ctx.accounts.owner,
-            &ctx.accounts.token_program,
+    if has_stake_hook {
+        let args = StakeChangeHookArgs {
+            farm_state: ctx.accounts.farm_state.key(),
+            owner: ctx.accounts.owner.key(),
+            old_stake_scaled,
+            new_stake_scaled: user_state.active_stake_scaled,
+        };
+        hook_operations::invoke_stake_hook(
+            args,
+            ctx.accounts.hook_program.as_ref().unwrap(),
+            ctx.accounts.hook_config.as_ref().unwrap(),
+            ctx.remaining_accounts,
         )?;
     }
 
+    let mut return_data = stake_gained_scaled.to_le_bytes().to_vec();
+    return_data.extend_from_slice(&amount_to_stake.to_le_bytes());
+    set_return_data(&return_data);
+
     Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct Stake<'info> {
     pub owner: Signer<'info>,
@@ -75,20 +179,28 @@ pub struct Stake<'info> {
         constraint = farm_vault.delegate.is_none() @ FarmError::FarmVaultHasDelegate,
         constraint = farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
     )]
-    pub farm_vault: Box<Account<'info, TokenAccount>>,
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
         has_one = owner,
         constraint = user_ata.mint == farm_state.load_mut()?.token.mint @ FarmError::UserAtaFarmTokenMintMissmatch,
     )]
-    pub user_ata: Box<Account<'info, TokenAccount>>,
+    pub user_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         constraint = token_mint.key() == farm_state.load_mut()?.token.mint @ FarmError::TokenFarmTokenMintMissmatch,
     )]
-    pub token_mint: Box<Account<'info, Mint>>,
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    #[account(mut)]
+    pub hook_config: Option<AccountInfo<'info>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
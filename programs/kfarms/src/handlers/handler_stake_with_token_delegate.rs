@@ -0,0 +1,203 @@
+use crate::emit_event;
+use crate::events::StakeEvent;
+use crate::farm_operations;
+use crate::hook_operations::{self, StakeChangeHookArgs};
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
+use crate::token_operations;
+use crate::types::StakeEffects;
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::consts::*;
+use crate::utils::scope::load_scope_price;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
+use anchor_lang::solana_program::program_option::COption;
+use anchor_lang::ToAccountInfo;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Same as `stake`, but the tokens are pulled from `user_ata` by its approved SPL
+/// token delegate. The position stays owned by the token account owner: the
+/// delegate can only add stake, never unstake, harvest or withdraw.
+///
+/// `amount == u64::MAX` stakes `min(user_ata.amount, user_ata.delegated_amount)`.
+/// Return data: same layout as `stake`.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, StakeWithTokenDelegate<'info>>,
+    amount: u64,
+) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::StakeWithTokenDelegate,
+    )?;
+    require!(amount != 0, FarmError::StakeZero);
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+
+    let has_stake_hook = farm_state.has_stake_hook();
+    let transfer_hook_accounts = if has_stake_hook {
+        hook_operations::validate_hook_accounts(
+            farm_state,
+            &ctx.accounts.hook_program,
+            &ctx.accounts.hook_config,
+        )?;
+        // Both hooks are given all of `remaining_accounts`
+        if farm_state.token.has_transfer_hook() {
+            ctx.remaining_accounts
+        } else {
+            &[]
+        }
+    } else {
+        token_operations::transfer_hook_accounts(&farm_state.token, ctx.remaining_accounts)?
+    };
+
+    let old_stake_scaled = user_state.active_stake_scaled;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+    let time_unit = farm_state.time_unit;
+
+    let amount = if amount == u64::MAX {
+        std::cmp::min(
+            ctx.accounts.user_ata.amount,
+            ctx.accounts.user_ata.delegated_amount,
+        )
+    } else {
+        amount
+    };
+    require!(amount != 0, FarmError::StakeZero);
+    require_gte!(
+        ctx.accounts.user_ata.delegated_amount,
+        amount,
+        FarmError::DelegatedAmountExceeded
+    );
+
+    require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::Stake),
+        FarmError::FarmOperationPaused
+    );
+
+    let clock = Clock::get()?;
+    farm_operations::check_refreshed_in_slot(farm_state, clock.slot)?;
+    farm_operations::check_not_upgrade_paused(farm_state, clock.slot)?;
+    let ts = TimeUnit::now_from_clock(time_unit, &clock);
+    farm_operations::stake_amount_within_capacity(farm_state, scope_price, amount, false, ts)?;
+
+    // Only what the vault receives is staked, see `stake`
+    let farm_vault_amount = ctx.accounts.farm_vault.amount;
+    token_operations::transfer_token_from_user_with_hook(
+        &farm_state.token,
+        amount,
+        &ctx.accounts.user_ata.to_account_info(),
+        &ctx.accounts.farm_vault.to_account_info(),
+        &ctx.accounts.delegate,
+        Some(ctx.accounts.token_mint.to_account_info()),
+        &ctx.accounts.token_program,
+        transfer_hook_accounts,
+    )?;
+    let amount_received =
+        token_operations::received_amount(&mut ctx.accounts.farm_vault, farm_vault_amount)?;
+
+    let StakeEffects {
+        amount_to_stake,
+        stake_gained_scaled,
+    } = farm_operations::stake(
+        farm_state,
+        user_state,
+        scope_price,
+        amount_received,
+        false,
+        ts,
+    )?;
+
+    vmsg!(
+        "StakeWithTokenDelegate {:} delegate {} ts {:?}",
+        amount_to_stake,
+        ctx.accounts.delegate.key(),
+        ts
+    );
+
+    emit_event!(
+        ctx,
+        StakeEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            user_state: ctx.accounts.user_state.key(),
+            owner: ctx.accounts.owner.key(),
+            amount: amount_to_stake,
+            active_stake_scaled: user_state.active_stake_scaled,
+            ts,
+        }
+    );
+
+    if has_stake_hook {
+        let args = StakeChangeHookArgs {
+            farm_state: ctx.accounts.farm_state.key(),
+            owner: ctx.accounts.owner.key(),
+            old_stake_scaled,
+            new_stake_scaled: user_state.active_stake_scaled,
+        };
+        hook_operations::invoke_stake_hook(
+            args,
+            ctx.accounts.hook_program.as_ref().unwrap(),
+            ctx.accounts.hook_config.as_ref().unwrap(),
+            ctx.remaining_accounts,
+        )?;
+    }
+
+    let mut return_data = stake_gained_scaled.to_le_bytes().to_vec();
+    return_data.extend_from_slice(&amount_to_stake.to_le_bytes());
+    set_return_data(&return_data);
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct StakeWithTokenDelegate<'info> {
+    pub delegate: Signer<'info>,
+
+    pub owner: AccountInfo<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut,
+        has_one = farm_vault,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_FARM_VAULT, farm_state.key().as_ref(), farm_state.load_mut()?.token.mint.as_ref()],
+        bump,
+        constraint = farm_vault.delegate.is_none() @ FarmError::FarmVaultHasDelegate,
+        constraint = farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
+    )]
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        has_one = owner,
+        constraint = user_ata.delegate == COption::Some(delegate.key()) @ FarmError::InvalidTokenDelegate,
+        constraint = user_ata.mint == farm_state.load_mut()?.token.mint @ FarmError::UserAtaFarmTokenMintMissmatch,
+    )]
+    pub user_ata: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        constraint = token_mint.key() == farm_state.load_mut()?.token.mint @ FarmError::TokenFarmTokenMintMissmatch,
+    )]
+    pub token_mint: Box<InterfaceAccount<'info, Mint>>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    #[account(mut)]
+    pub hook_config: Option<AccountInfo<'info>>,
+}
@@ -0,0 +1,115 @@
+use crate::emit_event;
+use crate::events::DivertedRewardsSweptEvent;
+use crate::farm_operations;
+use crate::gen_signer_seeds_two;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::token_operations;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::consts::*;
+use crate::utils::scope::load_scope_price;
+use crate::{FarmError, FarmState, GlobalConfig};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Permissionless, moves the emissions of `reward_index` diverted while the farm had
+/// no stake from the rewards vault to the treasury vault.
+pub fn process(ctx: Context<SweepDivertedRewards>, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::SweepDivertedRewards,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+    let reward_index = reward_index as usize;
+
+    let amount =
+        farm_operations::sweep_diverted_rewards(farm_state, scope_price, reward_index, ts)?;
+    require!(amount > 0, FarmError::NothingToWithdraw);
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+        BASE_SEED_FARM_VAULTS_AUTHORITY,
+        farm_state_key,
+        farm_state.farm_vaults_authority_bump as u8
+    );
+
+    token_operations::transfer_token_from_vault(
+        &farm_state.reward_infos[reward_index].token,
+        amount,
+        signer_seeds,
+        &ctx.accounts.rewards_treasury_vault.to_account_info(),
+        &ctx.accounts.rewards_vault.to_account_info(),
+        &ctx.accounts.farm_vaults_authority,
+        ctx.accounts
+            .reward_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
+        &ctx.accounts.token_program,
+    )?;
+
+    emit_event!(
+        ctx,
+        DivertedRewardsSweptEvent {
+            farm_state: farm_state_key,
+            reward_index: reward_index as u64,
+            reward_mint: ctx.accounts.rewards_vault.mint,
+            amount,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+#[instruction(reward_index: u64)]
+pub struct SweepDivertedRewards<'info> {
+    #[account(
+        mut,
+        has_one = global_config,
+        has_one = farm_vaults_authority,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        seeds = [
+            BASE_SEED_REWARD_VAULT,
+            farm_state.key().as_ref(),
+            rewards_vault.mint.as_ref(),
+            farm_state.load()?.reward_vault_seed_suffix(reward_index as usize, &rewards_vault.mint),
+        ],
+        bump,
+        constraint = rewards_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
+    )]
+    pub rewards_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_REWARD_TREASURY_VAULT.as_ref(), global_config.key().as_ref(), rewards_vault.mint.as_ref()],
+        bump,
+        constraint = rewards_treasury_vault.delegate.is_none() @ FarmError::RewardsTreasuryVaultHasDelegate,
+        constraint = rewards_treasury_vault.close_authority.is_none() @ FarmError::RewardsTreasuryVaultHasCloseAuthority,
+    )]
+    pub rewards_treasury_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_vaults_authority: AccountInfo<'info>,
+
+    /// Required for Token-2022 rewards
+    #[account(
+        address = rewards_vault.mint @ FarmError::UnexpectedAccount,
+    )]
+    pub reward_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
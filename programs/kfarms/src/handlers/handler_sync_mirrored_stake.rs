@@ -0,0 +1,60 @@
+use crate::farm_operations;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
+use anchor_lang::prelude::*;
+
+/// Permissionless, sets the stake of `user_state` on the mirror farm to the active
+/// stake amount of the same owner on the primary farm.
+pub fn process(ctx: Context<SyncMirroredStake>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::SyncMirroredStake,
+    )?;
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    let primary_farm_state = ctx.accounts.primary_farm_state.load()?;
+    let primary_user_state = ctx.accounts.primary_user_state.load()?;
+
+    require_keys_eq!(
+        primary_user_state.owner,
+        user_state.owner,
+        FarmError::UnexpectedAccount
+    );
+
+    let ts = TimeUnit::now_from_clock(farm_state.time_unit, &Clock::get()?);
+    farm_operations::sync_mirrored_stake(
+        farm_state,
+        user_state,
+        &primary_farm_state,
+        &primary_user_state,
+        ts,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct SyncMirroredStake<'info> {
+    #[account(mut,
+        has_one = farm_state,
+    )]
+    pub user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut,
+        constraint = farm_state.load()?.primary_farm == primary_farm_state.key() @ FarmError::FarmNotMirror,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    pub primary_farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(
+        constraint = primary_user_state.load()?.farm_state == primary_farm_state.key() @ FarmError::UnexpectedAccount,
+    )]
+    pub primary_user_state: AccountLoader<'info, UserState>,
+}
@@ -1,5 +1,7 @@
-use crate::state::UserState;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::state::{FarmState, GlobalConfig, ProgramInstruction, UserState};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::vmsg;
+use crate::FarmError;
 use anchor_lang::prelude::*;
 use anchor_lang::{
     prelude::{msg, Context},
@@ -7,12 +9,17 @@ use anchor_lang::{
 };
 
 pub fn process(ctx: Context<TransferOwnership>, new_owner: Pubkey) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::TransferOwnership,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let user_state = &mut ctx.accounts.user_state.load_mut()?;
+    require!(!user_state.is_renounced(), FarmError::PositionRenounced);
     user_state.owner = new_owner;
 
-    msg!(
+    vmsg!(
         "Transferring ownership of farm account {} from {} to {}",
         ctx.accounts.user_state.key(),
         ctx.accounts.owner.key,
@@ -30,4 +37,10 @@ pub struct TransferOwnership<'info> {
         has_one = owner,
     )]
     pub user_state: AccountLoader<'info, UserState>,
+
+    /// Only needed to check `global_config`
+    #[account(address = user_state.load()?.farm_state @ FarmError::UnexpectedAccount)]
+    pub farm_state: AccountLoader<'info, FarmState>,
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -0,0 +1,68 @@
+use crate::farm_operations;
+use crate::state::{ProgramInstruction, TimeUnit};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::utils::scope::load_scope_price;
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
+use anchor_lang::prelude::*;
+use decimal_wad::decimal::Decimal;
+
+pub fn process(ctx: Context<TransferStakeInternal>, stake_shares: Decimal) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::TransferStakeInternal,
+    )?;
+    require!(stake_shares != Decimal::zero(), FarmError::UnstakeZero);
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let time_unit = farm_state.time_unit;
+    let from_user_state = &mut ctx.accounts.from_user_state.load_mut()?;
+    let to_user_state = &mut ctx.accounts.to_user_state.load_mut()?;
+    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
+
+    let stake_transferred = farm_operations::transfer_stake_internal(
+        farm_state,
+        from_user_state,
+        to_user_state,
+        scope_price,
+        stake_shares,
+        TimeUnit::now_from_clock(time_unit, &Clock::get()?),
+    )?;
+
+    vmsg!(
+        "TransferStakeInternal {} -> {} stake {} ts {}",
+        ctx.accounts.from_user_state.key(),
+        ctx.accounts.to_user_state.key(),
+        stake_transferred,
+        TimeUnit::now_from_clock(time_unit, &Clock::get()?)
+    );
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct TransferStakeInternal<'info> {
+    pub owner: Signer<'info>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+    )]
+    pub from_user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut,
+        has_one = owner,
+        has_one = farm_state,
+        constraint = to_user_state.key() != from_user_state.key() @ FarmError::UnexpectedAccount,
+    )]
+    pub to_user_state: AccountLoader<'info, UserState>,
+
+    #[account(mut)]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+}
@@ -1,31 +1,120 @@
+use crate::emit_event;
+use crate::events::UnstakeEvent;
 use crate::farm_operations;
-use crate::state::TimeUnit;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::hook_operations::{self, StakeChangeHookArgs};
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
+use crate::types::UnstakeEffects;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::scope::load_scope_price;
-use crate::{FarmError, FarmState, UserState};
+use crate::vmsg;
+use crate::{FarmError, FarmState, GlobalConfig, UserState};
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::program::set_return_data;
 use decimal_wad::decimal::Decimal;
 
-pub fn process(ctx: Context<Unstake>, amount: Decimal) -> Result<()> {
+/// `unstake` is `unstake_with_min_amount_out` without minimum.
+///
+/// Fails with `UnstakeAmountOutTooLow` when less than `min_amount_out` tokens would
+/// move to pending withdrawal. Zero disables the check.
+///
+/// Return data: the token amount moved to pending withdrawal, after any early
+/// withdrawal penalty, as a little-endian `u64` (8 bytes).
+///
+/// The stake hook is best-effort on unstake: users must always be able to exit,
+/// so missing or mismatching hook accounts only skip the notification. A failing
+/// hook program still aborts the transaction (CPI errors can't be caught), in
+/// which case the user can unstake again without passing the hook accounts.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, Unstake<'info>>,
+    amount: Decimal,
+    min_amount_out: u64,
+    instruction: ProgramInstruction,
+) -> Result<()> {
+    check_instruction_enabled(&ctx.accounts.global_config, instruction)?;
     require!(amount != Decimal::zero(), FarmError::UnstakeZero);
-    check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
     let time_unit = farm_state.time_unit;
     let user_state = &mut ctx.accounts.user_state.load_mut()?;
+
+    let invoke_hook = farm_state.has_stake_hook()
+        && hook_operations::validate_hook_accounts(
+            farm_state,
+            &ctx.accounts.hook_program,
+            &ctx.accounts.hook_config,
+        )
+        .is_ok();
+    if !invoke_hook {
+        if farm_state.has_stake_hook() {
+            vmsg!("Unstake: stake hook accounts missing or invalid, skipping hook");
+        }
+        check_remaining_accounts(&ctx)?;
+    }
+
+    let old_stake_scaled = user_state.active_stake_scaled;
     let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
 
     require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::Unstake),
+        FarmError::FarmOperationPaused
+    );
+
+    let clock = Clock::get()?;
+    farm_operations::check_refreshed_in_slot(farm_state, clock.slot)?;
+    farm_operations::check_not_upgrade_paused(farm_state, clock.slot)?;
+    let ts = TimeUnit::now_from_clock(time_unit, &clock);
+    let UnstakeEffects {
+        amount_to_pending_withdrawal,
+        amount_unlocked,
+        amount_locked,
+        amount_penalty,
+    } = farm_operations::unstake(farm_state, user_state, scope_price, amount, ts)?;
+
+    if min_amount_out > 0 {
+        require_gte!(
+            amount_to_pending_withdrawal,
+            min_amount_out,
+            FarmError::UnstakeAmountOutTooLow
+        );
+    }
+
+    emit_event!(
+        ctx,
+        UnstakeEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            user_state: ctx.accounts.user_state.key(),
+            owner: ctx.accounts.owner.key(),
+            stake_shares_scaled: amount.to_scaled_val().unwrap(),
+            active_stake_scaled: user_state.active_stake_scaled,
+            amount_unlocked,
+            amount_locked,
+            amount_penalty,
+            ts,
+        }
+    );
+
+    if invoke_hook {
+        let args = StakeChangeHookArgs {
+            farm_state: ctx.accounts.farm_state.key(),
+            owner: ctx.accounts.owner.key(),
+            old_stake_scaled,
+            new_stake_scaled: user_state.active_stake_scaled,
+        };
+        hook_operations::invoke_stake_hook(
+            args,
+            ctx.accounts.hook_program.as_ref().unwrap(),
+            ctx.accounts.hook_config.as_ref().unwrap(),
+            ctx.remaining_accounts,
+        )?;
+    }
 
-    farm_operations::unstake(
-        farm_state,
-        user_state,
-        scope_price,
-        amount,
-        TimeUnit::now_from_clock(time_unit, &Clock::get()?),
-    )
+    set_return_data(&amount_to_pending_withdrawal.to_le_bytes());
+
+    Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct Unstake<'info> {
     #[account(mut)]
@@ -41,4 +130,12 @@ pub struct Unstake<'info> {
     pub farm_state: AccountLoader<'info, FarmState>,
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    pub hook_program: Option<AccountInfo<'info>>,
+
+    #[account(mut)]
+    pub hook_config: Option<AccountInfo<'info>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
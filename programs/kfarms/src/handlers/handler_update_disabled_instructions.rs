@@ -0,0 +1,46 @@
+use crate::emit_event;
+use crate::events::UpdateDisabledInstructionsEvent;
+use crate::farm_operations;
+use crate::state::GlobalConfig;
+use crate::utils::constraints::check_remaining_accounts;
+use anchor_lang::prelude::*;
+
+/// `authority` is either the global admin, who can set any instructions, or the global
+/// config emergency authority, who can only disable non-exit instructions.
+/// `disabled_instructions` is a bitmask of `ProgramInstruction`.
+pub fn process(
+    ctx: Context<UpdateDisabledInstructions>,
+    disabled_instructions: u128,
+) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let global_config = &mut ctx.accounts.global_config.load_mut()?;
+    let previous_disabled_instructions = global_config.disabled_instructions;
+
+    farm_operations::update_disabled_instructions(
+        global_config,
+        &ctx.accounts.authority.key(),
+        disabled_instructions,
+    )?;
+
+    emit_event!(
+        ctx,
+        UpdateDisabledInstructionsEvent {
+            global_config: ctx.accounts.global_config.key(),
+            authority: ctx.accounts.authority.key(),
+            previous_disabled_instructions,
+            disabled_instructions,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct UpdateDisabledInstructions<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
@@ -1,8 +1,15 @@
-use crate::utils::constraints::check_remaining_accounts;
+use crate::state::ProgramInstruction;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
+use crate::FarmError;
 use crate::FarmState;
+use crate::GlobalConfig;
 use anchor_lang::prelude::*;
 
 pub fn process(ctx: Context<UpdateFarmAdmin>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::UpdateFarmAdmin,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
@@ -22,4 +29,7 @@ pub struct UpdateFarmAdmin<'info> {
         has_one = pending_farm_admin,
     )]
     pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -1,31 +1,97 @@
-use crate::state::FarmConfigOption;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::emit_event;
+use crate::events::{RewardDepletionProjectionEvent, UpdateFarmConfigEvent};
+use crate::state::{FarmConfigOption, ProgramInstruction};
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::scope::load_scope_price;
-use crate::{farm_operations, FarmState};
+use crate::{farm_operations, FarmError, FarmState, GlobalConfig};
 use anchor_lang::prelude::*;
 
 pub fn process(ctx: Context<UpdateFarmConfig>, mode: u16, data: &[u8]) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::UpdateFarmConfig,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
-    let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state).map_or(None, |v| v);
+    let config_option =
+        FarmConfigOption::try_from(mode).map_err(|_| FarmError::InvalidFarmConfigMode)?;
 
-    let mode: FarmConfigOption = mode.try_into().unwrap();
-    farm_operations::update_farm_config(farm_state, scope_price, mode, data)?;
+    // Reward changes must not apply to the time elapsed since the last refresh, which
+    // needs a valid price on oracle farms
+    let scope_price = if config_option.is_reward_config() {
+        load_scope_price(&ctx.accounts.scope_prices, farm_state)?
+    } else {
+        load_scope_price(&ctx.accounts.scope_prices, farm_state).map_or(None, |v| v)
+    };
+    farm_operations::check_farm_config_authority(
+        farm_state,
+        config_option,
+        &ctx.accounts.signer.key(),
+    )?;
+    let config_value = config_option.decode(data)?;
+    let global_config = ctx.accounts.global_config.load()?;
+    let global_admin_signed = match &ctx.accounts.global_admin {
+        Some(global_admin) => {
+            require_keys_eq!(
+                global_admin.key(),
+                global_config.global_admin,
+                FarmError::InvalidAdminAuthority
+            );
+            true
+        }
+        None => false,
+    };
+
+    let updated_reward_index = farm_operations::update_farm_config(
+        farm_state,
+        scope_price,
+        config_value,
+        &global_config,
+        global_admin_signed,
+    )?;
+
+    emit_event!(
+        ctx,
+        UpdateFarmConfigEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            mode,
+            data: data.to_vec(),
+        }
+    );
+
+    if let Some(reward_index) = updated_reward_index {
+        let reward_info = &farm_state.reward_infos[reward_index];
+        emit_event!(
+            ctx,
+            RewardDepletionProjectionEvent {
+                farm_state: ctx.accounts.farm_state.key(),
+                reward_index: reward_index as u64,
+                reward_mint: reward_info.token.mint,
+                rewards_available: reward_info.rewards_available,
+                projected_depletion_ts: reward_info.projected_depletion_ts,
+            }
+        );
+    }
 
     Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct UpdateFarmConfig<'info> {
+    /// `farm_admin` or the authority of the group `mode` belongs to
     #[account(mut)]
-    pub farm_admin: Signer<'info>,
+    pub signer: Signer<'info>,
 
-    #[account(
-        mut,
-        has_one = farm_admin,
-    )]
+    #[account(mut)]
     pub farm_state: AccountLoader<'info, FarmState>,
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
+
+    /// Co-signing with the global admin unlocks the changes reserved to it,
+    /// `global_config` bounds the harvest fee overrides
+    pub global_admin: Option<Signer<'info>>,
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -0,0 +1,49 @@
+use crate::emit_event;
+use crate::events::UpdateFarmPauseFlagsEvent;
+use crate::farm_operations;
+use crate::state::GlobalConfig;
+use crate::utils::constraints::check_remaining_accounts;
+use crate::FarmState;
+use anchor_lang::prelude::*;
+
+/// `authority` is either the global admin, who can set any flags, or the global
+/// config emergency authority, who can only add flags.
+pub fn process(ctx: Context<UpdateFarmPauseFlags>, pause_flags: u8) -> Result<()> {
+    check_remaining_accounts(&ctx)?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let global_config = ctx.accounts.global_config.load()?;
+    let previous_pause_flags = farm_state.pause_flags;
+
+    farm_operations::update_farm_pause_flags(
+        &global_config,
+        farm_state,
+        &ctx.accounts.authority.key(),
+        pause_flags,
+    )?;
+
+    emit_event!(
+        ctx,
+        UpdateFarmPauseFlagsEvent {
+            farm_state: ctx.accounts.farm_state.key(),
+            authority: ctx.accounts.authority.key(),
+            previous_pause_flags,
+            pause_flags,
+        }
+    );
+
+    Ok(())
+}
+
+#[cfg_attr(feature = "event-cpi", event_cpi)]
+#[derive(Accounts)]
+pub struct UpdateFarmPauseFlags<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut,
+        has_one = global_config,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+}
@@ -1,4 +1,7 @@
+use crate::emit_event;
+use crate::events::UpdateGlobalConfigEvent;
 use crate::utils::constraints::check_remaining_accounts;
+use crate::vmsg;
 use crate::{farm_operations, GlobalConfig, GlobalConfigOption};
 use anchor_lang::prelude::*;
 
@@ -9,15 +12,25 @@ pub fn process(
     key: GlobalConfigOption,
     value: &[u8; VALUE_BYTE_ARRAY_LEN],
 ) -> Result<()> {
-    msg!("Update global config key={:?} value={:?}", key, value);
+    vmsg!("Update global config key={:?} value={:?}", key, value);
     check_remaining_accounts(&ctx)?;
     let global_config = &mut ctx.accounts.global_config.load_mut()?;
 
     farm_operations::update_global_config(global_config, key, value)?;
 
+    emit_event!(
+        ctx,
+        UpdateGlobalConfigEvent {
+            global_config: ctx.accounts.global_config.key(),
+            mode: key as u8,
+            value: *value,
+        }
+    );
+
     Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 pub struct UpdateGlobalConfig<'info> {
     pub global_admin: Signer<'info>,
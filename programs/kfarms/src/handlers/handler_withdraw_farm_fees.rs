@@ -0,0 +1,132 @@
+use crate::farm_operations;
+use crate::state::{GlobalConfig, ProgramInstruction};
+use crate::token_operations;
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::consts::*;
+use crate::{gen_signer_seeds_two, FarmError, FarmState};
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Moves the penalties accrued in the farm vault to the fee vault, then transfers
+/// `amount` (`u64::MAX` for everything) from the fee vault to the destination.
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawFarmFees<'info>>,
+    amount: u64,
+) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::WithdrawFarmFees,
+    )?;
+
+    let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let transfer_hook_accounts =
+        token_operations::transfer_hook_accounts(&farm_state.token, ctx.remaining_accounts)?;
+    let global_config = ctx.accounts.global_config.load()?;
+
+    let farm_state_key = ctx.accounts.farm_state.key();
+    let global_config_key = ctx.accounts.global_config.key();
+
+    let amount_to_sweep = farm_operations::sweep_farm_fees(farm_state)?;
+    if amount_to_sweep > 0 {
+        let farm_signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+            BASE_SEED_FARM_VAULTS_AUTHORITY,
+            farm_state_key,
+            farm_state.farm_vaults_authority_bump as u8
+        );
+        token_operations::transfer_token_from_vault_with_hook(
+            &farm_state.token,
+            amount_to_sweep,
+            farm_signer_seeds,
+            &ctx.accounts.fee_vault.to_account_info(),
+            &ctx.accounts.farm_vault.to_account_info(),
+            &ctx.accounts.farm_vaults_authority,
+            ctx.accounts
+                .token_mint
+                .as_ref()
+                .map(|mint| mint.to_account_info()),
+            &ctx.accounts.token_program,
+            transfer_hook_accounts,
+        )?;
+        ctx.accounts.fee_vault.reload()?;
+    }
+
+    let amount = std::cmp::min(amount, ctx.accounts.fee_vault.amount);
+    require!(amount > 0, FarmError::NothingToWithdraw);
+
+    msg!(
+        "WithdrawFarmFees farm {} swept {} withdrawn {}",
+        farm_state_key,
+        amount_to_sweep,
+        amount
+    );
+
+    let treasury_signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
+        BASE_SEED_TREASURY_VAULTS_AUTHORITY,
+        global_config_key,
+        global_config.treasury_vaults_authority_bump as u8
+    );
+    token_operations::transfer_token_from_vault_with_hook(
+        &farm_state.token,
+        amount,
+        treasury_signer_seeds,
+        &ctx.accounts
+            .withdraw_destination_token_account
+            .to_account_info(),
+        &ctx.accounts.fee_vault.to_account_info(),
+        &ctx.accounts.treasury_vaults_authority,
+        ctx.accounts
+            .token_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
+        &ctx.accounts.token_program,
+        transfer_hook_accounts,
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawFarmFees<'info> {
+    #[account(mut)]
+    pub global_admin: Signer<'info>,
+
+    #[account(
+        has_one = global_admin,
+        has_one = treasury_vaults_authority,
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(mut,
+        has_one = global_config,
+        has_one = farm_vault,
+        has_one = farm_vaults_authority,
+        has_one = fee_vault,
+    )]
+    pub farm_state: AccountLoader<'info, FarmState>,
+
+    #[account(mut)]
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    #[account(
+        seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
+        bump,
+    )]
+    pub farm_vaults_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        token::authority = treasury_vaults_authority,
+    )]
+    pub fee_vault: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub treasury_vaults_authority: AccountInfo<'info>,
+
+    #[account(mut,
+        token::mint = farm_state.load()?.token.mint,
+    )]
+    pub withdraw_destination_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Required for Token-2022 farm tokens
+    pub token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+}
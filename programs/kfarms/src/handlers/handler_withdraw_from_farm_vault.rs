@@ -1,15 +1,24 @@
 use crate::farm_operations;
+use crate::state::ProgramInstruction;
 use crate::token_operations;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::check_instruction_enabled;
 use crate::utils::consts::*;
-use crate::{gen_signer_seeds_two, FarmError, FarmState};
+use crate::{gen_signer_seeds_two, FarmError, FarmState, GlobalConfig};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-pub fn process(ctx: Context<WithdrawFromFarmVault>, amount_to_withdraw: u64) -> Result<()> {
-    check_remaining_accounts(&ctx)?;
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawFromFarmVault<'info>>,
+    amount_to_withdraw: u64,
+) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::WithdrawFromFarmVault,
+    )?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let transfer_hook_accounts =
+        token_operations::transfer_hook_accounts(&farm_state.token, ctx.remaining_accounts)?;
 
     require!(
         farm_state.withdraw_authority != Pubkey::default(),
@@ -18,8 +27,24 @@ pub fn process(ctx: Context<WithdrawFromFarmVault>, amount_to_withdraw: u64) ->
 
     require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
 
-    let final_amount_to_withdraw =
-        farm_operations::withdraw_from_farm_vault(farm_state, amount_to_withdraw)?;
+    let override_limit = match &ctx.accounts.global_admin {
+        Some(global_admin) => {
+            require_keys_eq!(
+                global_admin.key(),
+                ctx.accounts.global_config.load()?.global_admin,
+                FarmError::InvalidAdminAuthority
+            );
+            true
+        }
+        None => false,
+    };
+
+    let final_amount_to_withdraw = farm_operations::withdraw_from_farm_vault(
+        farm_state,
+        amount_to_withdraw,
+        override_limit,
+        Clock::get()?.unix_timestamp.try_into().unwrap(),
+    )?;
 
     let farm_state_key = ctx.accounts.farm_state.key();
     let signer_seeds: &[&[&[u8]]] = gen_signer_seeds_two!(
@@ -28,13 +53,19 @@ pub fn process(ctx: Context<WithdrawFromFarmVault>, amount_to_withdraw: u64) ->
         farm_state.farm_vaults_authority_bump as u8
     );
 
-    token_operations::transfer_from_vault(
+    token_operations::transfer_token_from_vault_with_hook(
+        &farm_state.token,
         final_amount_to_withdraw,
         signer_seeds,
         &ctx.accounts.withdrawer_token_account.to_account_info(),
         &ctx.accounts.farm_vault.to_account_info(),
         &ctx.accounts.farm_vaults_authority,
+        ctx.accounts
+            .token_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
         &ctx.accounts.token_program,
+        transfer_hook_accounts,
     )?;
 
     Ok(())
@@ -56,7 +87,7 @@ pub struct WithdrawFromFarmVault<'info> {
         token::mint = farm_state.load()?.token.mint,
         token::authority = withdraw_authority,
     )]
-    pub withdrawer_token_account: Box<Account<'info, TokenAccount>>,
+    pub withdrawer_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
         seeds = [BASE_SEED_FARM_VAULT, farm_state.key().as_ref(), farm_state.load()?.token.mint.as_ref()],
@@ -64,7 +95,7 @@ pub struct WithdrawFromFarmVault<'info> {
         constraint = farm_vault.delegate.is_none() @ FarmError::FarmVaultHasDelegate,
         constraint = farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
     )]
-    pub farm_vault: Box<Account<'info, TokenAccount>>,
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
@@ -72,5 +103,13 @@ pub struct WithdrawFromFarmVault<'info> {
     )]
     pub farm_vaults_authority: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Required for Token-2022 farm tokens
+    pub token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    /// Co-signing with the global admin lifts the admin withdrawal limit
+    pub global_admin: Option<Signer<'info>>,
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -1,14 +1,23 @@
+use crate::emit_event;
+use crate::events::RewardDepletionProjectionEvent;
 use crate::farm_operations;
-use crate::state::TimeUnit;
+use crate::state::{ProgramInstruction, TimeUnit};
 use crate::types::WithdrawRewardEffects;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::{
+    check_instruction_enabled, check_remaining_accounts, check_reward_index,
+};
 use crate::utils::consts::BASE_SEED_FARM_VAULTS_AUTHORITY;
 use crate::utils::scope::load_scope_price;
-use crate::{gen_signer_seeds_two, token_operations, FarmState};
+use crate::vmsg;
+use crate::{gen_signer_seeds_two, token_operations, FarmError, FarmState, GlobalConfig};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
 pub fn process(ctx: Context<WithdrawReward>, amount: u64, reward_index: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::WithdrawReward,
+    )?;
     check_remaining_accounts(&ctx)?;
 
     let farm_state_key = ctx.accounts.farm_state.key();
@@ -16,7 +25,8 @@ pub fn process(ctx: Context<WithdrawReward>, amount: u64, reward_index: u64) ->
     let time_unit = farm_state.time_unit;
     let reward_mint = ctx.accounts.reward_vault.mint;
     let scope_price = load_scope_price(&ctx.accounts.scope_prices, farm_state)?;
-    msg!(
+    let reward_index = check_reward_index(farm_state, reward_index)?;
+    vmsg!(
         "WithdrawReward farm_state {:?} amount {}, reward_index {} ts {}",
         ctx.accounts.farm_state.key(),
         amount,
@@ -24,16 +34,19 @@ pub fn process(ctx: Context<WithdrawReward>, amount: u64, reward_index: u64) ->
         TimeUnit::now_from_clock(time_unit, &Clock::get()?)
     );
 
-    let WithdrawRewardEffects { reward_amount } = farm_operations::withdraw_reward(
+    let WithdrawRewardEffects {
+        reward_amount,
+        projected_depletion_ts,
+    } = farm_operations::withdraw_reward(
         farm_state,
         scope_price,
         &reward_mint,
-        reward_index as usize,
+        reward_index,
         amount,
         TimeUnit::now_from_clock(time_unit, &Clock::get()?),
     )?;
 
-    msg!(
+    vmsg!(
         "withdraw {} from reward {:?} index {}",
         reward_amount,
         reward_mint.key(),
@@ -46,18 +59,35 @@ pub fn process(ctx: Context<WithdrawReward>, amount: u64, reward_index: u64) ->
         farm_state.farm_vaults_authority_bump as u8
     );
 
-    token_operations::transfer_from_vault(
+    token_operations::transfer_token_from_vault(
+        &farm_state.reward_infos[reward_index].token,
         reward_amount,
         signer_seeds,
         &ctx.accounts.admin_reward_token_ata.to_account_info(),
         &ctx.accounts.reward_vault.to_account_info(),
         &ctx.accounts.farm_vaults_authority,
+        ctx.accounts
+            .reward_mint
+            .as_ref()
+            .map(|mint| mint.to_account_info()),
         &ctx.accounts.token_program,
     )?;
 
+    emit_event!(
+        ctx,
+        RewardDepletionProjectionEvent {
+            farm_state: farm_state_key,
+            reward_index: reward_index as u64,
+            reward_mint,
+            rewards_available: farm_state.reward_infos[reward_index].rewards_available,
+            projected_depletion_ts,
+        }
+    );
+
     Ok(())
 }
 
+#[cfg_attr(feature = "event-cpi", event_cpi)]
 #[derive(Accounts)]
 #[instruction(amount: u64, reward_index: u64)]
 pub struct WithdrawReward<'info> {
@@ -66,15 +96,16 @@ pub struct WithdrawReward<'info> {
 
     #[account(mut,
         has_one = farm_admin,
-        has_one = farm_vaults_authority
+        has_one = farm_vaults_authority,
+        constraint = reward_index < farm_state.load()?.num_reward_tokens @ FarmError::RewardIndexOutOfRange,
     )]
     pub farm_state: AccountLoader<'info, FarmState>,
 
     #[account(mut,
         token::authority = farm_vaults_authority,
-        constraint = reward_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault,
+        constraint = reward_vault.key() == farm_state.load()?.reward_infos[reward_index as usize].rewards_vault @ FarmError::RewardVaultMismatch,
     )]
-    pub reward_vault: Box<Account<'info, TokenAccount>>,
+    pub reward_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
@@ -85,9 +116,15 @@ pub struct WithdrawReward<'info> {
     #[account(mut,
         token::mint = reward_vault.mint,
     )]
-    pub admin_reward_token_ata: Box<Account<'info, TokenAccount>>,
+    pub admin_reward_token_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     pub scope_prices: Option<AccountLoader<'info, scope::OraclePrices>>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Required for Token-2022 rewards
+    pub reward_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -1,15 +1,21 @@
 use crate::farm_operations;
+use crate::state::ProgramInstruction;
 use crate::token_operations;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::check_instruction_enabled;
 use crate::utils::consts::*;
-use crate::{gen_signer_seeds_two, FarmError, FarmState};
+use crate::{gen_signer_seeds_two, FarmError, FarmState, GlobalConfig};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-pub fn process(ctx: Context<WithdrawSlashedAmount>) -> Result<()> {
-    check_remaining_accounts(&ctx)?;
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawSlashedAmount<'info>>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::WithdrawSlashedAmount,
+    )?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let transfer_hook_accounts =
+        token_operations::transfer_hook_accounts(&farm_state.token, ctx.remaining_accounts)?;
 
     require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
 
@@ -23,13 +29,19 @@ pub fn process(ctx: Context<WithdrawSlashedAmount>) -> Result<()> {
     );
 
     if amount_to_withdraw > 0 {
-        token_operations::transfer_from_vault(
+        token_operations::transfer_token_from_vault_with_hook(
+            &farm_state.token,
             amount_to_withdraw,
             signer_seeds,
             &ctx.accounts.slashed_amount_spill_address.to_account_info(),
             &ctx.accounts.farm_vault.to_account_info(),
             &ctx.accounts.farm_vaults_authority,
+            ctx.accounts
+                .token_mint
+                .as_ref()
+                .map(|mint| mint.to_account_info()),
             &ctx.accounts.token_program,
+            transfer_hook_accounts,
         )?;
     }
 
@@ -51,7 +63,7 @@ pub struct WithdrawSlashedAmount<'info> {
     #[account(mut,
         token::mint = farm_state.load()?.token.mint,
     )]
-    pub slashed_amount_spill_address: Box<Account<'info, TokenAccount>>,
+    pub slashed_amount_spill_address: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
         seeds = [BASE_SEED_FARM_VAULT, farm_state.key().as_ref(), farm_state.load()?.token.mint.as_ref()],
@@ -59,7 +71,7 @@ pub struct WithdrawSlashedAmount<'info> {
         constraint = farm_vault.delegate.is_none() @ FarmError::FarmVaultHasDelegate,
         constraint = farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
     )]
-    pub farm_vault: Box<Account<'info, TokenAccount>>,
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
@@ -67,5 +79,11 @@ pub struct WithdrawSlashedAmount<'info> {
     )]
     pub farm_vaults_authority: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Required for Token-2022 farm tokens
+    pub token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
@@ -1,14 +1,29 @@
-use crate::state::GlobalConfig;
+use crate::state::{GlobalConfig, ProgramInstruction};
 use crate::token_operations;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::{check_instruction_enabled, check_remaining_accounts};
 use crate::utils::consts::*;
 use crate::FarmError;
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Mint, Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
+/// Only available while `treasury_withdrawal_delay_s` is 0, otherwise withdrawals
+/// go through `queue_treasury_withdrawal`.
 pub fn process(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::WithdrawTreasury,
+    )?;
     check_remaining_accounts(&ctx)?;
 
+    require!(
+        ctx.accounts
+            .global_config
+            .load()?
+            .treasury_withdrawal_delay_s
+            == 0,
+        FarmError::TreasuryWithdrawalDelayed
+    );
+
     let global_config_key = ctx.accounts.global_config.key();
 
     let signer_seeds: &[&[&[u8]]] = &[&[
@@ -18,14 +33,16 @@ pub fn process(ctx: Context<WithdrawTreasury>, amount: u64) -> Result<()> {
     ]];
 
     if amount > 0 {
-        token_operations::transfer_from_vault(
+        token_operations::transfer_checked_from_vault(
             amount,
+            ctx.accounts.reward_mint.decimals,
             signer_seeds,
             &ctx.accounts
                 .withdraw_destination_token_account
                 .to_account_info(),
             &ctx.accounts.reward_treasury_vault.to_account_info(),
             &ctx.accounts.treasury_vault_authority,
+            &ctx.accounts.reward_mint.to_account_info(),
             &ctx.accounts.token_program,
         )?;
     } else {
@@ -51,7 +68,7 @@ pub struct WithdrawTreasury<'info> {
         token::mint = reward_mint,
         token::authority = treasury_vault_authority,
     )]
-    pub reward_treasury_vault: Box<Account<'info, TokenAccount>>,
+    pub reward_treasury_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_TREASURY_VAULTS_AUTHORITY, global_config.key().as_ref()],
@@ -62,9 +79,10 @@ pub struct WithdrawTreasury<'info> {
     #[account(mut,
         token::mint = reward_mint,
     )]
-    pub withdraw_destination_token_account: Box<Account<'info, TokenAccount>>,
+    pub withdraw_destination_token_account: Box<InterfaceAccount<'info, TokenAccount>>,
 
-    pub reward_mint: Box<Account<'info, Mint>>,
+    pub reward_mint: Box<InterfaceAccount<'info, Mint>>,
 
-    pub token_program: Program<'info, Token>,
+    #[account(address = *reward_mint.to_account_info().owner @ FarmError::UnexpectedAccount)]
+    pub token_program: Interface<'info, TokenInterface>,
 }
@@ -0,0 +1,145 @@
+use crate::state::{GlobalConfig, ProgramInstruction};
+use crate::token_operations;
+use crate::utils::constraints::check_instruction_enabled;
+use crate::utils::consts::*;
+use crate::vmsg;
+use crate::FarmError;
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
+
+/// Drains every non-zero treasury vault passed in `remaining_accounts`, as
+/// `(reward_treasury_vault, withdraw_destination_token_account)` pairs. The vaults
+/// must all belong to `token_program`, outside of SPL Token each pair is followed by
+/// the reward mint.
+///
+/// Same rules as `withdraw_treasury`: only available while `treasury_withdrawal_delay_s` is 0.
+pub fn process<'info>(ctx: Context<'_, '_, '_, 'info, WithdrawTreasuryAll<'info>>) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::WithdrawTreasuryAll,
+    )?;
+    require!(
+        ctx.accounts
+            .global_config
+            .load()?
+            .treasury_withdrawal_delay_s
+            == 0,
+        FarmError::TreasuryWithdrawalDelayed
+    );
+    let is_spl_token = ctx.accounts.token_program.key() == anchor_spl::token::ID;
+    let accounts_per_vault = if is_spl_token { 2 } else { 3 };
+    require!(
+        !ctx.remaining_accounts.is_empty()
+            && ctx.remaining_accounts.len() % accounts_per_vault == 0,
+        FarmError::MissingRemainingAccounts
+    );
+
+    let global_config_key = ctx.accounts.global_config.key();
+    let signer_seeds: &[&[&[u8]]] = &[&[
+        BASE_SEED_TREASURY_VAULTS_AUTHORITY,
+        global_config_key.as_ref(),
+        &[ctx.bumps.treasury_vault_authority],
+    ]];
+
+    let mut total_withdrawn_vaults = 0;
+
+    for pair in ctx.remaining_accounts.chunks(accounts_per_vault) {
+        let (treasury_vault_info, destination_info) = (&pair[0], &pair[1]);
+
+        require_keys_eq!(
+            *treasury_vault_info.owner,
+            ctx.accounts.token_program.key(),
+            FarmError::UnexpectedAccount
+        );
+        let treasury_vault = InterfaceAccount::<TokenAccount>::try_from(treasury_vault_info)?;
+        let (expected_vault, _) = Pubkey::find_program_address(
+            &[
+                BASE_SEED_REWARD_TREASURY_VAULT,
+                global_config_key.as_ref(),
+                treasury_vault.mint.as_ref(),
+            ],
+            ctx.program_id,
+        );
+        require_keys_eq!(
+            treasury_vault_info.key(),
+            expected_vault,
+            FarmError::UnexpectedAccount
+        );
+        require_keys_eq!(
+            treasury_vault.owner,
+            ctx.accounts.treasury_vault_authority.key(),
+            FarmError::UnexpectedAccount
+        );
+
+        let destination = InterfaceAccount::<TokenAccount>::try_from(destination_info)?;
+        require_keys_eq!(
+            destination.mint,
+            treasury_vault.mint,
+            FarmError::UserAtaRewardVaultMintMissmatch
+        );
+
+        if treasury_vault.amount == 0 {
+            continue;
+        }
+
+        vmsg!(
+            "WithdrawTreasuryAll mint {} amount {}",
+            treasury_vault.mint,
+            treasury_vault.amount
+        );
+
+        if is_spl_token {
+            token_operations::transfer_from_vault(
+                treasury_vault.amount,
+                signer_seeds,
+                destination_info,
+                treasury_vault_info,
+                &ctx.accounts.treasury_vault_authority,
+                &ctx.accounts.token_program,
+            )?;
+        } else {
+            let mint_info = &pair[2];
+            require_keys_eq!(
+                mint_info.key(),
+                treasury_vault.mint,
+                FarmError::UnexpectedAccount
+            );
+            let mint = InterfaceAccount::<Mint>::try_from(mint_info)?;
+            token_operations::transfer_checked_from_vault(
+                treasury_vault.amount,
+                mint.decimals,
+                signer_seeds,
+                destination_info,
+                treasury_vault_info,
+                &ctx.accounts.treasury_vault_authority,
+                mint_info,
+                &ctx.accounts.token_program,
+            )?;
+        }
+
+        total_withdrawn_vaults += 1;
+    }
+
+    require!(total_withdrawn_vaults > 0, FarmError::NothingToWithdraw);
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTreasuryAll<'info> {
+    #[account(mut)]
+    pub global_admin: Signer<'info>,
+
+    #[account(
+        has_one = global_admin
+    )]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
+
+    #[account(
+        seeds = [BASE_SEED_TREASURY_VAULTS_AUTHORITY, global_config.key().as_ref()],
+        bump,
+    )]
+    pub treasury_vault_authority: AccountInfo<'info>,
+
+    pub token_program: Interface<'info, TokenInterface>,
+}
@@ -1,25 +1,38 @@
-use crate::state::TimeUnit;
+use crate::state::{FarmPauseFlag, ProgramInstruction, TimeUnit};
 use crate::token_operations;
-use crate::utils::constraints::check_remaining_accounts;
+use crate::utils::constraints::check_instruction_enabled;
 use crate::utils::consts::*;
-use crate::{farm_operations, types::WithdrawEffects};
+use crate::{farm_operations, types::WithdrawEffects, GlobalConfig};
 use crate::{gen_signer_seeds_two, FarmError, FarmState, UserState};
 use anchor_lang::prelude::*;
-use anchor_spl::token::{Token, TokenAccount};
+use anchor_spl::token_interface::{Mint, TokenAccount, TokenInterface};
 
-pub fn process(ctx: Context<WithdrawUnstakedDeposits>) -> Result<()> {
-    check_remaining_accounts(&ctx)?;
+pub fn process<'info>(
+    ctx: Context<'_, '_, '_, 'info, WithdrawUnstakedDeposits<'info>>,
+) -> Result<()> {
+    check_instruction_enabled(
+        &ctx.accounts.global_config,
+        ProgramInstruction::WithdrawUnstakedDeposits,
+    )?;
 
     let farm_state = &mut ctx.accounts.farm_state.load_mut()?;
+    let transfer_hook_accounts =
+        token_operations::transfer_hook_accounts(&farm_state.token, ctx.remaining_accounts)?;
     let time_unit = farm_state.time_unit;
     let user_state = &mut ctx.accounts.user_state.load_mut()?;
 
     require!(!farm_state.is_delegated(), FarmError::FarmDelegated);
+    require!(
+        !farm_state.is_paused(FarmPauseFlag::WithdrawUnstakedDeposits),
+        FarmError::FarmOperationPaused
+    );
 
+    let clock = Clock::get()?;
     let WithdrawEffects { amount_to_withdraw } = farm_operations::withdraw_unstaked_deposits(
         farm_state,
         user_state,
-        TimeUnit::now_from_clock(time_unit, &Clock::get()?),
+        TimeUnit::now_from_clock(time_unit, &clock),
+        clock.unix_timestamp.try_into().unwrap(),
     )?;
 
     let farm_state_key = ctx.accounts.farm_state.key();
@@ -30,13 +43,19 @@ pub fn process(ctx: Context<WithdrawUnstakedDeposits>) -> Result<()> {
     );
 
     if amount_to_withdraw > 0 {
-        token_operations::transfer_from_vault(
+        token_operations::transfer_token_from_vault_with_hook(
+            &farm_state.token,
             amount_to_withdraw,
             signer_seeds,
             &ctx.accounts.user_ata.to_account_info(),
             &ctx.accounts.farm_vault.to_account_info(),
             &ctx.accounts.farm_vaults_authority,
+            ctx.accounts
+                .token_mint
+                .as_ref()
+                .map(|mint| mint.to_account_info()),
             &ctx.accounts.token_program,
+            transfer_hook_accounts,
         )?;
     }
 
@@ -64,7 +83,7 @@ pub struct WithdrawUnstakedDeposits<'info> {
         has_one = owner,
         constraint = user_ata.mint == farm_state.load()?.token.mint @ FarmError::UserAtaFarmTokenMintMissmatch,
     )]
-    pub user_ata: Box<Account<'info, TokenAccount>>,
+    pub user_ata: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(mut,
         seeds = [BASE_SEED_FARM_VAULT, farm_state.key().as_ref(), farm_state.load()?.token.mint.as_ref()],
@@ -72,7 +91,7 @@ pub struct WithdrawUnstakedDeposits<'info> {
         constraint = farm_vault.delegate.is_none() @ FarmError::FarmVaultHasDelegate,
         constraint = farm_vault.close_authority.is_none() @ FarmError::FarmVaultHasCloseAuthority,
     )]
-    pub farm_vault: Box<Account<'info, TokenAccount>>,
+    pub farm_vault: Box<InterfaceAccount<'info, TokenAccount>>,
 
     #[account(
         seeds = [BASE_SEED_FARM_VAULTS_AUTHORITY, farm_state.key().as_ref()],
@@ -80,5 +99,11 @@ pub struct WithdrawUnstakedDeposits<'info> {
     )]
     pub farm_vaults_authority: AccountInfo<'info>,
 
-    pub token_program: Program<'info, Token>,
+    pub token_program: Interface<'info, TokenInterface>,
+
+    /// Required for Token-2022 farm tokens
+    pub token_mint: Option<Box<InterfaceAccount<'info, Mint>>>,
+
+    #[account(address = farm_state.load()?.global_config @ FarmError::UnexpectedAccount)]
+    pub global_config: AccountLoader<'info, GlobalConfig>,
 }
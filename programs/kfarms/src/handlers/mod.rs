@@ -1,49 +1,135 @@
 pub mod handler_add_reward;
+pub mod handler_cancel_pending_farm_admin;
+pub mod handler_cancel_queued_withdrawal;
+pub mod handler_cancel_treasury_withdrawal;
+pub mod handler_claim_referral_fees_all;
+pub mod handler_clone_farm;
+pub mod handler_close_user;
+pub mod handler_commit_raffle;
+pub mod handler_credit_users;
 pub mod handler_deposit_to_farm_vault;
+pub mod handler_execute_treasury_withdrawal;
+pub mod handler_get_deposit_capacity;
+pub mod handler_get_farm_history;
+pub mod handler_get_farm_registry_page;
+pub mod handler_get_farm_status;
+pub mod handler_get_farm_tvl;
+pub mod handler_get_harvest_eligibility;
+pub mod handler_get_treasury_balances;
+pub mod handler_get_upgrade_snapshots;
+pub mod handler_harvest_across_farms;
+pub mod handler_harvest_and_stake_other;
 pub mod handler_harvest_reward;
+pub mod handler_harvest_to_owner;
 pub mod handler_initialize_farm;
 pub mod handler_initialize_farm_delegated;
+pub mod handler_initialize_farm_fee_vault;
+pub mod handler_initialize_farm_history;
+pub mod handler_initialize_farm_registry;
 pub mod handler_initialize_global_config;
+pub mod handler_initialize_referrer_state;
 pub mod handler_initialize_reward;
 pub mod handler_initialize_user;
+pub mod handler_initialize_user_page;
+pub mod handler_initialize_withdrawal_queue;
+pub mod handler_process_withdrawal_queue;
+pub mod handler_queue_treasury_withdrawal;
+pub mod handler_queue_withdrawal;
 pub mod handler_refresh_farm;
 pub mod handler_refresh_user_state;
+pub mod handler_renounce_position;
+pub mod handler_report_reward_shortfall;
 pub mod handler_reward_user_once;
+pub mod handler_set_reward_destination;
 pub mod handler_set_stake_delegated;
+pub mod handler_settle_raffle;
+pub mod handler_snapshot_farm_for_upgrade;
+pub mod handler_split_stake;
 pub mod handler_stake;
+pub mod handler_stake_with_token_delegate;
+pub mod handler_sweep_diverted_rewards;
+pub mod handler_sync_mirrored_stake;
 pub mod handler_transfer_ownership;
+pub mod handler_transfer_stake_internal;
 pub mod handler_unstake;
+pub mod handler_update_disabled_instructions;
 pub mod handler_update_farm_admin;
 pub mod handler_update_farm_config;
+pub mod handler_update_farm_pause_flags;
 pub mod handler_update_global_config;
 pub mod handler_update_global_config_admin;
+pub mod handler_withdraw_farm_fees;
 pub mod handler_withdraw_from_farm_vault;
 pub mod handler_withdraw_reward;
 pub mod handler_withdraw_slashed_amount;
 pub mod handler_withdraw_treasury;
+pub mod handler_withdraw_treasury_all;
 pub mod handler_withdraw_unstaked_deposits;
 
 pub use handler_add_reward::*;
+pub use handler_cancel_pending_farm_admin::*;
+pub use handler_cancel_queued_withdrawal::*;
+pub use handler_cancel_treasury_withdrawal::*;
+pub use handler_claim_referral_fees_all::*;
+pub use handler_clone_farm::*;
+pub use handler_close_user::*;
+pub use handler_commit_raffle::*;
+pub use handler_credit_users::*;
 pub use handler_deposit_to_farm_vault::*;
+pub use handler_execute_treasury_withdrawal::*;
+pub use handler_get_deposit_capacity::*;
+pub use handler_get_farm_history::*;
+pub use handler_get_farm_registry_page::*;
+pub use handler_get_farm_status::*;
+pub use handler_get_farm_tvl::*;
+pub use handler_get_harvest_eligibility::*;
+pub use handler_get_treasury_balances::*;
+pub use handler_get_upgrade_snapshots::*;
+pub use handler_harvest_across_farms::*;
+pub use handler_harvest_and_stake_other::*;
 pub use handler_harvest_reward::*;
+pub use handler_harvest_to_owner::*;
 pub use handler_initialize_farm::*;
 pub use handler_initialize_farm_delegated::*;
+pub use handler_initialize_farm_fee_vault::*;
+pub use handler_initialize_farm_history::*;
+pub use handler_initialize_farm_registry::*;
 pub use handler_initialize_global_config::*;
+pub use handler_initialize_referrer_state::*;
 pub use handler_initialize_reward::*;
 pub use handler_initialize_user::*;
+pub use handler_initialize_user_page::*;
+pub use handler_initialize_withdrawal_queue::*;
+pub use handler_process_withdrawal_queue::*;
+pub use handler_queue_treasury_withdrawal::*;
+pub use handler_queue_withdrawal::*;
 pub use handler_refresh_farm::*;
 pub use handler_refresh_user_state::*;
+pub use handler_renounce_position::*;
+pub use handler_report_reward_shortfall::*;
 pub use handler_reward_user_once::*;
+pub use handler_set_reward_destination::*;
 pub use handler_set_stake_delegated::*;
+pub use handler_settle_raffle::*;
+pub use handler_snapshot_farm_for_upgrade::*;
+pub use handler_split_stake::*;
 pub use handler_stake::*;
+pub use handler_stake_with_token_delegate::*;
+pub use handler_sweep_diverted_rewards::*;
+pub use handler_sync_mirrored_stake::*;
 pub use handler_transfer_ownership::*;
+pub use handler_transfer_stake_internal::*;
 pub use handler_unstake::*;
+pub use handler_update_disabled_instructions::*;
 pub use handler_update_farm_admin::*;
 pub use handler_update_farm_config::*;
+pub use handler_update_farm_pause_flags::*;
 pub use handler_update_global_config::*;
 pub use handler_update_global_config_admin::*;
+pub use handler_withdraw_farm_fees::*;
 pub use handler_withdraw_from_farm_vault::*;
 pub use handler_withdraw_reward::*;
 pub use handler_withdraw_slashed_amount::*;
 pub use handler_withdraw_treasury::*;
+pub use handler_withdraw_treasury_all::*;
 pub use handler_withdraw_unstaked_deposits::*;
@@ -0,0 +1,88 @@
+use anchor_lang::prelude::*;
+use anchor_lang::solana_program::{
+    instruction::{AccountMeta, Instruction},
+    program::invoke,
+};
+
+use crate::{vmsg, FarmError, FarmState};
+
+/// First 8 bytes of `sha256("global:on_farm_stake_change")`.
+pub const STAKE_HOOK_IX_DISCRIMINATOR: [u8; 8] = [15, 211, 103, 220, 134, 198, 209, 46];
+
+/// Instruction data sent to the hook program, after [`STAKE_HOOK_IX_DISCRIMINATOR`]:
+///
+/// | offset | size | field              |
+/// |--------|------|--------------------|
+/// | 8      | 32   | `farm_state`       |
+/// | 40     | 32   | `owner`            |
+/// | 72     | 16   | `old_stake_scaled` |
+/// | 88     | 16   | `new_stake_scaled` |
+///
+/// Accounts: `[hook_config (writable), ...remaining_accounts]`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StakeChangeHookArgs {
+    pub farm_state: Pubkey,
+    pub owner: Pubkey,
+    pub old_stake_scaled: u128,
+    pub new_stake_scaled: u128,
+}
+
+pub fn validate_hook_accounts(
+    farm_state: &FarmState,
+    hook_program: &Option<AccountInfo>,
+    hook_config: &Option<AccountInfo>,
+) -> Result<()> {
+    match (hook_program, hook_config) {
+        (Some(hook_program), Some(hook_config)) => {
+            require_keys_eq!(
+                hook_program.key(),
+                farm_state.hook_program,
+                FarmError::InvalidHookAccounts
+            );
+            require_keys_eq!(
+                hook_config.key(),
+                farm_state.hook_config,
+                FarmError::InvalidHookAccounts
+            );
+            require!(hook_program.executable, FarmError::InvalidHookAccounts);
+            Ok(())
+        }
+        _ => err!(FarmError::InvalidHookAccounts),
+    }
+}
+
+pub fn invoke_stake_hook<'info>(
+    args: StakeChangeHookArgs,
+    hook_program: &AccountInfo<'info>,
+    hook_config: &AccountInfo<'info>,
+    remaining_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    vmsg!(
+        "hook_operations::invoke_stake_hook program={} old_stake={} new_stake={}",
+        hook_program.key(),
+        args.old_stake_scaled,
+        args.new_stake_scaled
+    );
+
+    let mut data = STAKE_HOOK_IX_DISCRIMINATOR.to_vec();
+    args.serialize(&mut data)?;
+
+    let mut accounts = vec![AccountMeta::new(hook_config.key(), false)];
+    accounts.extend(remaining_accounts.iter().map(|acc| AccountMeta {
+        pubkey: acc.key(),
+        is_signer: acc.is_signer,
+        is_writable: acc.is_writable,
+    }));
+
+    let mut account_infos = vec![hook_config.clone()];
+    account_infos.extend(remaining_accounts.iter().cloned());
+    account_infos.push(hook_program.clone());
+
+    let ix = Instruction {
+        program_id: hook_program.key(),
+        accounts,
+        data,
+    };
+
+    invoke(&ix, &account_infos).map_err(Into::into)
+}
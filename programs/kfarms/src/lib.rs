@@ -1,10 +1,14 @@
 #![allow(clippy::result_large_err)]
+pub mod events;
 pub mod farm_operations;
 mod handlers;
+mod hook_operations;
+#[cfg(feature = "no-entrypoint")]
+pub mod simulate;
 pub mod stake_operations;
 pub mod state;
 mod token_operations;
-mod types;
+pub mod types;
 pub mod utils;
 
 use crate::handlers::*;
@@ -92,12 +96,28 @@ pub mod farms {
         handler_reward_user_once::process(ctx, reward_index, amount)
     }
 
+    pub fn credit_users<'info>(
+        ctx: Context<'_, '_, '_, 'info, CreditUsers<'info>>,
+        reward_index: u64,
+        batch_nonce: u64,
+        amounts: Vec<u64>,
+    ) -> Result<()> {
+        handler_credit_users::process(ctx, reward_index, batch_nonce, amounts)
+    }
+
     pub fn refresh_farm(ctx: Context<RefreshFarm>) -> Result<()> {
         handler_refresh_farm::process(ctx)
     }
 
-    pub fn stake(ctx: Context<Stake>, amount: u64) -> Result<()> {
-        handler_stake::process(ctx, amount)
+    pub fn stake<'info>(ctx: Context<'_, '_, '_, 'info, Stake<'info>>, amount: u64) -> Result<()> {
+        handler_stake::process(ctx, amount, false, 0, ProgramInstruction::Stake)
+    }
+
+    pub fn stake_with_token_delegate<'info>(
+        ctx: Context<'_, '_, '_, 'info, StakeWithTokenDelegate<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        handler_stake_with_token_delegate::process(ctx, amount)
     }
 
     pub fn set_stake_delegated(ctx: Context<SetStakeDelegated>, new_amount: u64) -> Result<()> {
@@ -108,15 +128,25 @@ pub mod farms {
         handler_harvest_reward::process(ctx, reward_index)
     }
 
-    pub fn unstake(ctx: Context<Unstake>, stake_shares_scaled: u128) -> Result<()> {
-        handler_unstake::process(ctx, Decimal::from_scaled_val(stake_shares_scaled))
+    pub fn unstake<'info>(
+        ctx: Context<'_, '_, '_, 'info, Unstake<'info>>,
+        stake_shares_scaled: u128,
+    ) -> Result<()> {
+        handler_unstake::process(
+            ctx,
+            Decimal::from_scaled_val(stake_shares_scaled),
+            0,
+            ProgramInstruction::Unstake,
+        )
     }
 
     pub fn refresh_user_state(ctx: Context<RefreshUserState>) -> Result<()> {
-        handler_refresh_user_state::process(ctx)
+        handler_refresh_user_state::process(ctx, 0, ProgramInstruction::RefreshUserState)
     }
 
-    pub fn withdraw_unstaked_deposits(ctx: Context<WithdrawUnstakedDeposits>) -> Result<()> {
+    pub fn withdraw_unstaked_deposits<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawUnstakedDeposits<'info>>,
+    ) -> Result<()> {
         handler_withdraw_unstaked_deposits::process(ctx)
     }
 
@@ -124,18 +154,23 @@ pub mod farms {
         handler_withdraw_treasury::process(ctx, amount)
     }
 
-    pub fn deposit_to_farm_vault(ctx: Context<DepositToFarmVault>, amount: u64) -> Result<()> {
+    pub fn deposit_to_farm_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, DepositToFarmVault<'info>>,
+        amount: u64,
+    ) -> Result<()> {
         handler_deposit_to_farm_vault::process(ctx, amount)
     }
 
-    pub fn withdraw_from_farm_vault(
-        ctx: Context<WithdrawFromFarmVault>,
+    pub fn withdraw_from_farm_vault<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFromFarmVault<'info>>,
         amount: u64,
     ) -> Result<()> {
         handler_withdraw_from_farm_vault::process(ctx, amount)
     }
 
-    pub fn withdraw_slashed_amount(ctx: Context<WithdrawSlashedAmount>) -> Result<()> {
+    pub fn withdraw_slashed_amount<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawSlashedAmount<'info>>,
+    ) -> Result<()> {
         handler_withdraw_slashed_amount::process(ctx)
     }
 
@@ -155,6 +190,276 @@ pub mod farms {
         handler_withdraw_reward::process(ctx, amount, reward_index)
     }
 
+    pub fn initialize_referrer_state(ctx: Context<InitializeReferrerState>) -> Result<()> {
+        handler_initialize_referrer_state::process(ctx)
+    }
+
+    pub fn claim_referral_fees_all<'info>(
+        ctx: Context<'_, '_, '_, 'info, ClaimReferralFeesAll<'info>>,
+    ) -> Result<()> {
+        handler_claim_referral_fees_all::process(ctx)
+    }
+
+    pub fn transfer_stake_internal(
+        ctx: Context<TransferStakeInternal>,
+        stake_shares_scaled: u128,
+    ) -> Result<()> {
+        handler_transfer_stake_internal::process(ctx, Decimal::from_scaled_val(stake_shares_scaled))
+    }
+
+    pub fn split_stake(
+        ctx: Context<SplitStake>,
+        stake_shares_scaled: u128,
+        new_user_id: u64,
+        lock_end_ts: u64,
+    ) -> Result<()> {
+        handler_split_stake::process(
+            ctx,
+            Decimal::from_scaled_val(stake_shares_scaled),
+            new_user_id,
+            lock_end_ts,
+        )
+    }
+
+    pub fn initialize_farm_registry(ctx: Context<InitializeFarmRegistry>) -> Result<()> {
+        handler_initialize_farm_registry::process(ctx)
+    }
+
+    pub fn get_farm_registry_page(
+        ctx: Context<GetFarmRegistryPage>,
+        start: u32,
+        limit: u32,
+    ) -> Result<()> {
+        handler_get_farm_registry_page::process(ctx, start, limit)
+    }
+
+    pub fn initialize_user_page(ctx: Context<InitializeUserPage>, page_index: u64) -> Result<()> {
+        handler_initialize_user_page::process(ctx, page_index)
+    }
+
+    pub fn initialize_farm_fee_vault(ctx: Context<InitializeFarmFeeVault>) -> Result<()> {
+        handler_initialize_farm_fee_vault::process(ctx)
+    }
+
+    pub fn withdraw_farm_fees<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawFarmFees<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        handler_withdraw_farm_fees::process(ctx, amount)
+    }
+
+    pub fn get_deposit_capacity(ctx: Context<GetDepositCapacity>) -> Result<()> {
+        handler_get_deposit_capacity::process(ctx)
+    }
+
+    pub fn get_farm_status(ctx: Context<GetFarmStatus>) -> Result<()> {
+        handler_get_farm_status::process(ctx)
+    }
+
+    pub fn get_farm_tvl(ctx: Context<GetFarmTvl>) -> Result<()> {
+        handler_get_farm_tvl::process(ctx)
+    }
+
+    pub fn get_harvest_eligibility(ctx: Context<GetHarvestEligibility>) -> Result<()> {
+        handler_get_harvest_eligibility::process(ctx)
+    }
+
+    pub fn clone_farm(ctx: Context<CloneFarm>) -> Result<()> {
+        handler_clone_farm::process(ctx)
+    }
+
+    pub fn cancel_pending_farm_admin(ctx: Context<CancelPendingFarmAdmin>) -> Result<()> {
+        handler_cancel_pending_farm_admin::process(ctx)
+    }
+
+    pub fn update_farm_pause_flags(
+        ctx: Context<UpdateFarmPauseFlags>,
+        pause_flags: u8,
+    ) -> Result<()> {
+        handler_update_farm_pause_flags::process(ctx, pause_flags)
+    }
+
+    pub fn queue_treasury_withdrawal(
+        ctx: Context<QueueTreasuryWithdrawal>,
+        amount: u64,
+    ) -> Result<()> {
+        handler_queue_treasury_withdrawal::process(ctx, amount)
+    }
+
+    pub fn execute_treasury_withdrawal(ctx: Context<ExecuteTreasuryWithdrawal>) -> Result<()> {
+        handler_execute_treasury_withdrawal::process(ctx)
+    }
+
+    pub fn cancel_treasury_withdrawal(ctx: Context<CancelTreasuryWithdrawal>) -> Result<()> {
+        handler_cancel_treasury_withdrawal::process(ctx)
+    }
+
+    pub fn report_reward_shortfall(
+        ctx: Context<ReportRewardShortfall>,
+        reward_index: u64,
+    ) -> Result<()> {
+        handler_report_reward_shortfall::process(ctx, reward_index)
+    }
+
+    pub fn withdraw_treasury_all<'info>(
+        ctx: Context<'_, '_, '_, 'info, WithdrawTreasuryAll<'info>>,
+    ) -> Result<()> {
+        handler_withdraw_treasury_all::process(ctx)
+    }
+
+    pub fn set_reward_destination(
+        ctx: Context<SetRewardDestination>,
+        reward_index: u64,
+    ) -> Result<()> {
+        handler_set_reward_destination::process(ctx, reward_index)
+    }
+
+    pub fn sync_mirrored_stake(ctx: Context<SyncMirroredStake>) -> Result<()> {
+        handler_sync_mirrored_stake::process(ctx)
+    }
+
+    pub fn harvest_and_stake_other(
+        ctx: Context<HarvestAndStakeOther>,
+        reward_index: u64,
+    ) -> Result<()> {
+        handler_harvest_and_stake_other::process(ctx, reward_index)
+    }
+
+    pub fn update_disabled_instructions(
+        ctx: Context<UpdateDisabledInstructions>,
+        disabled_instructions: u128,
+    ) -> Result<()> {
+        handler_update_disabled_instructions::process(ctx, disabled_instructions)
+    }
+
+    pub fn harvest_to_owner(ctx: Context<HarvestToOwner>, reward_index: u64) -> Result<()> {
+        handler_harvest_to_owner::process(ctx, reward_index)
+    }
+
+    pub fn commit_raffle(
+        ctx: Context<CommitRaffle>,
+        reward_index: u64,
+        slot_target: u64,
+        prize_amount: u64,
+    ) -> Result<()> {
+        handler_commit_raffle::process(ctx, reward_index, slot_target, prize_amount)
+    }
+
+    pub fn settle_raffle<'info>(
+        ctx: Context<'_, '_, '_, 'info, SettleRaffle<'info>>,
+    ) -> Result<()> {
+        handler_settle_raffle::process(ctx)
+    }
+
+    pub fn get_treasury_balances<'info>(
+        ctx: Context<'_, '_, '_, 'info, GetTreasuryBalances<'info>>,
+    ) -> Result<()> {
+        handler_get_treasury_balances::process(ctx)
+    }
+
+    pub fn renounce_position(ctx: Context<RenouncePosition>, allow_harvest: bool) -> Result<()> {
+        handler_renounce_position::process(ctx, allow_harvest)
+    }
+
+    pub fn initialize_farm_history(ctx: Context<InitializeFarmHistory>) -> Result<()> {
+        handler_initialize_farm_history::process(ctx)
+    }
+
+    pub fn get_farm_history(ctx: Context<GetFarmHistory>, start: u32, limit: u32) -> Result<()> {
+        handler_get_farm_history::process(ctx, start, limit)
+    }
+
+    pub fn snapshot_farm_for_upgrade(
+        ctx: Context<SnapshotFarmForUpgrade>,
+        marker: u64,
+        pause_slots: u64,
+    ) -> Result<()> {
+        handler_snapshot_farm_for_upgrade::process(ctx, marker, pause_slots)
+    }
+
+    pub fn get_upgrade_snapshots<'info>(
+        ctx: Context<'_, '_, '_, 'info, GetUpgradeSnapshots<'info>>,
+        expected_marker: u64,
+    ) -> Result<()> {
+        handler_get_upgrade_snapshots::process(ctx, expected_marker)
+    }
+
+    pub fn sweep_diverted_rewards(
+        ctx: Context<SweepDivertedRewards>,
+        reward_index: u64,
+    ) -> Result<()> {
+        handler_sweep_diverted_rewards::process(ctx, reward_index)
+    }
+
+    pub fn harvest_across_farms<'info>(
+        ctx: Context<'_, '_, '_, 'info, HarvestAcrossFarms<'info>>,
+        reward_masks: Vec<u16>,
+    ) -> Result<()> {
+        handler_harvest_across_farms::process(ctx, reward_masks)
+    }
+
+    pub fn stake_with_options<'info>(
+        ctx: Context<'_, '_, '_, 'info, Stake<'info>>,
+        amount: u64,
+        allow_partial: bool,
+        min_shares_out: u64,
+    ) -> Result<()> {
+        handler_stake::process(
+            ctx,
+            amount,
+            allow_partial,
+            min_shares_out,
+            ProgramInstruction::StakeWithOptions,
+        )
+    }
+
+    pub fn unstake_with_min_amount_out<'info>(
+        ctx: Context<'_, '_, '_, 'info, Unstake<'info>>,
+        stake_shares_scaled: u128,
+        min_amount_out: u64,
+    ) -> Result<()> {
+        handler_unstake::process(
+            ctx,
+            Decimal::from_scaled_val(stake_shares_scaled),
+            min_amount_out,
+            ProgramInstruction::UnstakeWithMinAmountOut,
+        )
+    }
+
+    pub fn refresh_user_state_masked(
+        ctx: Context<RefreshUserState>,
+        rewards_mask: u16,
+    ) -> Result<()> {
+        handler_refresh_user_state::process(
+            ctx,
+            rewards_mask,
+            ProgramInstruction::RefreshUserStateMasked,
+        )
+    }
+
+    pub fn initialize_withdrawal_queue(ctx: Context<InitializeWithdrawalQueue>) -> Result<()> {
+        handler_initialize_withdrawal_queue::process(ctx)
+    }
+
+    pub fn queue_withdrawal(ctx: Context<QueueWithdrawal>) -> Result<()> {
+        handler_queue_withdrawal::process(ctx)
+    }
+
+    pub fn process_withdrawal_queue<'info>(
+        ctx: Context<'_, '_, '_, 'info, ProcessWithdrawalQueue<'info>>,
+        max_items: u8,
+    ) -> Result<()> {
+        handler_process_withdrawal_queue::process(ctx, max_items)
+    }
+
+    pub fn cancel_queued_withdrawal(ctx: Context<CancelQueuedWithdrawal>) -> Result<()> {
+        handler_cancel_queued_withdrawal::process(ctx)
+    }
+
+    pub fn close_user(ctx: Context<CloseUser>) -> Result<()> {
+        handler_close_user::process(ctx)
+    }
+
     pub fn idl_missing_types(
         _ctx: Context<UpdateGlobalConfig>,
         _global_config_option_kind: GlobalConfigOption,
@@ -167,115 +472,252 @@ pub mod farms {
     }
 }
 
+/// Error codes are `6000 + discriminant`. The variants up to 101 keep the values they
+/// were first released with, new ones take the next free value of their group.
 #[error_code]
 #[derive(Error, PartialEq, Eq, FromPrimitive)]
 pub enum FarmError {
+    // General: accounts and arithmetic, new variants from 200
+    #[msg("Integer overflow")]
+    IntegerOverflow = 12,
+    #[msg("Conversion failure")]
+    ConversionFailure = 13,
+    #[msg("Decimal operation with overflow")]
+    MathOverflow = 16,
+    #[msg("Unexpected account in instruction")]
+    UnexpectedAccount = 14,
+    #[msg("Operation forbidden")]
+    OperationForbidden = 15,
+    #[msg("Not enough remaining accounts provided")]
+    MissingRemainingAccounts = 53,
+    #[msg("Invalid timestamp")]
+    InvalidTimestamp = 44,
+
+    // Stake: stake, unstake, withdrawals and locking, new variants from 300
     #[msg("Cannot stake 0 amount")]
-    StakeZero,
+    StakeZero = 0,
     #[msg("Cannot unstake 0 amount")]
-    UnstakeZero,
+    UnstakeZero = 1,
     #[msg("Nothing to unstake")]
-    NothingToUnstake,
+    NothingToUnstake = 2,
+    #[msg("No tokens available to withdraw")]
+    NothingToWithdraw = 31,
+    #[msg("Cannot deposit zero amount directly to farm vault")]
+    DepositZero = 38,
+    #[msg("Deposit cap reached")]
+    DepositCapReached = 45,
+    #[msg("Unstake lockup period is not elapsed. Deposit is locked until end of unstake period")]
+    UnstakeNotElapsed = 36,
+    #[msg("Pending withdrawal already exist and not withdrawn yet")]
+    PendingWithdrawalNotWithdrawnYet = 37,
+    #[msg("Early withdrawal not allowed")]
+    EarlyWithdrawalNotAllowed = 41,
+    #[msg("Invalid locking timestamps")]
+    InvalidLockingTimestamps = 42,
+    #[msg("User stake is locked until the position lock ends")]
+    UserStakeLocked = 54,
+    #[msg("user, user_ref, authority and payer must match for non-delegated farm")]
+    UserDelegatedFarmNonDelegatedMissmatch = 32,
+    #[msg("Authority must match farm delegate authority")]
+    AuthorityFarmDelegateMissmatch = 33,
+    #[msg("Farm not delegated, can not set stake")]
+    FarmNotDelegated = 34,
+    #[msg("Operation not allowed for delegated farm")]
+    FarmDelegated = 35,
+    #[msg("Stake hook accounts missing or not matching farm configuration")]
+    InvalidHookAccounts = 55,
+    #[msg("Signer is not the approved delegate of the user token account")]
+    InvalidTokenDelegate = 56,
+    #[msg("Amount exceeds the amount approved to the token delegate")]
+    DelegatedAmountExceeded = 57,
+    #[msg("User page missing or not matching the user being created")]
+    InvalidUserPage = 59,
+    #[msg("Unstaked token amount is below the requested minimum")]
+    UnstakeAmountOutTooLow = 61,
+    #[msg("Stake shares credited are below the requested minimum")]
+    StakeSharesOutTooLow = 62,
+    #[msg("Farm does not accept new users anymore")]
+    NewUsersCutoffReached = 63,
+    #[msg("Operation is paused on this farm")]
+    FarmOperationPaused = 65,
+    #[msg("Farm does not mirror a primary farm")]
+    FarmNotMirror = 78,
+    #[msg("Stake of mirror farms is only updated through sync_mirrored_stake")]
+    FarmIsMirror = 79,
+    #[msg("Mirrored stake must be synced before harvesting")]
+    MirroredStakeStale = 80,
+    #[msg("Farm requires refresh_farm earlier in the same slot, prepend it to the transaction")]
+    FarmNotRefreshedInSlot = 89,
+    #[msg("Position was renounced")]
+    PositionRenounced = 97,
+    #[msg("Positions of delegated farms cannot be renounced")]
+    RenounceNotSupported = 98,
+    #[msg("Stake and unstake are paused for a program upgrade")]
+    UpgradePaused = 99,
+    #[msg("Withdrawal exceeds the user withdrawal allowance for the current window")]
+    UserWithdrawalLimitExceeded = 300,
+    #[msg("Withdrawals are queued, join the withdrawal queue")]
+    WithdrawalQueueNotEmpty = 301,
+    #[msg("Withdrawal is not blocked by the user withdrawal limit")]
+    WithdrawalNotBlocked = 302,
+    #[msg("User already has a queued withdrawal")]
+    WithdrawalAlreadyQueued = 303,
+    #[msg("User has no queued withdrawal")]
+    NoQueuedWithdrawal = 304,
+    #[msg("User still has stake, pending deposits or withdrawals, or unclaimed rewards")]
+    UserStateNotEmpty = 305,
+
+    // Rewards: rewards, harvests, credits and raffles, new variants from 400
     #[msg("No reward to harvest")]
-    NoRewardToHarvest,
+    NoRewardToHarvest = 3,
     #[msg("Reward not present in reward list")]
-    NoRewardInList,
+    NoRewardInList = 4,
     #[msg("Reward already initialized")]
-    RewardAlreadyInitialized,
+    RewardAlreadyInitialized = 5,
     #[msg("Max number of reward tokens reached")]
-    MaxRewardNumberReached,
+    MaxRewardNumberReached = 6,
     #[msg("Reward does not exist")]
-    RewardDoesNotExist,
-    #[msg("Reward vault exists but the account is wrong")]
-    WrongRewardVaultAccount,
-    #[msg("Reward vault pubkey does not match staking pool vault")]
-    RewardVaultMismatch,
-    #[msg("Reward vault authority pubkey does not match staking pool vault")]
-    RewardVaultAuthorityMismatch,
+    RewardDoesNotExist = 7,
+    #[msg("Reward Index is higher than number of rewards")]
+    RewardIndexOutOfRange = 30,
     #[msg("Nothing staked, cannot collect any rewards")]
-    NothingStaked,
-    #[msg("Integer overflow")]
-    IntegerOverflow,
-    #[msg("Conversion failure")]
-    ConversionFailure,
-    #[msg("Unexpected account in instruction")]
-    UnexpectedAccount,
-    #[msg("Operation forbidden")]
-    OperationForbidden,
-    #[msg("Mathematical operation with overflow")]
-    MathOverflow,
+    NothingStaked = 11,
     #[msg("Minimum claim duration has not been reached")]
-    MinClaimDurationNotReached,
+    MinClaimDurationNotReached = 17,
+    #[msg("Reward cannot be claimed yet")]
+    RewardClaimsNotOpenYet = 81,
+    #[msg("Invalid penalty percentage")]
+    InvalidPenaltyPercentage = 40,
+    #[msg("Invalid reward rate curve point")]
+    InvalidRpsCurvePoint = 43,
+    #[msg("Cannot withdraw reward as available amount is zero")]
+    WithdrawRewardZeroAvailable = 51,
+    #[msg("Cannot withdraw reward as reward schedule is set")]
+    RewardScheduleCurveSet = 52,
+    #[msg("Rewards vault covers all outstanding rewards")]
+    NoRewardShortfall = 74,
+    #[msg("Reward token account does not match the user reward destination")]
+    InvalidRewardDestination = 77,
+    #[msg("Reward has a split, its destination accounts are required")]
+    MissingRewardSplitAccounts = 83,
+    #[msg("Credit batch nonce was already used")]
+    CreditBatchAlreadyApplied = 87,
+    #[msg("Rewards vault does not cover the rewards owed and the credited amount")]
+    CreditExceedsRewardsVault = 88,
+    #[msg("Raffle target slot must be in the future")]
+    InvalidRaffleSlotTarget = 93,
+    #[msg("Raffle prize exceeds the rewards available")]
+    RafflePrizeExceedsRewardsAvailable = 94,
+    #[msg("Block hash of the raffle target slot is not available yet")]
+    RaffleNotReady = 95,
+    #[msg("Raffle is not in the expected status")]
+    InvalidRaffleStatus = 96,
+    #[msg("Too many harvest groups")]
+    TooManyHarvestGroups = 400,
+
+    // Admin: authorities, configuration and treasury, new variants from 500
+    #[msg("Mode to update global_config is invalid")]
+    InvalidGlobalConfigMode = 29,
+    #[msg("Mode to update farm_config is invalid")]
+    InvalidFarmConfigMode = 82,
+    #[msg("Invalid config value")]
+    InvalidConfigValue = 39,
+    #[msg("Signer is neither the farm admin nor the global admin")]
+    InvalidAdminAuthority = 64,
+    #[msg("This config mode can only be updated by the farm admin")]
+    FarmAdminAuthorityRequired = 70,
+    #[msg("This config mode requires the farm admin or the emissions authority")]
+    EmissionsAuthorityRequired = 71,
+    #[msg("This config mode requires the farm admin or the risk authority")]
+    RiskAuthorityRequired = 72,
+    #[msg("This config mode requires the farm admin or the metadata authority")]
+    MetadataAuthorityRequired = 73,
+    #[msg("Emergency authority can only pause, not unpause")]
+    EmergencyAuthorityCannotUnpause = 66,
+    #[msg("Maximum number of farms for this global config reached")]
+    MaxFarmsReached = 58,
+    #[msg("Allowlisted mints are full")]
+    AllowlistedMintsFull = 76,
+    #[msg("Treasury withdrawals must be queued while a delay is configured")]
+    TreasuryWithdrawalDelayed = 67,
+    #[msg("Treasury withdrawal request is not executable yet")]
+    TreasuryWithdrawalNotExecutableYet = 68,
+    #[msg("Withdrawal exceeds the farm admin withdrawal allowance for the current window")]
+    AdminWithdrawalLimitExceeded = 69,
+    #[msg("Instruction is disabled in the global config")]
+    InstructionDisabled = 90,
+    #[msg("Instruction cannot be disabled")]
+    InstructionCannotBeDisabled = 91,
+    #[msg("Only the global admin can disable or re-enable this instruction")]
+    GlobalAdminRequiredForInstruction = 92,
+    #[msg("Upgrade snapshot marker must be non-zero and not below the current one")]
+    InvalidUpgradeSnapshotMarker = 100,
+    #[msg("Upgrade pause exceeds the maximum number of slots")]
+    UpgradePauseTooLong = 101,
+    #[msg("Allowlisted transfer hook programs are full")]
+    AllowlistedTransferHookProgramsFull = 500,
+    #[msg("Additional delegate authorities are full")]
+    DelegateAuthoritiesFull = 501,
+    #[msg("Delegate authority still has stake attributed, it must be set to zero first")]
+    DelegateAuthorityHasAttributedStake = 502,
+
+    // Oracle: Scope prices, new variants from 600
+    #[msg("Scope prices account missing")]
+    MissingScopePrices = 46,
+    #[msg("Scope price is too old")]
+    ScopeOraclePriceTooOld = 47,
+    #[msg("Invalid Oracle Config")]
+    InvalidOracleConfig = 48,
+    #[msg("Could not deserialize scope")]
+    CouldNotDeserializeScope = 49,
+
+    // Vault: vaults, token accounts and mints, new variants from 700
+    #[msg("Reward vault exists but the account is wrong")]
+    WrongRewardVaultAccount = 8,
+    #[msg("Reward vault does not match the farm reward vault")]
+    RewardVaultMismatch = 9,
+    #[msg("Reward vault authority does not match the farm vaults authority")]
+    RewardVaultAuthorityMismatch = 10,
     #[msg("Reward vault has a delegate")]
-    RewardsVaultHasDelegate,
+    RewardsVaultHasDelegate = 18,
     #[msg("Reward vault has a close authority")]
-    RewardsVaultHasCloseAuthority,
+    RewardsVaultHasCloseAuthority = 19,
+    #[msg("Reward treasury vault has a delegate")]
+    RewardsTreasuryVaultHasDelegate = 22,
+    #[msg("Reward treasury vault has a close authority")]
+    RewardsTreasuryVaultHasCloseAuthority = 23,
     #[msg("Farm vault has a delegate")]
-    FarmVaultHasDelegate,
+    FarmVaultHasDelegate = 20,
     #[msg("Farm vault has a close authority")]
-    FarmVaultHasCloseAuthority,
-    #[msg("Reward vault has a delegate")]
-    RewardsTreasuryVaultHasDelegate,
-    #[msg("Reward vault has a close authority")]
-    RewardsTreasuryVaultHasCloseAuthority,
+    FarmVaultHasCloseAuthority = 21,
+    #[msg("Farm fee vault already initialized")]
+    FeeVaultAlreadyInitialized = 60,
     #[msg("User ata and reward vault have different mints")]
-    UserAtaRewardVaultMintMissmatch,
+    UserAtaRewardVaultMintMissmatch = 24,
     #[msg("User ata and farm token have different mints")]
-    UserAtaFarmTokenMintMissmatch,
+    UserAtaFarmTokenMintMissmatch = 25,
     #[msg("Token mint and farm token have different mints")]
-    TokenFarmTokenMintMissmatch,
+    TokenFarmTokenMintMissmatch = 26,
     #[msg("Reward ata mint is different than reward mint")]
-    RewardAtaRewardMintMissmatch,
+    RewardAtaRewardMintMissmatch = 27,
     #[msg("Reward ata owner is different than payer")]
-    RewardAtaOwnerNotPayer,
-    #[msg("Mode to update global_config is invalid")]
-    InvalidGlobalConfigMode,
-    #[msg("Reward Index is higher than number of rewards")]
-    RewardIndexOutOfRange,
-    #[msg("No tokens available to withdraw")]
-    NothingToWithdraw,
-    #[msg("user, user_ref, authority and payer must match for non-delegated farm")]
-    UserDelegatedFarmNonDelegatedMissmatch,
-    #[msg("Authority must match farm delegate authority")]
-    AuthorityFarmDelegateMissmatch,
-    #[msg("Farm not delegated, can not set stake")]
-    FarmNotDelegated,
-    #[msg("Operation not allowed for delegated farm")]
-    FarmDelegated,
-    #[msg("Unstake lockup period is not elapsed. Deposit is locked until end of unstake period")]
-    UnstakeNotElapsed,
-    #[msg("Pending withdrawal already exist and not withdrawn yet")]
-    PendingWithdrawalNotWithdrawnYet,
-    #[msg("Cannot deposit zero amount directly to farm vault")]
-    DepositZero,
-    #[msg("Invalid config value")]
-    InvalidConfigValue,
-    #[msg("Invalid penalty percentage")]
-    InvalidPenaltyPercentage,
-    #[msg("Early withdrawal not allowed")]
-    EarlyWithdrawalNotAllowed,
-    #[msg("Invalid locking timestamps")]
-    InvalidLockingTimestamps,
-    #[msg("Invalid reward rate curve point")]
-    InvalidRpsCurvePoint,
-    #[msg("Invalid timestamp")]
-    InvalidTimestamp,
-    #[msg("Deposit cap reached")]
-    DepositCapReached,
-    #[msg("Missing Scope Prices")]
-    MissingScopePrices,
-    #[msg("Scope Oracle Price Too Old")]
-    ScopeOraclePriceTooOld,
-    #[msg("Invalid Oracle Config")]
-    InvalidOracleConfig,
-    #[msg("Could not deserialize scope")]
-    CouldNotDeserializeScope,
+    RewardAtaOwnerNotPayer = 28,
     #[msg("Reward ata owner is different than farm admin")]
-    RewardAtaOwnerNotAdmin,
-    #[msg("Cannot withdraw reward as available amount is zero")]
-    WithdrawRewardZeroAvailable,
-    #[msg("Cannot withdraw reward as reward schedule is set")]
-    RewardScheduleCurveSet,
+    RewardAtaOwnerNotAdmin = 50,
+    #[msg("Farm token mint has a freeze authority and is not allowlisted")]
+    FarmTokenMintHasFreezeAuthority = 75,
+    #[msg("Token-2022 transfers require the mint account")]
+    MissingTokenMint = 84,
+    #[msg("Token mint has a permanent delegate")]
+    TokenMintHasPermanentDelegate = 85,
+    #[msg("Token mint has a close authority")]
+    TokenMintHasCloseAuthority = 86,
+    #[msg("Token mint transfer hook program is not allowlisted")]
+    TransferHookProgramNotAllowlisted = 700,
+    #[msg("Reward token mint has a transfer hook")]
+    RewardMintHasTransferHook = 701,
+    #[msg("Token mint transfer hook program changed since the token was registered")]
+    TransferHookProgramChanged = 702,
 }
 
 impl From<DecimalError> for FarmError {
@@ -0,0 +1,130 @@
+//! Off-chain helpers to plan reward campaigns with the same math as the program.
+//!
+//! Only compiled with `no-entrypoint`, so it never ends up in the deployed program.
+//! Timestamps and durations are in the farm `time_unit`, amounts in raw token units.
+
+use anchor_lang::prelude::*;
+use scope::DatedPrice;
+
+use crate::farm_operations::scale_curve_amount;
+use crate::state::{RewardPerTimeUnitPoint, RewardScheduleCurve, RewardType};
+use crate::{FarmState, RewardInfo};
+
+pub const SECONDS_PER_YEAR: u64 = 365 * 24 * 60 * 60;
+
+/// Parameters of a hypothetical reward campaign on a farm
+#[derive(Debug, Clone)]
+pub struct CampaignParams {
+    pub curve_points: Vec<RewardPerTimeUnitPoint>,
+    pub reward_type: RewardType,
+    pub rps_decimals: u8,
+    /// Rewards funded up front, issuance stops once they are exhausted
+    pub funding: u64,
+    /// Oracle price used by the farm to scale rewards, `None` for farms without one.
+    /// Its `unix_timestamp` must not be later than the simulated interval
+    pub scope_price: Option<DatedPrice>,
+}
+
+/// Prices of one whole token, in any common quote unit
+#[derive(Debug, Clone, Copy)]
+pub struct TokenPrices {
+    pub stake_token_price: f64,
+    pub stake_token_decimals: u8,
+    pub reward_token_price: f64,
+    pub reward_token_decimals: u8,
+}
+
+impl CampaignParams {
+    fn to_farm_state(&self, total_staked_amount: u64) -> Result<FarmState> {
+        let mut farm_state = FarmState {
+            total_staked_amount,
+            ..Default::default()
+        };
+        farm_state.reward_infos[0] = RewardInfo {
+            reward_schedule_curve: RewardScheduleCurve::from_points(&self.curve_points)?,
+            reward_type: self.reward_type as u8,
+            rewards_per_second_decimals: self.rps_decimals,
+            rewards_available: self.funding,
+            ..Default::default()
+        };
+        if self.scope_price.is_some() {
+            farm_state.scope_oracle_price_id = 0;
+            farm_state.scope_oracle_max_age = u64::MAX;
+        }
+        Ok(farm_state)
+    }
+}
+
+/// Rewards issued during each `interval` from `start_ts` to `end_ts` for a farm holding
+/// `total_staked_amount`, assuming stake is active throughout. The last interval may be
+/// shorter.
+pub fn emissions_per_interval(
+    params: &CampaignParams,
+    total_staked_amount: u64,
+    start_ts: u64,
+    end_ts: u64,
+    interval: u64,
+) -> Result<Vec<u64>> {
+    require!(
+        interval > 0 && start_ts <= end_ts,
+        crate::FarmError::InvalidTimestamp
+    );
+
+    let farm_state = params.to_farm_state(total_staked_amount)?;
+    let reward_info = &farm_state.reward_infos[0];
+    let mut remaining = params.funding;
+    let mut emissions = Vec::new();
+
+    let mut from_ts = start_ts;
+    while from_ts < end_ts {
+        let to_ts = end_ts.min(from_ts.saturating_add(interval));
+        let curve_amt = reward_info
+            .reward_schedule_curve
+            .get_cumulative_amount_issued_since_last_ts(from_ts, to_ts)?;
+        let issued: u64 = scale_curve_amount(
+            &farm_state,
+            reward_info,
+            u128::from(curve_amt),
+            params.scope_price,
+            to_ts,
+        )?
+        .try_into()
+        .unwrap_or(u64::MAX);
+        let issued = issued.min(remaining);
+        remaining -= issued;
+        emissions.push(issued);
+        from_ts = to_ts;
+    }
+
+    Ok(emissions)
+}
+
+/// APR (as a fraction, 0.1 = 10%) seen by stakers over `start_ts..end_ts` for each of
+/// the `tvl_points`, given in raw staked token units.
+pub fn apr_at_tvl_points(
+    params: &CampaignParams,
+    prices: TokenPrices,
+    tvl_points: &[u64],
+    start_ts: u64,
+    end_ts: u64,
+) -> Result<Vec<f64>> {
+    require!(start_ts < end_ts, crate::FarmError::InvalidTimestamp);
+    let duration = end_ts - start_ts;
+
+    tvl_points
+        .iter()
+        .map(|&tvl| {
+            if tvl == 0 {
+                return Ok(0.0);
+            }
+            let issued: u64 = emissions_per_interval(params, tvl, start_ts, end_ts, duration)?
+                .iter()
+                .sum();
+            let issued_value = issued as f64 / 10f64.powi(prices.reward_token_decimals.into())
+                * prices.reward_token_price;
+            let tvl_value = tvl as f64 / 10f64.powi(prices.stake_token_decimals.into())
+                * prices.stake_token_price;
+            Ok(issued_value / tvl_value * SECONDS_PER_YEAR as f64 / duration as f64)
+        })
+        .collect()
+}
@@ -1,3 +1,4 @@
+use std::cmp;
 use std::ops::{Deref, DerefMut};
 
 use decimal_wad::decimal::Decimal;
@@ -7,9 +8,9 @@ use crate::{
     types::VaultWithdrawEffects,
     utils::{
         math::{full_decimal_mul_div, u64_mul_div},
-        withdrawal_penalty::apply_early_withdrawal_penalty,
+        withdrawal_penalty::apply_partial_early_withdrawal_penalty,
     },
-    xmsg, FarmError,
+    vmsg, FarmError,
 };
 
 #[derive(Debug, Copy, Clone, Default, PartialEq, Eq)]
@@ -18,6 +19,8 @@ pub struct UserStake {
     pending_deposit_stake: Decimal,
     pending_withdrawal_unstake: Decimal,
     last_stake_ts: u64,
+    penalty_free_unstaked: u64,
+    penalty_terms_version: u64,
 }
 
 pub trait UserStakeAccessor {
@@ -60,10 +63,14 @@ pub struct FarmStake {
     total_pending_stake: Decimal,
     total_active_amount: u64,
     total_pending_amount: u64,
+    virtual_stake_offset: u64,
+    virtual_amount_offset: u64,
     locking_mode: LockingMode,
     locking_start_timestamp: u64,
     locking_duration: u64,
     locking_early_withdrawal_penalty_bps: u64,
+    penalty_terms_version: u64,
+    grandfathered_penalty_bps: u64,
 }
 
 pub trait FarmStakeAccessor {
@@ -107,6 +114,8 @@ impl UserStakeAccessor for state::UserState {
                 pending_deposit_stake: self.get_pending_deposit_stake_decimal(),
                 pending_withdrawal_unstake: self.get_pending_withdrawal_unstake_decimal(),
                 last_stake_ts: self.last_stake_ts,
+                penalty_free_unstaked: self.penalty_free_unstaked_amount,
+                penalty_terms_version: self.penalty_terms_version,
             },
             src_ref: self,
         }
@@ -116,6 +125,7 @@ impl UserStakeAccessor for state::UserState {
         self.set_active_stake_decimal(abstract_val.active_stake);
         self.set_pending_deposit_stake_decimal(abstract_val.pending_deposit_stake);
         self.set_pending_withdrawal_unstake_decimal(abstract_val.pending_withdrawal_unstake);
+        self.penalty_free_unstaked_amount = abstract_val.penalty_free_unstaked;
     }
 }
 
@@ -127,10 +137,14 @@ impl FarmStakeAccessor for state::FarmState {
                 total_pending_stake: self.get_total_pending_stake_decimal(),
                 total_active_amount: self.total_staked_amount,
                 total_pending_amount: self.total_pending_amount,
+                virtual_stake_offset: self.virtual_stake_offset,
+                virtual_amount_offset: self.virtual_amount_offset,
                 locking_duration: self.locking_duration,
                 locking_early_withdrawal_penalty_bps: self.locking_early_withdrawal_penalty_bps,
                 locking_mode: self.get_locking_mode(),
                 locking_start_timestamp: self.locking_start_timestamp,
+                penalty_terms_version: self.penalty_terms_version,
+                grandfathered_penalty_bps: self.grandfathered_penalty_bps,
             },
             src_ref: self,
         }
@@ -144,12 +158,45 @@ impl FarmStakeAccessor for state::FarmState {
     }
 }
 
-pub fn convert_stake_to_amount(
-    stake: Decimal,
-    total_stake: Decimal,
-    total_amount: u64,
-    round_up: bool,
-) -> u64 {
+// Rounding policy: the protocol always keeps the dust.
+//
+// Every conversion between tokens and stake shares rounds down, in favor of the
+// farm: a deposit mints at most its fair share of stake, and burning stake
+// returns at most its fair share of tokens. A stake -> unstake round trip can
+// therefore never return more tokens than were deposited, the remainder stays
+// in the vault. Delegated farms track stake 1:1 with the amount (see
+// `farm_operations::set_stake`) and don't go through these conversions, so no
+// rounding happens on that path.
+
+// Virtual offsets: when configured, the active stake conversions use
+// `total_active_stake + virtual_stake_offset` and `total_active_amount + virtual_amount_offset`
+// (ERC-4626 style). The virtual position is never withdrawable, so raising the amount per
+// share of a small farm costs the donor most of its donation, and the rounding loss of
+// the next depositor is bounded by the virtual amount per share instead of the donation.
+
+impl FarmStake {
+    fn active_totals_with_offsets(&self) -> (Decimal, u64) {
+        (
+            self.total_active_stake + Decimal::from(self.virtual_stake_offset),
+            self.total_active_amount + self.virtual_amount_offset,
+        )
+    }
+
+    /// Early withdrawal penalty for a position opened under `penalty_terms_version`
+    fn early_withdrawal_penalty_bps(&self, penalty_terms_version: u64) -> u64 {
+        if penalty_terms_version < self.penalty_terms_version {
+            cmp::min(
+                self.grandfathered_penalty_bps,
+                self.locking_early_withdrawal_penalty_bps,
+            )
+        } else {
+            self.locking_early_withdrawal_penalty_bps
+        }
+    }
+}
+
+/// Tokens owed for `stake` shares, rounded down.
+pub fn convert_stake_to_amount(stake: Decimal, total_stake: Decimal, total_amount: u64) -> u64 {
     if stake == Decimal::zero() {
         return 0;
     }
@@ -160,13 +207,10 @@ pub fn convert_stake_to_amount(
         total_amount.into()
     };
 
-    if round_up {
-        amount_dec.try_ceil().unwrap()
-    } else {
-        amount_dec.try_floor().unwrap()
-    }
+    amount_dec.try_floor().unwrap()
 }
 
+/// Stake shares minted for `amount` tokens, rounded down to the decimal precision.
 pub fn convert_amount_to_stake(amount: u64, total_stake: Decimal, total_amount: u64) -> Decimal {
     if amount == 0 {
         return Decimal::zero();
@@ -216,7 +260,6 @@ pub fn remove_pending_deposit_stake(
         user_stake.pending_deposit_stake,
         farm.total_pending_stake,
         farm.total_pending_amount,
-        false,
     );
 
     farm.total_pending_amount -= pending_amount_removed;
@@ -236,11 +279,9 @@ pub fn add_active_stake(
     let mut user_stake = user_stake.get_accessor();
     let mut farm = farm.get_accessor();
 
-    let user_gained_active_stake = convert_amount_to_stake(
-        staked_amount,
-        farm.total_active_stake,
-        farm.total_active_amount,
-    );
+    let (total_active_stake, total_active_amount) = farm.active_totals_with_offsets();
+    let user_gained_active_stake =
+        convert_amount_to_stake(staked_amount, total_active_stake, total_active_amount);
 
     user_stake.active_stake = user_stake.active_stake + user_gained_active_stake;
 
@@ -274,12 +315,9 @@ pub fn remove_active_stake(
         unstaked_shares
     );
 
-    let unstaked_amount: u64 = convert_stake_to_amount(
-        unstaked_shares,
-        farm.total_active_stake,
-        farm.total_active_amount,
-        false,
-    );
+    let (total_active_stake, total_active_amount) = farm.active_totals_with_offsets();
+    let unstaked_amount: u64 =
+        convert_stake_to_amount(unstaked_shares, total_active_stake, total_active_amount);
 
     user_stake.active_stake = user_stake.active_stake - unstaked_shares;
 
@@ -312,41 +350,60 @@ pub fn add_pending_withdrawal_stake(
     Ok(user_gained_pending_stake)
 }
 
+/// Returns the amount moved to pending withdrawal, the pending stake gained, the penalty
+/// and the part of the unstaked amount that was within the unlocked share of the position.
 pub fn unstake(
     user_stake: &mut impl UserStakeAccessor,
     farm: &mut impl FarmStakeAccessor,
     stake_share_to_unstake: Decimal,
     ts: u64,
-) -> Result<(u64, Decimal, u64), FarmError> {
+) -> Result<(u64, Decimal, u64, u64), FarmError> {
+    let position_amount = {
+        let user_accessor = user_stake.get_accessor();
+        let farm_accessor = farm.get_accessor();
+        let (total_active_stake, total_active_amount) = farm_accessor.active_totals_with_offsets();
+        convert_stake_to_amount(
+            user_accessor.active_stake,
+            total_active_stake,
+            total_active_amount,
+        )
+    };
     let amount_to_unstake = remove_active_stake(user_stake, farm, stake_share_to_unstake)?;
 
     let farm_accessor = farm.get_accessor();
-    let user_accessor = user_stake.get_accessor();
-    let (amount_to_unstake_post_penalty, unstake_penalty) = match farm_accessor.locking_mode {
-        LockingMode::None => (amount_to_unstake, 0),
-        LockingMode::WithExpiry => apply_early_withdrawal_penalty(
-            farm_accessor.locking_duration,
-            farm_accessor.locking_start_timestamp,
-            ts,
-            farm_accessor.locking_early_withdrawal_penalty_bps,
-            amount_to_unstake,
-        )?,
-        LockingMode::Continuous => apply_early_withdrawal_penalty(
+    let mut user_accessor = user_stake.get_accessor();
+    let locking_start = match farm_accessor.locking_mode {
+        LockingMode::None => None,
+        LockingMode::WithExpiry => Some(farm_accessor.locking_start_timestamp),
+        LockingMode::Continuous => Some(user_accessor.last_stake_ts),
+    };
+    let (amount_to_unstake_post_penalty, unstake_penalty, unlocked_amount) = match locking_start {
+        None => (amount_to_unstake, 0, amount_to_unstake),
+        Some(locking_start) => apply_partial_early_withdrawal_penalty(
             farm_accessor.locking_duration,
-            user_accessor.last_stake_ts,
+            locking_start,
             ts,
-            farm_accessor.locking_early_withdrawal_penalty_bps,
+            farm_accessor.early_withdrawal_penalty_bps(user_accessor.penalty_terms_version),
             amount_to_unstake,
+            position_amount,
+            user_accessor.penalty_free_unstaked,
         )?,
     };
 
-    if farm_accessor.locking_mode != LockingMode::None {
-        xmsg!(
-            "Unstaking {}, with mode {:?}, got {} and penalty {}",
+    if let Some(locking_start) = locking_start {
+        // Only the unlocked share taken while the lock runs counts against it
+        let lock_running =
+            ts >= locking_start && ts < locking_start + farm_accessor.locking_duration;
+        if lock_running {
+            user_accessor.penalty_free_unstaked += unlocked_amount;
+        }
+        vmsg!(
+            "Unstaking {}, with mode {:?}, got {} and penalty {}, unlocked {}",
             amount_to_unstake,
             farm_accessor.locking_mode,
             amount_to_unstake_post_penalty,
-            unstake_penalty
+            unstake_penalty,
+            unlocked_amount
         );
     }
 
@@ -359,6 +416,7 @@ pub fn unstake(
         amount_to_unstake_post_penalty,
         gained_pending_stake,
         unstake_penalty,
+        unlocked_amount,
     ))
 }
 
@@ -373,7 +431,6 @@ pub fn remove_pending_withdrawal_stake(
         user_stake.pending_withdrawal_unstake,
         farm.total_pending_stake,
         farm.total_pending_amount,
-        false,
     );
 
     farm.total_pending_amount -= pending_amount_removed;
@@ -405,7 +462,7 @@ pub fn withdraw_farm(
     if req_withdraw_amount >= vault_amount {
         farm.total_active_amount = 0;
         farm.total_pending_amount = 0;
-        xmsg!("Withdraw all farm vault (left frozen): {vault_amount}");
+        vmsg!("Withdraw all farm vault (left frozen): {vault_amount}");
         return Ok(VaultWithdrawEffects {
             amount_to_withdraw: vault_amount,
             farm_to_freeze: true,
@@ -422,7 +479,7 @@ pub fn withdraw_farm(
 
     let amount_to_withdraw = removed_active_amount + removed_pending_amount;
 
-    xmsg!("Withdraw farm vault: {removed_active_amount} (active) + {removed_pending_amount} (pending) = {amount_to_withdraw} / {req_withdraw_amount}");
+    vmsg!("Withdraw farm vault: {removed_active_amount} (active) + {removed_pending_amount} (pending) = {amount_to_withdraw} / {req_withdraw_amount}");
 
     Ok(VaultWithdrawEffects {
         amount_to_withdraw,
@@ -1,15 +1,20 @@
 #![allow(clippy::derivable_impls)]
 
 use crate::{
+    types::DelegatedStake,
     utils::{consts::REWARD_CURVE_POINTS, math::ten_pow},
-    xmsg,
+    vmsg, xmsg,
 };
 use anchor_lang::prelude::*;
+use anchor_lang::solana_program::hash::Hasher;
 use bytemuck::{Pod, Zeroable};
 use scope::DatedPrice;
 
 use crate::{
-    utils::consts::{self, MAX_REWARDS_TOKENS},
+    utils::consts::{
+        self, FARM_CONFIG_AUTHORITY_GROUPS, MAX_ADDITIONAL_DELEGATE_AUTHORITIES,
+        MAX_ALLOWLISTED_MINTS, MAX_ALLOWLISTED_TRANSFER_HOOK_PROGRAMS, MAX_REWARDS_TOKENS,
+    },
     FarmError,
 };
 use anchor_lang::prelude::Pubkey;
@@ -33,7 +38,48 @@ pub struct GlobalConfig {
 
     pub pending_global_admin: Pubkey,
 
-    pub _padding1: [u128; 126],
+    pub num_farms: u64,
+    /// Maximum number of farms that can be created under this config, 0 means unlimited
+    pub max_farms: u64,
+
+    /// Applied to the farms created by `initialize_farm`, existing farms are not affected
+    pub default_deposit_warmup_period: u32,
+    pub default_withdrawal_cooldown_period: u32,
+    pub default_deposit_cap_amount: u64,
+
+    /// Can only set farm pause flags, unpausing is done by the global admin
+    pub emergency_authority: Pubkey,
+
+    /// Delay between queuing and executing a treasury withdrawal, in seconds. When 0,
+    /// `withdraw_treasury` can be used directly
+    pub treasury_withdrawal_delay_s: u64,
+    pub num_treasury_withdrawal_requests: u64,
+
+    /// Mints accepted despite a freeze authority on farm tokens, or a Token-2022
+    /// permanent delegate or close authority on farm and reward tokens.
+    /// `Pubkey::default()` for empty slots
+    pub allowlisted_mints: [Pubkey; MAX_ALLOWLISTED_MINTS],
+
+    /// Bounds of the per-farm harvest fee overrides, which are ignored while
+    /// `max_harvest_fee_bps` is 0
+    pub min_harvest_fee_bps: u64,
+    pub max_harvest_fee_bps: u64,
+
+    /// Bitmask of the `ProgramInstruction`s rejected with `InstructionDisabled`
+    pub disabled_instructions: u128,
+
+    /// `add_rewards` logs a warning when the reward funding covers less of the
+    /// scheduled emissions than this, disabled when 0
+    pub min_reward_funding_coverage_bps: u64,
+    /// Share of the treasury fee of a harvest credited to the `ReferrerState` passed
+    /// by the harvester, disabled when 0
+    pub referral_fee_bps: u64,
+
+    /// Token-2022 transfer hook programs accepted on farm tokens, reward tokens
+    /// cannot have a transfer hook. `Pubkey::default()` for empty slots
+    pub allowlisted_transfer_hook_programs: [Pubkey; MAX_ALLOWLISTED_TRANSFER_HOOK_PROGRAMS],
+
+    pub _padding1: [u128; 94],
 }
 
 impl Default for GlobalConfig {
@@ -45,7 +91,23 @@ impl Default for GlobalConfig {
             treasury_vaults_authority_bump: 0,
             treasury_fee_bps: 0,
             pending_global_admin: Pubkey::default(),
-            _padding1: [0; 126],
+            num_farms: 0,
+            max_farms: 0,
+            default_deposit_warmup_period: 0,
+            default_withdrawal_cooldown_period: 0,
+            default_deposit_cap_amount: 0,
+            emergency_authority: Pubkey::default(),
+            treasury_withdrawal_delay_s: 0,
+            num_treasury_withdrawal_requests: 0,
+            allowlisted_mints: [Pubkey::default(); MAX_ALLOWLISTED_MINTS],
+            min_harvest_fee_bps: 0,
+            max_harvest_fee_bps: 0,
+            disabled_instructions: 0,
+            min_reward_funding_coverage_bps: 0,
+            referral_fee_bps: 0,
+            allowlisted_transfer_hook_programs: [Pubkey::default();
+                MAX_ALLOWLISTED_TRANSFER_HOOK_PROGRAMS],
+            _padding1: [0; 94],
         }
     }
 }
@@ -57,6 +119,172 @@ impl Default for GlobalConfig {
 pub enum GlobalConfigOption {
     SetPendingGlobalAdmin = 0,
     SetTreasuryFeeBps = 1,
+    SetMaxFarms = 2,
+    SetDefaultDepositWarmupPeriod = 3,
+    SetDefaultWithdrawalCooldownPeriod = 4,
+    SetDefaultDepositCapAmount = 5,
+    SetEmergencyAuthority = 6,
+    SetTreasuryWithdrawalDelay = 7,
+    AddAllowlistedMint = 8,
+    RemoveAllowlistedMint = 9,
+    SetMinHarvestFeeBps = 10,
+    SetMaxHarvestFeeBps = 11,
+    SetMinRewardFundingCoverageBps = 12,
+    AddAllowlistedTransferHookProgram = 13,
+    RemoveAllowlistedTransferHookProgram = 14,
+    SetReferralFeeBps = 15,
+}
+
+/// Bit of each instruction in `GlobalConfig::disabled_instructions`, in the order of
+/// the `#[program]` module. New instructions are appended, `idl_missing_types` has no bit.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug,
+)]
+#[repr(u8)]
+pub enum ProgramInstruction {
+    InitializeGlobalConfig = 0,
+    UpdateGlobalConfig = 1,
+    InitializeFarm = 2,
+    InitializeFarmDelegated = 3,
+    InitializeReward = 4,
+    AddRewards = 5,
+    UpdateFarmConfig = 6,
+    InitializeUser = 7,
+    TransferOwnership = 8,
+    RewardUserOnce = 9,
+    CreditUsers = 10,
+    RefreshFarm = 11,
+    Stake = 12,
+    StakeWithTokenDelegate = 13,
+    SetStakeDelegated = 14,
+    HarvestReward = 15,
+    Unstake = 16,
+    RefreshUserState = 17,
+    WithdrawUnstakedDeposits = 18,
+    WithdrawTreasury = 19,
+    DepositToFarmVault = 20,
+    WithdrawFromFarmVault = 21,
+    WithdrawSlashedAmount = 22,
+    UpdateFarmAdmin = 23,
+    UpdateGlobalConfigAdmin = 24,
+    WithdrawReward = 25,
+    InitializeReferrerState = 26,
+    ClaimReferralFeesAll = 27,
+    TransferStakeInternal = 28,
+    SplitStake = 29,
+    InitializeFarmRegistry = 30,
+    GetFarmRegistryPage = 31,
+    InitializeUserPage = 32,
+    InitializeFarmFeeVault = 33,
+    WithdrawFarmFees = 34,
+    GetDepositCapacity = 35,
+    GetFarmStatus = 36,
+    GetFarmTvl = 37,
+    GetHarvestEligibility = 38,
+    CloneFarm = 39,
+    CancelPendingFarmAdmin = 40,
+    UpdateFarmPauseFlags = 41,
+    QueueTreasuryWithdrawal = 42,
+    ExecuteTreasuryWithdrawal = 43,
+    CancelTreasuryWithdrawal = 44,
+    ReportRewardShortfall = 45,
+    WithdrawTreasuryAll = 46,
+    SetRewardDestination = 47,
+    SyncMirroredStake = 48,
+    HarvestAndStakeOther = 49,
+    UpdateDisabledInstructions = 50,
+    HarvestToOwner = 51,
+    CommitRaffle = 52,
+    SettleRaffle = 53,
+    GetTreasuryBalances = 54,
+    RenouncePosition = 55,
+    InitializeFarmHistory = 56,
+    GetFarmHistory = 57,
+    SnapshotFarmForUpgrade = 58,
+    GetUpgradeSnapshots = 59,
+    SweepDivertedRewards = 60,
+    HarvestAcrossFarms = 61,
+    StakeWithOptions = 62,
+    UnstakeWithMinAmountOut = 63,
+    RefreshUserStateMasked = 64,
+    InitializeWithdrawalQueue = 65,
+    QueueWithdrawal = 66,
+    ProcessWithdrawalQueue = 67,
+    CancelQueuedWithdrawal = 68,
+    CloseUser = 69,
+}
+
+impl ProgramInstruction {
+    pub const fn mask(self) -> u128 {
+        1 << self as u8
+    }
+
+    /// Instructions that keep working whatever `disabled_instructions` says: the global
+    /// config administration, the emergency pauses, the upgrade snapshots and the
+    /// read-only views
+    pub fn can_be_disabled(self) -> bool {
+        use ProgramInstruction::*;
+        !matches!(
+            self,
+            InitializeGlobalConfig
+                | UpdateGlobalConfig
+                | UpdateGlobalConfigAdmin
+                | UpdateFarmPauseFlags
+                | UpdateDisabledInstructions
+                | GetFarmRegistryPage
+                | GetDepositCapacity
+                | GetFarmStatus
+                | GetFarmTvl
+                | GetHarvestEligibility
+                | GetTreasuryBalances
+                | GetFarmHistory
+                | SnapshotFarmForUpgrade
+                | GetUpgradeSnapshots
+        )
+    }
+
+    /// Exits that only the global admin can disable, the emergency authority cannot
+    pub fn is_exit(self) -> bool {
+        use ProgramInstruction::*;
+        matches!(
+            self,
+            Unstake
+                | UnstakeWithMinAmountOut
+                | WithdrawUnstakedDeposits
+                | WithdrawTreasury
+                | WithdrawFromFarmVault
+                | WithdrawSlashedAmount
+                | WithdrawReward
+                | WithdrawFarmFees
+                | WithdrawTreasuryAll
+                | QueueWithdrawal
+                | ProcessWithdrawalQueue
+                | CancelQueuedWithdrawal
+        )
+    }
+}
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug,
+)]
+#[repr(u8)]
+pub enum FarmPauseFlag {
+    Stake = 1 << 0,
+    Unstake = 1 << 1,
+    Harvest = 1 << 2,
+    WithdrawUnstakedDeposits = 1 << 3,
+}
+
+/// Groups of `FarmConfigOption` modes that can be delegated to a distinct authority
+/// through `FarmState::config_authorities`, indexed by the group value
+#[derive(
+    AnchorSerialize, AnchorDeserialize, TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug,
+)]
+#[repr(u8)]
+pub enum FarmConfigAuthorityGroup {
+    Emissions = 0,
+    Risk = 1,
+    Metadata = 2,
 }
 
 // static_assertions::const_assert_eq!(0, std::mem::size_of::<FarmState>() % 8);
@@ -90,7 +318,12 @@ pub struct FarmState {
 
     pub is_farm_delegated: u8,
 
-    pub _padding0: [u8; 5],
+    pub user_pages_enabled: u8,
+
+    /// Bitmask of `FarmPauseFlag`
+    pub pause_flags: u8,
+
+    pub _padding0: [u8; 3],
 
     pub withdraw_authority: Pubkey,
 
@@ -119,7 +352,121 @@ pub struct FarmState {
 
     pub pending_farm_admin: Pubkey,
     pub strategy_id: Pubkey,
-    pub _padding: [u64; 86],
+
+    pub hook_program: Pubkey,
+    pub hook_config: Pubkey,
+
+    /// Farm token vault owned by the treasury authority collecting the early withdrawal
+    /// penalties, `Pubkey::default()` while not initialized
+    pub fee_vault: Pubkey,
+    /// Penalties still held in `farm_vault`, moved to `fee_vault` on `withdraw_farm_fees`
+    pub fees_pending_amount: u64,
+    pub fees_cumulative_amount: u64,
+
+    /// Virtual stake (in whole shares) and virtual amount added to the active totals
+    /// when converting between amount and stake, both zero when disabled
+    pub virtual_stake_offset: u64,
+    pub virtual_amount_offset: u64,
+
+    /// No new users can be created from this timestamp on (in `time_unit`), 0 to disable
+    pub new_users_cutoff_ts: u64,
+
+    /// Maximum amount `withdraw_authority` can take out of the farm vault per rolling
+    /// window of `admin_withdrawal_window_s` seconds, 0 when unlimited
+    pub max_admin_withdrawal_per_window: u64,
+    pub admin_withdrawal_window_s: u64,
+    pub admin_withdrawal_window_start_ts: u64,
+    pub admin_withdrawn_in_window: u64,
+
+    /// Authorities allowed to update the modes of each `FarmConfigAuthorityGroup`,
+    /// `Pubkey::default()` when the group is only accessible to `farm_admin`
+    pub config_authorities: [Pubkey; FARM_CONFIG_AUTHORITY_GROUPS],
+
+    /// Set on delegated farms mirroring the active stake of the users of `primary_farm`
+    /// through `sync_mirrored_stake` instead of `set_stake_delegated`
+    pub primary_farm: Pubkey,
+    /// Harvests require a sync of the user stake at most this long ago (in `time_unit`)
+    pub mirror_max_staleness: u64,
+
+    /// Users with an active stake of at least `min_active_user_stake` as of their last
+    /// refresh, each accruing the full rate of the `ConstantPerUser` rewards
+    pub active_user_count: u64,
+    pub min_active_user_stake: u64,
+
+    /// `LockTopUpPolicy`, how staking into an existing position moves its
+    /// `Continuous` lock
+    pub lock_top_up_policy: u64,
+
+    /// Replaces `GlobalConfig::treasury_fee_bps` on harvests when enabled, clamped to
+    /// the global harvest fee bounds in force at the time of the harvest
+    pub harvest_fee_override_enabled: u64,
+    pub harvest_fee_bps_override: u64,
+
+    /// Nonce of the last `credit_users` batch, each batch must use a higher one
+    pub last_credit_batch_nonce: u64,
+
+    /// When enabled, stake and unstake fail unless `refresh_farm` ran for this farm in
+    /// the current slot, see `last_refresh_slot`
+    pub require_refresh_in_slot: u64,
+    /// Slot of the last `refresh_farm`
+    pub last_refresh_slot: u64,
+
+    /// Bumped whenever the early withdrawal penalty is enabled or increased. Positions
+    /// opened under an older version pay at most `grandfathered_penalty_bps`
+    pub penalty_terms_version: u64,
+    /// Lowest early withdrawal penalty in force since the first `penalty_terms_version`
+    /// bump, 0 while locking was disabled
+    pub grandfathered_penalty_bps: u64,
+
+    /// Raffles committed on this farm, the id of the next `RaffleState`
+    pub num_raffles: u64,
+
+    /// Minimum time between two `FarmHistory` snapshots, in the farm `time_unit`.
+    /// 0 disables the snapshots
+    pub history_snapshot_interval: u64,
+
+    /// Program version marker of the last `snapshot_farm_for_upgrade`, 0 when the farm
+    /// was never snapshotted
+    pub upgrade_snapshot_marker: u64,
+    /// Slot of the last `snapshot_farm_for_upgrade`
+    pub upgrade_snapshot_slot: u64,
+    /// `accumulators_hash` right after the last `snapshot_farm_for_upgrade`
+    pub upgrade_snapshot_accumulators_hash: [u8; 32],
+    /// Stake and unstake fail before this slot, set by `snapshot_farm_for_upgrade`
+    pub upgrade_pause_until_slot: u64,
+
+    /// When enabled, the `Proportional` emissions of the periods without stake are
+    /// diverted to the treasury, see `RewardInfo::rewards_diverted_to_treasury`.
+    /// Otherwise nothing is issued and they stay in `rewards_available`
+    pub zero_stake_emissions_to_treasury: u64,
+
+    /// Maximum amount paid out of the pending withdrawals of all users per rolling
+    /// window of `user_withdrawal_window_s` seconds, 0 when unlimited
+    pub max_user_withdrawal_per_window: u64,
+    pub user_withdrawal_window_s: u64,
+    pub user_withdrawal_window_start_ts: u64,
+    pub user_withdrawn_in_window: u64,
+
+    /// `WithdrawalQueue` of the farm, `Pubkey::default()` until initialized
+    pub withdrawal_queue: Pubkey,
+    /// Amount of the queued withdrawals, still in the farm vault but outside of the
+    /// pending stake
+    pub queued_withdrawal_amount: u64,
+    pub num_queued_withdrawals: u64,
+
+    /// Allowed to `set_stake_delegated` besides `delegate_authority`, `Pubkey::default()`
+    /// for empty slots
+    pub additional_delegate_authorities: [Pubkey; MAX_ADDITIONAL_DELEGATE_AUTHORITIES],
+    /// Active stake last set by each of `additional_delegate_authorities`, the rest of
+    /// `total_staked_amount` is attributed to `delegate_authority`
+    pub additional_delegate_authorities_stake: [u64; MAX_ADDITIONAL_DELEGATE_AUTHORITIES],
+
+    /// When enabled, `initialize_reward` accepts a mint already used by another reward
+    /// of the farm. The vault of such a reward is seeded by its reward index on top of
+    /// the farm and the mint
+    pub allow_duplicate_reward_mints: u64,
+
+    pub _padding: [u64; 7],
 }
 
 impl FarmState {
@@ -139,23 +486,148 @@ impl FarmState {
         self.total_pending_stake_scaled = value.to_scaled_val().unwrap();
     }
 
+    pub fn has_reward_mint(&self, mint: &Pubkey) -> bool {
+        self.reward_infos[..self.num_reward_tokens as usize]
+            .iter()
+            .any(|reward_info| reward_info.token.mint == *mint)
+    }
+
+    pub fn can_initialize_reward_mint(&self, mint: &Pubkey) -> bool {
+        self.allow_duplicate_reward_mints != 0 || !self.has_reward_mint(mint)
+    }
+
+    /// Last seed of the vault of the reward at `reward_index` for `mint`: empty for the
+    /// first reward of the mint, the reward index for its duplicates
+    pub fn reward_vault_seed_suffix(&self, reward_index: usize, mint: &Pubkey) -> &'static [u8] {
+        static REWARD_INDICES: [u8; MAX_REWARDS_TOKENS] = [0, 1, 2, 3, 4, 5, 6, 7, 8, 9];
+        let reward_index = reward_index.min(MAX_REWARDS_TOKENS - 1);
+        if self.reward_infos[..reward_index]
+            .iter()
+            .any(|reward_info| reward_info.token.mint == *mint)
+        {
+            &REWARD_INDICES[reward_index..=reward_index]
+        } else {
+            &[]
+        }
+    }
+
+    pub fn new_reward_vault_seed_suffix(&self, mint: &Pubkey) -> &'static [u8] {
+        self.reward_vault_seed_suffix(self.num_reward_tokens as usize, mint)
+    }
+
     pub fn is_delegated(&self) -> bool {
         self.delegate_authority != Pubkey::default()
     }
 
+    /// `UserState::delegate_authority_index` of `authority`, 0 for `delegate_authority`
+    /// and `i + 1` for `additional_delegate_authorities[i]`
+    pub fn delegate_authority_index(&self, authority: &Pubkey) -> Option<u8> {
+        if *authority == Pubkey::default() {
+            return None;
+        }
+        if *authority == self.delegate_authority {
+            return Some(0);
+        }
+        self.additional_delegate_authorities
+            .iter()
+            .position(|a| a == authority)
+            .map(|i| i as u8 + 1)
+    }
+
+    /// Active stake attributed to the delegate authority of `authority_index`
+    pub fn delegated_stake(&self, authority_index: u8) -> u64 {
+        match (authority_index as usize).checked_sub(1) {
+            Some(i) => self.additional_delegate_authorities_stake[i],
+            None => self
+                .total_staked_amount
+                .saturating_sub(self.additional_delegate_authorities_stake.iter().sum()),
+        }
+    }
+
+    /// Active stake attributed to each delegate authority, `delegate_authority` first
+    pub fn delegated_stake_by_authority(&self) -> Vec<DelegatedStake> {
+        if !self.is_delegated() {
+            return Vec::new();
+        }
+        let authorities = std::iter::once(self.delegate_authority)
+            .chain(self.additional_delegate_authorities.iter().copied());
+        authorities
+            .enumerate()
+            .filter(|(_, authority)| *authority != Pubkey::default())
+            .map(|(index, authority)| DelegatedStake {
+                authority,
+                stake: self.delegated_stake(index as u8),
+            })
+            .collect()
+    }
+
+    pub fn is_paused(&self, flag: FarmPauseFlag) -> bool {
+        self.pause_flags & flag as u8 != 0
+    }
+
+    pub fn is_upgrade_paused(&self, slot: u64) -> bool {
+        slot < self.upgrade_pause_until_slot
+    }
+
+    /// Hash of the stake totals and of the reward accumulators, which any accrual or
+    /// stake change since `upgrade_snapshot_slot` alters
+    pub fn accumulators_hash(&self) -> [u8; 32] {
+        let mut hasher = Hasher::default();
+        hasher.hash(&self.total_staked_amount.to_le_bytes());
+        hasher.hash(&self.total_active_stake_scaled.to_le_bytes());
+        hasher.hash(&self.total_pending_stake_scaled.to_le_bytes());
+        for reward_info in &self.reward_infos[..self.num_reward_tokens as usize] {
+            hasher.hash(&reward_info.reward_per_share_scaled.to_le_bytes());
+            hasher.hash(&reward_info.last_issuance_ts.to_le_bytes());
+            hasher.hash(&reward_info.rewards_available.to_le_bytes());
+            hasher.hash(&reward_info.rewards_issued_unclaimed.to_le_bytes());
+            hasher.hash(&reward_info.rewards_issued_cumulative.to_le_bytes());
+        }
+        hasher.result().to_bytes()
+    }
+
+    pub fn is_mirror(&self) -> bool {
+        self.primary_farm != Pubkey::default()
+    }
+
+    pub fn has_fee_vault(&self) -> bool {
+        self.fee_vault != Pubkey::default()
+    }
+
+    pub fn has_stake_hook(&self) -> bool {
+        self.hook_program != Pubkey::default()
+    }
+
     pub fn get_locking_mode(&self) -> LockingMode {
         LockingMode::try_from(self.locking_mode).unwrap()
     }
 
+    pub fn get_lock_top_up_policy(&self) -> LockTopUpPolicy {
+        LockTopUpPolicy::try_from(self.lock_top_up_policy).unwrap()
+    }
+
     pub fn can_accept_deposit(
         &self,
         amount: u64,
         scope_price: Option<DatedPrice>,
         ts: u64,
     ) -> Result<bool> {
-        let unadjusted_total = self.total_staked_amount + amount;
-        let final_amount = if self.scope_oracle_price_id == u64::MAX {
-            unadjusted_total
+        Ok(self
+            .remaining_deposit_capacity(scope_price, ts)?
+            .map_or(true, |remaining| amount <= remaining))
+    }
+
+    /// Amount of tokens that can still be staked before reaching `deposit_cap_amount`,
+    /// `None` when the farm is uncapped. With an oracle, the cap is in quote units and
+    /// a deposit is accepted as long as `floor(total * price / 10^exp) <= cap`.
+    pub fn remaining_deposit_capacity(
+        &self,
+        scope_price: Option<DatedPrice>,
+        ts: u64,
+    ) -> Result<Option<u64>> {
+        let total = u128::from(self.total_staked_amount);
+        let max_total = if self.scope_oracle_price_id == u64::MAX {
+            u128::from(self.deposit_cap_amount)
         } else {
             let price = scope_price.ok_or(FarmError::MissingScopePrices)?;
             if ts - price.unix_timestamp > self.scope_oracle_max_age {
@@ -167,16 +639,22 @@ impl FarmState {
                 );
                 return Err(FarmError::ScopeOraclePriceTooOld.into());
             } else {
-                xmsg!("Price: {:?}", price);
-                let unadjusted_total = u128::from(unadjusted_total);
+                vmsg!("Price: {:?}", price);
                 let price_value = u128::from(price.price.value);
                 let price_ten_pow = u128::from(ten_pow(price.price.exp as usize));
-                (unadjusted_total * price_value / price_ten_pow)
-                    .try_into()
-                    .unwrap()
+                if price_value == 0 {
+                    return Ok(None);
+                }
+                ((u128::from(self.deposit_cap_amount) + 1) * price_ten_pow - 1) / price_value
             }
         };
-        Ok(self.deposit_cap_amount == 0 || final_amount <= self.deposit_cap_amount)
+
+        if self.deposit_cap_amount == 0 {
+            return Ok(None);
+        }
+
+        let remaining = max_total.saturating_sub(total);
+        Ok(Some(remaining.try_into().unwrap_or(u64::MAX)))
     }
 }
 
@@ -203,7 +681,9 @@ impl Default for FarmState {
             is_farm_frozen: 0,
             is_farm_delegated: 0,
 
-            _padding0: [0; 5],
+            user_pages_enabled: 0,
+            pause_flags: 0,
+            _padding0: [0; 3],
 
             withdraw_authority: Pubkey::default(),
 
@@ -232,7 +712,54 @@ impl Default for FarmState {
             pending_farm_admin: Pubkey::default(),
             strategy_id: Pubkey::default(),
 
-            _padding: [0; 86],
+            hook_program: Pubkey::default(),
+            hook_config: Pubkey::default(),
+
+            fee_vault: Pubkey::default(),
+            fees_pending_amount: 0,
+            fees_cumulative_amount: 0,
+
+            virtual_stake_offset: 0,
+            virtual_amount_offset: 0,
+
+            new_users_cutoff_ts: 0,
+            max_admin_withdrawal_per_window: 0,
+            admin_withdrawal_window_s: 0,
+            admin_withdrawal_window_start_ts: 0,
+            admin_withdrawn_in_window: 0,
+            config_authorities: [Pubkey::default(); FARM_CONFIG_AUTHORITY_GROUPS],
+            primary_farm: Pubkey::default(),
+            mirror_max_staleness: 0,
+            active_user_count: 0,
+            min_active_user_stake: 0,
+            lock_top_up_policy: 0,
+            harvest_fee_override_enabled: 0,
+            harvest_fee_bps_override: 0,
+            last_credit_batch_nonce: 0,
+            require_refresh_in_slot: 0,
+            last_refresh_slot: 0,
+            penalty_terms_version: 0,
+            grandfathered_penalty_bps: 0,
+            num_raffles: 0,
+            history_snapshot_interval: 0,
+            upgrade_snapshot_marker: 0,
+            upgrade_snapshot_slot: 0,
+            upgrade_snapshot_accumulators_hash: [0; 32],
+            upgrade_pause_until_slot: 0,
+            zero_stake_emissions_to_treasury: 0,
+            max_user_withdrawal_per_window: 0,
+            user_withdrawal_window_s: 0,
+            user_withdrawal_window_start_ts: 0,
+            user_withdrawn_in_window: 0,
+            withdrawal_queue: Pubkey::default(),
+            queued_withdrawal_amount: 0,
+            num_queued_withdrawals: 0,
+            additional_delegate_authorities: [Pubkey::default();
+                MAX_ADDITIONAL_DELEGATE_AUTHORITIES],
+            additional_delegate_authorities_stake: [0; MAX_ADDITIONAL_DELEGATE_AUTHORITIES],
+            allow_duplicate_reward_mints: 0,
+
+            _padding: [0; 7],
         }
     }
 }
@@ -343,19 +870,11 @@ impl RewardScheduleCurve {
         Ok(())
     }
 
-    fn most_recent_curve_starting_point(&self, last_issued_ts: u64) -> Result<usize> {
-        for (i, point) in self.points.iter().enumerate() {
-            if point.ts_start > last_issued_ts {
-                return if i > 0 {
-                    Ok(i - 1)
-                } else {
-                    msg!("Invalid curve: first point has ts_start > last_issued_ts");
-                    err!(FarmError::InvalidRpsCurvePoint)
-                };
-            }
-        }
-        Ok(self.points.len() - 1)
+    /// Last point started at `ts`, `None` before the first point of the curve
+    fn most_recent_curve_starting_point(&self, ts: u64) -> Option<usize> {
+        self.points.iter().rposition(|point| point.ts_start <= ts)
     }
+
     pub fn get_cumulative_amount_issued_since_last_ts(
         &self,
         last_issued_ts: u64,
@@ -368,7 +887,10 @@ impl RewardScheduleCurve {
 
         let mut cumulative_amount = 0u64;
 
-        let start_index = self.most_recent_curve_starting_point(last_issued_ts)?;
+        // Nothing is issued before the first point
+        let start_index = self
+            .most_recent_curve_starting_point(last_issued_ts)
+            .unwrap_or(0);
 
         for i in start_index..self.points.len() {
             let point = &self.points[i];
@@ -395,9 +917,310 @@ impl RewardScheduleCurve {
         Ok(cumulative_amount)
     }
 
+    /// Amount issued from `current_ts` to the last point, which must issue nothing.
+    /// `None` when the last point keeps issuing forever
+    pub fn get_remaining_amount_scheduled(&self, current_ts: u64) -> Result<Option<u64>> {
+        let last_point = self
+            .points
+            .iter()
+            .rev()
+            .find(|point| point.ts_start != u64::MAX)
+            .unwrap_or(&self.points[0]);
+        if last_point.reward_per_time_unit != 0 {
+            return Ok(None);
+        }
+
+        let start_ts = current_ts.max(self.points[0].ts_start);
+        if start_ts >= last_point.ts_start {
+            return Ok(Some(0));
+        }
+        self.get_cumulative_amount_issued_since_last_ts(start_ts, last_point.ts_start)
+            .map(Some)
+    }
+
     pub fn get_current_rps(&self, current_ts: u64) -> Result<u64> {
-        let index = self.most_recent_curve_starting_point(current_ts)?;
-        Ok(self.points[index].reward_per_time_unit)
+        Ok(self
+            .most_recent_curve_starting_point(current_ts)
+            .map_or(0, |index| self.points[index].reward_per_time_unit))
+    }
+}
+
+// static_assertions::const_assert_eq!(0, std::mem::size_of::<ReferrerState>() % 8);
+// static_assertions::const_assert_eq!(
+//     consts::SIZE_REFERRER_STATE,
+//     std::mem::size_of::<ReferrerState>() + 8
+// );
+#[account(zero_copy)]
+#[derive(Debug, Eq, PartialEq)]
+pub struct ReferrerState {
+    pub farm_state: Pubkey,
+    pub referrer: Pubkey,
+
+    pub fees_accrued: [u64; MAX_REWARDS_TOKENS],
+    pub fees_claimed_cumulative: [u64; MAX_REWARDS_TOKENS],
+
+    pub bump: u64,
+
+    pub _padding: [u64; 32],
+}
+
+impl Default for ReferrerState {
+    fn default() -> ReferrerState {
+        ReferrerState {
+            farm_state: Pubkey::default(),
+            referrer: Pubkey::default(),
+
+            fees_accrued: [0; MAX_REWARDS_TOKENS],
+            fees_claimed_cumulative: [0; MAX_REWARDS_TOKENS],
+
+            bump: 0,
+
+            _padding: [0; 32],
+        }
+    }
+}
+
+// static_assertions::const_assert_eq!(0, std::mem::size_of::<TreasuryWithdrawalRequest>() % 8);
+// static_assertions::const_assert_eq!(
+//     consts::SIZE_TREASURY_WITHDRAWAL_REQUEST,
+//     std::mem::size_of::<TreasuryWithdrawalRequest>() + 8
+// );
+#[account(zero_copy)]
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct TreasuryWithdrawalRequest {
+    pub global_config: Pubkey,
+    pub request_id: u64,
+
+    pub mint: Pubkey,
+    pub destination: Pubkey,
+    pub amount: u64,
+
+    /// Unix timestamp from which the request can be executed
+    pub executable_ts: u64,
+    pub bump: u64,
+
+    pub _padding: [u64; 8],
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct QueuedWithdrawal {
+    pub user_state: Pubkey,
+    pub amount: u64,
+    /// Unix timestamp of `queue_withdrawal`
+    pub request_ts: u64,
+}
+
+/// Withdrawals blocked by `FarmState::max_user_withdrawal_per_window`, paid out in
+/// order by `process_withdrawal_queue`. Grows by one entry per queued withdrawal.
+#[account]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct WithdrawalQueue {
+    pub farm_state: Pubkey,
+    pub bump: u8,
+    pub withdrawals: Vec<QueuedWithdrawal>,
+}
+
+impl WithdrawalQueue {
+    pub fn space(num_withdrawals: usize) -> usize {
+        consts::SIZE_WITHDRAWAL_QUEUE_BASE + num_withdrawals * consts::SIZE_QUEUED_WITHDRAWAL
+    }
+}
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug,
+)]
+#[repr(u8)]
+pub enum RaffleStatus {
+    /// Waiting for the block hash of `slot_target`
+    Committed = 0,
+    /// Seed drawn, walking the user pages
+    Drawing = 1,
+    /// Winner known, waiting for its user state to be credited
+    Drawn = 2,
+    Settled = 3,
+    /// The block hash of `slot_target` was no longer available, the prize went back
+    /// to the reward
+    Cancelled = 4,
+}
+
+// static_assertions::const_assert_eq!(0, std::mem::size_of::<RaffleState>() % 8);
+// static_assertions::const_assert_eq!(
+//     consts::SIZE_RAFFLE_STATE,
+//     std::mem::size_of::<RaffleState>() + 8
+// );
+/// Stake-weighted draw of `prize_amount` of a reward among the users of a farm.
+///
+/// The seed comes from the hash of the first block at or after `slot_target`. The
+/// winner is then drawn over the cumulative stake distribution, walking the user pages
+/// in `user_id` order: each eligible user replaces the current winner with probability
+/// `stake / cumulative_stake`.
+#[account(zero_copy)]
+#[derive(Debug, Default, Eq, PartialEq)]
+pub struct RaffleState {
+    pub farm_state: Pubkey,
+    pub raffle_id: u64,
+    pub reward_index: u64,
+    /// Taken out of `RewardInfo::rewards_available` on commit
+    pub prize_amount: u64,
+    pub slot_target: u64,
+    /// `FarmState::num_users` on commit, users created later are not walked
+    pub num_users: u64,
+    /// Next `user_id` to walk
+    pub next_user_id: u64,
+    pub bump: u64,
+
+    pub status: u8,
+    pub _padding_0: [u8; 7],
+
+    pub seed: [u8; 32],
+    /// Stake of the eligible users walked so far
+    pub cumulative_stake: u128,
+    /// User state of the current winner, `Pubkey::default()` until one is drawn
+    pub winner: Pubkey,
+
+    pub _padding: [u64; 16],
+}
+
+impl RaffleState {
+    pub fn get_status(&self) -> RaffleStatus {
+        RaffleStatus::try_from(self.status).unwrap()
+    }
+}
+
+#[derive(
+    AnchorSerialize, AnchorDeserialize, TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug,
+)]
+#[repr(u8)]
+pub enum FarmRegistryStatus {
+    Active = 0,
+    Closed = 1,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FarmRegistryEntry {
+    pub farm: Pubkey,
+    pub status: u8,
+}
+
+/// Growable list of the farms created under a `GlobalConfig`, one entry appended
+/// per farm initialization.
+#[account]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FarmRegistry {
+    pub global_config: Pubkey,
+    pub bump: u8,
+    pub farms: Vec<FarmRegistryEntry>,
+}
+
+impl FarmRegistry {
+    pub fn space(num_farms: usize) -> usize {
+        consts::SIZE_FARM_REGISTRY_BASE + num_farms * consts::SIZE_FARM_REGISTRY_ENTRY
+    }
+}
+
+// static_assertions::const_assert_eq!(0, std::mem::size_of::<UserPage>() % 8);
+// static_assertions::const_assert_eq!(
+//     consts::SIZE_USER_PAGE,
+//     std::mem::size_of::<UserPage>() + 8
+// );
+/// Fixed-size page of the `UserState` addresses of a farm, in `user_id` order.
+/// User `user_id` lives in page `user_id / USER_PAGE_CAPACITY`, at slot
+/// `user_id % USER_PAGE_CAPACITY`; a `Pubkey::default()` slot is a tombstone.
+#[account(zero_copy)]
+#[derive(Debug, Eq, PartialEq)]
+pub struct UserPage {
+    pub farm_state: Pubkey,
+    pub page_index: u64,
+    pub num_users: u64,
+
+    pub user_states: [Pubkey; consts::USER_PAGE_CAPACITY],
+
+    pub _padding: [u64; 16],
+}
+
+impl Default for UserPage {
+    fn default() -> UserPage {
+        UserPage {
+            farm_state: Pubkey::default(),
+            page_index: 0,
+            num_users: 0,
+            user_states: [Pubkey::default(); consts::USER_PAGE_CAPACITY],
+            _padding: [0; 16],
+        }
+    }
+}
+
+impl UserPage {
+    /// Returns `(page_index, slot)` of the user with the given `user_id`.
+    pub fn location(user_id: u64) -> (u64, usize) {
+        let capacity = consts::USER_PAGE_CAPACITY as u64;
+        (user_id / capacity, (user_id % capacity) as usize)
+    }
+
+    pub fn find_address(farm_state: &Pubkey, page_index: u64) -> (Pubkey, u8) {
+        Pubkey::find_program_address(
+            &[
+                consts::BASE_SEED_USER_PAGE,
+                farm_state.as_ref(),
+                &page_index.to_le_bytes(),
+            ],
+            &crate::ID,
+        )
+    }
+}
+
+#[zero_copy]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct FarmHistorySnapshot {
+    /// In the farm `time_unit`
+    pub ts: u64,
+    pub total_staked_amount: u64,
+    /// Issued per reward since the previous snapshot, net of early claim penalties
+    pub rewards_issued: [u64; MAX_REWARDS_TOKENS],
+}
+
+// static_assertions::const_assert_eq!(0, std::mem::size_of::<FarmHistory>() % 8);
+// static_assertions::const_assert_eq!(
+//     consts::SIZE_FARM_HISTORY,
+//     std::mem::size_of::<FarmHistory>() + 8
+// );
+/// Ring buffer of the last `FARM_HISTORY_CAPACITY` snapshots of a farm, written by
+/// `refresh_farm` at most once per `FarmState::history_snapshot_interval`.
+#[account(zero_copy)]
+#[derive(Debug, Eq, PartialEq)]
+pub struct FarmHistory {
+    pub farm_state: Pubkey,
+    /// Slot of the next snapshot in `snapshots`
+    pub next_index: u64,
+    /// Snapshots written so far, at most `FARM_HISTORY_CAPACITY` of them are kept
+    pub num_snapshots: u64,
+    /// `RewardInfo::rewards_issued_cumulative` as of the last snapshot
+    pub last_rewards_issued_cumulative: [u64; MAX_REWARDS_TOKENS],
+
+    pub snapshots: [FarmHistorySnapshot; consts::FARM_HISTORY_CAPACITY],
+
+    pub _padding: [u64; 8],
+}
+
+impl Default for FarmHistory {
+    fn default() -> FarmHistory {
+        FarmHistory {
+            farm_state: Pubkey::default(),
+            next_index: 0,
+            num_snapshots: 0,
+            last_rewards_issued_cumulative: [0; MAX_REWARDS_TOKENS],
+            snapshots: [FarmHistorySnapshot::default(); consts::FARM_HISTORY_CAPACITY],
+            _padding: [0; 8],
+        }
+    }
+}
+
+impl FarmHistory {
+    pub fn last_snapshot(&self) -> Option<&FarmHistorySnapshot> {
+        (self.num_snapshots > 0).then(|| {
+            let capacity = consts::FARM_HISTORY_CAPACITY;
+            &self.snapshots[(self.next_index as usize + capacity - 1) % capacity]
+        })
     }
 }
 
@@ -414,7 +1237,17 @@ pub struct UserState {
     pub owner: Pubkey,
 
     pub is_farm_delegated: u8,
-    pub _padding_0: [u8; 7],
+    /// Whether this user is counted in `FarmState::active_user_count`
+    pub is_active_user: u8,
+    /// Set for good by `renounce_position`: unstake, withdrawals, stake transfers and
+    /// ownership transfers are refused from then on
+    pub is_renounced: u8,
+    /// Whether harvests stay allowed on a renounced position
+    pub renounced_harvest_allowed: u8,
+    /// `FarmState::delegate_authority_index` of the authority that last set the stake
+    /// of this user, on delegated farms
+    pub delegate_authority_index: u8,
+    pub _padding_0: [u8; 3],
 
     pub rewards_tally_scaled: [u128; MAX_REWARDS_TOKENS],
     pub rewards_issued_unclaimed: [u64; MAX_REWARDS_TOKENS],
@@ -432,10 +1265,60 @@ pub struct UserState {
 
     pub last_stake_ts: u64,
 
-    pub _padding_1: [u64; 50],
+    pub locked_until_ts: u64,
+
+    /// Token accounts harvests pay to instead of an owner ATA, `Pubkey::default()` when unset
+    pub reward_destinations: [Pubkey; MAX_REWARDS_TOKENS],
+
+    /// Last `sync_mirrored_stake` of this user, on mirror farms
+    pub last_mirror_sync_ts: u64,
+
+    /// Unstaked within the linearly unlocked share of the position since the lock started,
+    /// without penalty. Reset when the lock start moves: the topped-up `last_stake_ts` of
+    /// `Continuous` locks, never by stakes for `WithExpiry` locks, which all start at
+    /// `FarmState::locking_start_timestamp`
+    pub penalty_free_unstaked_amount: u64,
+
+    /// `FarmState::penalty_terms_version` when the position was opened, top-ups keep it
+    pub penalty_terms_version: u64,
+
+    /// `FarmState::num_raffles` at the last change of the active stake, the user only
+    /// takes part in the raffles committed since
+    pub raffles_at_last_stake_change: u64,
+
+    /// `RewardInfo::last_issuance_ts` when the unclaimed rewards of this user last
+    /// grew from zero, saturated to `u32::MAX`. The early claim penalty decays from it
+    pub reward_accrual_start_ts: [u32; MAX_REWARDS_TOKENS],
 }
 
 impl UserState {
+    pub fn set_last_stake_ts(&mut self, locking_mode: LockingMode, ts: u64) {
+        // Only the lock start of `Continuous` locks follows `last_stake_ts`
+        if locking_mode == LockingMode::Continuous && ts != self.last_stake_ts {
+            self.penalty_free_unstaked_amount = 0;
+        }
+        self.last_stake_ts = ts;
+    }
+
+    pub fn is_renounced(&self) -> bool {
+        self.is_renounced != 0
+    }
+
+    pub fn add_rewards_issued_unclaimed(&mut self, reward_index: usize, amount: u64, ts: u64) {
+        if amount == 0 {
+            return;
+        }
+        if self.rewards_issued_unclaimed[reward_index] == 0 {
+            self.reward_accrual_start_ts[reward_index] = u32::try_from(ts).unwrap_or(u32::MAX);
+        }
+        self.rewards_issued_unclaimed[reward_index] += amount;
+    }
+
+    pub fn reward_destination(&self, reward_index: usize) -> Option<Pubkey> {
+        let destination = self.reward_destinations[reward_index];
+        (destination != Pubkey::default()).then_some(destination)
+    }
+
     pub fn get_active_stake_decimal(&self) -> Decimal {
         Decimal::from_scaled_val(self.active_stake_scaled)
     }
@@ -477,6 +1360,10 @@ impl Default for UserState {
             owner: Pubkey::default(),
 
             is_farm_delegated: false as u8,
+            is_active_user: 0,
+            is_renounced: 0,
+            renounced_harvest_allowed: 0,
+            delegate_authority_index: 0,
             _padding_0: Default::default(),
 
             rewards_tally_scaled: [0; MAX_REWARDS_TOKENS],
@@ -491,7 +1378,13 @@ impl Default for UserState {
             bump: 0,
             delegatee: Pubkey::default(),
             last_stake_ts: 0,
-            _padding_1: [0; 50],
+            locked_until_ts: 0,
+            reward_destinations: [Pubkey::default(); MAX_REWARDS_TOKENS],
+            last_mirror_sync_ts: 0,
+            penalty_free_unstaked_amount: 0,
+            penalty_terms_version: 0,
+            raffles_at_last_stake_change: 0,
+            reward_accrual_start_ts: [0; MAX_REWARDS_TOKENS],
         }
     }
 }
@@ -507,6 +1400,10 @@ pub struct RewardInfo {
     pub rewards_available: u64,
     pub reward_schedule_curve: RewardScheduleCurve,
     pub min_claim_duration_seconds: u64,
+    /// Rewards are issued from this time (in `time_unit`) on. Set to the creation time by
+    /// `initialize_reward` and to the change time by every rate, curve, type or decimals
+    /// update, so no reward is issued for time before it existed or before its current
+    /// configuration, whatever the start of its curve points
     pub last_issuance_ts: u64,
     pub rewards_issued_unclaimed: u64,
     pub rewards_issued_cumulative: u64,
@@ -516,8 +1413,44 @@ pub struct RewardInfo {
     pub reward_type: u8,
     pub rewards_per_second_decimals: u8,
 
-    pub _padding0: [u8; 6],
-    pub _padding1: [u64; 20],
+    /// Set by `report_reward_shortfall` when `rewards_vault` cannot cover
+    /// `rewards_available + rewards_issued_unclaimed`. Issuance is halted and harvests
+    /// are paid pro-rata until `add_reward` covers the deficit
+    pub shortfall: u8,
+
+    pub _padding0: [u8; 5],
+
+    /// Time (in `time_unit`) at which `rewards_available` runs out at the current rate,
+    /// refreshed on funding and curve changes. `u64::MAX` when not issuing and 0 when
+    /// the oracle price was unavailable. Stake changes move it for the `Constant` and
+    /// `ConstantPerUser` rewards without refreshing it
+    pub projected_depletion_ts: u64,
+
+    /// Share of a harvest forfeited back to `rewards_available` when claiming right after
+    /// the rewards of the user started accruing, decaying linearly to 0 over `penalty_decay_window_s` (in `time_unit`)
+    pub early_claim_penalty_bps: u64,
+    pub penalty_decay_window_s: u64,
+
+    /// Harvests of this reward fail before this timestamp (in `time_unit`), accrual is
+    /// not affected. Pushing it later requires the global admin
+    pub claims_open_ts: u64,
+
+    /// Share of every harvest (after the treasury fee) paid to the ATA of
+    /// `split_destination`, disabled when 0 or when `split_destination` is unset
+    pub split_destination: Pubkey,
+    pub split_bps: u16,
+    pub _padding2: [u8; 6],
+
+    /// Emitted while the farm had no stake, owed to the treasury and held by
+    /// `rewards_vault` until `sweep_diverted_rewards`, see
+    /// `FarmState::zero_stake_emissions_to_treasury`
+    pub rewards_diverted_to_treasury: u64,
+
+    /// Referral fees credited to the `ReferrerState`s of the farm and not claimed yet,
+    /// held by `rewards_vault` until `claim_referral_fees_all`
+    pub referral_fees_unclaimed: u64,
+
+    pub _padding1: [u64; 9],
 }
 
 impl RewardInfo {
@@ -536,7 +1469,33 @@ impl RewardInfo {
 pub struct TokenInfo {
     pub mint: Pubkey,
     pub decimals: u64,
-    pub _padding: [u64; 10],
+    /// Program the vaults of `mint` were created under, `Pubkey::default()` for the
+    /// tokens registered before Token-2022 support, all SPL Token
+    pub token_program: Pubkey,
+    /// Token-2022 transfer hook program of `mint` when the token was registered,
+    /// `Pubkey::default()` without a hook. Transfers fail once the mint hook changes
+    pub transfer_hook_program: Pubkey,
+    pub _padding: [u64; 2],
+}
+
+impl TokenInfo {
+    /// Tokens outside of SPL Token are moved with `transfer_checked`, which takes the
+    /// mint account
+    pub fn is_spl_token(&self) -> bool {
+        self.token_program() == anchor_spl::token::ID
+    }
+
+    pub fn has_transfer_hook(&self) -> bool {
+        self.transfer_hook_program != Pubkey::default()
+    }
+
+    pub fn token_program(&self) -> Pubkey {
+        if self.token_program == Pubkey::default() {
+            anchor_spl::token::ID
+        } else {
+            self.token_program
+        }
+    }
 }
 
 #[derive(
@@ -571,6 +1530,318 @@ pub enum FarmConfigOption {
     UpdateRewardScheduleCurvePoints,
     UpdatePendingFarmAdmin,
     UpdateStrategyId,
+    UpdateHookProgram,
+    UpdateHookConfig,
+    UpdateUserPagesEnabled,
+    UpdateVirtualOffsets,
+    UpdateNewUsersCutoffTs,
+    UpdateAdminWithdrawalLimit,
+    UpdateConfigAuthority,
+    UpdatePrimaryFarm,
+    UpdateRewardEarlyClaimPenaltyBps,
+    UpdateRewardPenaltyDecayWindow,
+    UpdateRewardClaimsOpenTs,
+    UpdateMinActiveUserStake,
+    UpdateRewardSplit,
+    UpdateLockTopUpPolicy,
+    UpdateHarvestFeeBpsOverride,
+    UpdateRequireRefreshInSlot,
+    UpdateHistorySnapshotInterval,
+    UpdateZeroStakeEmissionsToTreasury,
+    UpdateUserWithdrawalLimit,
+    AddDelegateAuthority,
+    RemoveDelegateAuthority,
+    UpdateAllowDuplicateRewardMints,
+}
+
+impl FarmConfigOption {
+    /// Modes applied to a single reward, the rewards are refreshed up to now before
+    /// they apply
+    pub fn is_reward_config(&self) -> bool {
+        matches!(
+            self,
+            FarmConfigOption::UpdateRewardRps
+                | FarmConfigOption::UpdateRewardMinClaimDuration
+                | FarmConfigOption::RewardType
+                | FarmConfigOption::RpsDecimals
+                | FarmConfigOption::UpdateRewardScheduleCurvePoints
+                | FarmConfigOption::UpdateRewardEarlyClaimPenaltyBps
+                | FarmConfigOption::UpdateRewardPenaltyDecayWindow
+                | FarmConfigOption::UpdateRewardClaimsOpenTs
+                | FarmConfigOption::UpdateRewardSplit
+        )
+    }
+
+    /// Group whose authority may apply this mode besides `farm_admin`, `None` for the
+    /// modes reserved to `farm_admin`
+    pub fn authority_group(&self) -> Option<FarmConfigAuthorityGroup> {
+        use FarmConfigOption::*;
+        match self {
+            UpdateRewardRps
+            | UpdateRewardMinClaimDuration
+            | RewardType
+            | RpsDecimals
+            | UpdateRewardScheduleCurvePoints
+            | UpdateRewardEarlyClaimPenaltyBps
+            | UpdateRewardPenaltyDecayWindow
+            | UpdateRewardClaimsOpenTs
+            | UpdateMinActiveUserStake
+            | UpdateZeroStakeEmissionsToTreasury => Some(FarmConfigAuthorityGroup::Emissions),
+            DepositWarmupPeriod
+            | WithdrawCooldownPeriod
+            | LockingMode
+            | LockingStartTimestamp
+            | LockingDuration
+            | LockingEarlyWithdrawalPenaltyBps
+            | DepositCapAmount
+            | ScopePricesAccount
+            | ScopeOraclePriceId
+            | ScopeOracleMaxAge
+            | UpdateNewUsersCutoffTs
+            | UpdateLockTopUpPolicy
+            | UpdateRequireRefreshInSlot
+            | UpdateUserWithdrawalLimit => Some(FarmConfigAuthorityGroup::Risk),
+            UpdateStrategyId | UpdateHistorySnapshotInterval => {
+                Some(FarmConfigAuthorityGroup::Metadata)
+            }
+            WithdrawAuthority
+            | SlashedAmountSpillAddress
+            | UpdatePendingFarmAdmin
+            | UpdateHookProgram
+            | UpdateHookConfig
+            | UpdateUserPagesEnabled
+            | UpdateVirtualOffsets
+            | UpdateAdminWithdrawalLimit
+            | UpdateConfigAuthority
+            | UpdatePrimaryFarm
+            | UpdateRewardSplit
+            | UpdateHarvestFeeBpsOverride
+            | AddDelegateAuthority
+            | RemoveDelegateAuthority
+            | UpdateAllowDuplicateRewardMints => None,
+        }
+    }
+
+    /// Decodes the `update_farm_config` data of this mode. The reward modes start with
+    /// the reward index as a `u64`. Trailing zero bytes are ignored, so that clients can
+    /// pad every value to the same length
+    pub fn decode(&self, data: &[u8]) -> Result<FarmConfigValue> {
+        use FarmConfigOption::*;
+        Ok(match self {
+            UpdateRewardRps => reward_config_value(data, RewardConfigValue::UpdateRewardRps)?,
+            UpdateRewardMinClaimDuration => {
+                reward_config_value(data, RewardConfigValue::UpdateRewardMinClaimDuration)?
+            }
+            RewardType => reward_config_value(data, RewardConfigValue::RewardType)?,
+            RpsDecimals => reward_config_value(data, RewardConfigValue::RpsDecimals)?,
+            UpdateRewardScheduleCurvePoints => {
+                reward_config_value(data, RewardConfigValue::UpdateRewardScheduleCurvePoints)?
+            }
+            UpdateRewardEarlyClaimPenaltyBps => {
+                reward_config_value(data, RewardConfigValue::UpdateRewardEarlyClaimPenaltyBps)?
+            }
+            UpdateRewardPenaltyDecayWindow => {
+                reward_config_value(data, RewardConfigValue::UpdateRewardPenaltyDecayWindow)?
+            }
+            UpdateRewardClaimsOpenTs => {
+                reward_config_value(data, RewardConfigValue::UpdateRewardClaimsOpenTs)?
+            }
+            UpdateRewardSplit => reward_config_value(data, |(destination, bps): (Pubkey, u16)| {
+                RewardConfigValue::UpdateRewardSplit { destination, bps }
+            })?,
+            WithdrawAuthority => FarmConfigValue::WithdrawAuthority(decode_config_value(data)?),
+            DepositWarmupPeriod => FarmConfigValue::DepositWarmupPeriod(decode_config_value(data)?),
+            WithdrawCooldownPeriod => {
+                FarmConfigValue::WithdrawCooldownPeriod(decode_config_value(data)?)
+            }
+            LockingMode => FarmConfigValue::LockingMode(decode_config_value(data)?),
+            LockingStartTimestamp => {
+                FarmConfigValue::LockingStartTimestamp(decode_config_value(data)?)
+            }
+            LockingDuration => FarmConfigValue::LockingDuration(decode_config_value(data)?),
+            LockingEarlyWithdrawalPenaltyBps => {
+                FarmConfigValue::LockingEarlyWithdrawalPenaltyBps(decode_config_value(data)?)
+            }
+            DepositCapAmount => FarmConfigValue::DepositCapAmount(decode_config_value(data)?),
+            SlashedAmountSpillAddress => {
+                FarmConfigValue::SlashedAmountSpillAddress(decode_config_value(data)?)
+            }
+            ScopePricesAccount => FarmConfigValue::ScopePricesAccount(decode_config_value(data)?),
+            ScopeOraclePriceId => FarmConfigValue::ScopeOraclePriceId(decode_config_value(data)?),
+            ScopeOracleMaxAge => FarmConfigValue::ScopeOracleMaxAge(decode_config_value(data)?),
+            UpdatePendingFarmAdmin => {
+                FarmConfigValue::UpdatePendingFarmAdmin(decode_config_value(data)?)
+            }
+            UpdateStrategyId => FarmConfigValue::UpdateStrategyId(decode_config_value(data)?),
+            UpdateHookProgram => FarmConfigValue::UpdateHookProgram(decode_config_value(data)?),
+            UpdateHookConfig => FarmConfigValue::UpdateHookConfig(decode_config_value(data)?),
+            UpdateUserPagesEnabled => {
+                FarmConfigValue::UpdateUserPagesEnabled(decode_config_value(data)?)
+            }
+            UpdateVirtualOffsets => {
+                let (virtual_stake_offset, virtual_amount_offset) = decode_config_value(data)?;
+                FarmConfigValue::UpdateVirtualOffsets {
+                    virtual_stake_offset,
+                    virtual_amount_offset,
+                }
+            }
+            UpdateNewUsersCutoffTs => {
+                FarmConfigValue::UpdateNewUsersCutoffTs(decode_config_value(data)?)
+            }
+            UpdateAdminWithdrawalLimit => {
+                let (max_per_window, window_s) = decode_config_value(data)?;
+                FarmConfigValue::UpdateAdminWithdrawalLimit {
+                    max_per_window,
+                    window_s,
+                }
+            }
+            UpdateConfigAuthority => {
+                let (group, authority): (u8, Pubkey) = decode_config_value(data)?;
+                let group = FarmConfigAuthorityGroup::try_from(group)
+                    .map_err(|_| error!(FarmError::InvalidConfigValue))?;
+                FarmConfigValue::UpdateConfigAuthority { group, authority }
+            }
+            UpdatePrimaryFarm => {
+                let (primary_farm, max_staleness) = decode_config_value(data)?;
+                FarmConfigValue::UpdatePrimaryFarm {
+                    primary_farm,
+                    max_staleness,
+                }
+            }
+            UpdateMinActiveUserStake => {
+                FarmConfigValue::UpdateMinActiveUserStake(decode_config_value(data)?)
+            }
+            UpdateLockTopUpPolicy => {
+                FarmConfigValue::UpdateLockTopUpPolicy(decode_config_value(data)?)
+            }
+            UpdateHarvestFeeBpsOverride => {
+                FarmConfigValue::UpdateHarvestFeeBpsOverride(decode_config_value(data)?)
+            }
+            UpdateRequireRefreshInSlot => {
+                FarmConfigValue::UpdateRequireRefreshInSlot(decode_config_value(data)?)
+            }
+            UpdateHistorySnapshotInterval => {
+                FarmConfigValue::UpdateHistorySnapshotInterval(decode_config_value(data)?)
+            }
+            UpdateZeroStakeEmissionsToTreasury => {
+                FarmConfigValue::UpdateZeroStakeEmissionsToTreasury(decode_config_value(data)?)
+            }
+            UpdateUserWithdrawalLimit => {
+                let (max_per_window, window_s) = decode_config_value(data)?;
+                FarmConfigValue::UpdateUserWithdrawalLimit {
+                    max_per_window,
+                    window_s,
+                }
+            }
+            AddDelegateAuthority => {
+                FarmConfigValue::AddDelegateAuthority(decode_config_value(data)?)
+            }
+            RemoveDelegateAuthority => {
+                FarmConfigValue::RemoveDelegateAuthority(decode_config_value(data)?)
+            }
+            UpdateAllowDuplicateRewardMints => {
+                FarmConfigValue::UpdateAllowDuplicateRewardMints(decode_config_value(data)?)
+            }
+        })
+    }
+}
+
+fn reward_config_value<T: AnchorDeserialize>(
+    data: &[u8],
+    value: impl FnOnce(T) -> RewardConfigValue,
+) -> Result<FarmConfigValue> {
+    require!(data.len() >= 8, FarmError::InvalidConfigValue);
+    Ok(FarmConfigValue::Reward {
+        reward_index: decode_config_value(&data[..8])?,
+        value: value(decode_config_value(&data[8..])?),
+    })
+}
+
+/// Decodes a config value, rejecting malformed data instead of reading past it or
+/// silently dropping part of it.
+///
+/// Clients may pad values to a fixed size, so trailing zero bytes are accepted.
+fn decode_config_value<T: AnchorDeserialize>(data: &[u8]) -> Result<T> {
+    let mut buf = data;
+    let value = T::deserialize(&mut buf).map_err(|_| {
+        xmsg!("Config value of len {} could not be decoded", data.len());
+        error!(FarmError::InvalidConfigValue)
+    })?;
+    if buf.iter().any(|b| *b != 0) {
+        xmsg!("Config value has {} unexpected trailing bytes", buf.len());
+        return err!(FarmError::InvalidConfigValue);
+    }
+    Ok(value)
+}
+
+/// Value of a `FarmConfigOption`, as decoded by `FarmConfigOption::decode`
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum FarmConfigValue {
+    Reward {
+        reward_index: u64,
+        value: RewardConfigValue,
+    },
+    WithdrawAuthority(Pubkey),
+    DepositWarmupPeriod(u32),
+    WithdrawCooldownPeriod(u32),
+    LockingMode(u64),
+    LockingStartTimestamp(u64),
+    LockingDuration(u64),
+    LockingEarlyWithdrawalPenaltyBps(u64),
+    DepositCapAmount(u64),
+    SlashedAmountSpillAddress(Pubkey),
+    ScopePricesAccount(Pubkey),
+    ScopeOraclePriceId(u16),
+    ScopeOracleMaxAge(u64),
+    UpdatePendingFarmAdmin(Pubkey),
+    UpdateStrategyId(Pubkey),
+    UpdateHookProgram(Pubkey),
+    UpdateHookConfig(Pubkey),
+    UpdateUserPagesEnabled(u8),
+    UpdateVirtualOffsets {
+        virtual_stake_offset: u64,
+        virtual_amount_offset: u64,
+    },
+    UpdateNewUsersCutoffTs(u64),
+    UpdateAdminWithdrawalLimit {
+        max_per_window: u64,
+        window_s: u64,
+    },
+    UpdateConfigAuthority {
+        group: FarmConfigAuthorityGroup,
+        authority: Pubkey,
+    },
+    UpdatePrimaryFarm {
+        primary_farm: Pubkey,
+        max_staleness: u64,
+    },
+    UpdateMinActiveUserStake(u64),
+    UpdateLockTopUpPolicy(u64),
+    UpdateHarvestFeeBpsOverride(Option<u64>),
+    UpdateRequireRefreshInSlot(u64),
+    UpdateHistorySnapshotInterval(u64),
+    UpdateZeroStakeEmissionsToTreasury(u64),
+    UpdateUserWithdrawalLimit {
+        max_per_window: u64,
+        window_s: u64,
+    },
+    AddDelegateAuthority(Pubkey),
+    RemoveDelegateAuthority(Pubkey),
+    UpdateAllowDuplicateRewardMints(u64),
+}
+
+/// Value of a `FarmConfigOption` applied to a single reward
+#[derive(PartialEq, Eq, Clone, Debug)]
+pub enum RewardConfigValue {
+    UpdateRewardRps(u64),
+    UpdateRewardMinClaimDuration(u64),
+    RewardType(u8),
+    RpsDecimals(u8),
+    UpdateRewardScheduleCurvePoints(Vec<RewardPerTimeUnitPoint>),
+    UpdateRewardEarlyClaimPenaltyBps(u64),
+    UpdateRewardPenaltyDecayWindow(u64),
+    UpdateRewardClaimsOpenTs(u64),
+    UpdateRewardSplit { destination: Pubkey, bps: u16 },
 }
 
 #[derive(
@@ -588,7 +1859,12 @@ pub enum TimeUnit {
 #[repr(u8)]
 pub enum RewardType {
     Proportional = 0,
+    /// The curve rate is per staked token, the farm issues `rps * total_staked_amount`
+    /// so the effective rate follows the TVL
     Constant = 1,
+    /// The curve rate is paid to each of the `FarmState::active_user_count` users,
+    /// regardless of their stake
+    ConstantPerUser = 2,
 }
 
 #[derive(
@@ -607,6 +1883,23 @@ impl Default for LockingMode {
     }
 }
 
+/// Start of the `Continuous` lock of a position after a stake into it. Unstaking always
+/// pays the early withdrawal penalty of the whole position against that start.
+#[derive(
+    AnchorSerialize, AnchorDeserialize, TryFromPrimitive, PartialEq, Eq, Clone, Copy, Debug,
+)]
+#[repr(u64)]
+pub enum LockTopUpPolicy {
+    /// The whole position is locked again from the stake time
+    ResetAll = 0,
+    /// The lock start is unchanged, the added amount is only locked for what remains
+    /// of the original lock
+    KeepOriginal = 1,
+    /// The lock start moves to the average of the previous start and the stake time,
+    /// weighted by the amounts
+    WeightedAverage = 2,
+}
+
 impl TimeUnit {
     pub fn now_from_clock(value: u8, click: &Clock) -> u64 {
         let unit = TimeUnit::try_from(value).unwrap();
@@ -622,6 +1915,10 @@ impl RewardInfo {
         self.rewards_vault != Pubkey::default()
     }
 
+    pub fn has_split(&self) -> bool {
+        self.split_bps > 0 && self.split_destination != Pubkey::default()
+    }
+
     pub fn has_rewards_available(&self) -> bool {
         self.rewards_available > 0
     }
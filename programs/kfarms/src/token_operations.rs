@@ -1,6 +1,19 @@
-use anchor_lang::prelude::{msg, AccountInfo, CpiContext, Result};
+use anchor_lang::prelude::{
+    err, require_keys_eq, AccountInfo, CpiContext, InterfaceAccount, Key, Pubkey, Result,
+};
 
+use anchor_spl::associated_token::{self, Create};
 use anchor_spl::token::{self, Transfer};
+use anchor_spl::token_2022::spl_token_2022::extension::{
+    mint_close_authority::MintCloseAuthority, permanent_delegate::PermanentDelegate,
+    transfer_hook::TransferHook, BaseStateWithExtensions, StateWithExtensions,
+};
+use anchor_spl::token_2022::spl_token_2022::onchain::invoke_transfer_checked;
+use anchor_spl::token_2022::spl_token_2022::state::Mint as MintState;
+use anchor_spl::token_interface::{self, TokenAccount, TransferChecked};
+
+use crate::state::{GlobalConfig, TokenInfo};
+use crate::{dbg_msg, vmsg, xmsg, FarmError};
 
 #[allow(clippy::too_many_arguments)]
 pub fn transfer_from_vault<'info>(
@@ -36,6 +49,394 @@ pub fn transfer_from_user<'info>(
     let cpi_ctx = CpiContext::new(token_program.clone(), cpi_transfer_accounts);
 
     let result = token::transfer(cpi_ctx, amount);
-    msg!("Transferred {:?}", result);
+    vmsg!("Transferred {:?}", result);
+    result
+}
+
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_checked_from_vault<'info>(
+    amount: u64,
+    decimals: u8,
+    signer: &[&[&[u8]]],
+    to_vault: &AccountInfo<'info>,
+    from_vault: &AccountInfo<'info>,
+    from_vault_authority: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let cpi_transfer_accounts = TransferChecked {
+        from: from_vault.clone(),
+        mint: mint.clone(),
+        to: to_vault.clone(),
+        authority: from_vault_authority.clone(),
+    };
+
+    let cpi_ctx = CpiContext::new(token_program.clone(), cpi_transfer_accounts).with_signer(signer);
+    token_interface::transfer_checked(cpi_ctx, amount, decimals)
+}
+
+pub fn transfer_checked_from_user<'info>(
+    amount: u64,
+    decimals: u8,
+    from_ata: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let cpi_transfer_accounts = TransferChecked {
+        from: from_ata.clone(),
+        mint: mint.clone(),
+        to: to.clone(),
+        authority: authority.clone(),
+    };
+    let cpi_ctx = CpiContext::new(token_program.clone(), cpi_transfer_accounts);
+
+    let result = token_interface::transfer_checked(cpi_ctx, amount, decimals);
+    vmsg!("Transferred {:?}", result);
     result
 }
+
+/// Checks `token_program` is the program the vaults of `token` were created under.
+/// `mint` is required outside of SPL Token, whose transfers go through
+/// `transfer_checked`.
+pub fn check_token_accounts(
+    token: &TokenInfo,
+    mint: Option<&AccountInfo>,
+    token_program: &AccountInfo,
+) -> Result<()> {
+    require_keys_eq!(
+        token_program.key(),
+        token.token_program(),
+        FarmError::UnexpectedAccount
+    );
+    match mint {
+        Some(mint) => require_keys_eq!(mint.key(), token.mint, FarmError::UnexpectedAccount),
+        None if !token.is_spl_token() => return err!(FarmError::MissingTokenMint),
+        None => {}
+    }
+    Ok(())
+}
+
+/// `transfer_from_vault` of `token`, through `transfer_checked` when `mint` is given.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_token_from_vault<'info>(
+    token: &TokenInfo,
+    amount: u64,
+    signer: &[&[&[u8]]],
+    to_vault: &AccountInfo<'info>,
+    from_vault: &AccountInfo<'info>,
+    from_vault_authority: &AccountInfo<'info>,
+    mint: Option<AccountInfo<'info>>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    transfer_token_from_vault_with_hook(
+        token,
+        amount,
+        signer,
+        to_vault,
+        from_vault,
+        from_vault_authority,
+        mint,
+        token_program,
+        &[],
+    )
+}
+
+/// `transfer_token_from_vault` forwarding `transfer_hook_accounts` to the transfer
+/// hook of `token`, see `transfer_hook_accounts`.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_token_from_vault_with_hook<'info>(
+    token: &TokenInfo,
+    amount: u64,
+    signer: &[&[&[u8]]],
+    to_vault: &AccountInfo<'info>,
+    from_vault: &AccountInfo<'info>,
+    from_vault_authority: &AccountInfo<'info>,
+    mint: Option<AccountInfo<'info>>,
+    token_program: &AccountInfo<'info>,
+    transfer_hook_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    check_token_accounts(token, mint.as_ref(), token_program)?;
+    if let Some(mint) = &mint {
+        check_transfer_hook_unchanged(token, mint)?;
+    }
+    match &mint {
+        Some(mint) if token.has_transfer_hook() => Ok(invoke_transfer_checked(
+            token_program.key,
+            from_vault.clone(),
+            mint.clone(),
+            to_vault.clone(),
+            from_vault_authority.clone(),
+            transfer_hook_accounts,
+            amount,
+            token.decimals as u8,
+            signer,
+        )?),
+        Some(mint) => transfer_checked_from_vault(
+            amount,
+            token.decimals as u8,
+            signer,
+            to_vault,
+            from_vault,
+            from_vault_authority,
+            mint,
+            token_program,
+        ),
+        None => transfer_from_vault(
+            amount,
+            signer,
+            to_vault,
+            from_vault,
+            from_vault_authority,
+            token_program,
+        ),
+    }
+}
+
+/// `transfer_from_user` of `token`, through `transfer_checked` when `mint` is given.
+pub fn transfer_token_from_user<'info>(
+    token: &TokenInfo,
+    amount: u64,
+    from_ata: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    mint: Option<AccountInfo<'info>>,
+    token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    transfer_token_from_user_with_hook(
+        token,
+        amount,
+        from_ata,
+        to,
+        authority,
+        mint,
+        token_program,
+        &[],
+    )
+}
+
+/// `transfer_token_from_user` forwarding `transfer_hook_accounts` to the transfer
+/// hook of `token`, see `transfer_hook_accounts`.
+#[allow(clippy::too_many_arguments)]
+pub fn transfer_token_from_user_with_hook<'info>(
+    token: &TokenInfo,
+    amount: u64,
+    from_ata: &AccountInfo<'info>,
+    to: &AccountInfo<'info>,
+    authority: &AccountInfo<'info>,
+    mint: Option<AccountInfo<'info>>,
+    token_program: &AccountInfo<'info>,
+    transfer_hook_accounts: &[AccountInfo<'info>],
+) -> Result<()> {
+    check_token_accounts(token, mint.as_ref(), token_program)?;
+    if let Some(mint) = &mint {
+        check_transfer_hook_unchanged(token, mint)?;
+    }
+    match &mint {
+        Some(mint) if token.has_transfer_hook() => {
+            let result = invoke_transfer_checked(
+                token_program.key,
+                from_ata.clone(),
+                mint.clone(),
+                to.clone(),
+                authority.clone(),
+                transfer_hook_accounts,
+                amount,
+                token.decimals as u8,
+                &[],
+            );
+            vmsg!("Transferred {:?}", result);
+            Ok(result?)
+        }
+        Some(mint) => transfer_checked_from_user(
+            amount,
+            token.decimals as u8,
+            from_ata,
+            to,
+            authority,
+            mint,
+            token_program,
+        ),
+        None => transfer_from_user(amount, from_ata, to, authority, token_program),
+    }
+}
+
+/// Amount `vault` gained since it held `amount_before`, which is less than the
+/// amount transferred when the mint charges a transfer fee.
+pub fn received_amount(
+    vault: &mut InterfaceAccount<TokenAccount>,
+    amount_before: u64,
+) -> Result<u64> {
+    vault.reload()?;
+    let received = vault
+        .amount
+        .checked_sub(amount_before)
+        .ok_or_else(|| dbg_msg!(FarmError::IntegerOverflow))?;
+    vmsg!("Vault {} received {}", vault.key(), received);
+    Ok(received)
+}
+
+/// Refuses the Token-2022 mints whose extensions let a third party move or burn
+/// the vault balances, a permanent delegate or a mint close authority, unless the
+/// mint is in `GlobalConfig::allowlisted_mints`.
+pub fn check_mint_extensions(global_config: &GlobalConfig, mint: &AccountInfo) -> Result<()> {
+    if *mint.owner == token::ID {
+        return Ok(());
+    }
+
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    let allowlisted = global_config.allowlisted_mints.contains(&mint.key());
+
+    if let Ok(permanent_delegate) = mint_state.get_extension::<PermanentDelegate>() {
+        if let Some(delegate) = Option::<Pubkey>::from(permanent_delegate.delegate) {
+            if !allowlisted {
+                xmsg!(
+                    "Mint {} has permanent delegate {} and is not allowlisted",
+                    mint.key(),
+                    delegate
+                );
+                return err!(FarmError::TokenMintHasPermanentDelegate);
+            }
+            vmsg!(
+                "Mint {} has permanent delegate {}, allowlisted",
+                mint.key(),
+                delegate
+            );
+        }
+    }
+
+    if let Ok(close_authority) = mint_state.get_extension::<MintCloseAuthority>() {
+        if let Some(authority) = Option::<Pubkey>::from(close_authority.close_authority) {
+            if !allowlisted {
+                xmsg!(
+                    "Mint {} has close authority {} and is not allowlisted",
+                    mint.key(),
+                    authority
+                );
+                return err!(FarmError::TokenMintHasCloseAuthority);
+            }
+            vmsg!(
+                "Mint {} has close authority {}, allowlisted",
+                mint.key(),
+                authority
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Program of the Token-2022 transfer hook of `mint`, `None` for SPL Token mints
+/// and mints without a hook.
+pub fn transfer_hook_program(mint: &AccountInfo) -> Result<Option<Pubkey>> {
+    if *mint.owner == token::ID {
+        return Ok(None);
+    }
+
+    let mint_data = mint.try_borrow_data()?;
+    let mint_state = StateWithExtensions::<MintState>::unpack(&mint_data)?;
+    Ok(mint_state
+        .get_extension::<TransferHook>()
+        .ok()
+        .and_then(|transfer_hook| Option::<Pubkey>::from(transfer_hook.program_id)))
+}
+
+/// Checks the transfer hook of the farm token `mint` is in
+/// `GlobalConfig::allowlisted_transfer_hook_programs`. Returns the program to record
+/// in `TokenInfo::transfer_hook_program`, `Pubkey::default()` without a hook.
+pub fn check_farm_token_transfer_hook(
+    global_config: &GlobalConfig,
+    mint: &AccountInfo,
+) -> Result<Pubkey> {
+    match transfer_hook_program(mint)? {
+        Some(program) => {
+            if !global_config
+                .allowlisted_transfer_hook_programs
+                .contains(&program)
+            {
+                xmsg!(
+                    "Mint {} has transfer hook program {} which is not allowlisted",
+                    mint.key(),
+                    program
+                );
+                return err!(FarmError::TransferHookProgramNotAllowlisted);
+            }
+            vmsg!(
+                "Mint {} has transfer hook program {}, allowlisted",
+                mint.key(),
+                program
+            );
+            Ok(program)
+        }
+        None => Ok(Pubkey::default()),
+    }
+}
+
+/// Reward tokens are moved by instructions whose `remaining_accounts` are already
+/// used otherwise, they cannot forward the accounts of a transfer hook.
+pub fn check_reward_transfer_hook(mint: &AccountInfo) -> Result<()> {
+    if let Some(program) = transfer_hook_program(mint)? {
+        xmsg!(
+            "Reward mint {} has transfer hook program {}",
+            mint.key(),
+            program
+        );
+        return err!(FarmError::RewardMintHasTransferHook);
+    }
+    Ok(())
+}
+
+/// The hook authority of a mint can change its hook program at any time, transfers
+/// only go through to the program recorded when the token was registered.
+fn check_transfer_hook_unchanged(token: &TokenInfo, mint: &AccountInfo) -> Result<()> {
+    let program = transfer_hook_program(mint)?.unwrap_or_default();
+    if program != token.transfer_hook_program {
+        xmsg!(
+            "Mint {} transfer hook program {} -> {}",
+            mint.key(),
+            token.transfer_hook_program,
+            program
+        );
+        return err!(FarmError::TransferHookProgramChanged);
+    }
+    Ok(())
+}
+
+/// Accounts to forward to the transfer hook of `token`: all of `remaining_accounts`,
+/// the hook program, its extra account metas account and the extra accounts they
+/// list, in any order. Tokens without a hook take no remaining accounts.
+pub fn transfer_hook_accounts<'c, 'info>(
+    token: &TokenInfo,
+    remaining_accounts: &'c [AccountInfo<'info>],
+) -> Result<&'c [AccountInfo<'info>]> {
+    if token.has_transfer_hook() {
+        Ok(remaining_accounts)
+    } else if remaining_accounts.is_empty() {
+        Ok(&[])
+    } else {
+        err!(FarmError::UnexpectedAccount)
+    }
+}
+
+pub fn create_ata_if_needed<'info>(
+    payer: &AccountInfo<'info>,
+    ata: &AccountInfo<'info>,
+    owner: &AccountInfo<'info>,
+    mint: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    token_program: &AccountInfo<'info>,
+    associated_token_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let cpi_accounts = Create {
+        payer: payer.clone(),
+        associated_token: ata.clone(),
+        authority: owner.clone(),
+        mint: mint.clone(),
+        system_program: system_program.clone(),
+        token_program: token_program.clone(),
+    };
+    let cpi_ctx = CpiContext::new(associated_token_program.clone(), cpi_accounts);
+    associated_token::create_idempotent(cpi_ctx)
+}
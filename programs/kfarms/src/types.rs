@@ -1,7 +1,190 @@
+use anchor_lang::prelude::*;
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[repr(u8)]
+pub enum DepositCapacityConstraint {
+    None = 0,
+    FarmDepositCap = 1,
+}
+
+/// Return data of `get_deposit_capacity`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DepositCapacity {
+    pub binding_constraint: DepositCapacityConstraint,
+    /// `u64::MAX` when uncapped
+    pub remaining_farm_cap: u64,
+}
+
+pub const FARM_STATUS_VERSION: u8 = 6;
+
+/// Return data of `get_farm_status`, Borsh serialized in field order.
+///
+/// `version` is always the first byte, fields are only ever appended and bump it.
+/// `total_staked_value` is in the unit of `deposit_cap_amount` on oracle farms and
+/// `None` without an oracle or a fresh price. `deposit_cap_utilization_bps` is `None`
+/// when uncapped or when the value is unknown.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FarmStatus {
+    pub version: u8,
+    /// In the farm `time_unit`
+    pub ts: u64,
+    pub total_staked_amount: u64,
+    pub total_staked_value: Option<u64>,
+    pub deposit_cap_amount: u64,
+    pub deposit_cap_utilization_bps: Option<u64>,
+    /// Bitmask of `FarmPauseFlag`
+    pub pause_flags: u8,
+    pub is_farm_frozen: bool,
+    /// Set while a farm admin transfer is waiting to be accepted
+    pub pending_farm_admin: Option<Pubkey>,
+    pub rewards: Vec<RewardStatus>,
+    /// See `FarmState::active_user_count`
+    pub active_user_count: u64,
+    /// Delegated farms only, `FarmState::delegate_authority` first
+    pub delegated_stake_by_authority: Vec<DelegatedStake>,
+}
+
+/// Active stake last set by `authority` through `set_stake_delegated`
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct DelegatedStake {
+    pub authority: Pubkey,
+    pub stake: u64,
+}
+
+pub const FARM_TVL_VERSION: u8 = 1;
+
+/// Return data of `get_farm_tvl`, Borsh serialized in field order, versioned like
+/// `FarmStatus`. `price` and `total_staked_value` are `None` without an oracle or a
+/// fresh price, the value is computed like the deposit cap: `floor(total * price / 10^exp)`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FarmTvl {
+    pub version: u8,
+    /// In the farm `time_unit`
+    pub ts: u64,
+    pub total_staked_amount: u64,
+    pub total_staked_value: Option<u64>,
+    pub price: Option<TvlPrice>,
+}
+
+pub const HARVEST_ELIGIBILITY_VERSION: u8 = 1;
+
+/// Return data of `get_harvest_eligibility`, Borsh serialized in field order, versioned
+/// like `FarmStatus`. `eligibility_ts` holds, per reward index, the earliest time a
+/// harvest is accepted, in the farm `time_unit` like `ts`.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct HarvestEligibility {
+    pub version: u8,
+    pub ts: u64,
+    pub eligibility_ts: Vec<u64>,
+}
+
+pub const FARM_HISTORY_VERSION: u8 = 1;
+
+/// Return data of `get_farm_history`, Borsh serialized in field order, versioned like
+/// `FarmStatus`. `snapshots` are oldest first, `num_snapshots` counts every snapshot
+/// ever written, only the last `FARM_HISTORY_CAPACITY` are kept.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FarmHistoryPage {
+    pub version: u8,
+    pub num_snapshots: u64,
+    pub snapshots: Vec<FarmHistoryEntry>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct FarmHistoryEntry {
+    pub ts: u64,
+    pub total_staked_amount: u64,
+    /// Per reward index, see `FarmHistorySnapshot::rewards_issued`
+    pub rewards_issued: Vec<u64>,
+}
+
+pub const UPGRADE_SNAPSHOTS_VERSION: u8 = 1;
+
+/// Return data of `get_upgrade_snapshots`, Borsh serialized in field order, versioned
+/// like `FarmStatus`. `all_ready` is set when every farm carries `expected_marker` and
+/// its accumulators did not change since its snapshot.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct UpgradeSnapshots {
+    pub version: u8,
+    pub slot: u64,
+    pub all_ready: bool,
+    pub farms: Vec<UpgradeSnapshot>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct UpgradeSnapshot {
+    pub farm_state: Pubkey,
+    /// 0 when the farm was never snapshotted
+    pub marker: u64,
+    pub snapshot_slot: u64,
+    pub accumulators_unchanged: bool,
+    pub pause_until_slot: u64,
+}
+
+pub const TREASURY_BALANCES_VERSION: u8 = 1;
+
+/// Return data of `get_treasury_balances`, Borsh serialized in field order, versioned
+/// like `FarmStatus`. One entry per treasury vault given, in the same order.
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, PartialEq, Eq)]
+pub struct TreasuryBalances {
+    pub version: u8,
+    pub balances: Vec<TreasuryBalance>,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TreasuryBalance {
+    pub mint: Pubkey,
+    pub amount: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TvlPrice {
+    pub value: u64,
+    pub exp: u64,
+    pub unix_timestamp: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub struct RewardStatus {
+    pub mint: Pubkey,
+    /// `RewardType`, `current_rps` is per eligible user for `ConstantPerUser`
+    pub reward_type: u8,
+    /// Raw curve value, scaled by `10^rps_decimals`
+    pub current_rps: u64,
+    pub rps_decimals: u8,
+    pub rewards_available: u64,
+    /// Time units left at `issuance_rate`, `u64::MAX` when not issuing and `None`
+    /// when the oracle price is missing
+    pub runway: Option<u64>,
+    /// As stored by the last funding or curve change, see `RewardInfo`
+    pub projected_depletion_ts: u64,
+    /// See `RewardInfo::shortfall`
+    pub shortfall: bool,
+    /// Reward tokens issued per time unit at the current rate and TVL, `None` when the
+    /// oracle price is missing
+    pub issuance_rate: Option<u64>,
+}
+
 #[derive(Debug)]
 pub struct HarvestEffects {
     pub reward_user: u64,
     pub reward_treasury: u64,
+    /// Returned to `rewards_available` by the early claim penalty
+    pub reward_penalty: u64,
+    /// Paid to `RewardInfo::split_destination`, taken from the user part
+    pub reward_split: u64,
+    /// Fee applied to compute `reward_treasury`, see `farm_operations::harvest_fee_bps`
+    pub treasury_fee_bps: u64,
+    /// The farm harvest fee override is outside of the global bounds
+    pub fee_override_clamped: bool,
+}
+
+#[derive(Debug)]
+pub struct RewardShortfall {
+    pub rewards_vault_amount: u64,
+    /// `rewards_available + rewards_issued_unclaimed`
+    pub rewards_owed: u64,
+    pub deficit: u64,
 }
 
 #[derive(Debug)]
@@ -11,15 +194,36 @@ pub struct WithdrawEffects {
 
 pub struct AddRewardEffects {
     pub reward_amount: u64,
+    pub projected_depletion_ts: u64,
+    pub funding_coverage: Option<RewardFundingCoverage>,
+}
+
+pub struct RewardFundingCoverage {
+    /// Emissions left on the curve at the current TVL
+    pub scheduled_remaining: u128,
+    /// `rewards_available` over `scheduled_remaining`, `u64::MAX` when nothing is left
+    pub coverage_bps: u64,
 }
 
 pub struct WithdrawRewardEffects {
     pub reward_amount: u64,
+    pub projected_depletion_ts: u64,
 }
 
 #[derive(Debug, PartialEq, Eq)]
 pub struct StakeEffects {
     pub amount_to_stake: u64,
+    pub stake_gained_scaled: u128,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+pub struct UnstakeEffects {
+    pub amount_to_pending_withdrawal: u64,
+    /// Unstaked within the unlocked share of the position, without penalty
+    pub amount_unlocked: u64,
+    /// Unstaked beyond the unlocked share, before `amount_penalty` is taken from it
+    pub amount_locked: u64,
+    pub amount_penalty: u64,
 }
 
 #[derive(Debug)]
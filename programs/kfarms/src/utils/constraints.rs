@@ -1,8 +1,9 @@
-use crate::FarmError;
+use crate::state::ProgramInstruction;
+use crate::{xmsg, FarmError, FarmState, GlobalConfig};
 use anchor_lang::{
     err,
-    prelude::{Context, Result},
-    Bumps,
+    prelude::{AccountLoader, Context, Result},
+    require, Bumps,
 };
 
 pub fn check_remaining_accounts<T>(ctx: &Context<T>) -> Result<()>
@@ -15,3 +16,26 @@ where
 
     Ok(())
 }
+
+/// Fails when `instruction` was disabled protocol-wide in `global_config`.
+pub fn check_instruction_enabled(
+    global_config: &AccountLoader<GlobalConfig>,
+    instruction: ProgramInstruction,
+) -> Result<()> {
+    if global_config.load()?.disabled_instructions & instruction.mask() != 0 {
+        xmsg!("Instruction {:?} is disabled", instruction);
+        return err!(FarmError::InstructionDisabled);
+    }
+
+    Ok(())
+}
+
+/// Validates the `reward_index` instruction argument, the index is only used as a
+/// `usize` afterwards.
+pub fn check_reward_index(farm_state: &FarmState, reward_index: u64) -> Result<usize> {
+    require!(
+        reward_index < farm_state.num_reward_tokens,
+        FarmError::RewardIndexOutOfRange
+    );
+    Ok(reward_index as usize)
+}
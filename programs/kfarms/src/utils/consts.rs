@@ -1,14 +1,46 @@
 pub const MAX_REWARDS_TOKENS: usize = 10;
 pub const REWARD_CURVE_POINTS: usize = 20;
+pub const USER_PAGE_CAPACITY: usize = 64;
+pub const FARM_HISTORY_CAPACITY: usize = 30;
 pub const BPS_DIV_FACTOR: u64 = 10_000;
 
 pub const BASE_SEED_FARM_VAULT: &[u8; 6] = b"fvault";
 pub const BASE_SEED_REWARD_VAULT: &[u8; 6] = b"rvault";
 pub const BASE_SEED_REWARD_TREASURY_VAULT: &[u8; 6] = b"tvault";
+pub const BASE_SEED_FARM_FEE_VAULT: &[u8; 7] = b"ffvault";
 pub const BASE_SEED_FARM_VAULTS_AUTHORITY: &[u8; 9] = b"authority";
 pub const BASE_SEED_TREASURY_VAULTS_AUTHORITY: &[u8; 9] = b"authority";
 pub const BASE_SEED_USER_STATE: &[u8; 4] = b"user";
+pub const BASE_SEED_REFERRER_STATE: &[u8; 8] = b"referrer";
+pub const BASE_SEED_FARM_REGISTRY: &[u8; 8] = b"registry";
+pub const BASE_SEED_USER_PAGE: &[u8; 9] = b"user_page";
+pub const BASE_SEED_TREASURY_WITHDRAWAL_REQUEST: &[u8; 19] = b"treasury_withdrawal";
+pub const BASE_SEED_RAFFLE_STATE: &[u8; 6] = b"raffle";
+pub const BASE_SEED_FARM_HISTORY: &[u8; 7] = b"history";
+pub const BASE_SEED_WITHDRAWAL_QUEUE: &[u8; 16] = b"withdrawal_queue";
 
 pub const SIZE_GLOBAL_CONFIG: usize = 2136;
 pub const SIZE_FARM_STATE: usize = 8336;
 pub const SIZE_USER_STATE: usize = 920;
+pub const SIZE_REFERRER_STATE: usize = 496;
+pub const SIZE_USER_PAGE: usize = 2232;
+pub const SIZE_TREASURY_WITHDRAWAL_REQUEST: usize = 200;
+pub const SIZE_RAFFLE_STATE: usize = 312;
+pub const SIZE_FARM_HISTORY: usize = 3080;
+
+pub const FARM_CONFIG_AUTHORITY_GROUPS: usize = 3;
+pub const MAX_ALLOWLISTED_MINTS: usize = 8;
+pub const MAX_ALLOWLISTED_TRANSFER_HOOK_PROGRAMS: usize = 4;
+pub const MAX_ADDITIONAL_DELEGATE_AUTHORITIES: usize = 2;
+pub const SIZE_FARM_REGISTRY_BASE: usize = 8 + 32 + 1 + 4;
+pub const SIZE_FARM_REGISTRY_ENTRY: usize = 32 + 1;
+pub const SIZE_WITHDRAWAL_QUEUE_BASE: usize = 8 + 32 + 1 + 4;
+pub const SIZE_QUEUED_WITHDRAWAL: usize = 32 + 8 + 8;
+
+pub const MAX_FARM_REGISTRY_PAGE_SIZE: usize = 30;
+pub const MAX_TREASURY_BALANCES_PAGE_SIZE: usize = 25;
+pub const MAX_FARM_HISTORY_PAGE_SIZE: usize = 9;
+pub const MAX_RAFFLE_PAGES_PER_SETTLE: usize = 2;
+pub const MAX_UPGRADE_SNAPSHOTS_PAGE_SIZE: usize = 16;
+pub const MAX_HARVEST_ACROSS_FARMS_GROUPS: usize = 16;
+pub const MAX_UPGRADE_PAUSE_SLOTS: u64 = 9_000;
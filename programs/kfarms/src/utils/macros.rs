@@ -71,3 +71,56 @@ macro_rules! dbg_msg {
         ($($crate::dbg_msg!($val)),+,)
     };
 }
+
+#[cfg(feature = "event-cpi")]
+#[macro_export]
+macro_rules! emit_event {
+    ($ctx: expr, $event: expr) => {
+        $crate::events::emit_cpi_event(
+            &$ctx.accounts.event_authority,
+            $ctx.bumps.event_authority,
+            &$event,
+        )?
+    };
+}
+
+#[cfg(not(feature = "event-cpi"))]
+#[macro_export]
+macro_rules! emit_event {
+    ($ctx: expr, $event: expr) => {
+        ::anchor_lang::prelude::emit!($event)
+    };
+}
+
+/// Non-essential logging, compiled out unless the `verbose-logs` feature is enabled.
+/// Logs that give context to an error should keep using `msg!`/`xmsg!`.
+#[cfg(feature = "verbose-logs")]
+#[macro_export]
+macro_rules! vmsg {
+    ($($arg:tt)*) => {{
+        $crate::xmsg!($($arg)*);
+    }};
+}
+
+#[cfg(not(feature = "verbose-logs"))]
+#[macro_export]
+macro_rules! vmsg {
+    ($($arg:tt)*) => {{
+        if false {
+            $crate::xmsg!($($arg)*);
+        }
+    }};
+}
+
+/// Logs `name=value` context for a `FarmError`, then evaluates to its `Err`:
+/// `return err_ctx!(FarmError::UnexpectedAccount, user_state = key, amount = amount);`
+#[macro_export]
+macro_rules! err_ctx {
+    (FarmError::$variant:ident $(, $name:ident = $value:expr)* $(,)?) => {{
+        $crate::xmsg!(
+            concat!(stringify!($variant) $(, " ", stringify!($name), "={:?}")*)
+            $(, $value)*
+        );
+        Err(::anchor_lang::error!($crate::FarmError::$variant))
+    }};
+}
@@ -2,5 +2,8 @@ pub mod constraints;
 pub mod consts;
 pub mod macros;
 pub mod math;
+pub mod registry;
 pub mod scope;
+pub mod slot_hashes;
 pub mod withdrawal_penalty;
+pub mod withdrawal_queue;
@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::state::{FarmRegistry, FarmRegistryEntry, FarmRegistryStatus};
+use crate::vmsg;
+
+/// Appends `farm` to the registry, growing the account by a single entry so that
+/// the realloc stays far below `MAX_PERMITTED_DATA_INCREASE`. `payer` funds the
+/// additional rent.
+pub fn append_farm_to_registry<'info>(
+    farm_registry: &mut Account<'info, FarmRegistry>,
+    farm: Pubkey,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let registry_info = farm_registry.to_account_info();
+    let new_len = FarmRegistry::space(farm_registry.farms.len() + 1);
+
+    let rent_needed = Rent::get()?
+        .minimum_balance(new_len)
+        .saturating_sub(registry_info.lamports());
+    if rent_needed > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: registry_info.clone(),
+                },
+            ),
+            rent_needed,
+        )?;
+    }
+    registry_info.realloc(new_len, false)?;
+
+    farm_registry.farms.push(FarmRegistryEntry {
+        farm,
+        status: FarmRegistryStatus::Active as u8,
+    });
+
+    vmsg!(
+        "Farm registry {} append farm {} num_farms {}",
+        registry_info.key(),
+        farm,
+        farm_registry.farms.len()
+    );
+
+    Ok(())
+}
@@ -0,0 +1,55 @@
+use anchor_lang::prelude::*;
+
+use crate::FarmError;
+
+const SLOT_HASH_ENTRY_SIZE: usize = 8 + 32;
+
+pub enum SlotHashLookup {
+    Found([u8; 32]),
+    /// No block at or after the slot yet
+    NotYetProduced,
+    /// The slot is older than the oldest entry of the sysvar
+    Evicted,
+}
+
+/// Hash of the first block produced at or after `slot`, skipped slots have no hash.
+///
+/// Reads the raw `SlotHashes` sysvar data, too large to deserialize on-chain: a `u64`
+/// length followed by `(slot, hash)` entries, newest first.
+pub fn first_block_hash_from(slot_hashes: &AccountInfo, slot: u64) -> Result<SlotHashLookup> {
+    let data = slot_hashes.try_borrow_data()?;
+    let len_bytes: [u8; 8] = data
+        .get(..8)
+        .and_then(|bytes| bytes.try_into().ok())
+        .ok_or(FarmError::UnexpectedAccount)?;
+    let num_entries =
+        (u64::from_le_bytes(len_bytes) as usize).min((data.len() - 8) / SLOT_HASH_ENTRY_SIZE);
+
+    let entry_slot = |index: usize| {
+        let offset = 8 + index * SLOT_HASH_ENTRY_SIZE;
+        u64::from_le_bytes(data[offset..offset + 8].try_into().unwrap())
+    };
+
+    if num_entries == 0 || entry_slot(0) < slot {
+        return Ok(SlotHashLookup::NotYetProduced);
+    }
+    if entry_slot(num_entries - 1) > slot {
+        return Ok(SlotHashLookup::Evicted);
+    }
+
+    // Entries [0, low) are at or after `slot`, the last of them is the first block
+    let (mut low, mut high) = (1, num_entries);
+    while low < high {
+        let mid = (low + high) / 2;
+        if entry_slot(mid) >= slot {
+            low = mid + 1;
+        } else {
+            high = mid;
+        }
+    }
+
+    let offset = 8 + (low - 1) * SLOT_HASH_ENTRY_SIZE + 8;
+    Ok(SlotHashLookup::Found(
+        data[offset..offset + 32].try_into().unwrap(),
+    ))
+}
@@ -1,4 +1,4 @@
-use crate::{xmsg, FarmError};
+use crate::{vmsg, xmsg, FarmError};
 
 use super::{consts::BPS_DIV_FACTOR, math::u64_mul_div};
 
@@ -13,7 +13,7 @@ fn get_withdrawal_penalty_bps(
     }
 
     if timestamp_now < timestamp_beginning {
-        xmsg!(
+        vmsg!(
             "timestamp_now < timestamp_beginning where the user withdraws before
             the official locking period starts in the case of a \"WithExpiry\" locking mode"
         );
@@ -21,7 +21,7 @@ fn get_withdrawal_penalty_bps(
     }
 
     if timestamp_now >= timestamp_maturity {
-        xmsg!(
+        vmsg!(
             "Time has passed, can unstake as usual ts_now={:?} ts_maturity={:?}",
             timestamp_now,
             timestamp_maturity
@@ -69,3 +69,72 @@ pub fn apply_early_withdrawal_penalty(
 
     Ok((unstake_amount - penalty_amount, penalty_amount))
 }
+
+/// Early withdrawal of `unstake_amount` out of a position of `position_amount` (before the
+/// unstake) whose lock unlocks linearly from `locking_start` over `locking_duration`.
+///
+/// With `elapsed = now - locking_start` and `taken = penalty_free_unstaked`:
+///
+/// ```text
+/// unlocked = min(unstake_amount, (position_amount + taken) * elapsed / locking_duration - taken)
+/// penalty  = (unstake_amount - unlocked) * penalty_bps * (locking_duration - elapsed)
+///            / locking_duration / 10_000
+/// ```
+///
+/// Both the unlocked share and the penalty rate decay linearly on purpose: a full exit at
+/// a fraction `f` of the lock pays `penalty_bps * (1 - f)^2` of the position. Farms whose
+/// `penalty_bps` forbids early withdrawals (0 or 100%) refuse any unstake during the lock,
+/// the unlocked share included. Returns the amount post penalty, the penalty and the
+/// penalty-free part.
+pub fn apply_partial_early_withdrawal_penalty(
+    locking_duration: u64,
+    locking_start: u64,
+    timestamp_now: u64,
+    penalty_bps: u64,
+    unstake_amount: u64,
+    position_amount: u64,
+    penalty_free_unstaked: u64,
+) -> Result<(u64, u64, u64), FarmError> {
+    let timestamp_maturity = locking_start + locking_duration;
+    if timestamp_now < locking_start || timestamp_now >= timestamp_maturity {
+        let (amount_post_penalty, penalty_amount) = apply_early_withdrawal_penalty(
+            locking_duration,
+            locking_start,
+            timestamp_now,
+            penalty_bps,
+            unstake_amount,
+        )?;
+        return Ok((amount_post_penalty, penalty_amount, unstake_amount));
+    }
+
+    if penalty_bps == 0 || penalty_bps == BPS_DIV_FACTOR {
+        xmsg!("Penalty percentage is 0 or 100, therefore early withdrawal is not allowed");
+        return Err(FarmError::EarlyWithdrawalNotAllowed);
+    }
+
+    let unlocked_total = u64_mul_div(
+        position_amount.saturating_add(penalty_free_unstaked),
+        timestamp_now - locking_start,
+        locking_duration,
+    );
+    let unlocked_amount = unstake_amount.min(unlocked_total.saturating_sub(penalty_free_unstaked));
+    let locked_amount = unstake_amount - unlocked_amount;
+
+    if locked_amount == 0 {
+        return Ok((unstake_amount, 0, unlocked_amount));
+    }
+
+    let (locked_post_penalty, penalty_amount) = apply_early_withdrawal_penalty(
+        locking_duration,
+        locking_start,
+        timestamp_now,
+        penalty_bps,
+        locked_amount,
+    )?;
+
+    Ok((
+        unlocked_amount + locked_post_penalty,
+        penalty_amount,
+        unlocked_amount,
+    ))
+}
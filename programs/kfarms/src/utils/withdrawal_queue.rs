@@ -0,0 +1,49 @@
+use anchor_lang::prelude::*;
+use anchor_lang::system_program;
+
+use crate::state::{QueuedWithdrawal, WithdrawalQueue};
+use crate::vmsg;
+
+/// Appends `withdrawal` to the queue. The account is grown by a single entry when
+/// the space freed by the paid out withdrawals does not fit it, `payer` funds the
+/// additional rent.
+pub fn append_queued_withdrawal<'info>(
+    withdrawal_queue: &mut Account<'info, WithdrawalQueue>,
+    withdrawal: QueuedWithdrawal,
+    payer: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+) -> Result<()> {
+    let queue_info = withdrawal_queue.to_account_info();
+    let new_len = WithdrawalQueue::space(withdrawal_queue.withdrawals.len() + 1);
+
+    if queue_info.data_len() < new_len {
+        let rent_needed = Rent::get()?
+            .minimum_balance(new_len)
+            .saturating_sub(queue_info.lamports());
+        if rent_needed > 0 {
+            system_program::transfer(
+                CpiContext::new(
+                    system_program.clone(),
+                    system_program::Transfer {
+                        from: payer.clone(),
+                        to: queue_info.clone(),
+                    },
+                ),
+                rent_needed,
+            )?;
+        }
+        queue_info.realloc(new_len, false)?;
+    }
+
+    withdrawal_queue.withdrawals.push(withdrawal);
+
+    vmsg!(
+        "Withdrawal queue {} append user_state {} amount {} num_withdrawals {}",
+        queue_info.key(),
+        withdrawal.user_state,
+        withdrawal.amount,
+        withdrawal_queue.withdrawals.len()
+    );
+
+    Ok(())
+}
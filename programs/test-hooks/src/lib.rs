@@ -0,0 +1,213 @@
+//! Hooks called by the farms program, deployed only by the integration tests.
+
+use anchor_lang::prelude::*;
+use anchor_spl::token_interface::{Mint, TokenAccount};
+use spl_tlv_account_resolution::{
+    account::ExtraAccountMeta, seeds::Seed, state::ExtraAccountMetaList,
+};
+use spl_transfer_hook_interface::instruction::{ExecuteInstruction, TransferHookInstruction};
+
+declare_id!("3yLobUnmMMc2JVfhmjNvWkwfdt6WrRW7bp85dBjb1Z6f");
+
+pub const BASE_SEED_HOOK_CONFIG: &[u8; 11] = b"hook_config";
+pub const BASE_SEED_EXTRA_ACCOUNT_METAS: &[u8; 19] = b"extra-account-metas";
+pub const BASE_SEED_TRANSFER_COUNTER: &[u8; 16] = b"transfer_counter";
+
+#[program]
+pub mod test_hooks {
+    use super::*;
+
+    pub fn initialize_hook_config(ctx: Context<InitializeHookConfig>) -> Result<()> {
+        let hook_config = &mut ctx.accounts.hook_config;
+        hook_config.authority = ctx.accounts.authority.key();
+        hook_config.bump = ctx.bumps.hook_config;
+        Ok(())
+    }
+
+    /// Makes the next stake hook calls fail, or succeed again
+    pub fn set_hook_failing(ctx: Context<SetHookFailing>, failing: bool) -> Result<()> {
+        ctx.accounts.hook_config.failing = failing;
+        Ok(())
+    }
+
+    /// Stake hook of the farms program, `STAKE_HOOK_IX_DISCRIMINATOR` being the
+    /// discriminator of this instruction
+    pub fn on_farm_stake_change(
+        ctx: Context<OnFarmStakeChange>,
+        farm_state: Pubkey,
+        owner: Pubkey,
+        old_stake_scaled: u128,
+        new_stake_scaled: u128,
+    ) -> Result<()> {
+        let hook_config = &mut ctx.accounts.hook_config;
+        require!(!hook_config.failing, TestHooksError::HookFailing);
+        hook_config.num_calls += 1;
+        hook_config.last_farm_state = farm_state;
+        hook_config.last_owner = owner;
+        hook_config.last_old_stake_scaled = old_stake_scaled;
+        hook_config.last_new_stake_scaled = new_stake_scaled;
+        Ok(())
+    }
+
+    /// Lists the `TransferCounter` of the mint as the only extra account of its
+    /// Token-2022 transfer hook
+    pub fn initialize_extra_account_meta_list(
+        ctx: Context<InitializeExtraAccountMetaList>,
+    ) -> Result<()> {
+        let extra_account_metas = [ExtraAccountMeta::new_with_seeds(
+            &[
+                Seed::Literal {
+                    bytes: BASE_SEED_TRANSFER_COUNTER.to_vec(),
+                },
+                Seed::AccountKey { index: 1 },
+            ],
+            false,
+            true,
+        )?];
+        let mut data = ctx.accounts.extra_account_meta_list.try_borrow_mut_data()?;
+        ExtraAccountMetaList::init::<ExecuteInstruction>(&mut data, &extra_account_metas)?;
+        Ok(())
+    }
+
+    /// Token-2022 transfer hook, reached through `fallback`
+    pub fn transfer_hook(ctx: Context<TransferHook>, amount: u64) -> Result<()> {
+        let counter = &mut ctx.accounts.counter;
+        counter.num_transfers += 1;
+        counter.amount_transferred += amount;
+        Ok(())
+    }
+
+    /// Token-2022 calls the hook with the `Execute` discriminator of the transfer hook
+    /// interface, not an Anchor one
+    pub fn fallback<'info>(
+        program_id: &Pubkey,
+        accounts: &'info [AccountInfo<'info>],
+        data: &[u8],
+    ) -> Result<()> {
+        match TransferHookInstruction::unpack(data)? {
+            TransferHookInstruction::Execute { amount } => {
+                __private::__global::transfer_hook(program_id, accounts, &amount.to_le_bytes())
+            }
+            _ => Err(ProgramError::InvalidInstructionData.into()),
+        }
+    }
+}
+
+#[account]
+#[derive(Default)]
+pub struct HookConfig {
+    pub authority: Pubkey,
+    pub bump: u8,
+    pub failing: bool,
+    pub num_calls: u64,
+    pub last_farm_state: Pubkey,
+    pub last_owner: Pubkey,
+    pub last_old_stake_scaled: u128,
+    pub last_new_stake_scaled: u128,
+}
+
+impl HookConfig {
+    pub const SPACE: usize = 8 + 32 + 1 + 1 + 8 + 32 + 32 + 16 + 16;
+}
+
+#[account]
+#[derive(Default)]
+pub struct TransferCounter {
+    pub num_transfers: u64,
+    pub amount_transferred: u64,
+}
+
+impl TransferCounter {
+    pub const SPACE: usize = 8 + 8 + 8;
+}
+
+#[derive(Accounts)]
+pub struct InitializeHookConfig<'info> {
+    #[account(mut)]
+    pub authority: Signer<'info>,
+
+    #[account(init,
+        payer = authority,
+        space = HookConfig::SPACE,
+        seeds = [BASE_SEED_HOOK_CONFIG, authority.key().as_ref()],
+        bump,
+    )]
+    pub hook_config: Account<'info, HookConfig>,
+
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetHookFailing<'info> {
+    pub authority: Signer<'info>,
+
+    #[account(mut, has_one = authority)]
+    pub hook_config: Account<'info, HookConfig>,
+}
+
+#[derive(Accounts)]
+pub struct OnFarmStakeChange<'info> {
+    #[account(mut)]
+    pub hook_config: Account<'info, HookConfig>,
+}
+
+#[derive(Accounts)]
+pub struct InitializeExtraAccountMetaList<'info> {
+    #[account(mut)]
+    pub payer: Signer<'info>,
+
+    /// CHECK: initialized by the TLV account resolution library
+    #[account(init,
+        payer = payer,
+        space = ExtraAccountMetaList::size_of(1)?,
+        seeds = [BASE_SEED_EXTRA_ACCOUNT_METAS, mint.key().as_ref()],
+        bump,
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(init,
+        payer = payer,
+        space = TransferCounter::SPACE,
+        seeds = [BASE_SEED_TRANSFER_COUNTER, mint.key().as_ref()],
+        bump,
+    )]
+    pub counter: Account<'info, TransferCounter>,
+
+    pub system_program: Program<'info, System>,
+}
+
+/// Accounts of the `Execute` instruction of the transfer hook interface, in its order
+#[derive(Accounts)]
+pub struct TransferHook<'info> {
+    #[account(token::mint = mint)]
+    pub source_token: InterfaceAccount<'info, TokenAccount>,
+
+    pub mint: InterfaceAccount<'info, Mint>,
+
+    #[account(token::mint = mint)]
+    pub destination_token: InterfaceAccount<'info, TokenAccount>,
+
+    /// CHECK: owner or delegate of the source account
+    pub owner: AccountInfo<'info>,
+
+    /// CHECK: checked by its seeds
+    #[account(
+        seeds = [BASE_SEED_EXTRA_ACCOUNT_METAS, mint.key().as_ref()],
+        bump,
+    )]
+    pub extra_account_meta_list: AccountInfo<'info>,
+
+    #[account(mut,
+        seeds = [BASE_SEED_TRANSFER_COUNTER, mint.key().as_ref()],
+        bump,
+    )]
+    pub counter: Account<'info, TransferCounter>,
+}
+
+#[error_code]
+pub enum TestHooksError {
+    #[msg("Stake hook set to fail")]
+    HookFailing,
+}
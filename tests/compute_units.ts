@@ -0,0 +1,97 @@
+import { BN } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  computeUnitsConsumed,
+  createDelegatedFarm,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  rewardValue,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+// Ceilings for the builds without `verbose-logs`, a handler going past them fails the
+// test. Lower them when a handler gets cheaper, the logged figures being the reference
+const STAKE_CU_BUDGET = 45_000;
+const SET_STAKE_DELEGATED_CU_BUDGET = 20_000;
+const HARVEST_CU_BUDGET = 50_000;
+
+describe("compute units", () => {
+  let globalConfig: PublicKey;
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  it("stake and harvest stay within their budget", async () => {
+    const tokenMint = await newMint();
+    const rewardMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, 1_000_000_000);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(1_000))
+    );
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    const rewardAta = await fundedAta(rewardMint, owner.publicKey, 0);
+
+    const stakeCu = await computeUnitsConsumed(
+      await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc({
+        commitment: "confirmed",
+      })
+    );
+    await sleep(2_000);
+    const harvestCu = await computeUnitsConsumed(
+      await harvestIx(
+        globalConfig,
+        farmState,
+        rewardMint,
+        owner,
+        rewardAta,
+        0
+      ).rpc({ commitment: "confirmed" })
+    );
+
+    console.log(`      stake: ${stakeCu} CU, harvest_reward: ${harvestCu} CU`);
+    expect(stakeCu).to.be.lessThan(STAKE_CU_BUDGET);
+    expect(harvestCu).to.be.lessThan(HARVEST_CU_BUDGET);
+  });
+
+  it("set_stake_delegated stays within its budget", async () => {
+    const delegate = await newUser();
+    const farmState = await createDelegatedFarm(globalConfig, delegate);
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner, delegate);
+
+    const signature = await program.methods
+      .setStakeDelegated(new BN(1_000_000))
+      .accounts({
+        delegateAuthority: delegate.publicKey,
+        userState,
+        farmState,
+        globalConfig,
+      })
+      .signers([delegate])
+      .rpc({ commitment: "confirmed" });
+    const cu = await computeUnitsConsumed(signature);
+
+    console.log(`      set_stake_delegated: ${cu} CU`);
+    expect(cu).to.be.lessThan(SET_STAKE_DELEGATED_CU_BUDGET);
+  });
+});
@@ -0,0 +1,132 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  newMint,
+  newUser,
+  programEvents,
+  provider,
+  stakeIx,
+  u64,
+  unstakeIx,
+  updateFarmConfig,
+} from "./utils";
+
+const LOCKING_MODE_WITH_EXPIRY = 2;
+const LOCKING_DURATION = 1_000;
+const PENALTY_BPS = 5_000;
+const AMOUNT = 1_000_000;
+const WAD = new BN(10).pow(new BN(18));
+
+describe("early unstake of the unlocked share", () => {
+  let globalConfig: PublicKey;
+
+  async function chainTime(): Promise<number> {
+    const slot = await provider.connection.getSlot("confirmed");
+    return provider.connection.getBlockTime(slot);
+  }
+
+  /** Staker of a farm whose `WithExpiry` lock is half way through */
+  async function halfUnlockedPosition(penaltyBps: number) {
+    const tokenMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    const lockStart = (await chainTime()) - LOCKING_DURATION / 2;
+    for (const [mode, value] of [
+      [FarmConfigOption.LockingMode, LOCKING_MODE_WITH_EXPIRY],
+      [FarmConfigOption.LockingStartTimestamp, lockStart],
+      [FarmConfigOption.LockingDuration, LOCKING_DURATION],
+      [FarmConfigOption.LockingEarlyWithdrawalPenaltyBps, penaltyBps],
+    ]) {
+      await updateFarmConfig(farmState, mode, u64(value));
+    }
+
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 2 * AMOUNT);
+    await stakeIx(farmState, tokenMint, owner, ata, AMOUNT).rpc();
+
+    /** Unstakes `amount` tokens, a share is worth a token on this farm */
+    async function unstake(amount: number) {
+      const shares = new BN(amount).mul(WAD);
+      const signature = await unstakeIx(farmState, owner, shares).rpc({
+        commitment: "confirmed",
+      });
+      const [event] = await programEvents(signature, "UnstakeEvent");
+      return {
+        ts: event.ts.toNumber(),
+        unlocked: event.amountUnlocked.toNumber(),
+        penalty: event.amountPenalty.toNumber(),
+      };
+    }
+
+    return { farmState, tokenMint, owner, ata, lockStart, unstake };
+  }
+
+  /** Unlocked share left at `ts` of a position of `AMOUNT` staked all along */
+  function unlockedAt(lockStart: number, ts: number, taken: number): number {
+    return Math.floor((AMOUNT * (ts - lockStart)) / LOCKING_DURATION) - taken;
+  }
+
+  function penaltyOn(lockStart: number, ts: number, locked: number): number {
+    const penaltyBps = Math.floor(
+      (PENALTY_BPS * (lockStart + LOCKING_DURATION - ts)) / LOCKING_DURATION
+    );
+    return Math.floor((locked * penaltyBps) / 10_000);
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  it("unstakes exactly the unlocked share without penalty", async () => {
+    const { lockStart, unstake } = await halfUnlockedPosition(PENALTY_BPS);
+
+    // The share only grows until the unstake lands
+    const unlocked = unlockedAt(lockStart, await chainTime(), 0);
+    const atBoundary = await unstake(unlocked);
+    expect(atBoundary.unlocked).to.equal(unlocked);
+    expect(atBoundary.penalty).to.equal(0);
+
+    const beyond = await unstake(100_000);
+    const unlockedBeyond = unlockedAt(lockStart, beyond.ts, unlocked);
+    expect(beyond.unlocked).to.equal(unlockedBeyond);
+    expect(beyond.penalty).to.be.greaterThan(0);
+    expect(beyond.penalty).to.equal(
+      penaltyOn(lockStart, beyond.ts, 100_000 - unlockedBeyond)
+    );
+  });
+
+  it("keeps the unlocked share taken across top-ups", async () => {
+    const { farmState, tokenMint, owner, ata, lockStart, unstake } =
+      await halfUnlockedPosition(PENALTY_BPS);
+
+    const unlocked = unlockedAt(lockStart, await chainTime(), 0);
+    expect((await unstake(unlocked)).penalty).to.equal(0);
+
+    // A 1 lamport stake must not make the same share penalty-free again
+    await stakeIx(farmState, tokenMint, owner, ata, 1).rpc();
+    const again = await unstake(unlocked);
+    expect(again.unlocked).to.be.lessThan(unlocked / 10);
+    expect(again.penalty).to.be.greaterThan(0);
+  });
+
+  it("refuses early unstakes on farms forbidding them", async () => {
+    for (const penaltyBps of [0, 10_000]) {
+      const { unstake } = await halfUnlockedPosition(penaltyBps);
+      try {
+        // Within the unlocked share
+        await unstake(1_000);
+        expect.fail(`early unstake allowed with ${penaltyBps} bps`);
+      } catch (err) {
+        expect(err).to.be.instanceOf(anchor.AnchorError);
+        expect(err.error.errorCode.code).to.equal("EarlyWithdrawalNotAllowed");
+      }
+    }
+  });
+});
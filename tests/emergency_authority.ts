@@ -0,0 +1,287 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  admin,
+  addRewards,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  farmVault,
+  farmVaultsAuthority,
+  fundedAta,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  rewardTreasuryVault,
+  rewardVault,
+  stakeIx,
+  treasuryVaultsAuthority,
+  u64,
+  updateGlobalConfig,
+} from "./utils";
+
+const SET_EMERGENCY_AUTHORITY = 6;
+const SET_TREASURY_FEE_BPS = 1;
+const PAUSE_STAKE = 1 << 0;
+const PAUSE_HARVEST = 1 << 2;
+const STAKE_INSTRUCTION_BIT = 12;
+const UNSTAKE_INSTRUCTION_BIT = 16;
+
+describe("emergency authority", () => {
+  let emergency: Keypair;
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+  let rewardMint: PublicKey;
+
+  async function expectRejected(
+    call: Promise<unknown>,
+    what: string,
+    code?: string
+  ) {
+    try {
+      await call;
+      expect.fail(`emergency authority allowed to ${what}`);
+    } catch (err) {
+      expect(err, `${what}: ${err}`).to.be.instanceOf(anchor.AnchorError);
+      if (code) {
+        expect(err.error.errorCode.code).to.equal(code);
+      }
+    }
+  }
+
+  function updatePauseFlags(authority: Keypair, pauseFlags: number) {
+    return program.methods
+      .updateFarmPauseFlags(pauseFlags)
+      .accounts({ authority: authority.publicKey, farmState, globalConfig })
+      .signers(authority === admin ? [] : [authority])
+      .rpc();
+  }
+
+  function updateDisabledInstructions(authority: Keypair, mask: BN) {
+    return program.methods
+      .updateDisabledInstructions(mask)
+      .accounts({ authority: authority.publicKey, globalConfig })
+      .signers(authority === admin ? [] : [authority])
+      .rpc();
+  }
+
+  before(async () => {
+    emergency = await newUser();
+    globalConfig = await createGlobalConfig();
+    await updateGlobalConfig(
+      globalConfig,
+      SET_EMERGENCY_AUTHORITY,
+      emergency.publicKey.toBuffer()
+    );
+    tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, 1_000_000);
+  });
+
+  it("pauses a farm", async () => {
+    await updatePauseFlags(emergency, PAUSE_STAKE);
+
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+    await expectRejected(
+      stakeIx(farmState, tokenMint, owner, ata, 1_000).rpc(),
+      "leave stake running",
+      "FarmOperationPaused"
+    );
+  });
+
+  it("adds pause flags but never removes them", async () => {
+    await updatePauseFlags(emergency, PAUSE_STAKE | PAUSE_HARVEST);
+    await expectRejected(
+      updatePauseFlags(emergency, PAUSE_HARVEST),
+      "unpause",
+      "EmergencyAuthorityCannotUnpause"
+    );
+    await expectRejected(
+      updatePauseFlags(emergency, 0),
+      "unpause",
+      "EmergencyAuthorityCannotUnpause"
+    );
+
+    await updatePauseFlags(admin, 0);
+    expect(
+      (await program.account.farmState.fetch(farmState)).pauseFlags
+    ).to.equal(0);
+  });
+
+  it("disables non-exit instructions, never enables any", async () => {
+    const stake = new BN(1).shln(STAKE_INSTRUCTION_BIT);
+    await updateDisabledInstructions(emergency, stake);
+    await expectRejected(
+      updateDisabledInstructions(
+        emergency,
+        stake.or(new BN(1).shln(UNSTAKE_INSTRUCTION_BIT))
+      ),
+      "disable unstake",
+      "GlobalAdminRequiredForInstruction"
+    );
+    await expectRejected(
+      updateDisabledInstructions(emergency, new BN(0)),
+      "enable instructions",
+      "EmergencyAuthorityCannotUnpause"
+    );
+    await updateDisabledInstructions(admin, new BN(0));
+  });
+
+  it("is rejected by update_global_config", async () => {
+    await expectRejected(
+      program.methods
+        .updateGlobalConfig(SET_TREASURY_FEE_BPS, [...Buffer.alloc(32)])
+        .accounts({ globalAdmin: emergency.publicKey, globalConfig })
+        .signers([emergency])
+        .rpc(),
+      "update the global config",
+      "ConstraintHasOne"
+    );
+  });
+
+  it("is rejected by update_farm_config", async () => {
+    await expectRejected(
+      program.methods
+        .updateFarmConfig(FarmConfigOption.DepositCapAmount, u64(1))
+        .accounts({
+          signer: emergency.publicKey,
+          farmState,
+          scopePrices: null,
+          globalAdmin: null,
+          globalConfig,
+        })
+        .signers([emergency])
+        .rpc(),
+      "update the farm config"
+    );
+    await expectRejected(
+      program.methods
+        .updateFarmConfig(FarmConfigOption.DepositCapAmount, u64(1))
+        .accounts({
+          signer: emergency.publicKey,
+          farmState,
+          scopePrices: null,
+          globalAdmin: emergency.publicKey,
+          globalConfig,
+        })
+        .signers([emergency])
+        .rpc(),
+      "update the farm config as global admin",
+      "InvalidAdminAuthority"
+    );
+  });
+
+  it("is rejected by update_global_config_admin", async () => {
+    await expectRejected(
+      program.methods
+        .updateGlobalConfigAdmin()
+        .accounts({ pendingGlobalAdmin: emergency.publicKey, globalConfig })
+        .signers([emergency])
+        .rpc(),
+      "take over the global admin",
+      "ConstraintHasOne"
+    );
+  });
+
+  it("is rejected by update_farm_admin", async () => {
+    await expectRejected(
+      program.methods
+        .updateFarmAdmin()
+        .accounts({
+          pendingFarmAdmin: emergency.publicKey,
+          farmState,
+          globalConfig,
+        })
+        .signers([emergency])
+        .rpc(),
+      "take over the farm admin",
+      "ConstraintHasOne"
+    );
+  });
+
+  it("is rejected by withdraw_reward", async () => {
+    await expectRejected(
+      program.methods
+        .withdrawReward(new BN(1), new BN(0))
+        .accounts({
+          farmAdmin: emergency.publicKey,
+          farmState,
+          rewardVault: rewardVault(farmState, rewardMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          adminRewardTokenAta: await fundedAta(
+            rewardMint,
+            emergency.publicKey,
+            0
+          ),
+          scopePrices: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rewardMint: null,
+          globalConfig,
+        })
+        .signers([emergency])
+        .rpc(),
+      "withdraw rewards",
+      "ConstraintHasOne"
+    );
+  });
+
+  it("is rejected by withdraw_treasury", async () => {
+    await expectRejected(
+      program.methods
+        .withdrawTreasury(new BN(1))
+        .accounts({
+          globalAdmin: emergency.publicKey,
+          globalConfig,
+          rewardTreasuryVault: rewardTreasuryVault(globalConfig, rewardMint),
+          treasuryVaultAuthority: treasuryVaultsAuthority(globalConfig),
+          withdrawDestinationTokenAccount: await fundedAta(
+            rewardMint,
+            emergency.publicKey,
+            0
+          ),
+          rewardMint,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .signers([emergency])
+        .rpc(),
+      "withdraw the treasury",
+      "ConstraintHasOne"
+    );
+  });
+
+  it("is rejected by withdraw_from_farm_vault", async () => {
+    await expectRejected(
+      program.methods
+        .withdrawFromFarmVault(new BN(1))
+        .accounts({
+          withdrawAuthority: emergency.publicKey,
+          farmState,
+          withdrawerTokenAccount: await fundedAta(
+            tokenMint,
+            emergency.publicKey,
+            0
+          ),
+          farmVault: farmVault(farmState, tokenMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          tokenProgram: TOKEN_PROGRAM_ID,
+          tokenMint: null,
+          globalAdmin: emergency.publicKey,
+          globalConfig,
+        })
+        .signers([emergency])
+        .rpc(),
+      "withdraw the farm vault",
+      "ConstraintHasOne"
+    );
+  });
+});
@@ -0,0 +1,139 @@
+import { PublicKey, Transaction } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  globalConfigOf,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  rewardValue,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+const RPS = 1_000;
+const AMOUNT = 1_000_000;
+
+type StakedFarm = { farmState: PublicKey; userState: PublicKey };
+
+describe("emission cut", () => {
+  let globalConfig: PublicKey;
+
+  /** Farm issuing `RPS` of a reward to a single staker */
+  async function stakedFarm(): Promise<StakedFarm> {
+    const tokenMint = await newMint();
+    const rewardMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, 1_000_000_000);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(RPS))
+    );
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, AMOUNT);
+    await stakeIx(farmState, tokenMint, owner, ata, AMOUNT).rpc();
+    return { farmState, userState };
+  }
+
+  function refreshFarmIx({ farmState }: StakedFarm) {
+    return program.methods
+      .refreshFarm()
+      .accounts({
+        farmState,
+        scopePrices: null,
+        farmHistory: null,
+        globalConfig: globalConfigOf(farmState),
+      })
+      .instruction();
+  }
+
+  function refreshUserIx({ farmState, userState }: StakedFarm) {
+    return program.methods
+      .refreshUserState()
+      .accounts({
+        userState,
+        farmState,
+        scopePrices: null,
+        globalConfig: globalConfigOf(farmState),
+      })
+      .instruction();
+  }
+
+  function cutEmissionsIx({ farmState }: StakedFarm) {
+    return program.methods
+      .updateFarmConfig(
+        FarmConfigOption.UpdateRewardRps,
+        rewardValue(0, u64(RPS / 2))
+      )
+      .accounts({
+        signer: admin.publicKey,
+        farmState,
+        scopePrices: null,
+        globalAdmin: null,
+        globalConfig: globalConfigOf(farmState),
+      })
+      .instruction();
+  }
+
+  async function claimable({ userState }: StakedFarm): Promise<number> {
+    const user = await program.account.userState.fetch(userState);
+    return user.rewardsIssuedUnclaimed[0].toNumber();
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  it("leaves the claimable amount unchanged by an emission cut", async () => {
+    // Identical farms, `cut` halves its emissions, `control` keeps them
+    const control = await stakedFarm();
+    const cut = await stakedFarm();
+    const sync = async () =>
+      provider.sendAndConfirm(
+        new Transaction().add(
+          await refreshFarmIx(control),
+          await refreshUserIx(control),
+          await refreshFarmIx(cut),
+          await refreshUserIx(cut)
+        )
+      );
+    await sync();
+    const synced = await claimable(cut);
+    await sleep(3_000);
+
+    // Same transaction, so both farms are at the same timestamp
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        await refreshFarmIx(control),
+        await refreshUserIx(control),
+        await cutEmissionsIx(cut),
+        await refreshUserIx(cut)
+      )
+    );
+    const beforeCut = await claimable(control);
+    const afterCut = await claimable(cut);
+    expect(afterCut).to.be.above(synced);
+    expect(afterCut).to.equal(beforeCut);
+
+    // Only the time after the cut is issued at the lower rate
+    await sleep(3_000);
+    await sync();
+    const controlGain = (await claimable(control)) - beforeCut;
+    const cutGain = (await claimable(cut)) - afterCut;
+    expect(controlGain).to.be.above(0);
+    expect(cutGain * 2).to.be.closeTo(controlGain, 2);
+  });
+});
@@ -0,0 +1,72 @@
+import * as anchor from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import { sha256 } from "@coral-xyz/anchor/dist/cjs/utils";
+import {
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  fundedAta,
+  newMint,
+  newUser,
+  program,
+  provider,
+  stakeIx,
+} from "./utils";
+
+// First 8 bytes of sha256("anchor:event"), prepended by emit_cpi! to the event data
+const EVENT_IX_TAG = Buffer.from(sha256.hash("anchor:event"), "hex").subarray(
+  0,
+  8
+);
+
+describe("event CPI", () => {
+  const eventAuthority = PublicKey.findProgramAddressSync(
+    [Buffer.from("__event_authority")],
+    program.programId
+  )[0];
+
+  before(function () {
+    // The events are only emitted through CPI by builds with the `event-cpi` feature
+    const stake = program.idl.instructions.find((ix) => ix.name === "stake");
+    if (!stake.accounts.some((account) => account.name === "eventAuthority")) {
+      this.skip();
+    }
+  });
+
+  it("decodes the stake event from the CPI data", async () => {
+    const globalConfig = await createGlobalConfig();
+    const tokenMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+
+    const signature = await stakeIx(farmState, tokenMint, owner, ata, 1_000)
+      .accounts({ eventAuthority, program: program.programId } as any)
+      .rpc({ commitment: "confirmed" });
+
+    const tx = await provider.connection.getTransaction(signature, {
+      commitment: "confirmed",
+      maxSupportedTransactionVersion: 0,
+    });
+    const accountKeys = tx.transaction.message.getAccountKeys();
+    const events = tx.meta.innerInstructions
+      .flatMap((inner) => inner.instructions)
+      .filter((ix) =>
+        accountKeys.get(ix.programIdIndex).equals(program.programId)
+      )
+      .map((ix) => anchor.utils.bytes.bs58.decode(ix.data))
+      .filter((data) => data.subarray(0, 8).equals(EVENT_IX_TAG))
+      .map((data) =>
+        program.coder.events.decode(data.subarray(8).toString("base64"))
+      );
+
+    const stakeEvent = events.find((event) => event.name === "StakeEvent");
+    expect(stakeEvent, "StakeEvent emitted through CPI").to.not.be.undefined;
+    expect(stakeEvent.data.farmState.toBase58()).to.equal(farmState.toBase58());
+    expect(stakeEvent.data.userState.toBase58()).to.equal(userState.toBase58());
+    expect(stakeEvent.data.amount.toNumber()).to.equal(1_000);
+    expect(tx.meta.logMessages.join("\n")).to.not.include("Program data:");
+  });
+});
@@ -0,0 +1,91 @@
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createFarm,
+  createGlobalConfig,
+  newMint,
+  program,
+  provider,
+  u64,
+  updateGlobalConfig,
+} from "./utils";
+
+const SET_DEFAULT_DEPOSIT_WARMUP_PERIOD = 3;
+const SET_DEFAULT_WITHDRAWAL_COOLDOWN_PERIOD = 4;
+const SET_DEFAULT_DEPOSIT_CAP_AMOUNT = 5;
+
+type FarmDefaults = {
+  depositWarmupPeriod: number;
+  withdrawalCooldownPeriod: number;
+  depositCapAmount: number;
+};
+
+describe("global farm defaults", () => {
+  let globalConfig: PublicKey;
+
+  function u32(value: number): Buffer {
+    const buffer = Buffer.alloc(4);
+    buffer.writeUInt32LE(value);
+    return buffer;
+  }
+
+  async function setDefaults(defaults: FarmDefaults) {
+    await updateGlobalConfig(
+      globalConfig,
+      SET_DEFAULT_DEPOSIT_WARMUP_PERIOD,
+      u32(defaults.depositWarmupPeriod)
+    );
+    await updateGlobalConfig(
+      globalConfig,
+      SET_DEFAULT_WITHDRAWAL_COOLDOWN_PERIOD,
+      u32(defaults.withdrawalCooldownPeriod)
+    );
+    await updateGlobalConfig(
+      globalConfig,
+      SET_DEFAULT_DEPOSIT_CAP_AMOUNT,
+      u64(defaults.depositCapAmount)
+    );
+  }
+
+  async function farmDefaults(farmState: PublicKey): Promise<FarmDefaults> {
+    const farm = await program.account.farmState.fetch(farmState);
+    return {
+      depositWarmupPeriod: farm.depositWarmupPeriod,
+      withdrawalCooldownPeriod: farm.withdrawalCooldownPeriod,
+      depositCapAmount: farm.depositCapAmount.toNumber(),
+    };
+  }
+
+  async function farmBytes(farmState: PublicKey): Promise<Buffer> {
+    return (await provider.connection.getAccountInfo(farmState)).data;
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  it("applies the defaults current when each farm is created", async () => {
+    const first: FarmDefaults = {
+      depositWarmupPeriod: 10,
+      withdrawalCooldownPeriod: 20,
+      depositCapAmount: 1_000_000,
+    };
+    const second: FarmDefaults = {
+      depositWarmupPeriod: 30,
+      withdrawalCooldownPeriod: 0,
+      depositCapAmount: 2_000_000,
+    };
+
+    await setDefaults(first);
+    const before = await createFarm(globalConfig, await newMint());
+    expect(await farmDefaults(before)).to.deep.equal(first);
+    const beforeBytes = await farmBytes(before);
+
+    await setDefaults(second);
+    const after = await createFarm(globalConfig, await newMint());
+    expect(await farmDefaults(after)).to.deep.equal(second);
+
+    // Farms created earlier keep their settings
+    expect((await farmBytes(before)).equals(beforeBytes)).to.equal(true);
+  });
+});
@@ -0,0 +1,64 @@
+import * as anchor from "@coral-xyz/anchor";
+import { expect } from "chai";
+import {
+  createFarm,
+  createGlobalConfig,
+  duplicateRewardVault,
+  FarmConfigOption,
+  initializeReward,
+  newMint,
+  program,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+describe("initialize_reward duplicate mints", () => {
+  let globalConfig: anchor.web3.PublicKey;
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  it("rejects the same mint twice by default", async () => {
+    const farmState = await createFarm(globalConfig, await newMint());
+    const rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+
+    try {
+      await initializeReward(globalConfig, farmState, rewardMint);
+      expect.fail("duplicate reward mint accepted");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("RewardAlreadyInitialized");
+    }
+
+    const farm = await program.account.farmState.fetch(farmState);
+    expect(farm.numRewardTokens.toNumber()).to.equal(1);
+  });
+
+  it("accepts the same mint twice once the farm allows duplicates", async () => {
+    const farmState = await createFarm(globalConfig, await newMint());
+    const rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateAllowDuplicateRewardMints,
+      u64(1)
+    );
+
+    const vault = duplicateRewardVault(farmState, rewardMint, 1);
+    await initializeReward(globalConfig, farmState, rewardMint, vault);
+
+    const farm = await program.account.farmState.fetch(farmState);
+    expect(farm.numRewardTokens.toNumber()).to.equal(2);
+    expect(farm.rewardInfos[1].token.mint.toBase58()).to.equal(
+      rewardMint.toBase58()
+    );
+    expect(farm.rewardInfos[1].rewardsVault.toBase58()).to.equal(
+      vault.toBase58()
+    );
+    expect(farm.rewardInfos[0].rewardsVault.toBase58()).to.not.equal(
+      vault.toBase58()
+    );
+  });
+});
@@ -0,0 +1,159 @@
+import { getAccount } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  newMint,
+  newUser,
+  program,
+  provider,
+  sleep,
+  stakeIx,
+  u64,
+  unstakeIx,
+  updateFarmConfig,
+  withdrawUnstakedDepositsIx,
+} from "./utils";
+
+enum LockTopUpPolicy {
+  ResetAll,
+  KeepOriginal,
+  WeightedAverage,
+}
+
+const LOCKING_MODE_CONTINUOUS = 1;
+const LOCKING_DURATION = 1_000;
+const PENALTY_BPS = 5_000;
+const AMOUNT = 1_000;
+
+/** `apply_partial_early_withdrawal_penalty` for a full unstake of the position */
+function expectedPenalty(
+  lockStart: number,
+  ts: number,
+  positionAmount: number
+): number {
+  const unlocked = Math.min(
+    positionAmount,
+    Math.floor((positionAmount * (ts - lockStart)) / LOCKING_DURATION)
+  );
+  const penaltyBps = Math.floor(
+    (PENALTY_BPS * (lockStart + LOCKING_DURATION - ts)) / LOCKING_DURATION
+  );
+  return Math.floor(((positionAmount - unlocked) * penaltyBps) / 10_000);
+}
+
+/** Block times around `signature`, the clock of its slot may be a second off */
+async function txTimes(signature: string): Promise<number[]> {
+  const tx = await provider.connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  return [tx.blockTime - 1, tx.blockTime, tx.blockTime + 1];
+}
+
+describe("lock top-up policies", () => {
+  let globalConfig: PublicKey;
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  /** Stakes twice a few seconds apart, then unstakes everything early */
+  async function topUp(policy: LockTopUpPolicy | null) {
+    const tokenMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    for (const [mode, value] of [
+      [FarmConfigOption.LockingMode, LOCKING_MODE_CONTINUOUS],
+      [FarmConfigOption.LockingDuration, LOCKING_DURATION],
+      [FarmConfigOption.LockingEarlyWithdrawalPenaltyBps, PENALTY_BPS],
+    ]) {
+      await updateFarmConfig(farmState, mode, u64(value));
+    }
+    if (policy !== null) {
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateLockTopUpPolicy,
+        u64(policy)
+      );
+    }
+
+    const owner: Keypair = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 2 * AMOUNT);
+
+    await stakeIx(farmState, tokenMint, owner, ata, AMOUNT).rpc();
+    const firstLockStart = (
+      await program.account.userState.fetch(userState)
+    ).lastStakeTs.toNumber();
+    await sleep(4_000);
+    const topUpTimes = await txTimes(
+      await stakeIx(farmState, tokenMint, owner, ata, AMOUNT).rpc()
+    );
+    const user = await program.account.userState.fetch(userState);
+    const lockStart = user.lastStakeTs.toNumber();
+
+    await sleep(2_000);
+    const unstakeTimes = await txTimes(
+      await unstakeIx(farmState, owner, user.activeStakeScaled).rpc()
+    );
+    await withdrawUnstakedDepositsIx(farmState, tokenMint, owner, ata).rpc();
+    const penalty =
+      2 * AMOUNT - Number((await getAccount(provider.connection, ata)).amount);
+
+    return {
+      farmState,
+      firstLockStart,
+      topUpTimes,
+      lockStart,
+      penalty,
+      expectedPenalties: unstakeTimes.map((ts) =>
+        expectedPenalty(lockStart, ts, 2 * AMOUNT)
+      ),
+    };
+  }
+
+  it("defaults to ResetAll", async () => {
+    const result = await topUp(null);
+    const farm = await program.account.farmState.fetch(result.farmState);
+    expect(farm.lockTopUpPolicy.toNumber()).to.equal(LockTopUpPolicy.ResetAll);
+    expect(result.topUpTimes).to.include(result.lockStart);
+    expect(result.expectedPenalties).to.include(result.penalty);
+  });
+
+  it("ResetAll locks the whole position again from the top-up", async () => {
+    const result = await topUp(LockTopUpPolicy.ResetAll);
+    expect(result.lockStart).to.be.above(result.firstLockStart);
+    expect(result.topUpTimes).to.include(result.lockStart);
+    expect(result.expectedPenalties).to.include(result.penalty);
+  });
+
+  it("KeepOriginal keeps the lock start of the position", async () => {
+    const result = await topUp(LockTopUpPolicy.KeepOriginal);
+    expect(result.lockStart).to.equal(result.firstLockStart);
+    expect(result.expectedPenalties).to.include(result.penalty);
+  });
+
+  it("WeightedAverage moves the lock start to the weighted average", async () => {
+    const result = await topUp(LockTopUpPolicy.WeightedAverage);
+    // Equal amounts, halfway between the two stakes
+    expect(
+      result.topUpTimes.map((ts) => Math.floor((result.firstLockStart + ts) / 2))
+    ).to.include(result.lockStart);
+    expect(result.lockStart).to.be.above(result.firstLockStart);
+    expect(result.expectedPenalties).to.include(result.penalty);
+  });
+
+  it("penalizes KeepOriginal least and ResetAll most", async () => {
+    const [reset, keep, weighted] = [
+      await topUp(LockTopUpPolicy.ResetAll),
+      await topUp(LockTopUpPolicy.KeepOriginal),
+      await topUp(LockTopUpPolicy.WeightedAverage),
+    ].map(({ penalty }) => penalty);
+    expect(keep).to.be.below(weighted);
+    expect(weighted).to.be.below(reset);
+  });
+});
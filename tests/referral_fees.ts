@@ -0,0 +1,180 @@
+import * as anchor from "@coral-xyz/anchor";
+import { getAccount, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  farmVaultsAuthority,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  rewardTreasuryVault,
+  rewardValue,
+  rewardVault,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+  updateGlobalConfig,
+} from "./utils";
+
+const SET_TREASURY_FEE_BPS = 1;
+const SET_REFERRAL_FEE_BPS = 15;
+
+describe("referral fees", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let rewardMint: PublicKey;
+  let referrer: Keypair;
+  let referrerState: PublicKey;
+  let referrerAta: PublicKey;
+
+  /** One `[rewards_vault, referrer_ata]` pair per non-zero bucket */
+  function claimReferralFeesAll(buckets: PublicKey[][]) {
+    return program.methods
+      .claimReferralFeesAll()
+      .accounts({
+        referrer: referrer.publicKey,
+        referrerState,
+        farmState,
+        globalConfig,
+        farmVaultsAuthority: farmVaultsAuthority(farmState),
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .remainingAccounts(
+        buckets.flat().map((pubkey) => ({
+          pubkey,
+          isSigner: false,
+          isWritable: true,
+        }))
+      )
+      .signers([referrer])
+      .rpc();
+  }
+
+  async function balance(ata: PublicKey): Promise<number> {
+    return Number((await getAccount(provider.connection, ata)).amount);
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    await updateGlobalConfig(globalConfig, SET_TREASURY_FEE_BPS, u64(5_000));
+    await updateGlobalConfig(globalConfig, SET_REFERRAL_FEE_BPS, u64(2_000));
+
+    const tokenMint = await newMint();
+    rewardMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, 1_000_000_000);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(1_000))
+    );
+
+    referrer = await newUser();
+    referrerState = PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("referrer"),
+        farmState.toBuffer(),
+        referrer.publicKey.toBuffer(),
+      ],
+      program.programId
+    )[0];
+    await program.methods
+      .initializeReferrerState()
+      .accounts({
+        referrer: referrer.publicKey,
+        farmState,
+        globalConfig,
+        referrerState,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .signers([referrer])
+      .rpc();
+    referrerAta = await fundedAta(rewardMint, referrer.publicKey, 0);
+
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+    const ownerRewardAta = await fundedAta(rewardMint, owner.publicKey, 0);
+    await sleep(2_000);
+    await harvestIx(
+      globalConfig,
+      farmState,
+      rewardMint,
+      owner,
+      ownerRewardAta,
+      0
+    )
+      .accounts({ referrerState })
+      .rpc();
+  });
+
+  it("credits the referrer a share of the treasury fee", async () => {
+    const referrerAccount = await program.account.referrerState.fetch(
+      referrerState
+    );
+    const accrued = referrerAccount.feesAccrued[0].toNumber();
+    expect(accrued).to.be.above(0);
+
+    const farm = await program.account.farmState.fetch(farmState);
+    expect(farm.rewardInfos[0].referralFeesUnclaimed.toNumber()).to.equal(
+      accrued
+    );
+
+    // 20% of the treasury fee is kept for the referrer, the rest is paid out
+    const treasury = await balance(
+      rewardTreasuryVault(globalConfig, rewardMint)
+    );
+    expect(treasury).to.be.closeTo(accrued * 4, 4);
+  });
+
+  it("drains and resets the bucket on claim", async () => {
+    const accrued = (
+      await program.account.referrerState.fetch(referrerState)
+    ).feesAccrued[0].toNumber();
+
+    await claimReferralFeesAll([
+      [rewardVault(farmState, rewardMint), referrerAta],
+    ]);
+
+    expect(await balance(referrerAta)).to.equal(accrued);
+    const referrerAccount = await program.account.referrerState.fetch(
+      referrerState
+    );
+    expect(referrerAccount.feesAccrued[0].toNumber()).to.equal(0);
+    expect(referrerAccount.feesClaimedCumulative[0].toNumber()).to.equal(
+      accrued
+    );
+    const farm = await program.account.farmState.fetch(farmState);
+    expect(farm.rewardInfos[0].referralFeesUnclaimed.toNumber()).to.equal(0);
+  });
+
+  it("refuses to claim the same fees twice", async () => {
+    const claimed = await balance(referrerAta);
+    try {
+      await claimReferralFeesAll([]);
+      expect.fail("claimed an empty bucket");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("NothingToWithdraw");
+    }
+    expect(await balance(referrerAta)).to.equal(claimed);
+  });
+});
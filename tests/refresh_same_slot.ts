@@ -0,0 +1,143 @@
+import { PublicKey, Transaction, VersionedTransaction } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+const REWARD_TYPES = { Proportional: 0, Constant: 1, ConstantPerUser: 2 };
+const RPS = 10;
+
+/** Curve points `[tsStart, rewardPerTimeUnit]` relative to now */
+const CURVE_SHAPES: Record<string, [number, number][]> = {
+  flat: [[-60, RPS]],
+  stepped: [
+    [-60, RPS],
+    [5, 2 * RPS],
+    [3_600, 0],
+  ],
+  ended: [
+    [-60, RPS],
+    [-30, 0],
+  ],
+};
+
+describe("refresh in the same slot", () => {
+  let globalConfig: PublicKey;
+
+  async function chainTime(): Promise<number> {
+    const slot = await provider.connection.getSlot("confirmed");
+    return provider.connection.getBlockTime(slot);
+  }
+
+  function curvePoints(
+    rewardIndex: number,
+    points: [number, number][],
+    now: number
+  ): Buffer {
+    const length = Buffer.alloc(4);
+    length.writeUInt32LE(points.length);
+    return Buffer.concat([
+      u64(rewardIndex),
+      length,
+      ...points.flatMap(([tsStart, rps]) => [u64(now + tsStart), u64(rps)]),
+    ]);
+  }
+
+  /** Farm state after `numRefreshes` refresh_farm in one transaction, with the
+   * slot it was simulated at */
+  async function simulateRefreshes(
+    farmState: PublicKey,
+    numRefreshes: number
+  ): Promise<{ slot: number; data: Buffer }> {
+    const refresh = await program.methods
+      .refreshFarm()
+      .accounts({
+        farmState,
+        scopePrices: null,
+        farmHistory: null,
+        globalConfig,
+      })
+      .instruction();
+    const tx = new Transaction().add(...Array(numRefreshes).fill(refresh));
+    tx.feePayer = admin.publicKey;
+    tx.recentBlockhash = (
+      await provider.connection.getLatestBlockhash()
+    ).blockhash;
+    const { context, value } = await provider.connection.simulateTransaction(
+      new VersionedTransaction(tx.compileMessage()),
+      {
+        sigVerify: false,
+        replaceRecentBlockhash: true,
+        accounts: { encoding: "base64", addresses: [farmState.toBase58()] },
+      }
+    );
+    expect(value.err, JSON.stringify(value.logs)).to.be.null;
+    return {
+      slot: context.slot,
+      data: Buffer.from(value.accounts[0].data[0], "base64"),
+    };
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  for (const [typeName, rewardType] of Object.entries(REWARD_TYPES)) {
+    it(`refreshes a ${typeName} farm twice to identical bytes`, async () => {
+      const tokenMint = await newMint();
+      const farmState = await createFarm(globalConfig, tokenMint);
+      const now = await chainTime();
+      const shapes = Object.entries(CURVE_SHAPES);
+      for (const [rewardIndex, [, points]] of shapes.entries()) {
+        const rewardMint = await newMint();
+        await initializeReward(globalConfig, farmState, rewardMint);
+        await addRewards(farmState, rewardMint, rewardIndex, 1_000_000_000);
+        // Before any issuance, ConstantPerUser cannot be set afterwards
+        await updateFarmConfig(
+          farmState,
+          FarmConfigOption.RewardType,
+          Buffer.concat([u64(rewardIndex), Buffer.from([rewardType])])
+        );
+        await updateFarmConfig(
+          farmState,
+          FarmConfigOption.UpdateRewardScheduleCurvePoints,
+          curvePoints(rewardIndex, points, now)
+        );
+      }
+
+      const owner = await newUser();
+      await createUser(farmState, owner);
+      const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+      await stakeIx(farmState, tokenMint, owner, ata, 1_000).rpc();
+      await sleep(2_000);
+
+      // Both simulations must run at the same clock to compare
+      let once: { slot: number; data: Buffer };
+      let twice: { slot: number; data: Buffer };
+      do {
+        once = await simulateRefreshes(farmState, 1);
+        twice = await simulateRefreshes(farmState, 2);
+      } while (once.slot !== twice.slot);
+
+      const before = (await provider.connection.getAccountInfo(farmState)).data;
+      expect(once.data.equals(before), "first refresh left the farm").to.be
+        .false;
+      expect(twice.data.equals(once.data)).to.be.true;
+    });
+  }
+});
@@ -0,0 +1,107 @@
+import { getAccount } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  computeUnitsConsumed,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  rewardValue,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+const NUM_REWARDS = 8;
+const ALL_REWARDS = 0;
+
+describe("refresh_user_state_masked", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let rewardMints: PublicKey[];
+  let owner: Keypair;
+  let userState: PublicKey;
+
+  async function refreshMasked(rewardsMask: number): Promise<number> {
+    const signature = await program.methods
+      .refreshUserStateMasked(rewardsMask)
+      .accounts({
+        userState,
+        farmState,
+        scopePrices: null,
+        globalConfig,
+      })
+      .rpc({ commitment: "confirmed" });
+    return computeUnitsConsumed(signature);
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    const tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    rewardMints = [];
+    for (let i = 0; i < NUM_REWARDS; i++) {
+      const rewardMint = await newMint();
+      await initializeReward(globalConfig, farmState, rewardMint);
+      await addRewards(farmState, rewardMint, i, 1_000_000_000);
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateRewardRps,
+        rewardValue(i, u64(1_000))
+      );
+      rewardMints.push(rewardMint);
+    }
+
+    owner = await newUser();
+    userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+  });
+
+  it("costs less settling 1 reward than all 8", async () => {
+    await sleep(2_000);
+    const oneCu = await refreshMasked(1 << 0);
+    await sleep(2_000);
+    const allCu = await refreshMasked(ALL_REWARDS);
+
+    console.log(
+      `      refresh_user_state_masked, ${NUM_REWARDS} rewards: ` +
+        `1 reward ${oneCu} CU, all ${allCu} CU (${allCu - oneCu} CU saved)`
+    );
+    expect(oneCu).to.be.lessThan(allCu);
+  });
+
+  it("leaves harvests of the other rewards fully paid", async () => {
+    await sleep(2_000);
+    await refreshMasked(1 << 0);
+
+    // The only staker is owed everything issued for the last reward
+    const last = NUM_REWARDS - 1;
+    const rewardAta = await fundedAta(rewardMints[last], owner.publicKey, 0);
+    await harvestIx(
+      globalConfig,
+      farmState,
+      rewardMints[last],
+      owner,
+      rewardAta,
+      last
+    ).rpc({ commitment: "confirmed" });
+
+    const issued = (await program.account.farmState.fetch(farmState))
+      .rewardInfos[last].rewardsIssuedCumulative.toNumber();
+    const harvested = Number(
+      (await getAccount(provider.connection, rewardAta)).amount
+    );
+    expect(harvested).to.be.within(issued - 1, issued);
+  });
+});
@@ -0,0 +1,257 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  admin,
+  createDelegatedFarm,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  stakeIx,
+  unstakeIx,
+  withdrawUnstakedDepositsIx,
+} from "./utils";
+
+const SPLIT_USER_ID = 1;
+
+describe("renounce position", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+  let rewardMint: PublicKey;
+
+  function splitUserStatePda(owner: PublicKey, userId: number): PublicKey {
+    return PublicKey.findProgramAddressSync(
+      [
+        Buffer.from("user"),
+        farmState.toBuffer(),
+        owner.toBuffer(),
+        new BN(userId).toArrayLike(Buffer, "le", 8),
+      ],
+      program.programId
+    )[0];
+  }
+
+  function renounce(
+    owner: Keypair,
+    userState: PublicKey,
+    allowHarvest: boolean
+  ) {
+    return program.methods
+      .renouncePosition(allowHarvest)
+      .accounts({ owner: owner.publicKey, userState, farmState, globalConfig })
+      .signers([owner])
+      .rpc();
+  }
+
+  async function expectRejected(
+    call: Promise<unknown>,
+    what: string,
+    code: string
+  ) {
+    try {
+      await call;
+      expect.fail(`renounced position allowed to ${what}`);
+    } catch (err) {
+      expect(err, `${what}: ${err}`).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code, what).to.equal(code);
+    }
+  }
+
+  /** Staked position with a pending withdrawal and a second position of the
+   * same owner, so that every exit has something to move */
+  async function newPosition() {
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+
+    const stake = (await program.account.userState.fetch(userState))
+      .activeStakeScaled;
+    await unstakeIx(farmState, owner, stake.divn(10)).rpc();
+    const splitUserState = splitUserStatePda(owner.publicKey, SPLIT_USER_ID);
+    await program.methods
+      .splitStake(stake.divn(10), new BN(SPLIT_USER_ID), new BN(0))
+      .accounts({
+        owner: owner.publicKey,
+        userState,
+        newUserState: splitUserState,
+        farmState,
+        globalConfig,
+        scopePrices: null,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+        userPage: null,
+      })
+      .signers([owner])
+      .rpc();
+
+    return {
+      owner,
+      userState,
+      splitUserState,
+      ata,
+      rewardAta: await fundedAta(rewardMint, owner.publicKey, 0),
+    };
+  }
+
+  /** Every way out of `userState`, signed by `signer` posing as its owner */
+  function exits(
+    position: Awaited<ReturnType<typeof newPosition>>,
+    signer: Keypair
+  ): Record<string, () => Promise<unknown>> {
+    const { owner, userState, splitUserState, ata, rewardAta } = position;
+    const signers = signer === admin ? [] : [signer];
+    return {
+      unstake: () =>
+        unstakeIx(farmState, owner, new BN(1))
+          .accounts({ owner: signer.publicKey })
+          .signers(signers)
+          .rpc(),
+      withdraw_unstaked_deposits: () =>
+        withdrawUnstakedDepositsIx(farmState, tokenMint, owner, ata)
+          .accounts({ owner: signer.publicKey })
+          .signers(signers)
+          .rpc(),
+      transfer_ownership: () =>
+        program.methods
+          .transferOwnership(Keypair.generate().publicKey)
+          .accounts({
+            owner: signer.publicKey,
+            userState,
+            farmState,
+            globalConfig,
+          })
+          .signers(signers)
+          .rpc(),
+      transfer_stake_internal: () =>
+        program.methods
+          .transferStakeInternal(new BN(1))
+          .accounts({
+            owner: signer.publicKey,
+            fromUserState: userState,
+            toUserState: splitUserState,
+            farmState,
+            globalConfig,
+            scopePrices: null,
+          })
+          .signers(signers)
+          .rpc(),
+      split_stake: () =>
+        program.methods
+          .splitStake(new BN(1), new BN(SPLIT_USER_ID + 1), new BN(0))
+          .accounts({
+            owner: signer.publicKey,
+            userState,
+            newUserState: splitUserStatePda(
+              signer.publicKey,
+              SPLIT_USER_ID + 1
+            ),
+            farmState,
+            globalConfig,
+            scopePrices: null,
+            systemProgram: SystemProgram.programId,
+            rent: SYSVAR_RENT_PUBKEY,
+            userPage: null,
+          })
+          .signers(signers)
+          .rpc(),
+      harvest_reward: () =>
+        harvestIx(globalConfig, farmState, rewardMint, owner, rewardAta, 0)
+          .accounts({ owner: signer.publicKey })
+          .signers(signers)
+          .rpc(),
+    };
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, 1_000_000);
+  });
+
+  it("refuses every exit to the owner", async () => {
+    const position = await newPosition();
+    await renounce(position.owner, position.userState, false);
+
+    for (const [name, exit] of Object.entries(
+      exits(position, position.owner)
+    )) {
+      await expectRejected(exit(), name, "PositionRenounced");
+    }
+  });
+
+  it("refuses every exit to the farm and global admin", async () => {
+    const position = await newPosition();
+    await renounce(position.owner, position.userState, false);
+
+    // The admin of the test farm and global config is the same key
+    for (const [name, exit] of Object.entries(exits(position, admin))) {
+      await expectRejected(exit(), name, "ConstraintHasOne");
+    }
+  });
+
+  it("keeps harvests only when allowed at renunciation", async () => {
+    const position = await newPosition();
+    await renounce(position.owner, position.userState, true);
+
+    const { harvest_reward, ...others } = exits(position, position.owner);
+    await harvest_reward();
+    for (const [name, exit] of Object.entries(others)) {
+      await expectRejected(exit(), name, "PositionRenounced");
+    }
+  });
+
+  it("cannot be renounced again, nor cleared", async () => {
+    const position = await newPosition();
+    await renounce(position.owner, position.userState, false);
+
+    await expectRejected(
+      renounce(position.owner, position.userState, true),
+      "renounce again",
+      "PositionRenounced"
+    );
+    const user = await program.account.userState.fetch(position.userState);
+    expect(user.isRenounced).to.equal(1);
+    expect(user.renouncedHarvestAllowed).to.equal(0);
+  });
+
+  it("is refused on delegated farms", async () => {
+    // The delegate authority sets the stake of delegated farm positions
+    const delegate = await newUser();
+    const delegatedFarm = await createDelegatedFarm(globalConfig, delegate);
+    const owner = await newUser();
+    const userState = await createUser(delegatedFarm, owner, delegate);
+
+    await expectRejected(
+      program.methods
+        .renouncePosition(false)
+        .accounts({
+          owner: owner.publicKey,
+          userState,
+          farmState: delegatedFarm,
+          globalConfig,
+        })
+        .signers([owner])
+        .rpc(),
+      "be renounced",
+      "RenounceNotSupported"
+    );
+  });
+});
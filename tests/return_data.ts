@@ -0,0 +1,120 @@
+import { BN } from "@coral-xyz/anchor";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  returnData,
+  rewardValue,
+  sleep,
+  stakeIx,
+  u64,
+  unstakeIx,
+  updateFarmConfig,
+  userStatePda,
+} from "./utils";
+
+function readU128(data: Buffer, offset: number): BN {
+  return new BN(data.subarray(offset, offset + 16), "le");
+}
+
+describe("return data", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+  let rewardMint: PublicKey;
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    tokenMint = await newMint();
+    rewardMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, 1_000_000_000);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(1_000))
+    );
+  });
+
+  it("stake returns the shares gained and the amount staked", async () => {
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 3_000);
+
+    const simulated = await provider.connection.simulateTransaction(
+      await stakeIx(farmState, tokenMint, owner, ata, 1_000).transaction(),
+      [admin, owner]
+    );
+    const simulatedData = Buffer.from(
+      simulated.value.returnData.data[0],
+      "base64"
+    );
+    expect(simulatedData.length).to.equal(24);
+    expect(simulatedData.readBigUInt64LE(16)).to.equal(BigInt(1_000));
+
+    const signature = await stakeIx(farmState, tokenMint, owner, ata, 1_000).rpc(
+      { commitment: "confirmed" }
+    );
+    const data = await returnData(signature);
+    const user = await program.account.userState.fetch(
+      userStatePda(farmState, owner.publicKey)
+    );
+    expect(readU128(data, 0).toString()).to.equal(
+      user.activeStakeScaled.toString()
+    );
+    expect(data.readBigUInt64LE(16)).to.equal(BigInt(1_000));
+  });
+
+  it("unstake returns the amount moved to pending withdrawal", async () => {
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000).rpc();
+    const stake = (await program.account.userState.fetch(userState))
+      .activeStakeScaled;
+
+    const signature = await unstakeIx(farmState, owner, stake).rpc({
+      commitment: "confirmed",
+    });
+    const data = await returnData(signature);
+    expect(data.length).to.equal(8);
+    expect(data.readBigUInt64LE(0)).to.equal(BigInt(1_000));
+  });
+
+  it("harvest returns the amount transferred to the user", async () => {
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+    const rewardAta = await fundedAta(rewardMint, owner.publicKey, 0);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000).rpc();
+    await sleep(2_000);
+
+    const signature = await harvestIx(
+      globalConfig,
+      farmState,
+      rewardMint,
+      owner,
+      rewardAta,
+      0
+    ).rpc({ commitment: "confirmed" });
+    const data = await returnData(signature);
+
+    const balance = await provider.connection.getTokenAccountBalance(rewardAta);
+    expect(data.length).to.equal(8);
+    expect(data.readBigUInt64LE(0)).to.be.greaterThan(BigInt(0));
+    expect(data.readBigUInt64LE(0).toString()).to.equal(balance.value.amount);
+  });
+});
@@ -0,0 +1,120 @@
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  refreshFarm,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+const RPS = 100;
+const ONE_WEEK = 7 * 24 * 60 * 60;
+
+describe("reward accrual start", () => {
+  let farmState: PublicKey;
+  let rewardMint: PublicKey;
+
+  async function chainTime(): Promise<number> {
+    const slot = await provider.connection.getSlot("confirmed");
+    return provider.connection.getBlockTime(slot);
+  }
+
+  async function reward() {
+    return (await program.account.farmState.fetch(farmState)).rewardInfos[0];
+  }
+
+  before(async () => {
+    const globalConfig = await createGlobalConfig();
+    const tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+
+    // Staked well before the reward exists
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+
+    rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+  });
+
+  it("starts issuance at initialize_reward", async () => {
+    const initialized = await reward();
+    expect(initialized.lastIssuanceTs.toNumber()).to.be.at.least(
+      (await chainTime()) - 60
+    );
+    expect(initialized.rewardsIssuedCumulative.toNumber()).to.equal(0);
+  });
+
+  it("issues nothing for a curve point one week in the past", async () => {
+    await addRewards(farmState, rewardMint, 0, RPS * ONE_WEEK);
+    const startTs = (await chainTime()) - ONE_WEEK;
+    // Reward index, then a single curve point
+    const points = Buffer.alloc(4);
+    points.writeUInt32LE(1);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardScheduleCurvePoints,
+      Buffer.concat([u64(0), points, u64(startTs), u64(RPS)])
+    );
+    const configured = await reward();
+    expect(configured.rewardsIssuedCumulative.toNumber()).to.equal(0);
+
+    await sleep(2_000);
+    await refreshFarm(farmState);
+    const refreshed = await reward();
+
+    // Only the time since the curve was set, not since its first point
+    const elapsed =
+      refreshed.lastIssuanceTs.toNumber() - configured.lastIssuanceTs.toNumber();
+    expect(refreshed.rewardsIssuedCumulative.toNumber()).to.equal(
+      RPS * elapsed
+    );
+    expect(elapsed).to.be.below(60);
+  });
+
+  it("issues nothing until a future first curve point", async () => {
+    const startTs = (await chainTime()) + 4;
+    const points = Buffer.alloc(4);
+    points.writeUInt32LE(1);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardScheduleCurvePoints,
+      Buffer.concat([u64(0), points, u64(startTs), u64(RPS)])
+    );
+    const configured = await reward();
+
+    // Before the first point the farm still refreshes, issuing nothing
+    await refreshFarm(farmState);
+    const early = await reward();
+    expect(early.lastIssuanceTs.toNumber()).to.be.below(startTs);
+    expect(early.rewardsIssuedCumulative.toString()).to.equal(
+      configured.rewardsIssuedCumulative.toString()
+    );
+
+    await sleep(8_000);
+    await refreshFarm(farmState);
+    const started = await reward();
+
+    // Only the time since the first point
+    const elapsed = started.lastIssuanceTs.toNumber() - startTs;
+    expect(elapsed).to.be.above(0);
+    expect(
+      started.rewardsIssuedCumulative
+        .sub(configured.rewardsIssuedCumulative)
+        .toNumber()
+    ).to.equal(RPS * elapsed);
+  });
+});
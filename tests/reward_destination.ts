@@ -0,0 +1,125 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { getAccount } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  rewardValue,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+describe("reward destination", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let rewardMint: PublicKey;
+  let owner: Keypair;
+  let userState: PublicKey;
+  let ownerAta: PublicKey;
+  let vaultAta: PublicKey;
+
+  function setRewardDestination(rewardDestination: PublicKey | null) {
+    return program.methods
+      .setRewardDestination(new BN(0))
+      .accounts({
+        owner: owner.publicKey,
+        userState,
+        farmState,
+        globalConfig,
+        rewardDestination,
+      })
+      .signers([owner])
+      .rpc();
+  }
+
+  function harvestTo(userRewardAta: PublicKey) {
+    return harvestIx(
+      globalConfig,
+      farmState,
+      rewardMint,
+      owner,
+      userRewardAta,
+      0
+    ).rpc();
+  }
+
+  async function expectInvalidDestination(call: Promise<unknown>) {
+    try {
+      await call;
+      expect.fail("harvested to an account that is not the destination");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("InvalidRewardDestination");
+    }
+  }
+
+  async function balance(ata: PublicKey): Promise<number> {
+    return Number((await getAccount(provider.connection, ata)).amount);
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    const tokenMint = await newMint();
+    rewardMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, 1_000_000_000);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(1_000))
+    );
+
+    owner = await newUser();
+    userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+    ownerAta = await fundedAta(rewardMint, owner.publicKey, 0);
+
+    // Token account of an off-curve address, as a multisig vault would be
+    const vault = PublicKey.findProgramAddressSync(
+      [Buffer.from("vault"), owner.publicKey.toBuffer()],
+      program.programId
+    )[0];
+    expect(PublicKey.isOnCurve(vault.toBuffer())).to.equal(false);
+    vaultAta = await fundedAta(rewardMint, vault, 0);
+  });
+
+  it("refuses an account of another owner without a destination", async () => {
+    await sleep(1_000);
+    await expectInvalidDestination(harvestTo(vaultAta));
+  });
+
+  it("harvests into the PDA-owned destination", async () => {
+    await setRewardDestination(vaultAta);
+    await sleep(1_000);
+
+    await expectInvalidDestination(harvestTo(ownerAta));
+    await harvestTo(vaultAta);
+    expect(await balance(vaultAta)).to.be.above(0);
+    expect(await balance(ownerAta)).to.equal(0);
+  });
+
+  it("harvests to the owner again once the destination is unset", async () => {
+    await setRewardDestination(null);
+    await sleep(1_000);
+
+    await expectInvalidDestination(harvestTo(vaultAta));
+    await harvestTo(ownerAta);
+    expect(await balance(ownerAta)).to.be.above(0);
+  });
+});
@@ -0,0 +1,135 @@
+import { BN, BorshCoder, EventParser } from "@coral-xyz/anchor";
+import { TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  admin,
+  createFarm,
+  createGlobalConfig,
+  FarmConfigOption,
+  farmVaultsAuthority,
+  fundedAta,
+  initializeReward,
+  newMint,
+  program,
+  provider,
+  rewardVault,
+  u64,
+  updateFarmConfig,
+  updateGlobalConfig,
+} from "./utils";
+
+const SET_MIN_REWARD_FUNDING_COVERAGE_BPS = 12;
+const MIN_COVERAGE_BPS = 10_000;
+const RPS = 10;
+const CURVE_DURATION = 1_000;
+const SCHEDULED = RPS * CURVE_DURATION;
+
+describe("reward funding coverage", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+
+  async function chainTime(): Promise<number> {
+    const slot = await provider.connection.getSlot("confirmed");
+    return provider.connection.getBlockTime(slot);
+  }
+
+  /** Reward whose curve starts in an hour, so that the emissions left on it do
+   * not depend on when add_rewards lands */
+  async function scheduledReward(rewardIndex: number): Promise<PublicKey> {
+    const rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+    const startTs = (await chainTime()) + 3_600;
+    const numPoints = Buffer.alloc(4);
+    numPoints.writeUInt32LE(2);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardScheduleCurvePoints,
+      Buffer.concat([
+        u64(rewardIndex),
+        numPoints,
+        u64(startTs),
+        u64(RPS),
+        u64(startTs + CURVE_DURATION),
+        u64(0),
+      ])
+    );
+    return rewardMint;
+  }
+
+  /** Funds reward `rewardIndex` with `amount`, returns its coverage event and
+   * the program logs */
+  async function fund(rewardIndex: number, amount: number) {
+    const rewardMint = await scheduledReward(rewardIndex);
+    const signature = await program.methods
+      .addRewards(new BN(amount), new BN(rewardIndex))
+      .accounts({
+        payer: admin.publicKey,
+        farmState,
+        rewardVault: rewardVault(farmState, rewardMint),
+        farmVaultsAuthority: farmVaultsAuthority(farmState),
+        payerRewardTokenAta: await fundedAta(
+          rewardMint,
+          admin.publicKey,
+          amount
+        ),
+        rewardMint,
+        scopePrices: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        globalConfig,
+      })
+      .rpc({ commitment: "confirmed" });
+
+    const logs = (
+      await provider.connection.getTransaction(signature, {
+        commitment: "confirmed",
+        maxSupportedTransactionVersion: 0,
+      })
+    ).meta.logMessages;
+    const parser = new EventParser(
+      program.programId,
+      new BorshCoder(program.idl)
+    );
+    const events = [...parser.parseLogs(logs)].filter(
+      (event) => event.name === "RewardFundingCoverageEvent"
+    );
+    expect(events).to.have.length(1);
+    return { event: events[0].data, logs };
+  }
+
+  function hasWarning(logs: string[]): boolean {
+    return logs.some((log) => log.includes("WARNING: reward"));
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    await updateGlobalConfig(
+      globalConfig,
+      SET_MIN_REWARD_FUNDING_COVERAGE_BPS,
+      u64(MIN_COVERAGE_BPS)
+    );
+    farmState = await createFarm(globalConfig, await newMint());
+  });
+
+  const cases = [
+    { name: "under", amount: SCHEDULED / 4, belowThreshold: true },
+    { name: "exactly", amount: SCHEDULED, belowThreshold: false },
+    { name: "over", amount: SCHEDULED * 2, belowThreshold: false },
+  ];
+  cases.forEach(({ name, amount, belowThreshold }, rewardIndex) => {
+    it(`reports the coverage of ${name}-funded rewards`, async () => {
+      const { event, logs } = await fund(rewardIndex, amount);
+
+      expect(event.rewardIndex.toNumber()).to.equal(rewardIndex);
+      expect(event.rewardsAvailable.toNumber()).to.equal(amount);
+      expect(event.rewardsVaultAmount.toNumber()).to.equal(amount);
+      expect(event.scheduledRemaining.toNumber()).to.equal(SCHEDULED);
+      expect(event.coverageBps.toNumber()).to.equal(
+        Math.floor((amount * 10_000) / SCHEDULED)
+      );
+      expect(event.belowThreshold).to.equal(belowThreshold);
+      // A warning, the funding still went through
+      expect(hasWarning(logs)).to.equal(belowThreshold);
+    });
+  });
+});
@@ -0,0 +1,282 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import {
+  ASSOCIATED_TOKEN_PROGRAM_ID,
+  getAssociatedTokenAddressSync,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  farmVault,
+  farmVaultsAuthority,
+  fundedAta,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  rewardTreasuryVault,
+  rewardVault,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+  userStatePda,
+} from "./utils";
+
+const NUM_REWARDS = 2;
+const U64_MAX = new BN(2).pow(new BN(64)).subn(1);
+
+describe("reward index bounds", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let targetFarmState: PublicKey;
+  let rewardMint: PublicKey;
+  let owner: Keypair;
+  let userState: PublicKey;
+
+  // Accounts of the last reward: out of range indexes must fail on the index, before
+  // any account is looked up by it
+  const instructions: Record<string, (rewardIndex: BN) => Promise<unknown>> = {
+    add_rewards: async (rewardIndex) =>
+      program.methods
+        .addRewards(new BN(1), rewardIndex)
+        .accounts({
+          payer: admin.publicKey,
+          farmState,
+          rewardVault: rewardVault(farmState, rewardMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          payerRewardTokenAta: await fundedAta(rewardMint, admin.publicKey, 1),
+          rewardMint,
+          scopePrices: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          globalConfig,
+        })
+        .rpc(),
+    harvest_reward: async (rewardIndex) =>
+      program.methods
+        .harvestReward(rewardIndex)
+        .accounts({
+          owner: owner.publicKey,
+          userState,
+          farmState,
+          globalConfig,
+          userRewardAta: await fundedAta(rewardMint, owner.publicKey, 0),
+          rewardsVault: rewardVault(farmState, rewardMint),
+          rewardsTreasuryVault: rewardTreasuryVault(globalConfig, rewardMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          scopePrices: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          splitDestination: null,
+          splitDestinationAta: null,
+          rewardMint: null,
+          associatedTokenProgram: null,
+          systemProgram: null,
+          referrerState: null,
+        })
+        .signers([owner])
+        .rpc(),
+    harvest_to_owner: async (rewardIndex) =>
+      program.methods
+        .harvestToOwner(rewardIndex)
+        .accounts({
+          payer: admin.publicKey,
+          owner: owner.publicKey,
+          userState,
+          farmState,
+          globalConfig,
+          ownerRewardAta: getAssociatedTokenAddressSync(
+            rewardMint,
+            owner.publicKey
+          ),
+          rewardsVault: rewardVault(farmState, rewardMint),
+          rewardsTreasuryVault: rewardTreasuryVault(globalConfig, rewardMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          rewardMint,
+          scopePrices: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          associatedTokenProgram: ASSOCIATED_TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          splitDestination: null,
+          splitDestinationAta: null,
+        })
+        .rpc(),
+    harvest_and_stake_other: async (rewardIndex) =>
+      program.methods
+        .harvestAndStakeOther(rewardIndex)
+        .accounts({
+          owner: owner.publicKey,
+          userState,
+          farmState,
+          globalConfig,
+          rewardsVault: rewardVault(farmState, rewardMint),
+          rewardsTreasuryVault: rewardTreasuryVault(globalConfig, rewardMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          scopePrices: null,
+          targetFarmState,
+          targetUserState: userStatePda(targetFarmState, owner.publicKey),
+          targetFarmVault: farmVault(targetFarmState, rewardMint),
+          targetScopePrices: null,
+          targetUserPage: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          systemProgram: SystemProgram.programId,
+          rewardMint: null,
+        })
+        .signers([owner])
+        .rpc(),
+    withdraw_reward: async (rewardIndex) =>
+      program.methods
+        .withdrawReward(new BN(1), rewardIndex)
+        .accounts({
+          farmAdmin: admin.publicKey,
+          farmState,
+          rewardVault: rewardVault(farmState, rewardMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          adminRewardTokenAta: await fundedAta(rewardMint, admin.publicKey, 0),
+          scopePrices: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          rewardMint: null,
+          globalConfig,
+        })
+        .rpc(),
+    reward_user_once: async (rewardIndex) =>
+      program.methods
+        .rewardUserOnce(rewardIndex, new BN(1))
+        .accounts({
+          farmAdmin: admin.publicKey,
+          farmState,
+          userState,
+          globalConfig,
+        })
+        .rpc(),
+    credit_users: async (rewardIndex) =>
+      program.methods
+        .creditUsers(rewardIndex, new BN(Date.now()), [])
+        .accounts({
+          farmAdmin: admin.publicKey,
+          farmState,
+          globalConfig,
+          rewardsVault: rewardVault(farmState, rewardMint),
+        })
+        .rpc(),
+    report_reward_shortfall: async (rewardIndex) =>
+      program.methods
+        .reportRewardShortfall(rewardIndex)
+        .accounts({
+          farmState,
+          globalConfig,
+          rewardsVault: rewardVault(farmState, rewardMint),
+          scopePrices: null,
+        })
+        .rpc(),
+    set_reward_destination: async (rewardIndex) =>
+      program.methods
+        .setRewardDestination(rewardIndex)
+        .accounts({
+          owner: owner.publicKey,
+          userState,
+          farmState,
+          globalConfig,
+          rewardDestination: null,
+        })
+        .signers([owner])
+        .rpc(),
+    commit_raffle: async (rewardIndex) => {
+      const numRaffles = (await program.account.farmState.fetch(farmState))
+        .numRaffles;
+      const raffleState = PublicKey.findProgramAddressSync(
+        [
+          Buffer.from("raffle"),
+          farmState.toBuffer(),
+          numRaffles.toArrayLike(Buffer, "le", 8),
+        ],
+        program.programId
+      )[0];
+      const slot = await program.provider.connection.getSlot();
+      return program.methods
+        .commitRaffle(rewardIndex, new BN(slot + 10), new BN(1))
+        .accounts({
+          farmAdmin: admin.publicKey,
+          farmState,
+          globalConfig,
+          raffleState,
+          scopePrices: null,
+          systemProgram: SystemProgram.programId,
+          rent: SYSVAR_RENT_PUBKEY,
+        })
+        .rpc();
+    },
+    sweep_diverted_rewards: async (rewardIndex) =>
+      program.methods
+        .sweepDivertedRewards(rewardIndex)
+        .accounts({
+          farmState,
+          globalConfig,
+          rewardsVault: rewardVault(farmState, rewardMint),
+          rewardsTreasuryVault: rewardTreasuryVault(globalConfig, rewardMint),
+          farmVaultsAuthority: farmVaultsAuthority(farmState),
+          rewardMint: null,
+          scopePrices: null,
+          tokenProgram: TOKEN_PROGRAM_ID,
+        })
+        .rpc(),
+    update_farm_config: async (rewardIndex) =>
+      updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateRewardMinClaimDuration,
+        Buffer.concat([u64(rewardIndex), u64(0)])
+      ),
+  };
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    const tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    for (let i = 0; i < NUM_REWARDS; i++) {
+      rewardMint = await newMint();
+      await initializeReward(globalConfig, farmState, rewardMint);
+      await addRewards(farmState, rewardMint, i, 1_000);
+    }
+    targetFarmState = await createFarm(globalConfig, rewardMint);
+
+    owner = await newUser();
+    userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000).rpc();
+  });
+
+  for (const [name, call] of Object.entries(instructions)) {
+    it(`${name} checks the reward index`, async () => {
+      try {
+        await call(new BN(NUM_REWARDS - 1));
+      } catch (err) {
+        // The last reward may still fail for other reasons, not its index
+        expect(err?.error?.errorCode?.code, `${err}`).to.not.equal(
+          "RewardIndexOutOfRange"
+        );
+      }
+
+      for (const rewardIndex of [new BN(NUM_REWARDS), U64_MAX]) {
+        try {
+          await call(rewardIndex);
+          expect.fail(`reward index ${rewardIndex} accepted`);
+        } catch (err) {
+          expect(err, `${rewardIndex}: ${err}`).to.be.instanceOf(
+            anchor.AnchorError
+          );
+          expect(err.error.errorCode.code).to.equal("RewardIndexOutOfRange");
+        }
+      }
+    });
+  }
+});
@@ -0,0 +1,234 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import {
+  burn,
+  createInitializeMintInstruction,
+  createInitializePermanentDelegateInstruction,
+  ExtensionType,
+  getAccount,
+  getMintLen,
+  TOKEN_2022_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  refreshFarm,
+  rewardValue,
+  rewardVault,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+  updateGlobalConfig,
+} from "./utils";
+
+const ADD_ALLOWLISTED_MINT = 8;
+const FUNDING = 1_000;
+
+describe("reward shortfall", () => {
+  let globalConfig: PublicKey;
+  let farmState: PublicKey;
+  let rewardMint: PublicKey;
+  let vault: PublicKey;
+  let users: { owner: Keypair; rewardAta: PublicKey }[];
+
+  async function balance(ata: PublicKey): Promise<number> {
+    const account = await getAccount(
+      provider.connection,
+      ata,
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+    return Number(account.amount);
+  }
+
+  function reportShortfall() {
+    return program.methods
+      .reportRewardShortfall(new BN(0))
+      .accounts({
+        farmState,
+        globalConfig,
+        rewardsVault: vault,
+        scopePrices: null,
+      })
+      .rpc();
+  }
+
+  function harvest(user: { owner: Keypair; rewardAta: PublicKey }) {
+    return harvestIx(
+      globalConfig,
+      farmState,
+      rewardMint,
+      user.owner,
+      user.rewardAta,
+      0,
+      TOKEN_2022_PROGRAM_ID
+    ).rpc();
+  }
+
+  /** Token-2022 mint whose permanent delegate, the admin, can take tokens out of
+   * the rewards vault */
+  async function newPermanentDelegateMint(): Promise<PublicKey> {
+    const mint = Keypair.generate();
+    const space = getMintLen([ExtensionType.PermanentDelegate]);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: admin.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space,
+          lamports:
+            await provider.connection.getMinimumBalanceForRentExemption(space),
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializePermanentDelegateInstruction(
+          mint.publicKey,
+          admin.publicKey,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(
+          mint.publicKey,
+          6,
+          admin.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [mint]
+    );
+    return mint.publicKey;
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    const tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+
+    rewardMint = await newPermanentDelegateMint();
+    await updateGlobalConfig(
+      globalConfig,
+      ADD_ALLOWLISTED_MINT,
+      rewardMint.toBuffer()
+    );
+    vault = rewardVault(farmState, rewardMint);
+    await initializeReward(
+      globalConfig,
+      farmState,
+      rewardMint,
+      vault,
+      TOKEN_2022_PROGRAM_ID
+    );
+    await addRewards(farmState, rewardMint, 0, FUNDING, TOKEN_2022_PROGRAM_ID);
+
+    users = [];
+    for (let i = 0; i < 2; i++) {
+      const owner = await newUser();
+      await createUser(farmState, owner);
+      const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+      await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+      users.push({
+        owner,
+        rewardAta: await fundedAta(
+          rewardMint,
+          owner.publicKey,
+          0,
+          TOKEN_2022_PROGRAM_ID
+        ),
+      });
+    }
+
+    // Issues all of the funding in a second, then half of it leaves the vault
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(FUNDING))
+    );
+    await sleep(3_000);
+    await refreshFarm(farmState);
+    await burn(
+      provider.connection,
+      admin,
+      vault,
+      rewardMint,
+      admin,
+      FUNDING / 2,
+      [],
+      undefined,
+      TOKEN_2022_PROGRAM_ID
+    );
+  });
+
+  it("flags a reward whose vault holds less than owed", async () => {
+    const reward = (await program.account.farmState.fetch(farmState))
+      .rewardInfos[0];
+    expect(reward.rewardsIssuedUnclaimed.toNumber()).to.equal(FUNDING);
+    expect(reward.shortfall).to.equal(0);
+
+    await reportShortfall();
+    expect(
+      (await program.account.farmState.fetch(farmState)).rewardInfos[0]
+        .shortfall
+    ).to.equal(1);
+  });
+
+  it("pays harvests pro-rata to the vault coverage", async () => {
+    const [first] = users;
+    await harvest(first);
+
+    // Half of the owed rewards are in the vault, half of the user share is paid
+    const paid = await balance(first.rewardAta);
+    expect(paid).to.be.within(FUNDING / 4 - 1, FUNDING / 4);
+    expect(await balance(vault)).to.equal(FUNDING / 2 - paid);
+  });
+
+  it("clears once add_rewards restores the coverage", async () => {
+    await addRewards(
+      farmState,
+      rewardMint,
+      0,
+      FUNDING / 2,
+      TOKEN_2022_PROGRAM_ID
+    );
+    const reward = (await program.account.farmState.fetch(farmState))
+      .rewardInfos[0];
+    expect(reward.shortfall).to.equal(0);
+
+    const [first, second] = users;
+    await harvest(second);
+    await harvest(first);
+    expect(await balance(second.rewardAta)).to.be.within(
+      FUNDING / 2 - 1,
+      FUNDING / 2
+    );
+    expect(await balance(first.rewardAta)).to.be.within(
+      FUNDING / 2 - 1,
+      FUNDING / 2
+    );
+
+    try {
+      await reportShortfall();
+      expect.fail("shortfall reported on a covered reward");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("NoRewardShortfall");
+    }
+  });
+});
@@ -0,0 +1,114 @@
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  refreshFarm,
+  rewardValue,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+const RPS = 100;
+const FUNDING = 450;
+
+/** `simulate::emissions_per_interval` for a constant curve without oracle price */
+function emissionsPerInterval(
+  rps: number,
+  funding: number,
+  startTs: number,
+  endTs: number,
+  interval: number
+): number[] {
+  const emissions = [];
+  let remaining = funding;
+  for (let fromTs = startTs; fromTs < endTs; fromTs += interval) {
+    const toTs = Math.min(endTs, fromTs + interval);
+    const issued = Math.min(rps * (toTs - fromTs), remaining);
+    remaining -= issued;
+    emissions.push(issued);
+  }
+  return emissions;
+}
+
+describe("simulate", () => {
+  let farmState: PublicKey;
+
+  async function issuance(): Promise<{ ts: number; cumulative: number }> {
+    const reward = (await program.account.farmState.fetch(farmState))
+      .rewardInfos[0];
+    return {
+      ts: reward.lastIssuanceTs.toNumber(),
+      cumulative: reward.rewardsIssuedCumulative.toNumber(),
+    };
+  }
+
+  before(async () => {
+    const globalConfig = await createGlobalConfig();
+    const tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    const rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, FUNDING);
+
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+  });
+
+  it("matches the on-chain issuance of a constant campaign", async () => {
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(RPS))
+    );
+    const start = await issuance();
+
+    // Until the funding runs out, at RPS = 100 after 4.5s
+    const observed = [start];
+    for (let i = 0; i < 4; i++) {
+      await sleep(2_000);
+      await refreshFarm(farmState);
+      observed.push(await issuance());
+    }
+
+    const last = observed[observed.length - 1];
+    const [simulated] = emissionsPerInterval(
+      RPS,
+      FUNDING,
+      start.ts,
+      last.ts,
+      last.ts - start.ts
+    );
+    expect(last.cumulative - start.cumulative).to.equal(simulated);
+
+    // Interval by interval, with the funding carried over
+    let remaining = FUNDING;
+    for (let i = 1; i < observed.length; i++) {
+      const [expected] = emissionsPerInterval(
+        RPS,
+        remaining,
+        observed[i - 1].ts,
+        observed[i].ts,
+        observed[i].ts - observed[i - 1].ts
+      );
+      const issued = observed[i].cumulative - observed[i - 1].cumulative;
+      expect(issued, `${observed[i - 1].ts}..${observed[i].ts}`).to.equal(
+        expected ?? 0
+      );
+      remaining -= issued;
+    }
+    expect(remaining).to.equal(0);
+  });
+});
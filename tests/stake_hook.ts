@@ -0,0 +1,158 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, Program } from "@coral-xyz/anchor";
+import { PublicKey, SystemProgram } from "@solana/web3.js";
+import { expect } from "chai";
+import { TestHooks } from "../target/types/test_hooks";
+import {
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  newMint,
+  newUser,
+  program,
+  StakeHook,
+  stakeIx,
+  unstakeIx,
+  updateFarmConfig,
+} from "./utils";
+
+describe("stake hook", () => {
+  const hooks = anchor.workspace.TestHooks as Program<TestHooks>;
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+  let hook: StakeHook;
+
+  async function setHookFailing(failing: boolean) {
+    await hooks.methods
+      .setHookFailing(failing)
+      .accounts({ authority: admin.publicKey, hookConfig: hook.hookConfig })
+      .rpc();
+  }
+
+  before(async () => {
+    const hookConfig = PublicKey.findProgramAddressSync(
+      [Buffer.from("hook_config"), admin.publicKey.toBuffer()],
+      hooks.programId
+    )[0];
+    await hooks.methods
+      .initializeHookConfig()
+      .accounts({
+        authority: admin.publicKey,
+        hookConfig,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+    hook = { hookProgram: hooks.programId, hookConfig };
+
+    const globalConfig = await createGlobalConfig();
+    tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateHookProgram,
+      hooks.programId.toBuffer()
+    );
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateHookConfig,
+      hookConfig.toBuffer()
+    );
+  });
+
+  it("is called with the old and new stake after a stake", async () => {
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000, hook).rpc();
+
+    const config = await hooks.account.hookConfig.fetch(hook.hookConfig);
+    const user = await program.account.userState.fetch(userState);
+    expect(config.lastFarmState.toBase58()).to.equal(farmState.toBase58());
+    expect(config.lastOwner.toBase58()).to.equal(owner.publicKey.toBase58());
+    expect(config.lastOldStakeScaled.toString()).to.equal("0");
+    expect(config.lastNewStakeScaled.toString()).to.equal(
+      user.activeStakeScaled.toString()
+    );
+  });
+
+  it("is required on stake", async () => {
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+
+    try {
+      await stakeIx(farmState, tokenMint, owner, ata, 1_000).rpc();
+      expect.fail("stake without the hook accounts accepted");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidHookAccounts");
+    }
+  });
+
+  it("reverts the stake when it fails", async () => {
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+    await setHookFailing(true);
+
+    try {
+      await stakeIx(farmState, tokenMint, owner, ata, 1_000, hook).rpc();
+      expect.fail("stake accepted with a failing hook");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("HookFailing");
+    } finally {
+      await setHookFailing(false);
+    }
+
+    const user = await program.account.userState.fetch(userState);
+    expect(user.activeStakeScaled.toString()).to.equal("0");
+  });
+
+  it("is best-effort on unstake", async () => {
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000, hook).rpc();
+    const stake = (await program.account.userState.fetch(userState))
+      .activeStakeScaled;
+    const numCalls = (await hooks.account.hookConfig.fetch(hook.hookConfig))
+      .numCalls;
+
+    // A failing hook aborts the unstake passing its accounts, the user can still exit
+    // without them
+    await setHookFailing(true);
+    try {
+      await unstakeIx(farmState, owner, stake, hook).rpc();
+      expect.fail("unstake accepted with a failing hook");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("HookFailing");
+    }
+    await unstakeIx(farmState, owner, stake).rpc();
+    await setHookFailing(false);
+
+    const user = await program.account.userState.fetch(userState);
+    expect(user.activeStakeScaled.toString()).to.equal("0");
+    const config = await hooks.account.hookConfig.fetch(hook.hookConfig);
+    expect(config.numCalls.toString()).to.equal(numCalls.toString());
+  });
+
+  it("is notified of the unstake when its accounts are passed", async () => {
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000, hook).rpc();
+    const stake = (await program.account.userState.fetch(userState))
+      .activeStakeScaled;
+
+    await unstakeIx(farmState, owner, stake.div(new BN(2)), hook).rpc();
+
+    const config = await hooks.account.hookConfig.fetch(hook.hookConfig);
+    expect(config.lastOldStakeScaled.toString()).to.equal(stake.toString());
+    expect(config.lastNewStakeScaled.toString()).to.equal(
+      stake.sub(stake.div(new BN(2))).toString()
+    );
+  });
+});
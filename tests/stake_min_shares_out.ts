@@ -0,0 +1,111 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import { getAccount, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  donateToFarmVault,
+  farmVault,
+  fundedAta,
+  globalConfigOf,
+  newMint,
+  newUser,
+  program,
+  provider,
+  stakeIx,
+  userStatePda,
+} from "./utils";
+
+const AMOUNT = 1_000_000;
+const WAD = new BN(10).pow(new BN(18));
+
+describe("stake min shares out", () => {
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+
+  function stakeWithOptions(
+    owner: Keypair,
+    userAta: PublicKey,
+    minSharesOut: number
+  ) {
+    return program.methods
+      .stakeWithOptions(new BN(AMOUNT), false, new BN(minSharesOut))
+      .accounts({
+        owner: owner.publicKey,
+        userState: userStatePda(farmState, owner.publicKey),
+        farmState,
+        farmVault: farmVault(farmState, tokenMint),
+        userAta,
+        tokenMint,
+        scopePrices: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        hookProgram: null,
+        hookConfig: null,
+        globalConfig: globalConfigOf(farmState),
+      })
+      .signers([owner])
+      .rpc();
+  }
+
+  async function activeStake(owner: Keypair): Promise<BN> {
+    const user = await program.account.userState.fetch(
+      userStatePda(farmState, owner.publicKey)
+    );
+    return user.activeStakeScaled;
+  }
+
+  async function balance(ata: PublicKey): Promise<number> {
+    return Number((await getAccount(provider.connection, ata)).amount);
+  }
+
+  before(async () => {
+    const globalConfig = await createGlobalConfig();
+    tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+
+    // 1 share per token
+    const seed = await newUser();
+    await createUser(farmState, seed);
+    const seedAta = await fundedAta(tokenMint, seed.publicKey, AMOUNT);
+    await stakeIx(farmState, tokenMint, seed, seedAta, AMOUNT).rpc();
+  });
+
+  it("fails when a donation lands between quote and execution", async () => {
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, AMOUNT);
+    const quotedShares = AMOUNT;
+
+    // Doubles the amount per share, halving the shares of `AMOUNT`
+    await donateToFarmVault(farmState, tokenMint, AMOUNT);
+
+    try {
+      await stakeWithOptions(owner, ata, quotedShares);
+      expect.fail("staked below min_shares_out");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("StakeSharesOutTooLow");
+    }
+    expect((await activeStake(owner)).isZero()).to.equal(true);
+    expect(await balance(ata)).to.equal(AMOUNT);
+
+    // The shares actually credited at the new price pass
+    await stakeWithOptions(owner, ata, quotedShares / 2);
+    expect((await activeStake(owner)).div(WAD).toNumber()).to.equal(
+      quotedShares / 2
+    );
+  });
+
+  it("is disabled by a zero min_shares_out", async () => {
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, AMOUNT);
+
+    await donateToFarmVault(farmState, tokenMint, AMOUNT);
+    await stakeWithOptions(owner, ata, 0);
+    expect((await activeStake(owner)).isZero()).to.equal(false);
+  });
+});
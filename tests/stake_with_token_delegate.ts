@@ -0,0 +1,136 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import {
+  approve,
+  getAccount,
+  revoke,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  farmVault,
+  fundedAta,
+  globalConfigOf,
+  newMint,
+  newUser,
+  program,
+  provider,
+  userStatePda,
+} from "./utils";
+
+const APPROVED = 1_000_000;
+
+describe("stake with token delegate", () => {
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+  let owner: Keypair;
+  let delegate: Keypair;
+  let ownerAta: PublicKey;
+
+  function stakeWithTokenDelegate(amount: number | BN) {
+    return program.methods
+      .stakeWithTokenDelegate(new BN(amount))
+      .accounts({
+        delegate: delegate.publicKey,
+        owner: owner.publicKey,
+        userState: userStatePda(farmState, owner.publicKey),
+        farmState,
+        globalConfig: globalConfigOf(farmState),
+        farmVault: farmVault(farmState, tokenMint),
+        userAta: ownerAta,
+        tokenMint,
+        scopePrices: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        hookProgram: null,
+        hookConfig: null,
+      })
+      .signers([delegate])
+      .rpc();
+  }
+
+  async function expectError(call: Promise<unknown>, code: string) {
+    try {
+      await call;
+      expect.fail(`expected ${code}`);
+    } catch (err) {
+      expect(err, `${err}`).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal(code);
+    }
+  }
+
+  async function activeStake(): Promise<BN> {
+    const user = await program.account.userState.fetch(
+      userStatePda(farmState, owner.publicKey)
+    );
+    return user.activeStakeScaled;
+  }
+
+  before(async () => {
+    const globalConfig = await createGlobalConfig();
+    tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+
+    owner = await newUser();
+    delegate = await newUser();
+    await createUser(farmState, owner);
+    ownerAta = await fundedAta(tokenMint, owner.publicKey, 10 * APPROVED);
+    await approve(
+      provider.connection,
+      admin,
+      ownerAta,
+      delegate.publicKey,
+      owner,
+      APPROVED
+    );
+  });
+
+  it("stakes up to the approval into the owner's position", async () => {
+    await expectError(
+      stakeWithTokenDelegate(APPROVED + 1),
+      "DelegatedAmountExceeded"
+    );
+
+    await stakeWithTokenDelegate(APPROVED / 2);
+    expect((await activeStake()).isZero()).to.equal(false);
+    const ata = await getAccount(provider.connection, ownerAta);
+    expect(Number(ata.delegatedAmount)).to.equal(APPROVED / 2);
+  });
+
+  it("is not allowed to unstake the position it funded", async () => {
+    const stake = await activeStake();
+    await expectError(
+      program.methods
+        .unstake(stake)
+        .accounts({
+          owner: delegate.publicKey,
+          userState: userStatePda(farmState, owner.publicKey),
+          farmState,
+          scopePrices: null,
+          hookProgram: null,
+          hookConfig: null,
+          globalConfig: globalConfigOf(farmState),
+        })
+        .signers([delegate])
+        .rpc(),
+      "ConstraintHasOne"
+    );
+    expect((await activeStake()).eq(stake)).to.equal(true);
+  });
+
+  it("fails cleanly once the approval is revoked", async () => {
+    const stake = await activeStake();
+    await revoke(provider.connection, admin, ownerAta, owner);
+
+    await expectError(stakeWithTokenDelegate(1), "InvalidTokenDelegate");
+    await expectError(
+      stakeWithTokenDelegate(new BN("18446744073709551615")),
+      "InvalidTokenDelegate"
+    );
+    expect((await activeStake()).eq(stake)).to.equal(true);
+  });
+});
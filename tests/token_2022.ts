@@ -0,0 +1,208 @@
+import { BN } from "@coral-xyz/anchor";
+import {
+  createInitializeMintInstruction,
+  createInitializeTransferFeeConfigInstruction,
+  ExtensionType,
+  getAccount,
+  getMintLen,
+  TOKEN_2022_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+  TransactionInstruction,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  farmVault,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newUser,
+  program,
+  provider,
+  rewardValue,
+  rewardVault,
+  sleep,
+  stakeIx,
+  u64,
+  unstakeIx,
+  updateFarmConfig,
+  userStatePda,
+  withdrawUnstakedDepositsIx,
+} from "./utils";
+
+const AMOUNT = 1_000_000;
+const REWARDS = 10_000_000;
+const RPS = 1_000;
+const WAD = new BN(10).pow(new BN(18));
+
+describe("token 2022", () => {
+  let globalConfig: PublicKey;
+
+  /** Token-2022 mint charging `feeBps` on every transfer, no extension at 0 */
+  async function newToken2022Mint(feeBps: number): Promise<PublicKey> {
+    const mint = Keypair.generate();
+    const extensions = feeBps > 0 ? [ExtensionType.TransferFeeConfig] : [];
+    const space = getMintLen(extensions);
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(space);
+    const initializeFee: TransactionInstruction[] =
+      feeBps > 0
+        ? [
+            createInitializeTransferFeeConfigInstruction(
+              mint.publicKey,
+              admin.publicKey,
+              admin.publicKey,
+              feeBps,
+              BigInt(REWARDS),
+              TOKEN_2022_PROGRAM_ID
+            ),
+          ]
+        : [];
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: admin.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        ...initializeFee,
+        createInitializeMintInstruction(
+          mint.publicKey,
+          6,
+          admin.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [mint]
+    );
+    return mint.publicKey;
+  }
+
+  async function balance(ata: PublicKey): Promise<number> {
+    const account = await getAccount(
+      provider.connection,
+      ata,
+      "confirmed",
+      TOKEN_2022_PROGRAM_ID
+    );
+    return Number(account.amount);
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  for (const feeBps of [0, 100]) {
+    it(`round trips at a transfer fee of ${feeBps} bps`, async () => {
+      // Transfer fees round up
+      const fee = (amount: number) => Math.ceil((amount * feeBps) / 10_000);
+
+      const tokenMint = await newToken2022Mint(feeBps);
+      const rewardMint = await newToken2022Mint(feeBps);
+      const farmState = await createFarm(
+        globalConfig,
+        tokenMint,
+        TOKEN_2022_PROGRAM_ID
+      );
+      await initializeReward(
+        globalConfig,
+        farmState,
+        rewardMint,
+        rewardVault(farmState, rewardMint),
+        TOKEN_2022_PROGRAM_ID
+      );
+      await addRewards(
+        farmState,
+        rewardMint,
+        0,
+        REWARDS,
+        TOKEN_2022_PROGRAM_ID
+      );
+      const funded = await program.account.farmState.fetch(farmState);
+      expect(funded.rewardInfos[0].rewardsAvailable.toNumber()).to.equal(
+        REWARDS - fee(REWARDS)
+      );
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateRewardRps,
+        rewardValue(0, u64(RPS))
+      );
+
+      const owner = await newUser();
+      await createUser(farmState, owner);
+      const ata = await fundedAta(
+        tokenMint,
+        owner.publicKey,
+        AMOUNT,
+        TOKEN_2022_PROGRAM_ID
+      );
+      const rewardAta = await fundedAta(
+        rewardMint,
+        owner.publicKey,
+        0,
+        TOKEN_2022_PROGRAM_ID
+      );
+
+      // Only what the vault received is staked
+      await stakeIx(
+        farmState,
+        tokenMint,
+        owner,
+        ata,
+        AMOUNT,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      ).rpc();
+      const received = AMOUNT - fee(AMOUNT);
+      const vault = farmVault(farmState, tokenMint);
+      expect(await balance(vault)).to.equal(received);
+      const staked = await program.account.farmState.fetch(farmState);
+      expect(staked.totalStakedAmount.toNumber()).to.equal(received);
+      const userState = userStatePda(farmState, owner.publicKey);
+      const user = await program.account.userState.fetch(userState);
+      expect(user.activeStakeScaled.div(WAD).toNumber()).to.equal(received);
+
+      await sleep(2_000);
+      const rewardsVault = rewardVault(farmState, rewardMint);
+      const vaultBefore = await balance(rewardsVault);
+      await harvestIx(
+        globalConfig,
+        farmState,
+        rewardMint,
+        owner,
+        rewardAta,
+        0,
+        TOKEN_2022_PROGRAM_ID
+      ).rpc();
+      const harvested = vaultBefore - (await balance(rewardsVault));
+      expect(harvested).to.be.above(0);
+      expect(await balance(rewardAta)).to.equal(harvested - fee(harvested));
+
+      await unstakeIx(farmState, owner, user.activeStakeScaled).rpc();
+      await withdrawUnstakedDepositsIx(
+        farmState,
+        tokenMint,
+        owner,
+        ata,
+        TOKEN_2022_PROGRAM_ID
+      ).rpc();
+      expect(await balance(vault)).to.equal(0);
+      expect(await balance(ata)).to.equal(received - fee(received));
+      const exited = await program.account.farmState.fetch(farmState);
+      expect(exited.totalStakedAmount.toNumber()).to.equal(0);
+    });
+  }
+});
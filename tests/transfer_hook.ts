@@ -0,0 +1,220 @@
+import * as anchor from "@coral-xyz/anchor";
+import { Program } from "@coral-xyz/anchor";
+import {
+  createInitializeMintInstruction,
+  createInitializeTransferHookInstruction,
+  ExtensionType,
+  getAccount,
+  getMintLen,
+  TOKEN_2022_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  AccountMeta,
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  Transaction,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import { TestHooks } from "../target/types/test_hooks";
+import {
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  farmVault,
+  farmVaultsAuthority,
+  fundedAta,
+  newUser,
+  program,
+  provider,
+  stakeIx,
+  unstakeIx,
+  updateGlobalConfig,
+  userStatePda,
+} from "./utils";
+
+const ADD_ALLOWLISTED_TRANSFER_HOOK_PROGRAM = 13;
+
+describe("transfer hook", () => {
+  const hooks = anchor.workspace.TestHooks as Program<TestHooks>;
+  let globalConfig: PublicKey;
+  let tokenMint: PublicKey;
+  let counter: PublicKey;
+  let hookAccounts: AccountMeta[];
+  let farmState: PublicKey;
+
+  async function newHookMint(): Promise<PublicKey> {
+    const mint = Keypair.generate();
+    const space = getMintLen([ExtensionType.TransferHook]);
+    const lamports =
+      await provider.connection.getMinimumBalanceForRentExemption(space);
+    await provider.sendAndConfirm(
+      new Transaction().add(
+        SystemProgram.createAccount({
+          fromPubkey: admin.publicKey,
+          newAccountPubkey: mint.publicKey,
+          space,
+          lamports,
+          programId: TOKEN_2022_PROGRAM_ID,
+        }),
+        createInitializeTransferHookInstruction(
+          mint.publicKey,
+          admin.publicKey,
+          hooks.programId,
+          TOKEN_2022_PROGRAM_ID
+        ),
+        createInitializeMintInstruction(
+          mint.publicKey,
+          6,
+          admin.publicKey,
+          null,
+          TOKEN_2022_PROGRAM_ID
+        )
+      ),
+      [mint]
+    );
+    return mint.publicKey;
+  }
+
+  async function transferCount(): Promise<number> {
+    return (
+      await hooks.account.transferCounter.fetch(counter)
+    ).numTransfers.toNumber();
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+    tokenMint = await newHookMint();
+
+    const extraAccountMetaList = PublicKey.findProgramAddressSync(
+      [Buffer.from("extra-account-metas"), tokenMint.toBuffer()],
+      hooks.programId
+    )[0];
+    counter = PublicKey.findProgramAddressSync(
+      [Buffer.from("transfer_counter"), tokenMint.toBuffer()],
+      hooks.programId
+    )[0];
+    await hooks.methods
+      .initializeExtraAccountMetaList()
+      .accounts({
+        payer: admin.publicKey,
+        extraAccountMetaList,
+        mint: tokenMint,
+        counter,
+        systemProgram: SystemProgram.programId,
+      })
+      .rpc();
+
+    hookAccounts = [
+      { pubkey: hooks.programId, isSigner: false, isWritable: false },
+      { pubkey: extraAccountMetaList, isSigner: false, isWritable: false },
+      { pubkey: counter, isSigner: false, isWritable: true },
+    ];
+  });
+
+  it("rejects a farm token whose hook program is not allowlisted", async () => {
+    try {
+      await createFarm(globalConfig, tokenMint, TOKEN_2022_PROGRAM_ID);
+      expect.fail("farm created with a hook program not allowlisted");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal(
+        "TransferHookProgramNotAllowlisted"
+      );
+    }
+  });
+
+  it("records the allowlisted hook program of the farm token", async () => {
+    await updateGlobalConfig(
+      globalConfig,
+      ADD_ALLOWLISTED_TRANSFER_HOOK_PROGRAM,
+      hooks.programId.toBuffer()
+    );
+    farmState = await createFarm(
+      globalConfig,
+      tokenMint,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    const farm = await program.account.farmState.fetch(farmState);
+    expect(farm.token.transferHookProgram.toBase58()).to.equal(
+      hooks.programId.toBase58()
+    );
+  });
+
+  it("forwards the hook accounts on stake and withdrawal", async () => {
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(
+      tokenMint,
+      owner.publicKey,
+      1_000_000,
+      TOKEN_2022_PROGRAM_ID
+    );
+
+    try {
+      await stakeIx(
+        farmState,
+        tokenMint,
+        owner,
+        ata,
+        1_000_000,
+        null,
+        TOKEN_2022_PROGRAM_ID
+      ).rpc();
+      expect.fail("stake went through without the hook accounts");
+    } catch (err) {
+      expect(await transferCount()).to.equal(0);
+    }
+
+    await stakeIx(
+      farmState,
+      tokenMint,
+      owner,
+      ata,
+      1_000_000,
+      null,
+      TOKEN_2022_PROGRAM_ID
+    )
+      .remainingAccounts(hookAccounts)
+      .rpc();
+    expect(await transferCount()).to.equal(1);
+
+    const userState = userStatePda(farmState, owner.publicKey);
+    const user = await program.account.userState.fetch(userState);
+    await unstakeIx(farmState, owner, user.activeStakeScaled).rpc();
+    await program.methods
+      .withdrawUnstakedDeposits()
+      .accounts({
+        owner: owner.publicKey,
+        userState,
+        farmState,
+        userAta: ata,
+        farmVault: farmVault(farmState, tokenMint),
+        farmVaultsAuthority: farmVaultsAuthority(farmState),
+        tokenProgram: TOKEN_2022_PROGRAM_ID,
+        tokenMint,
+        globalConfig,
+      })
+      .remainingAccounts(hookAccounts)
+      .signers([owner])
+      .rpc();
+
+    const counted = await hooks.account.transferCounter.fetch(counter);
+    expect(counted.numTransfers.toNumber()).to.equal(2);
+    expect(counted.amountTransferred.toNumber()).to.equal(2_000_000);
+    expect(
+      Number(
+        (
+          await getAccount(
+            provider.connection,
+            ata,
+            undefined,
+            TOKEN_2022_PROGRAM_ID
+          )
+        ).amount
+      )
+    ).to.equal(1_000_000);
+  });
+});
@@ -0,0 +1,90 @@
+import { BN } from "@coral-xyz/anchor";
+import { getAccount, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  farmVault,
+  fundedAta,
+  newMint,
+  newUser,
+  program,
+  provider,
+  stakeIx,
+  unstakeIx,
+  userStatePda,
+  withdrawUnstakedDepositsIx,
+} from "./utils";
+
+describe("unstake rounding", () => {
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+  let seed: Keypair;
+
+  async function balance(ata: PublicKey): Promise<bigint> {
+    return (await getAccount(provider.connection, ata)).amount;
+  }
+
+  before(async () => {
+    const globalConfig = await createGlobalConfig();
+    tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+
+    // 3 shares worth 10 lamports, so that no amount converts exactly
+    seed = await newUser();
+    await createUser(farmState, seed);
+    const seedAta = await fundedAta(tokenMint, seed.publicKey, 3);
+    await stakeIx(farmState, tokenMint, seed, seedAta, 3).rpc();
+    const adminAta = await fundedAta(tokenMint, admin.publicKey, 7);
+    await program.methods
+      .depositToFarmVault(new BN(7))
+      .accounts({
+        depositor: admin.publicKey,
+        farmState,
+        farmVault: farmVault(farmState, tokenMint),
+        depositorAta: adminAta,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        tokenMint: null,
+        globalConfig,
+      })
+      .rpc();
+  });
+
+  it("never returns more than was staked, at 1-lamport granularity", async () => {
+    const owner = await newUser();
+    const userState = await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000);
+
+    for (let amount = 1; amount <= 40; amount++) {
+      const before = await balance(ata);
+      await stakeIx(farmState, tokenMint, owner, ata, amount).rpc();
+
+      const shares = (await program.account.userState.fetch(userState))
+        .activeStakeScaled;
+      if (!shares.isZero()) {
+        await unstakeIx(farmState, owner, shares).rpc();
+        await withdrawUnstakedDepositsIx(farmState, tokenMint, owner, ata).rpc();
+      }
+
+      const returned = (await balance(ata)) - (before - BigInt(amount));
+      expect(
+        returned <= BigInt(amount),
+        `staked ${amount}, got back ${returned}`
+      ).to.be.true;
+    }
+  });
+
+  it("leaves the dust of the round trips to the remaining stake", async () => {
+    const farm = await program.account.farmState.fetch(farmState);
+    const seedUser = await program.account.userState.fetch(
+      userStatePda(farmState, seed.publicKey)
+    );
+    expect(farm.totalActiveStakeScaled.toString()).to.equal(
+      seedUser.activeStakeScaled.toString()
+    );
+    expect(farm.totalStakedAmount.toNumber()).to.be.at.least(10);
+  });
+});
@@ -0,0 +1,81 @@
+import * as anchor from "@coral-xyz/anchor";
+import { expect } from "chai";
+import {
+  createFarm,
+  createGlobalConfig,
+  FarmConfigOption,
+  initializeReward,
+  newMint,
+  NUM_FARM_CONFIG_OPTIONS,
+  prng,
+  program,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+describe("update_farm_config decoding", () => {
+  const random = prng(242);
+  let farmState: anchor.web3.PublicKey;
+
+  before(async () => {
+    const globalConfig = await createGlobalConfig();
+    farmState = await createFarm(globalConfig, await newMint());
+    await initializeReward(globalConfig, farmState, await newMint());
+  });
+
+  it("rejects an unknown mode", async () => {
+    try {
+      await updateFarmConfig(farmState, NUM_FARM_CONFIG_OPTIONS, u64(1));
+      expect.fail("unknown mode accepted");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("InvalidFarmConfigMode");
+    }
+  });
+
+  it("rejects a reward mode without a reward index", async () => {
+    try {
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateRewardRps,
+        Buffer.alloc(4)
+      );
+      expect.fail("truncated reward value accepted");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("InvalidConfigValue");
+    }
+  });
+
+  it("never panics on random (mode, 32 bytes) pairs", async () => {
+    const farmAdmin = (await program.account.farmState.fetch(farmState))
+      .farmAdmin;
+    for (let i = 0; i < 200; i++) {
+      // Past the last mode too, and data mostly made of a reward index of 0 and
+      // zero bytes so that some values decode
+      const mode = Math.floor(random() * (NUM_FARM_CONFIG_OPTIONS + 8));
+      const data = Buffer.alloc(32);
+      const randomBytes = Math.floor(random() * 33);
+      for (let j = 32 - randomBytes; j < 32; j++) {
+        data[j] = Math.floor(random() * 256);
+      }
+      if (random() < 0.5) {
+        data.fill(0, 0, 8);
+      }
+
+      try {
+        await updateFarmConfig(farmState, mode, data);
+      } catch (err) {
+        expect(
+          err,
+          `mode ${mode} data ${data.toString("hex")}: ${err}`
+        ).to.be.instanceOf(anchor.AnchorError);
+      }
+    }
+
+    // A random value must not have locked out the admin
+    expect(
+      (await program.account.farmState.fetch(farmState)).farmAdmin.toBase58()
+    ).to.equal(farmAdmin.toBase58());
+  });
+});
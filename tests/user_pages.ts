@@ -0,0 +1,130 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN } from "@coral-xyz/anchor";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  admin,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  fundedAta,
+  globalConfigOf,
+  newMint,
+  newUser,
+  program,
+  provider,
+  stakeIx,
+  updateFarmConfig,
+  userPagePda,
+} from "./utils";
+
+const NUM_USERS = 5;
+
+describe("user pages", () => {
+  let farmState: PublicKey;
+  let tokenMint: PublicKey;
+  let userPage: PublicKey;
+  const owners: Keypair[] = [];
+  const userStates: PublicKey[] = [];
+
+  function closeUser(owner: Keypair, userState: PublicKey) {
+    return program.methods
+      .closeUser()
+      .accounts({
+        owner: owner.publicKey,
+        userState,
+        farmState,
+        globalConfig: globalConfigOf(farmState),
+        userPage,
+      })
+      .signers([owner])
+      .rpc();
+  }
+
+  /** Live users of the page, tombstones left out */
+  async function enumerate(): Promise<string[]> {
+    const page = await program.account.userPage.fetch(userPage);
+    return page.userStates
+      .filter((userState) => !userState.equals(PublicKey.default))
+      .map((userState) => userState.toBase58());
+  }
+
+  before(async () => {
+    const globalConfig = await createGlobalConfig();
+    tokenMint = await newMint();
+    farmState = await createFarm(globalConfig, tokenMint);
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateUserPagesEnabled,
+      Buffer.from([1])
+    );
+
+    userPage = userPagePda(farmState, 0);
+    await program.methods
+      .initializeUserPage(new BN(0))
+      .accounts({
+        payer: admin.publicKey,
+        farmState,
+        globalConfig,
+        userPage,
+        systemProgram: SystemProgram.programId,
+        rent: SYSVAR_RENT_PUBKEY,
+      })
+      .rpc();
+
+    for (let i = 0; i < NUM_USERS; i++) {
+      const owner = await newUser();
+      owners.push(owner);
+      userStates.push(await createUser(farmState, owner, owner, userPage));
+    }
+  });
+
+  it("enumerates the users created, in user_id order", async () => {
+    const page = await program.account.userPage.fetch(userPage);
+    expect(page.numUsers.toNumber()).to.equal(NUM_USERS);
+    expect(await enumerate()).to.deep.equal(
+      userStates.map((userState) => userState.toBase58())
+    );
+    for (let i = 0; i < NUM_USERS; i++) {
+      const user = await program.account.userState.fetch(userStates[i]);
+      expect(user.userId.toNumber()).to.equal(i);
+    }
+  });
+
+  it("tombstones the slot of a closed user", async () => {
+    const closed = 2;
+    await closeUser(owners[closed], userStates[closed]);
+
+    const account = await provider.connection.getAccountInfo(
+      userStates[closed]
+    );
+    expect(account).to.equal(null);
+    const page = await program.account.userPage.fetch(userPage);
+    expect(page.userStates[closed].equals(PublicKey.default)).to.equal(true);
+    expect(page.numUsers.toNumber()).to.equal(NUM_USERS - 1);
+    expect(await enumerate()).to.deep.equal(
+      userStates
+        .filter((_, i) => i !== closed)
+        .map((userState) => userState.toBase58())
+    );
+  });
+
+  it("refuses to close a user with stake", async () => {
+    const ata = await fundedAta(tokenMint, owners[0].publicKey, 1_000);
+    await stakeIx(farmState, tokenMint, owners[0], ata, 1_000).rpc();
+    try {
+      await closeUser(owners[0], userStates[0]);
+      expect.fail("closed a user with stake");
+    } catch (err) {
+      expect(err).to.be.instanceOf(anchor.AnchorError);
+      expect(err.error.errorCode.code).to.equal("UserStateNotEmpty");
+    }
+    expect(await enumerate()).to.include(userStates[0].toBase58());
+  });
+});
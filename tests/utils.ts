@@ -0,0 +1,580 @@
+import * as anchor from "@coral-xyz/anchor";
+import { BN, BorshCoder, EventParser, Program } from "@coral-xyz/anchor";
+import {
+  createMint,
+  getOrCreateAssociatedTokenAccount,
+  mintTo,
+  TOKEN_PROGRAM_ID,
+} from "@solana/spl-token";
+import {
+  Keypair,
+  PublicKey,
+  SystemProgram,
+  SYSVAR_RENT_PUBKEY,
+} from "@solana/web3.js";
+import { Farms } from "../target/types/farms";
+
+anchor.setProvider(anchor.AnchorProvider.env());
+
+export const provider = anchor.getProvider() as anchor.AnchorProvider;
+export const program = anchor.workspace.Farms as Program<Farms>;
+export const admin = (provider.wallet as anchor.Wallet).payer;
+
+/** `FarmConfigOption` modes, in the order of the enum */
+export enum FarmConfigOption {
+  UpdateRewardRps,
+  UpdateRewardMinClaimDuration,
+  WithdrawAuthority,
+  DepositWarmupPeriod,
+  WithdrawCooldownPeriod,
+  RewardType,
+  RpsDecimals,
+  LockingMode,
+  LockingStartTimestamp,
+  LockingDuration,
+  LockingEarlyWithdrawalPenaltyBps,
+  DepositCapAmount,
+  SlashedAmountSpillAddress,
+  ScopePricesAccount,
+  ScopeOraclePriceId,
+  ScopeOracleMaxAge,
+  UpdateRewardScheduleCurvePoints,
+  UpdatePendingFarmAdmin,
+  UpdateStrategyId,
+  UpdateHookProgram,
+  UpdateHookConfig,
+  UpdateUserPagesEnabled,
+  UpdateVirtualOffsets,
+  UpdateNewUsersCutoffTs,
+  UpdateAdminWithdrawalLimit,
+  UpdateConfigAuthority,
+  UpdatePrimaryFarm,
+  UpdateRewardEarlyClaimPenaltyBps,
+  UpdateRewardPenaltyDecayWindow,
+  UpdateRewardClaimsOpenTs,
+  UpdateMinActiveUserStake,
+  UpdateRewardSplit,
+  UpdateLockTopUpPolicy,
+  UpdateHarvestFeeBpsOverride,
+  UpdateRequireRefreshInSlot,
+  UpdateHistorySnapshotInterval,
+  UpdateZeroStakeEmissionsToTreasury,
+  UpdateUserWithdrawalLimit,
+  AddDelegateAuthority,
+  RemoveDelegateAuthority,
+  UpdateAllowDuplicateRewardMints,
+}
+
+export const NUM_FARM_CONFIG_OPTIONS = 41;
+
+export function u64(value: number | BN): Buffer {
+  return new BN(value).toArrayLike(Buffer, "le", 8);
+}
+
+export function rewardValue(rewardIndex: number, value: Buffer): Buffer {
+  return Buffer.concat([u64(rewardIndex), value]);
+}
+
+/** Deterministic generator, so that a failing fuzz case can be replayed */
+export function prng(seed: number): () => number {
+  return () => {
+    seed = (seed + 0x6d2b79f5) | 0;
+    let t = Math.imul(seed ^ (seed >>> 15), 1 | seed);
+    t = (t + Math.imul(t ^ (t >>> 7), 61 | t)) ^ t;
+    return ((t ^ (t >>> 14)) >>> 0) / 4294967296;
+  };
+}
+
+export function farmVaultsAuthority(farmState: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("authority"), farmState.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function treasuryVaultsAuthority(globalConfig: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("authority"), globalConfig.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function farmVault(farmState: PublicKey, mint: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("fvault"), farmState.toBuffer(), mint.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function rewardVault(farmState: PublicKey, mint: PublicKey): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("rvault"), farmState.toBuffer(), mint.toBuffer()],
+    program.programId
+  )[0];
+}
+
+/** Vault of a reward, `duplicateIndex` being its reward index when another reward uses the same mint */
+export function duplicateRewardVault(
+  farmState: PublicKey,
+  mint: PublicKey,
+  duplicateIndex: number
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [
+      Buffer.from("rvault"),
+      farmState.toBuffer(),
+      mint.toBuffer(),
+      Buffer.from([duplicateIndex]),
+    ],
+    program.programId
+  )[0];
+}
+
+export function rewardTreasuryVault(
+  globalConfig: PublicKey,
+  mint: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("tvault"), globalConfig.toBuffer(), mint.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function userStatePda(
+  farmState: PublicKey,
+  delegatee: PublicKey
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("user"), farmState.toBuffer(), delegatee.toBuffer()],
+    program.programId
+  )[0];
+}
+
+export function userPagePda(
+  farmState: PublicKey,
+  pageIndex: number
+): PublicKey {
+  return PublicKey.findProgramAddressSync(
+    [Buffer.from("user_page"), farmState.toBuffer(), u64(pageIndex)],
+    program.programId
+  )[0];
+}
+
+export async function newMint(decimals = 6): Promise<PublicKey> {
+  return createMint(provider.connection, admin, admin.publicKey, null, decimals);
+}
+
+export async function fundedAta(
+  mint: PublicKey,
+  owner: PublicKey,
+  amount: number | bigint,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+): Promise<PublicKey> {
+  const ata = await getOrCreateAssociatedTokenAccount(
+    provider.connection,
+    admin,
+    mint,
+    owner,
+    true,
+    undefined,
+    undefined,
+    tokenProgram
+  );
+  if (BigInt(amount) > BigInt(0)) {
+    await mintTo(
+      provider.connection,
+      admin,
+      mint,
+      ata.address,
+      admin,
+      amount,
+      [],
+      undefined,
+      tokenProgram
+    );
+  }
+  return ata.address;
+}
+
+export async function newUser(lamports = 1_000_000_000): Promise<Keypair> {
+  const user = Keypair.generate();
+  const sig = await provider.connection.requestAirdrop(user.publicKey, lamports);
+  await provider.connection.confirmTransaction(sig, "confirmed");
+  return user;
+}
+
+export async function computeUnitsConsumed(signature: string): Promise<number> {
+  const tx = await provider.connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  return tx.meta.computeUnitsConsumed;
+}
+
+/** Data of the `name` events the program logged in the transaction `signature` */
+export async function programEvents(
+  signature: string,
+  name: string
+): Promise<any[]> {
+  const tx = await provider.connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const parser = new EventParser(
+    program.programId,
+    new BorshCoder(program.idl)
+  );
+  return [...parser.parseLogs(tx.meta.logMessages)]
+    .filter((event) => event.name === name)
+    .map((event) => event.data);
+}
+
+export async function returnData(signature: string): Promise<Buffer> {
+  const tx = await provider.connection.getTransaction(signature, {
+    commitment: "confirmed",
+    maxSupportedTransactionVersion: 0,
+  });
+  const data = (tx.meta as any).returnData;
+  return data ? Buffer.from(data.data[0], "base64") : Buffer.alloc(0);
+}
+
+export async function createGlobalConfig(): Promise<PublicKey> {
+  const globalConfig = Keypair.generate();
+  await program.methods
+    .initializeGlobalConfig()
+    .accounts({
+      globalAdmin: admin.publicKey,
+      globalConfig: globalConfig.publicKey,
+      treasuryVaultsAuthority: treasuryVaultsAuthority(globalConfig.publicKey),
+      systemProgram: SystemProgram.programId,
+    })
+    .preInstructions([
+      await program.account.globalConfig.createInstruction(globalConfig),
+    ])
+    .signers([globalConfig])
+    .rpc();
+  return globalConfig.publicKey;
+}
+
+export async function updateGlobalConfig(
+  globalConfig: PublicKey,
+  mode: number,
+  value: Buffer
+): Promise<string> {
+  const padded = Buffer.alloc(32);
+  value.copy(padded);
+  return program.methods
+    .updateGlobalConfig(mode, [...padded])
+    .accounts({ globalAdmin: admin.publicKey, globalConfig })
+    .rpc();
+}
+
+/** `GlobalConfig` of every farm created by `createFarm` and
+ * `createDelegatedFarm` */
+const farmGlobalConfigs = new Map<string, PublicKey>();
+
+/** `GlobalConfig` of `farmState`, required by most instructions for the kill
+ * switch */
+export function globalConfigOf(farmState: PublicKey): PublicKey {
+  const globalConfig = farmGlobalConfigs.get(farmState.toBase58());
+  if (!globalConfig) {
+    throw new Error(`farm ${farmState} not created by the test helpers`);
+  }
+  return globalConfig;
+}
+
+export async function createFarm(
+  globalConfig: PublicKey,
+  tokenMint: PublicKey,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+): Promise<PublicKey> {
+  const farmState = Keypair.generate();
+  await program.methods
+    .initializeFarm()
+    .accounts({
+      farmAdmin: admin.publicKey,
+      farmState: farmState.publicKey,
+      globalConfig,
+      farmVault: farmVault(farmState.publicKey, tokenMint),
+      farmVaultsAuthority: farmVaultsAuthority(farmState.publicKey),
+      tokenMint,
+      tokenProgram,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+      farmRegistry: null,
+    })
+    .preInstructions([
+      await program.account.farmState.createInstruction(farmState),
+    ])
+    .signers([farmState])
+    .rpc();
+  farmGlobalConfigs.set(farmState.publicKey.toBase58(), globalConfig);
+  return farmState.publicKey;
+}
+
+export async function createDelegatedFarm(
+  globalConfig: PublicKey,
+  farmDelegate: Keypair
+): Promise<PublicKey> {
+  const farmState = Keypair.generate();
+  await program.methods
+    .initializeFarmDelegated()
+    .accounts({
+      farmAdmin: admin.publicKey,
+      farmDelegate: farmDelegate.publicKey,
+      farmState: farmState.publicKey,
+      globalConfig,
+      farmVaultsAuthority: farmVaultsAuthority(farmState.publicKey),
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+      farmRegistry: null,
+    })
+    .preInstructions([
+      await program.account.farmState.createInstruction(farmState),
+    ])
+    .signers([farmState, farmDelegate])
+    .rpc();
+  farmGlobalConfigs.set(farmState.publicKey.toBase58(), globalConfig);
+  return farmState.publicKey;
+}
+
+export async function updateFarmConfig(
+  farmState: PublicKey,
+  mode: number,
+  data: Buffer
+): Promise<string> {
+  return program.methods
+    .updateFarmConfig(mode, data)
+    .accounts({
+      signer: admin.publicKey,
+      farmState,
+      scopePrices: null,
+      globalAdmin: null,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .rpc();
+}
+
+export async function initializeReward(
+  globalConfig: PublicKey,
+  farmState: PublicKey,
+  rewardMint: PublicKey,
+  vault: PublicKey = rewardVault(farmState, rewardMint),
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+): Promise<void> {
+  await program.methods
+    .initializeReward()
+    .accounts({
+      farmAdmin: admin.publicKey,
+      farmState,
+      globalConfig,
+      rewardVault: vault,
+      rewardTreasuryVault: rewardTreasuryVault(globalConfig, rewardMint),
+      farmVaultsAuthority: farmVaultsAuthority(farmState),
+      treasuryVaultsAuthority: treasuryVaultsAuthority(globalConfig),
+      rewardMint,
+      tokenProgram,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+    })
+    .rpc();
+}
+
+export async function addRewards(
+  farmState: PublicKey,
+  rewardMint: PublicKey,
+  rewardIndex: number,
+  amount: number,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+): Promise<string> {
+  const payerAta = await fundedAta(
+    rewardMint,
+    admin.publicKey,
+    amount,
+    tokenProgram
+  );
+  return program.methods
+    .addRewards(new BN(amount), new BN(rewardIndex))
+    .accounts({
+      payer: admin.publicKey,
+      farmState,
+      rewardVault: rewardVault(farmState, rewardMint),
+      farmVaultsAuthority: farmVaultsAuthority(farmState),
+      payerRewardTokenAta: payerAta,
+      rewardMint,
+      scopePrices: null,
+      tokenProgram,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .rpc();
+}
+
+/** Creates a user state owned and delegated to `owner`, `authority` signs for delegated farms.
+ * `userPage` is the page of the next `user_id` on farms with user pages */
+export async function createUser(
+  farmState: PublicKey,
+  owner: Keypair,
+  authority: Keypair = owner,
+  userPage: PublicKey | null = null
+): Promise<PublicKey> {
+  const userState = userStatePda(farmState, owner.publicKey);
+  await program.methods
+    .initializeUser()
+    .accounts({
+      authority: authority.publicKey,
+      payer: owner.publicKey,
+      owner: owner.publicKey,
+      delegatee: owner.publicKey,
+      userState,
+      farmState,
+      systemProgram: SystemProgram.programId,
+      rent: SYSVAR_RENT_PUBKEY,
+      userPage,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .signers(authority === owner ? [owner] : [owner, authority])
+    .rpc();
+  return userState;
+}
+
+/** Stake hook accounts of a farm, `null` when not passed */
+export type StakeHook = { hookProgram: PublicKey; hookConfig: PublicKey };
+
+export function stakeIx(
+  farmState: PublicKey,
+  tokenMint: PublicKey,
+  owner: Keypair,
+  userAta: PublicKey,
+  amount: number,
+  hook: StakeHook | null = null,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+) {
+  return program.methods
+    .stake(new BN(amount))
+    .accounts({
+      owner: owner.publicKey,
+      userState: userStatePda(farmState, owner.publicKey),
+      farmState,
+      farmVault: farmVault(farmState, tokenMint),
+      userAta,
+      tokenMint,
+      scopePrices: null,
+      tokenProgram,
+      hookProgram: hook?.hookProgram ?? null,
+      hookConfig: hook?.hookConfig ?? null,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .signers([owner]);
+}
+
+export function unstakeIx(
+  farmState: PublicKey,
+  owner: Keypair,
+  stakeSharesScaled: BN,
+  hook: StakeHook | null = null
+) {
+  return program.methods
+    .unstake(stakeSharesScaled)
+    .accounts({
+      owner: owner.publicKey,
+      userState: userStatePda(farmState, owner.publicKey),
+      farmState,
+      scopePrices: null,
+      hookProgram: hook?.hookProgram ?? null,
+      hookConfig: hook?.hookConfig ?? null,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .signers([owner]);
+}
+
+export function withdrawUnstakedDepositsIx(
+  farmState: PublicKey,
+  tokenMint: PublicKey,
+  owner: Keypair,
+  userAta: PublicKey,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+) {
+  return program.methods
+    .withdrawUnstakedDeposits()
+    .accounts({
+      owner: owner.publicKey,
+      userState: userStatePda(farmState, owner.publicKey),
+      farmState,
+      userAta,
+      farmVault: farmVault(farmState, tokenMint),
+      farmVaultsAuthority: farmVaultsAuthority(farmState),
+      tokenProgram,
+      tokenMint: tokenProgram.equals(TOKEN_PROGRAM_ID) ? null : tokenMint,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .signers([owner]);
+}
+
+/** Farm admin deposit of `amount` to the farm vault, raising the amount per
+ * stake share */
+export async function donateToFarmVault(
+  farmState: PublicKey,
+  tokenMint: PublicKey,
+  amount: number
+): Promise<string> {
+  return program.methods
+    .depositToFarmVault(new BN(amount))
+    .accounts({
+      depositor: admin.publicKey,
+      farmState,
+      farmVault: farmVault(farmState, tokenMint),
+      depositorAta: await fundedAta(tokenMint, admin.publicKey, amount),
+      tokenProgram: TOKEN_PROGRAM_ID,
+      tokenMint: null,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .rpc();
+}
+
+export async function refreshFarm(farmState: PublicKey): Promise<string> {
+  return program.methods
+    .refreshFarm()
+    .accounts({
+      farmState,
+      scopePrices: null,
+      farmHistory: null,
+      globalConfig: globalConfigOf(farmState),
+    })
+    .rpc();
+}
+
+export function harvestIx(
+  globalConfig: PublicKey,
+  farmState: PublicKey,
+  rewardMint: PublicKey,
+  owner: Keypair,
+  userRewardAta: PublicKey,
+  rewardIndex: number,
+  tokenProgram: PublicKey = TOKEN_PROGRAM_ID
+) {
+  return program.methods
+    .harvestReward(new BN(rewardIndex))
+    .accounts({
+      owner: owner.publicKey,
+      userState: userStatePda(farmState, owner.publicKey),
+      farmState,
+      globalConfig,
+      userRewardAta,
+      rewardsVault: rewardVault(farmState, rewardMint),
+      rewardsTreasuryVault: rewardTreasuryVault(globalConfig, rewardMint),
+      farmVaultsAuthority: farmVaultsAuthority(farmState),
+      scopePrices: null,
+      tokenProgram,
+      splitDestination: null,
+      splitDestinationAta: null,
+      rewardMint: tokenProgram.equals(TOKEN_PROGRAM_ID) ? null : rewardMint,
+      associatedTokenProgram: null,
+      systemProgram: null,
+      referrerState: null,
+    })
+    .signers([owner]);
+}
+
+export async function sleep(ms: number): Promise<void> {
+  return new Promise((resolve) => setTimeout(resolve, ms));
+}
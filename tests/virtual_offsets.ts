@@ -0,0 +1,151 @@
+import { getAccount } from "@solana/spl-token";
+import { Keypair, PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  createDelegatedFarm,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  donateToFarmVault,
+  FarmConfigOption,
+  fundedAta,
+  newMint,
+  newUser,
+  program,
+  provider,
+  stakeIx,
+  u64,
+  unstakeIx,
+  updateFarmConfig,
+  userStatePda,
+  withdrawUnstakedDepositsIx,
+} from "./utils";
+
+const DONATION = 1_000_000;
+const VIRTUAL_OFFSET = 1_000_000;
+
+type Outcome = { attackerProfit: number; victimLoss: number };
+
+describe("virtual offsets", () => {
+  let globalConfig: PublicKey;
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  async function balance(ata: PublicKey): Promise<number> {
+    return Number((await getAccount(provider.connection, ata)).amount);
+  }
+
+  async function exit(
+    farmState: PublicKey,
+    tokenMint: PublicKey,
+    user: Keypair,
+    ata: PublicKey
+  ): Promise<number> {
+    const before = await balance(ata);
+    const shares = (
+      await program.account.userState.fetch(
+        userStatePda(farmState, user.publicKey)
+      )
+    ).activeStakeScaled;
+    await unstakeIx(farmState, user, shares).rpc();
+    await withdrawUnstakedDepositsIx(farmState, tokenMint, user, ata).rpc();
+    return (await balance(ata)) - before;
+  }
+
+  /** Attacker stakes 1 lamport, donates to the farm vault ahead of the victim
+   * staking just under the donation, then both exit */
+  async function donationAttack(offset: number): Promise<Outcome> {
+    const tokenMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    if (offset > 0) {
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateVirtualOffsets,
+        Buffer.concat([u64(offset), u64(offset)])
+      );
+    }
+
+    const attacker = await newUser();
+    const victim = await newUser();
+    await createUser(farmState, attacker);
+    await createUser(farmState, victim);
+    const attackerAta = await fundedAta(tokenMint, attacker.publicKey, 1);
+    const victimAta = await fundedAta(tokenMint, victim.publicKey, DONATION);
+
+    await stakeIx(farmState, tokenMint, attacker, attackerAta, 1).rpc();
+    // The donation goes through the farm admin, the only one able to raise the
+    // amount per share of the farm
+    await donateToFarmVault(farmState, tokenMint, DONATION);
+    await stakeIx(
+      farmState,
+      tokenMint,
+      victim,
+      victimAta,
+      DONATION - 1
+    ).rpc();
+
+    const attackerOut = await exit(farmState, tokenMint, attacker, attackerAta);
+    const victimOut = await exit(farmState, tokenMint, victim, victimAta);
+    return {
+      attackerProfit: attackerOut - (1 + DONATION),
+      victimLoss: DONATION - 1 - victimOut,
+    };
+  }
+
+  it("bounds the value extractable by a donation", async () => {
+    const without = await donationAttack(0);
+    const withOffsets = await donationAttack(VIRTUAL_OFFSET);
+    console.log(
+      `Donation of ${DONATION}: without offsets attacker ${without.attackerProfit}, ` +
+        `victim -${without.victimLoss}; with offsets of ${VIRTUAL_OFFSET} attacker ` +
+        `${withOffsets.attackerProfit}, victim -${withOffsets.victimLoss}`
+    );
+
+    // Shares are fractional, the victim loses at most the rounding of its exit
+    expect(without.attackerProfit).to.be.at.most(0);
+    expect(without.victimLoss).to.be.at.most(1);
+    expect(withOffsets.attackerProfit).to.be.at.most(0);
+    expect(withOffsets.victimLoss).to.be.at.most(1);
+    // The virtual position takes nearly all of the donation
+    expect(withOffsets.attackerProfit).to.be.below(-0.9 * DONATION);
+  });
+
+  it("only takes offsets while the farm holds no stake", async () => {
+    const tokenMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    const user = await newUser();
+    await createUser(farmState, user);
+    const ata = await fundedAta(tokenMint, user.publicKey, 1);
+    await stakeIx(farmState, tokenMint, user, ata, 1).rpc();
+
+    try {
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateVirtualOffsets,
+        Buffer.concat([u64(VIRTUAL_OFFSET), u64(VIRTUAL_OFFSET)])
+      );
+      expect.fail("offsets changed under existing stake");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("InvalidConfigValue");
+    }
+  });
+
+  it("stays at zero for delegated farms", async () => {
+    const farmState = await createDelegatedFarm(
+      globalConfig,
+      Keypair.generate()
+    );
+    try {
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateVirtualOffsets,
+        Buffer.concat([u64(VIRTUAL_OFFSET), u64(VIRTUAL_OFFSET)])
+      );
+      expect.fail("offsets set on a delegated farm");
+    } catch (err) {
+      expect(err.error.errorCode.code).to.equal("FarmDelegated");
+    }
+  });
+});
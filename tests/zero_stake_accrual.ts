@@ -0,0 +1,169 @@
+import { BN } from "@coral-xyz/anchor";
+import { getAccount, TOKEN_PROGRAM_ID } from "@solana/spl-token";
+import { PublicKey } from "@solana/web3.js";
+import { expect } from "chai";
+import {
+  addRewards,
+  createFarm,
+  createGlobalConfig,
+  createUser,
+  FarmConfigOption,
+  farmVaultsAuthority,
+  fundedAta,
+  harvestIx,
+  initializeReward,
+  newMint,
+  newUser,
+  program,
+  provider,
+  refreshFarm,
+  rewardTreasuryVault,
+  rewardValue,
+  rewardVault,
+  sleep,
+  stakeIx,
+  u64,
+  updateFarmConfig,
+} from "./utils";
+
+const RPS = 100;
+const FUNDING = 1_000_000_000;
+
+describe("zero stake accrual", () => {
+  let globalConfig: PublicKey;
+
+  async function reward(farmState: PublicKey) {
+    return (await program.account.farmState.fetch(farmState)).rewardInfos[0];
+  }
+
+  async function balance(ata: PublicKey): Promise<number> {
+    return Number((await getAccount(provider.connection, ata)).amount);
+  }
+
+  /** Funded farm whose reward runs from now on, with no staker yet */
+  async function emptyFarm(divertToTreasury: boolean) {
+    const tokenMint = await newMint();
+    const farmState = await createFarm(globalConfig, tokenMint);
+    const rewardMint = await newMint();
+    await initializeReward(globalConfig, farmState, rewardMint);
+    await addRewards(farmState, rewardMint, 0, FUNDING);
+    if (divertToTreasury) {
+      await updateFarmConfig(
+        farmState,
+        FarmConfigOption.UpdateZeroStakeEmissionsToTreasury,
+        u64(1)
+      );
+    }
+    await updateFarmConfig(
+      farmState,
+      FarmConfigOption.UpdateRewardRps,
+      rewardValue(0, u64(RPS))
+    );
+    return { tokenMint, farmState, rewardMint };
+  }
+
+  /** Stakes the first staker of `farmState` and checks that from then on only
+   * their time is issued, all of it to them */
+  async function expectFirstStakerEarnsFromStake(
+    farmState: PublicKey,
+    tokenMint: PublicKey,
+    rewardMint: PublicKey
+  ) {
+    const owner = await newUser();
+    await createUser(farmState, owner);
+    const ata = await fundedAta(tokenMint, owner.publicKey, 1_000_000);
+    await stakeIx(farmState, tokenMint, owner, ata, 1_000_000).rpc();
+    const staked = await reward(farmState);
+
+    await sleep(2_000);
+    await refreshFarm(farmState);
+    const refreshed = await reward(farmState);
+    const issued = refreshed.rewardsIssuedCumulative.toNumber();
+    expect(issued).to.equal(
+      staked.rewardsIssuedCumulative.toNumber() +
+        RPS *
+          (refreshed.lastIssuanceTs.toNumber() -
+            staked.lastIssuanceTs.toNumber())
+    );
+    expect(refreshed.rewardsDivertedToTreasury.toNumber()).to.equal(
+      staked.rewardsDivertedToTreasury.toNumber()
+    );
+
+    const rewardAta = await fundedAta(rewardMint, owner.publicKey, 0);
+    await harvestIx(
+      globalConfig,
+      farmState,
+      rewardMint,
+      owner,
+      rewardAta,
+      0
+    ).rpc({ commitment: "confirmed" });
+    // Harvest refreshes again, the staker gets at least what was issued above
+    expect(await balance(rewardAta)).to.be.at.least(issued - 1);
+  }
+
+  before(async () => {
+    globalConfig = await createGlobalConfig();
+  });
+
+  it("issues nothing while empty and keeps the funding", async () => {
+    const { tokenMint, farmState, rewardMint } = await emptyFarm(false);
+    const configured = await reward(farmState);
+
+    await sleep(3_000);
+    await refreshFarm(farmState);
+    const refreshed = await reward(farmState);
+    expect(refreshed.lastIssuanceTs.toNumber()).to.be.greaterThan(
+      configured.lastIssuanceTs.toNumber()
+    );
+    expect(refreshed.rewardsIssuedCumulative.toNumber()).to.equal(0);
+    expect(refreshed.rewardsDivertedToTreasury.toNumber()).to.equal(0);
+    expect(refreshed.rewardsAvailable.toNumber()).to.equal(FUNDING);
+
+    await expectFirstStakerEarnsFromStake(farmState, tokenMint, rewardMint);
+  });
+
+  it("diverts the empty period to the treasury when configured", async () => {
+    const { tokenMint, farmState, rewardMint } = await emptyFarm(true);
+    const configured = await reward(farmState);
+
+    await sleep(3_000);
+    await refreshFarm(farmState);
+    const refreshed = await reward(farmState);
+    const diverted =
+      RPS *
+      (refreshed.lastIssuanceTs.toNumber() -
+        configured.lastIssuanceTs.toNumber());
+    expect(diverted).to.be.greaterThan(0);
+    expect(refreshed.rewardsDivertedToTreasury.toNumber()).to.equal(diverted);
+    expect(refreshed.rewardsIssuedCumulative.toNumber()).to.equal(0);
+    expect(refreshed.rewardsAvailable.toNumber()).to.equal(FUNDING - diverted);
+
+    const treasury = rewardTreasuryVault(globalConfig, rewardMint);
+    const treasuryBefore = await balance(treasury);
+    await program.methods
+      .sweepDivertedRewards(new BN(0))
+      .accounts({
+        farmState,
+        globalConfig,
+        rewardsVault: rewardVault(farmState, rewardMint),
+        rewardsTreasuryVault: treasury,
+        farmVaultsAuthority: farmVaultsAuthority(farmState),
+        rewardMint: null,
+        scopePrices: null,
+        tokenProgram: TOKEN_PROGRAM_ID,
+      })
+      .rpc({ commitment: "confirmed" });
+    const swept = await reward(farmState);
+    // The sweep refreshes first, the farm is still empty
+    const sweptAmount =
+      diverted +
+      RPS *
+        (swept.lastIssuanceTs.toNumber() -
+          refreshed.lastIssuanceTs.toNumber());
+    expect(swept.rewardsDivertedToTreasury.toNumber()).to.equal(0);
+    expect((await balance(treasury)) - treasuryBefore).to.equal(sweptAmount);
+
+    await expectFirstStakerEarnsFromStake(farmState, tokenMint, rewardMint);
+  });
+});